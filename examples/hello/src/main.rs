@@ -11,6 +11,7 @@ struct AppState {
     results: Rc<ListView>,
     commands_sender: mpsc::Sender<WorkerCommand>,
     query_button: Rc<Button>,
+    browse_button: Rc<Button>,
     root_directory: Rc<Edit>,
     regex: Rc<Edit>,
     root_directory_label: Rc<Label>,
@@ -22,6 +23,7 @@ struct AppState {
 const CONTROL_ID_QUERY_BUTTON: ControlId = ControlId(1);
 const CONTROL_ID_ROOT_DIRECTORY: ControlId = ControlId(2);
 const CONTROL_ID_REGEX: ControlId = ControlId(3);
+const CONTROL_ID_BROWSE_BUTTON: ControlId = ControlId(4);
 
 fn main() {
     let form = Form::builder()
@@ -49,6 +51,10 @@ fn main() {
             w.set_text("Search");
             w.set_tab_stop(true);
         }),
+        browse_button: Button::new(&form, CONTROL_ID_BROWSE_BUTTON).with(|w| {
+            w.set_text("Browse...");
+            w.set_tab_stop(true);
+        }),
         root_directory: Edit::new(&form).with(|w| {
             w.set_text(r"d:\rust_forms\examples");
         }),
@@ -91,6 +97,7 @@ fn main() {
         items: vec![
             GridItem::new(0, 0, LayoutItem::Control(app.root_directory_label.clone())),
             GridItem::new(0, 1, LayoutItem::Control(app.root_directory.clone())),
+            GridItem::new(0, 2, LayoutItem::Control(app.browse_button.clone())),
             GridItem::new(1, 0, LayoutItem::Control(app.regex_label.clone())),
             GridItem::new(1, 1, LayoutItem::Control(app.regex.clone())),
             GridItem::new(1, 2, LayoutItem::Control(app.query_button.clone())),
@@ -106,7 +113,19 @@ fn main() {
 
     {
         let app = app.clone();
+        let form_weak = Rc::downgrade(&form);
         form.command_handler(move |control, command| match (control, command) {
+            (CONTROL_ID_BROWSE_BUTTON, Command::ButtonClicked) => {
+                let mut dialog = forms::file_dialog::FileDialog::new();
+                dialog.title = Some("Root Directory".to_string());
+                dialog.dir = Some(app.root_directory.get_text());
+                let form = form_weak.upgrade();
+                if let Some(selection) = dialog.pick_folder(form.as_deref()) {
+                    if let Some(dir) = selection.paths.first() {
+                        app.root_directory.set_text(&dir.to_string_lossy());
+                    }
+                }
+            }
             (CONTROL_ID_QUERY_BUTTON, Command::ButtonClicked) => {
                 let root_directory = app.root_directory.get_text();
                 let regex_text = app.regex.get_text();
@@ -63,4 +63,5 @@ pub enum SysColor {
     Menu = 4,
     WindowFrame = 6,
     Window = 5,
+    WindowText = 8,
 }
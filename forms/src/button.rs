@@ -4,6 +4,7 @@ use super::*;
 
 pub struct Button {
     control: ControlState,
+    images: RefCell<Option<Rc<ImageList>>>,
 }
 
 impl core::ops::Deref for Button {
@@ -54,6 +55,29 @@ pub enum ButtonKind {
     AutoThreeState,
 }
 
+/// Where a button's image is placed, relative to its text.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum ButtonImageAlign {
+    #[default]
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Center,
+}
+
+impl ButtonImageAlign {
+    fn to_native(self) -> BUTTON_IMAGELIST_ALIGN {
+        match self {
+            Self::Left => BUTTON_IMAGELIST_ALIGN_LEFT,
+            Self::Right => BUTTON_IMAGELIST_ALIGN_RIGHT,
+            Self::Top => BUTTON_IMAGELIST_ALIGN_TOP,
+            Self::Bottom => BUTTON_IMAGELIST_ALIGN_BOTTOM,
+            Self::Center => BUTTON_IMAGELIST_ALIGN_CENTER,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum CheckState {
     Checked,
@@ -136,6 +160,7 @@ impl Button {
 
             let this = Rc::new(Button {
                 control: ControlState::new(hwnd),
+                images: RefCell::new(None),
             });
 
             this.set_font(&form.style().button_font);
@@ -173,6 +198,28 @@ impl Button {
         set_window_text(self.control.handle(), text);
     }
 
+    /// Shows an image on the button. If `images` has one image, it is used for all
+    /// states. Otherwise, it has an image for each of the normal, hot, pressed,
+    /// disabled, defaulted and stylus-hot states, in that order.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/bcm-setimagelist>
+    pub fn set_image_list(&self, images: Rc<ImageList>, align: ButtonImageAlign) {
+        unsafe {
+            let info = BUTTON_IMAGELIST {
+                himl: images.himagelist,
+                margin: RECT::default(),
+                uAlign: align.to_native(),
+            };
+            SendMessageW(
+                self.handle(),
+                BCM_SETIMAGELIST,
+                None,
+                Some(LPARAM(&info as *const BUTTON_IMAGELIST as isize)),
+            );
+        }
+        *self.images.borrow_mut() = Some(images);
+    }
+
     pub fn is_checked(&self) -> bool {
         unsafe {
             let result = SendMessageW(self.handle(), BM_GETCHECK, None, None);
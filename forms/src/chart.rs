@@ -0,0 +1,520 @@
+//! Line and scatter charts. The scale and tick computations are in `chart::scale`.
+
+use super::*;
+use crate::custom::{CustomControl, CustomInner};
+use crate::gdi::dc::Dc;
+use scale::*;
+use std::cell::OnceCell;
+use windows::Win32::UI::Input::KeyboardAndMouse::VK_HOME;
+
+pub mod scale;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SeriesKind {
+    /// Points are joined by straight lines, in order.
+    Line,
+    /// Each point is drawn as a dot.
+    Scatter,
+    /// Both lines and dots.
+    LineAndMarkers,
+}
+
+/// A named set of points in a `Chart`.
+pub struct Series {
+    pub name: String,
+    pub kind: SeriesKind,
+    /// If `None`, a color is chosen from a standard palette.
+    pub color: Option<ColorRef>,
+    /// Points that are not finite (NaN or infinite) are skipped, and break lines.
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    pub fn line(name: &str, points: Vec<(f64, f64)>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: SeriesKind::Line,
+            color: None,
+            points,
+        }
+    }
+
+    pub fn scatter(name: &str, points: Vec<(f64, f64)>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: SeriesKind::Scatter,
+            color: None,
+            points,
+        }
+    }
+}
+
+/// Colors for series that do not have their own.
+const PALETTE: [ColorRef; 8] = [
+    ColorRef::from_u32_rgb(0x1f77b4),
+    ColorRef::from_u32_rgb(0xff7f0e),
+    ColorRef::from_u32_rgb(0x2ca02c),
+    ColorRef::from_u32_rgb(0xd62728),
+    ColorRef::from_u32_rgb(0x9467bd),
+    ColorRef::from_u32_rgb(0x8c564b),
+    ColorRef::from_u32_rgb(0xe377c2),
+    ColorRef::from_u32_rgb(0x7f7f7f),
+];
+
+const GRID_COLOR: ColorRef = ColorRef::from_u32_rgb(0xe4e4e4);
+const AXIS_COLOR: ColorRef = ColorRef::from_u32_rgb(0x808080);
+const MARKER_RADIUS: i32 = 3;
+/// How close the mouse must be to a point to show its value.
+const HOVER_DISTANCE: f64 = 8.0;
+/// Tick labels on the X axis are kept at least this far apart, in pixels.
+const X_TICK_SPACING: i32 = 80;
+const Y_TICK_SPACING: i32 = 40;
+
+/// Plots series of (x, y) points, with axes scaled to fit the data.
+///
+/// Turn the mouse wheel to zoom around the pointer, and drag to pan. Press Home to
+/// fit the data again. Hovering near a point shows its value.
+pub struct Chart {
+    control: Rc<CustomControl<ChartInner>>,
+}
+
+impl core::ops::Deref for Chart {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+struct ChartInner {
+    series: RefCell<Vec<Series>>,
+    font: RefCell<Rc<Font>>,
+    x_label: RefCell<String>,
+    y_label: RefCell<String>,
+    /// `None` fits the view to the data.
+    view: Cell<Option<(AxisRange, AxisRange)>>,
+    /// The plot area from the last time the chart was painted.
+    plot: Cell<Rect>,
+    hover: Cell<Option<(usize, usize)>>,
+    drag_from: Cell<Option<POINT>>,
+    view_changed_handler: OnceCell<Box<dyn Fn(AxisRange, AxisRange)>>,
+}
+
+impl Chart {
+    pub fn new(parent: &ControlState) -> Rc<Self> {
+        let inner = ChartInner {
+            series: RefCell::new(Vec::new()),
+            font: RefCell::new(Font::new("Segoe UI", 15).unwrap()),
+            x_label: RefCell::new(String::new()),
+            y_label: RefCell::new(String::new()),
+            view: Cell::new(None),
+            plot: Cell::new(Rect::default()),
+            hover: Cell::new(None),
+            drag_from: Cell::new(None),
+            view_changed_handler: OnceCell::new(),
+        };
+        Rc::new(Chart {
+            control: CustomControl::new(parent, inner),
+        })
+    }
+
+    fn inner(&self) -> &ChartInner {
+        self.control.inner()
+    }
+
+    /// Adds a series. Returns its index.
+    pub fn add_series(&self, series: Series) -> usize {
+        let mut all = self.inner().series.borrow_mut();
+        all.push(series);
+        self.invalidate_all();
+        all.len() - 1
+    }
+
+    /// Replaces the points of the series at `index`.
+    pub fn set_series_points(&self, index: usize, points: Vec<(f64, f64)>) {
+        let inner = self.inner();
+        inner.series.borrow_mut()[index].points = points;
+        inner.hover.set(None);
+        self.invalidate_all();
+    }
+
+    pub fn clear_series(&self) {
+        let inner = self.inner();
+        inner.series.borrow_mut().clear();
+        inner.hover.set(None);
+        self.invalidate_all();
+    }
+
+    pub fn set_axis_labels(&self, x_label: &str, y_label: &str) {
+        let inner = self.inner();
+        *inner.x_label.borrow_mut() = x_label.to_string();
+        *inner.y_label.borrow_mut() = y_label.to_string();
+        self.invalidate_all();
+    }
+
+    pub fn set_font(&self, font: Rc<Font>) {
+        *self.inner().font.borrow_mut() = font;
+        self.invalidate_all();
+    }
+
+    /// The ranges that are currently shown.
+    pub fn view(&self) -> (AxisRange, AxisRange) {
+        self.inner().current_view()
+    }
+
+    /// Shows fixed ranges, until the user zooms or pans, or `fit_to_data` is called.
+    pub fn set_view(&self, x: AxisRange, y: AxisRange) {
+        self.inner().set_view(&self.control, Some((x, y)));
+    }
+
+    /// Scales the axes to show all of the data. This is the default.
+    pub fn fit_to_data(&self) {
+        self.inner().set_view(&self.control, None);
+    }
+
+    /// Sets the handler that is called when the user zooms or pans.
+    pub fn view_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(AxisRange, AxisRange) + 'static,
+    {
+        let result = self.inner().view_changed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call view_changed_handler() more than once"
+        );
+    }
+}
+
+impl ChartInner {
+    fn current_view(&self) -> (AxisRange, AxisRange) {
+        if let Some(view) = self.view.get() {
+            return view;
+        }
+        let series = self.series.borrow();
+        match data_bounds(series.iter().flat_map(|s| s.points.iter())) {
+            Some((x, y)) => (x.padded(0.05), y.padded(0.05)),
+            None => (AxisRange::new(0.0, 1.0), AxisRange::new(0.0, 1.0)),
+        }
+    }
+
+    fn transform(&self) -> Transform {
+        let (x, y) = self.current_view();
+        Transform {
+            x,
+            y,
+            plot: self.plot.get(),
+        }
+    }
+
+    fn set_view(&self, control: &CustomControl<Self>, view: Option<(AxisRange, AxisRange)>) {
+        self.view.set(view);
+        self.hover.set(None);
+        control.invalidate_all();
+    }
+
+    /// Called when the user zooms or pans.
+    fn user_changed_view(&self, control: &CustomControl<Self>, transform: Transform) {
+        self.set_view(control, Some((transform.x, transform.y)));
+        if let Some(handler) = self.view_changed_handler.get() {
+            handler(transform.x, transform.y);
+        }
+    }
+
+    fn color_of(series: &Series, index: usize) -> ColorRef {
+        series.color.unwrap_or(PALETTE[index % PALETTE.len()])
+    }
+
+    fn in_plot(&self, pt: POINT) -> bool {
+        let plot = self.plot.get();
+        pt.x >= plot.left && pt.x < plot.right && pt.y >= plot.top && pt.y < plot.bottom
+    }
+
+    fn paint_series(&self, dc: &Dc, transform: &Transform) {
+        let series = self.series.borrow();
+        for (index, s) in series.iter().enumerate() {
+            let color = Self::color_of(s, index);
+            dc.set_pen_color(color);
+            dc.set_brush_color(color);
+
+            if s.kind != SeriesKind::Scatter {
+                // Non-finite points break the line into separate runs.
+                for run in s
+                    .points
+                    .split(|(x, y)| !x.is_finite() || !y.is_finite())
+                    .filter(|run| run.len() >= 2)
+                {
+                    let points: Vec<POINT> = run
+                        .iter()
+                        .map(|&(x, y)| {
+                            let (px, py) = transform.to_pixel_i32(x, y);
+                            POINT { x: px, y: py }
+                        })
+                        .collect();
+                    dc.polyline(&points);
+                }
+            }
+
+            if s.kind != SeriesKind::Line {
+                for &(x, y) in &s.points {
+                    if !x.is_finite() || !y.is_finite() {
+                        continue;
+                    }
+                    let (px, py) = transform.to_pixel_i32(x, y);
+                    dc.ellipse(
+                        px - MARKER_RADIUS,
+                        py - MARKER_RADIUS,
+                        px + MARKER_RADIUS + 1,
+                        py + MARKER_RADIUS + 1,
+                    );
+                }
+            }
+        }
+    }
+
+    fn paint_legend(&self, dc: &Dc, plot: &Rect, line_height: i32) {
+        let series = self.series.borrow();
+        if series.is_empty() {
+            return;
+        }
+        let swatch = line_height;
+        let text_width = series
+            .iter()
+            .map(|s| dc.text_extent(&s.name).0)
+            .max()
+            .unwrap_or(0);
+        let width = swatch + 6 + text_width + 12;
+        let height = series.len() as i32 * line_height + 8;
+        let right = plot.right - 8;
+        let left = right - width;
+        let top = plot.top + 8;
+
+        dc.set_pen_color(AXIS_COLOR);
+        dc.set_brush_color(ColorRef::from_sys_color(SysColor::Window));
+        dc.rectangle(left, top, right, top + height);
+
+        for (index, s) in series.iter().enumerate() {
+            let y = top + 4 + index as i32 * line_height;
+            let mid = y + line_height / 2;
+            let color = Self::color_of(s, index);
+            dc.set_pen_color(color);
+            dc.set_brush_color(color);
+            if s.kind != SeriesKind::Scatter {
+                dc.move_to(left + 6, mid);
+                dc.line_to(left + 6 + swatch, mid);
+            }
+            if s.kind != SeriesKind::Line {
+                let cx = left + 6 + swatch / 2;
+                dc.ellipse(
+                    cx - MARKER_RADIUS,
+                    mid - MARKER_RADIUS,
+                    cx + MARKER_RADIUS + 1,
+                    mid + MARKER_RADIUS + 1,
+                );
+            }
+            dc.text_out(left + 12 + swatch, y, &s.name);
+        }
+    }
+
+    fn paint_hover(&self, dc: &Dc, transform: &Transform, x_step: f64, y_step: f64) {
+        let Some((series_index, point_index)) = self.hover.get() else {
+            return;
+        };
+        let series = self.series.borrow();
+        let Some(s) = series.get(series_index) else {
+            return;
+        };
+        let Some(&(x, y)) = s.points.get(point_index) else {
+            return;
+        };
+
+        let (px, py) = transform.to_pixel_i32(x, y);
+        let r = MARKER_RADIUS + 3;
+        dc.set_pen_color(Self::color_of(s, series_index));
+        dc.select_null_brush();
+        dc.ellipse(px - r, py - r, px + r + 1, py + r + 1);
+
+        // Two more decimal places than the ticks have.
+        let text = format!(
+            "{}: {}, {}",
+            s.name,
+            format_tick(x, x_step / 100.0),
+            format_tick(y, y_step / 100.0)
+        );
+        let (width, height) = dc.text_extent(&text);
+        let plot = transform.plot;
+        let left = plot.left + 8;
+        let top = plot.top + 8;
+        dc.select_dc_pen_and_brush();
+        dc.set_pen_color(AXIS_COLOR);
+        dc.set_brush_color(ColorRef::from_sys_color(SysColor::Window));
+        dc.rectangle(left, top, left + width + 12, top + height + 8);
+        dc.text_out(left + 6, top + 4, &text);
+    }
+}
+
+impl CustomInner for ChartInner {
+    fn captures_mouse(&self) -> bool {
+        true
+    }
+
+    fn paint(&self, control: &CustomControl<Self>, dc: &Dc, rect: &Rect) {
+        let client = control.get_client_rect();
+        let saved = dc.save();
+        dc.select_font(&self.font.borrow());
+        dc.set_transparent_text();
+        dc.select_dc_pen_and_brush();
+        dc.set_text_color(ColorRef::from_sys_color(SysColor::WindowText));
+
+        let line_height = dc.text_extent("0").1;
+        let x_label = self.x_label.borrow();
+        let y_label = self.y_label.borrow();
+
+        // The bottom margin and the height of the plot do not depend on the width
+        // of the Y tick labels, so the Y ticks can be chosen first.
+        let top = 10
+            + if y_label.is_empty() {
+                0
+            } else {
+                line_height + 4
+            };
+        let mut bottom = client.bottom - line_height - 10;
+        if !x_label.is_empty() {
+            bottom -= line_height + 4;
+        }
+        let (x_range, y_range) = self.current_view();
+        let y_ticks = nice_ticks(y_range, ((bottom - top) / Y_TICK_SPACING).max(2) as usize);
+        let y_texts: Vec<String> = y_ticks
+            .values
+            .iter()
+            .map(|&v| format_tick(v, y_ticks.step))
+            .collect();
+        let y_text_width = y_texts
+            .iter()
+            .map(|t| dc.text_extent(t).0)
+            .max()
+            .unwrap_or(0);
+        let left = 10 + y_text_width + 6;
+        let right = client.right - 16;
+
+        let plot = Rect {
+            left,
+            top,
+            right: right.max(left + 1),
+            bottom: bottom.max(top + 1),
+        };
+        self.plot.set(plot);
+        let transform = Transform {
+            x: x_range,
+            y: y_range,
+            plot,
+        };
+        let x_ticks = nice_ticks(x_range, ((right - left) / X_TICK_SPACING).max(2) as usize);
+
+        // Grid lines and tick labels
+        dc.set_pen_color(GRID_COLOR);
+        for (value, text) in y_ticks.values.iter().zip(&y_texts) {
+            let (_, py) = transform.to_pixel_i32(0.0, *value);
+            dc.move_to(plot.left, py);
+            dc.line_to(plot.right, py);
+            let (width, _) = dc.text_extent(text);
+            dc.text_out(plot.left - 6 - width, py - line_height / 2, text);
+        }
+        for &value in &x_ticks.values {
+            let (px, _) = transform.to_pixel_i32(value, 0.0);
+            dc.move_to(px, plot.top);
+            dc.line_to(px, plot.bottom);
+            let text = format_tick(value, x_ticks.step);
+            let (width, _) = dc.text_extent(&text);
+            dc.text_out(px - width / 2, plot.bottom + 4, &text);
+        }
+
+        if !y_label.is_empty() {
+            dc.text_out(10, 6, &y_label);
+        }
+        if !x_label.is_empty() {
+            let (width, _) = dc.text_extent(&x_label);
+            let x = (plot.left + plot.right - width) / 2;
+            dc.text_out(x, plot.bottom + line_height + 8, &x_label);
+        }
+
+        dc.set_pen_color(AXIS_COLOR);
+        dc.select_null_brush();
+        dc.rectangle(plot.left, plot.top, plot.right + 1, plot.bottom + 1);
+
+        let clip = dc.save();
+        dc.intersect_clip_rect(plot.left, plot.top, plot.right, plot.bottom);
+        dc.select_dc_pen_and_brush();
+        self.paint_series(dc, &transform);
+        self.paint_legend(dc, &plot, line_height);
+        self.paint_hover(dc, &transform, x_ticks.step, y_ticks.step);
+        dc.restore(clip);
+
+        dc.restore(saved);
+    }
+
+    fn mouse_down(&self, control: &CustomControl<Self>, pt: POINT) {
+        if self.in_plot(pt) {
+            self.drag_from.set(Some(pt));
+        }
+    }
+
+    fn mouse_move(&self, control: &CustomControl<Self>, pt: POINT) {
+        if let Some(from) = self.drag_from.get() {
+            let transform = self
+                .transform()
+                .pan_by_pixels((pt.x - from.x) as f64, (pt.y - from.y) as f64);
+            self.drag_from.set(Some(pt));
+            self.user_changed_view(control, transform);
+            return;
+        }
+
+        let hover = if self.in_plot(pt) {
+            let series = self.series.borrow();
+            nearest_point(
+                &self.transform(),
+                series.iter().map(|s| s.points.as_slice()),
+                pt.x as f64,
+                pt.y as f64,
+                HOVER_DISTANCE,
+            )
+        } else {
+            None
+        };
+        if hover != self.hover.get() {
+            self.hover.set(hover);
+            control.invalidate_all();
+        }
+    }
+
+    fn mouse_up(&self, control: &CustomControl<Self>, pt: POINT) {
+        self.drag_from.set(None);
+    }
+
+    fn mouse_leave(&self, control: &CustomControl<Self>) {
+        if self.hover.take().is_some() {
+            control.invalidate_all();
+        }
+    }
+
+    fn mouse_wheel(&self, control: &CustomControl<Self>, delta: i16) {
+        let pt = unsafe {
+            let mut pt = get_cursor_pos();
+            _ = ScreenToClient(control.handle(), &mut pt);
+            pt
+        };
+        if !self.in_plot(pt) {
+            return;
+        }
+        // Each notch zooms in (or out) by 20%.
+        let factor = 0.8f64.powf(delta as f64 / WHEEL_DELTA as f64);
+        let transform = self.transform().zoom_at(pt.x as f64, pt.y as f64, factor);
+        self.user_changed_view(control, transform);
+    }
+
+    fn key_down(&self, control: &CustomControl<Self>, key: u16) -> bool {
+        if key == VK_HOME.0 {
+            self.set_view(control, None);
+            return true;
+        }
+        false
+    }
+}
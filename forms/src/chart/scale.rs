@@ -0,0 +1,466 @@
+//! Axis ranges, tick generation and the mapping between data and pixels. None of
+//! this needs a window.
+
+use crate::Rect;
+
+/// The range of data values shown along one axis.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AxisRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl AxisRange {
+    pub fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    pub fn span(&self) -> f64 {
+        self.max - self.min
+    }
+
+    /// Widens the range by `fraction` of its span at each end. A range that
+    /// contains a single value is widened so that it has a usable span.
+    pub fn padded(self, fraction: f64) -> Self {
+        let span = self.span();
+        if span > 0.0 {
+            let pad = span * fraction;
+            return Self::new(self.min - pad, self.max + pad);
+        }
+        let pad = if self.min == 0.0 {
+            1.0
+        } else {
+            self.min.abs() * 0.5
+        };
+        Self::new(self.min - pad, self.max + pad)
+    }
+
+    /// Scales the range by `factor` around `center`. Factors below 1 zoom in.
+    pub fn zoom(self, center: f64, factor: f64) -> Self {
+        Self::new(
+            center - (center - self.min) * factor,
+            center + (self.max - center) * factor,
+        )
+    }
+
+    /// Moves the range by `delta`, in data units.
+    pub fn pan(self, delta: f64) -> Self {
+        Self::new(self.min + delta, self.max + delta)
+    }
+}
+
+/// The smallest ranges that contain all of the points, ignoring points that are
+/// not finite. Returns `None` if there are no such points.
+pub fn data_bounds<'a, I>(points: I) -> Option<(AxisRange, AxisRange)>
+where
+    I: IntoIterator<Item = &'a (f64, f64)>,
+{
+    let mut bounds: Option<(AxisRange, AxisRange)> = None;
+    for &(x, y) in points {
+        if !x.is_finite() || !y.is_finite() {
+            continue;
+        }
+        bounds = Some(match bounds {
+            None => (AxisRange::new(x, x), AxisRange::new(y, y)),
+            Some((bx, by)) => (
+                AxisRange::new(bx.min.min(x), bx.max.max(x)),
+                AxisRange::new(by.min.min(y), by.max.max(y)),
+            ),
+        });
+    }
+    bounds
+}
+
+/// Rounds `x` to 1, 2, 5 or 10 times a power of ten. If `round` is false, the
+/// result is at least `x`.
+///
+/// From "Nice Numbers for Graph Labels", Paul Heckbert, Graphics Gems (1990).
+pub fn nice_number(x: f64, round: bool) -> f64 {
+    if x <= 0.0 || !x.is_finite() {
+        return 1.0;
+    }
+    let exponent = x.log10().floor();
+    let power = 10f64.powf(exponent);
+    let fraction = x / power;
+    let nice = if round {
+        if fraction < 1.5 {
+            1.0
+        } else if fraction < 3.0 {
+            2.0
+        } else if fraction < 7.0 {
+            5.0
+        } else {
+            10.0
+        }
+    } else if fraction <= 1.0 {
+        1.0
+    } else if fraction <= 2.0 {
+        2.0
+    } else if fraction <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * power
+}
+
+/// Tick marks for an axis. See `nice_ticks`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ticks {
+    pub step: f64,
+    /// The tick values that are within the range, in increasing order.
+    pub values: Vec<f64>,
+}
+
+/// Chooses evenly spaced tick values at "nice" numbers (multiples of 1, 2 or 5
+/// times a power of ten), with no more than about `max_ticks` of them in `range`.
+pub fn nice_ticks(range: AxisRange, max_ticks: usize) -> Ticks {
+    let span = range.span();
+    if !span.is_finite() || span <= 0.0 {
+        // A single value gets a single tick, and a range that is not finite gets none.
+        let values = if range.min.is_finite() {
+            vec![range.min]
+        } else {
+            Vec::new()
+        };
+        return Ticks { step: 1.0, values };
+    }
+    let max_ticks = max_ticks.max(2);
+    let step = nice_number(nice_number(span, false) / (max_ticks - 1) as f64, true);
+
+    let first = (range.min / step).ceil() as i64;
+    let last = (range.max / step).floor() as i64;
+    let values = (first..=last).map(|i| i as f64 * step).collect();
+    Ticks { step, values }
+}
+
+/// Formats a tick value with enough decimal places to tell ticks `step` apart.
+pub fn format_tick(value: f64, step: f64) -> String {
+    let decimals = if step > 0.0 && step < 1.0 {
+        (-step.log10().floor()) as usize
+    } else {
+        0
+    };
+    let text = format!("{value:.decimals$}");
+    if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+        text[1..].to_string()
+    } else {
+        text
+    }
+}
+
+/// Maps data values to pixels in the plot area of a chart, and back. Y increases
+/// upward in data, and downward in pixels.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub x: AxisRange,
+    pub y: AxisRange,
+    /// The plot area, in client coordinates.
+    pub plot: Rect,
+}
+
+impl Transform {
+    fn plot_width(&self) -> f64 {
+        (self.plot.right - self.plot.left) as f64
+    }
+
+    fn plot_height(&self) -> f64 {
+        (self.plot.bottom - self.plot.top) as f64
+    }
+
+    pub fn to_pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        let px = self.plot.left as f64 + (x - self.x.min) / self.x.span() * self.plot_width();
+        let py = self.plot.bottom as f64 - (y - self.y.min) / self.y.span() * self.plot_height();
+        (px, py)
+    }
+
+    pub fn to_data(&self, px: f64, py: f64) -> (f64, f64) {
+        let x = self.x.min + (px - self.plot.left as f64) / self.plot_width() * self.x.span();
+        let y = self.y.min + (self.plot.bottom as f64 - py) / self.plot_height() * self.y.span();
+        (x, y)
+    }
+
+    /// Like `to_pixel`, but rounded and kept within the range that GDI can draw.
+    pub fn to_pixel_i32(&self, x: f64, y: f64) -> (i32, i32) {
+        const LIMIT: f64 = 1_000_000.0;
+        let (px, py) = self.to_pixel(x, y);
+        let clamp = |v: f64| {
+            if v.is_nan() {
+                0
+            } else {
+                v.clamp(-LIMIT, LIMIT).round() as i32
+            }
+        };
+        (clamp(px), clamp(py))
+    }
+
+    /// Zooms both axes by `factor` around the pixel (`px`, `py`), which stays over
+    /// the same data point.
+    pub fn zoom_at(&self, px: f64, py: f64, factor: f64) -> Self {
+        let (cx, cy) = self.to_data(px, py);
+        Self {
+            x: self.x.zoom(cx, factor),
+            y: self.y.zoom(cy, factor),
+            plot: self.plot,
+        }
+    }
+
+    /// Moves the view so that the data follows the mouse, when it is dragged by
+    /// (`dx`, `dy`) pixels.
+    pub fn pan_by_pixels(&self, dx: f64, dy: f64) -> Self {
+        let x_delta = -dx / self.plot_width() * self.x.span();
+        let y_delta = dy / self.plot_height() * self.y.span();
+        Self {
+            x: self.x.pan(x_delta),
+            y: self.y.pan(y_delta),
+            plot: self.plot,
+        }
+    }
+}
+
+/// Finds the point nearest to the pixel (`px`, `py`), within `max_distance`
+/// pixels. Returns the index of the series and the index of the point.
+pub fn nearest_point<'a, I>(
+    transform: &Transform,
+    series: I,
+    px: f64,
+    py: f64,
+    max_distance: f64,
+) -> Option<(usize, usize)>
+where
+    I: IntoIterator<Item = &'a [(f64, f64)]>,
+{
+    let mut best: Option<((usize, usize), f64)> = None;
+    for (series_index, points) in series.into_iter().enumerate() {
+        for (point_index, &(x, y)) in points.iter().enumerate() {
+            let (qx, qy) = transform.to_pixel(x, y);
+            let distance = ((qx - px).powi(2) + (qy - py).powi(2)).sqrt();
+            if distance <= max_distance && best.is_none_or(|(_, d)| distance < d) {
+                best = Some(((series_index, point_index), distance));
+            }
+        }
+    }
+    best.map(|(indices, _)| indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{actual:?} != {expected:?}");
+        }
+    }
+
+    fn transform() -> Transform {
+        Transform {
+            x: AxisRange::new(0.0, 10.0),
+            y: AxisRange::new(0.0, 10.0),
+            plot: Rect {
+                left: 0,
+                top: 0,
+                right: 100,
+                bottom: 100,
+            },
+        }
+    }
+
+    #[test]
+    fn nice_number_rounds() {
+        assert_eq!(nice_number(1.4, true), 1.0);
+        assert_eq!(nice_number(1.5, true), 2.0);
+        assert_eq!(nice_number(2.9, true), 2.0);
+        assert_eq!(nice_number(3.0, true), 5.0);
+        assert_eq!(nice_number(6.9, true), 5.0);
+        assert_eq!(nice_number(7.0, true), 10.0);
+        assert_eq!(nice_number(1234.0, true), 1000.0);
+        assert_close(&[nice_number(0.07, true)], &[0.1]);
+    }
+
+    #[test]
+    fn nice_number_rounds_up() {
+        assert_eq!(nice_number(1.0, false), 1.0);
+        assert_eq!(nice_number(1.01, false), 2.0);
+        assert_eq!(nice_number(2.0, false), 2.0);
+        assert_eq!(nice_number(2.5, false), 5.0);
+        assert_eq!(nice_number(5.1, false), 10.0);
+        assert_eq!(nice_number(250.0, false), 500.0);
+        assert_close(&[nice_number(0.003, false)], &[0.005]);
+    }
+
+    #[test]
+    fn nice_number_of_degenerate_values() {
+        for round in [false, true] {
+            assert_eq!(nice_number(0.0, round), 1.0);
+            assert_eq!(nice_number(-5.0, round), 1.0);
+            assert_eq!(nice_number(f64::NAN, round), 1.0);
+            assert_eq!(nice_number(f64::INFINITY, round), 1.0);
+        }
+    }
+
+    #[test]
+    fn nice_ticks_within_range() {
+        let ticks = nice_ticks(AxisRange::new(0.0, 10.0), 5);
+        assert_eq!(ticks.step, 2.0);
+        assert_eq!(ticks.values, [0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+
+        let ticks = nice_ticks(AxisRange::new(0.5, 9.5), 5);
+        assert_eq!(ticks.values, [2.0, 4.0, 6.0, 8.0]);
+
+        let ticks = nice_ticks(AxisRange::new(0.0, 1.0), 5);
+        assert_close(&[ticks.step], &[0.2]);
+        assert_close(&ticks.values, &[0.0, 0.2, 0.4, 0.6, 0.8, 1.0]);
+    }
+
+    #[test]
+    fn nice_ticks_negative_spans() {
+        let ticks = nice_ticks(AxisRange::new(-3.0, 7.0), 5);
+        assert_eq!(ticks.values, [-2.0, 0.0, 2.0, 4.0, 6.0]);
+
+        let ticks = nice_ticks(AxisRange::new(-10.0, -2.0), 5);
+        assert_eq!(ticks.values, [-10.0, -8.0, -6.0, -4.0, -2.0]);
+    }
+
+    #[test]
+    fn nice_ticks_with_few_max_ticks() {
+        let range = AxisRange::new(0.0, 10.0);
+        let two = nice_ticks(range, 2);
+        assert_eq!(two.values, [0.0, 10.0]);
+        assert_eq!(nice_ticks(range, 1), two);
+        assert_eq!(nice_ticks(range, 0), two);
+    }
+
+    #[test]
+    fn nice_ticks_of_degenerate_ranges() {
+        let single = nice_ticks(AxisRange::new(5.0, 5.0), 5);
+        assert_eq!(single.step, 1.0);
+        assert_eq!(single.values, [5.0]);
+
+        // A reversed range has a negative span.
+        assert_eq!(nice_ticks(AxisRange::new(7.0, 3.0), 5).values, [7.0]);
+
+        assert!(nice_ticks(AxisRange::new(f64::NAN, 1.0), 5)
+            .values
+            .is_empty());
+        assert_eq!(nice_ticks(AxisRange::new(0.0, f64::NAN), 5).values, [0.0]);
+        assert!(nice_ticks(AxisRange::new(f64::NEG_INFINITY, 1.0), 5)
+            .values
+            .is_empty());
+        assert_eq!(
+            nice_ticks(AxisRange::new(0.0, f64::INFINITY), 5).values,
+            [0.0]
+        );
+    }
+
+    #[test]
+    fn format_tick_precision() {
+        assert_eq!(format_tick(5.0, 1.0), "5");
+        assert_eq!(format_tick(1_000_000.0, 200_000.0), "1000000");
+        assert_eq!(format_tick(-2.0, 2.0), "-2");
+        assert_eq!(format_tick(2.5, 0.5), "2.5");
+        assert_eq!(format_tick(0.6000000000000001, 0.2), "0.6");
+        assert_eq!(format_tick(0.3, 0.1), "0.3");
+        assert_eq!(format_tick(0.05, 0.05), "0.05");
+        // A step of 0 or less does not have decimals.
+        assert_eq!(format_tick(1.5, 0.0), "2");
+        assert_eq!(format_tick(1.5, -1.0), "2");
+    }
+
+    #[test]
+    fn format_tick_drops_the_sign_of_zero() {
+        assert_eq!(format_tick(-0.0, 1.0), "0");
+        assert_eq!(format_tick(-0.000_000_1, 0.2), "0.0");
+        assert_eq!(format_tick(-0.2, 0.2), "-0.2");
+    }
+
+    #[test]
+    fn data_bounds_of_points() {
+        assert_eq!(data_bounds(&[]), None);
+        assert_eq!(data_bounds(&[(f64::NAN, 1.0), (1.0, f64::INFINITY)]), None);
+        assert_eq!(
+            data_bounds(&[(2.0, 3.0)]),
+            Some((AxisRange::new(2.0, 2.0), AxisRange::new(3.0, 3.0)))
+        );
+        assert_eq!(
+            data_bounds(&[(2.0, 3.0), (f64::NAN, 100.0), (-1.0, 5.0), (4.0, -2.0)]),
+            Some((AxisRange::new(-1.0, 4.0), AxisRange::new(-2.0, 5.0)))
+        );
+    }
+
+    #[test]
+    fn padded_ranges() {
+        assert_eq!(
+            AxisRange::new(0.0, 10.0).padded(0.1),
+            AxisRange::new(-1.0, 11.0)
+        );
+        assert_eq!(
+            AxisRange::new(0.0, 0.0).padded(0.1),
+            AxisRange::new(-1.0, 1.0)
+        );
+        assert_eq!(
+            AxisRange::new(-4.0, -4.0).padded(0.1),
+            AxisRange::new(-6.0, -2.0)
+        );
+    }
+
+    #[test]
+    fn transform_round_trip() {
+        let transform = transform();
+        assert_eq!(transform.to_pixel(5.0, 2.0), (50.0, 80.0));
+        assert_eq!(transform.to_data(50.0, 80.0), (5.0, 2.0));
+        assert_eq!(transform.to_pixel_i32(f64::NAN, 1e300), (0, -1_000_000));
+        assert_eq!(
+            transform.zoom_at(50.0, 50.0, 0.5).x,
+            AxisRange::new(2.5, 7.5)
+        );
+        assert_eq!(
+            transform.pan_by_pixels(10.0, 10.0).y,
+            AxisRange::new(1.0, 11.0)
+        );
+    }
+
+    #[test]
+    fn nearest_point_within_distance() {
+        let transform = transform();
+        let first = [(1.0, 1.0), (5.0, 5.0)];
+        let second = [(5.5, 5.0)];
+        let series = || [first.as_slice(), second.as_slice()];
+        // (5, 5) is at (50, 50), and (5.5, 5) is at (55, 50).
+        assert_eq!(
+            nearest_point(&transform, series(), 54.0, 50.0, 10.0),
+            Some((1, 0))
+        );
+        assert_eq!(
+            nearest_point(&transform, series(), 51.0, 50.0, 10.0),
+            Some((0, 1))
+        );
+        assert_eq!(
+            nearest_point(&transform, series(), 50.0, 40.0, 10.0),
+            Some((0, 1))
+        );
+        assert_eq!(nearest_point(&transform, series(), 50.0, 39.0, 10.0), None);
+    }
+
+    #[test]
+    fn nearest_point_prefers_the_first_of_equal_points() {
+        let transform = transform();
+        let points = [(5.0, 5.0), (5.0, 5.0)];
+        assert_eq!(
+            nearest_point(
+                &transform,
+                [points.as_slice(), points.as_slice()],
+                50.0,
+                50.0,
+                1.0
+            ),
+            Some((0, 0))
+        );
+        assert_eq!(nearest_point(&transform, [], 50.0, 50.0, 1.0), None);
+        assert_eq!(
+            nearest_point(&transform, [[(f64::NAN, 5.0)].as_slice()], 50.0, 50.0, 1.0),
+            None
+        );
+    }
+}
@@ -0,0 +1,39 @@
+// https://learn.microsoft.com/en-us/windows/win32/dataxchg/using-the-clipboard
+
+use super::*;
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+
+/// Replaces the contents of the clipboard with `text`. `owner` becomes the owner of
+/// the clipboard, which is required to put data on it.
+pub fn set_clipboard_text(owner: HWND, text: &str) -> Result<()> {
+    unsafe {
+        let mut chars: Vec<u16> = text.encode_utf16().collect();
+        chars.push(0);
+
+        OpenClipboard(Some(owner))?;
+        let result = (|| {
+            EmptyClipboard()?;
+            let hglobal = GlobalAlloc(GMEM_MOVEABLE, chars.len() * size_of::<u16>())?;
+            let dst = GlobalLock(hglobal) as *mut u16;
+            if dst.is_null() {
+                _ = GlobalFree(Some(hglobal));
+                return Err(windows::core::Error::from_win32());
+            }
+            core::ptr::copy_nonoverlapping(chars.as_ptr(), dst, chars.len());
+            _ = GlobalUnlock(hglobal);
+
+            // On success, the clipboard owns the memory.
+            if let Err(e) = SetClipboardData(CF_UNICODETEXT.0 as u32, Some(HANDLE(hglobal.0))) {
+                _ = GlobalFree(Some(hglobal));
+                return Err(e);
+            }
+            Ok(())
+        })();
+        _ = CloseClipboard();
+        Ok(result?)
+    }
+}
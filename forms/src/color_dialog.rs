@@ -0,0 +1,87 @@
+use super::*;
+use windows::Win32::UI::Controls::Dialogs::*;
+
+/// The color chooser common dialog.
+///
+/// The 16 custom colors that the user can define in the dialog are shared by all
+/// color dialogs on the thread, so they are kept from one call to the next. Use
+/// `ColorDialog::custom_colors` and `ColorDialog::set_custom_colors` to save them
+/// in the application's settings, and restore them.
+pub struct ColorDialog {
+    /// The color that is selected when the dialog opens.
+    pub color: Option<ColorRef>,
+    /// Opens the dialog with the part for defining custom colors shown.
+    pub full_open: bool,
+    /// Hides the button that shows the part for defining custom colors.
+    pub prevent_full_open: bool,
+}
+
+/// The number of custom colors in the color dialog.
+pub const NUM_CUSTOM_COLORS: usize = 16;
+
+thread_local! {
+    static CUSTOM_COLORS: Cell<[COLORREF; NUM_CUSTOM_COLORS]> =
+        const { Cell::new([COLORREF(0x00ff_ffff); NUM_CUSTOM_COLORS]) };
+}
+
+impl Default for ColorDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColorDialog {
+    pub fn new() -> Self {
+        Self {
+            color: None,
+            full_open: false,
+            prevent_full_open: false,
+        }
+    }
+
+    /// Gets the custom colors. Colors that the user has not defined are white.
+    pub fn custom_colors() -> [ColorRef; NUM_CUSTOM_COLORS] {
+        CUSTOM_COLORS.get().map(|c| ColorRef::from_u32_bgr(c.0))
+    }
+
+    /// Sets the custom colors. If there are fewer than 16, the rest are not changed.
+    pub fn set_custom_colors(colors: &[ColorRef]) {
+        let mut custom = CUSTOM_COLORS.get();
+        for (slot, color) in custom.iter_mut().zip(colors) {
+            *slot = (*color).into();
+        }
+        CUSTOM_COLORS.set(custom);
+    }
+
+    /// Shows the dialog. Returns the selected color, or `None` if the user canceled.
+    pub fn show(&self, parent: Option<&Form>) -> Option<ColorRef> {
+        // https://learn.microsoft.com/en-us/windows/win32/api/commdlg/ns-commdlg-choosecolorw-r1
+        let mut custom_colors = CUSTOM_COLORS.get();
+        let mut cc: CHOOSECOLORW = unsafe { zeroed() };
+        cc.lStructSize = size_of_val(&cc) as u32;
+        cc.hwndOwner = parent.map(|f| f.handle()).unwrap_or(HWND(null_mut()));
+        cc.lpCustColors = custom_colors.as_mut_ptr();
+        cc.Flags = CC_ANYCOLOR;
+        if let Some(color) = self.color {
+            cc.rgbResult = color.into();
+            cc.Flags |= CC_RGBINIT;
+        }
+        if self.full_open {
+            cc.Flags |= CC_FULLOPEN;
+        }
+        if self.prevent_full_open {
+            cc.Flags |= CC_PREVENTFULLOPEN;
+        }
+
+        let succeeded = unsafe { ChooseColorW(&mut cc).as_bool() };
+        // The custom colors are kept even if the user cancels, as in other programs.
+        CUSTOM_COLORS.set(custom_colors);
+        if !succeeded {
+            debug!("color dialog canceled: {:?}", unsafe {
+                CommDlgExtendedError()
+            });
+            return None;
+        }
+        Some(ColorRef::from_u32_bgr(cc.rgbResult.0))
+    }
+}
@@ -1 +1,40 @@
 use super::*;
+
+/// Implemented by controls that handle their own `WM_COMMAND` notifications, such as
+/// `EN_CHANGE`. The parent window routes each notification to the control that sent
+/// it, before the app's `command_handler` sees it.
+pub(crate) trait CommandHandlerTrait {
+    /// Returns true if the control handled the notification, so that it should not
+    /// go on to the form's `command_handler`.
+    fn wm_command(&self, code: u32) -> bool;
+}
+
+thread_local! {
+    // key is HWND of the control that sends the notifications
+    static COMMAND_HANDLERS: RefCell<HashMap<isize, Weak<dyn CommandHandlerTrait>>> =
+        RefCell::new(HashMap::new());
+}
+
+pub(crate) fn register_command_handler(hwnd: HWND, handler: Weak<dyn CommandHandlerTrait>) {
+    COMMAND_HANDLERS.with_borrow_mut(|handlers| {
+        handlers.retain(|_, h| h.strong_count() != 0);
+        handlers.insert(hwnd.0 as isize, handler);
+    });
+}
+
+/// Routes a `WM_COMMAND` notification to the control that sent it, if that control
+/// registered a handler.
+pub(crate) fn dispatch_command(hwnd_from: HWND, code: u32) -> bool {
+    let handler = COMMAND_HANDLERS.with_borrow(|handlers| {
+        handlers
+            .get(&(hwnd_from.0 as isize))
+            .and_then(|h| h.upgrade())
+    });
+
+    // As with notifications, the registry is not borrowed while the handler runs.
+    if let Some(handler) = handler {
+        handler.wm_command(code)
+    } else {
+        false
+    }
+}
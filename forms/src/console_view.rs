@@ -0,0 +1,217 @@
+//! An output pane for the output of command-line programs, with support for ANSI
+//! colors. The escape sequence parser is in `console_view::ansi`.
+
+use super::*;
+use ansi::{strip_controls, AnsiColor, AnsiParser, TextStyle};
+
+pub mod ansi;
+
+/// Shows text written by a program, such as the output of a child process. Text is
+/// appended with `write` or `write_bytes`. ANSI SGR escape sequences select colors
+/// and bold, italic, underline and strikethrough; other escape sequences are
+/// removed.
+///
+/// Only the last `max_lines` lines are kept. Lines are not wrapped.
+pub struct ConsoleView {
+    edit: Rc<RichEdit>,
+    parser: RefCell<AnsiParser>,
+    /// The start of a UTF-8 sequence that was split across calls to `write_bytes`.
+    partial_utf8: RefCell<Vec<u8>>,
+    max_lines: Cell<usize>,
+    auto_scroll: Cell<bool>,
+    text_color: Cell<Option<ColorRef>>,
+    back_color: Cell<Option<ColorRef>>,
+    font: RefCell<Option<Rc<Font>>>,
+}
+
+impl core::ops::Deref for ConsoleView {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.edit
+    }
+}
+
+/// The number of lines a new `ConsoleView` keeps.
+pub const DEFAULT_CONSOLE_MAX_LINES: usize = 5000;
+
+impl ConsoleView {
+    pub fn new(parent: &ControlState) -> Rc<ConsoleView> {
+        let style = WS_VSCROLL
+            | WS_HSCROLL
+            | WINDOW_STYLE((ES_AUTOVSCROLL | ES_AUTOHSCROLL | ES_READONLY | ES_NOHIDESEL) as u32);
+        let edit = RichEdit::with_style(parent, style);
+        edit.set_text_limit(i32::MAX as usize);
+        edit.set_word_wrap(false);
+
+        let this = Rc::new(ConsoleView {
+            edit,
+            parser: RefCell::new(AnsiParser::new()),
+            partial_utf8: RefCell::new(Vec::new()),
+            max_lines: Cell::new(DEFAULT_CONSOLE_MAX_LINES),
+            auto_scroll: Cell::new(true),
+            text_color: Cell::new(None),
+            back_color: Cell::new(None),
+            font: RefCell::new(None),
+        });
+        if let Ok(font) = Font::new("Consolas", 16) {
+            this.set_font(font);
+        }
+        this
+    }
+
+    /// The underlying rich edit control.
+    pub fn rich_edit(&self) -> &Rc<RichEdit> {
+        &self.edit
+    }
+
+    pub fn set_font(&self, font: Rc<Font>) {
+        self.edit.set_font(&font);
+        *self.font.borrow_mut() = Some(font);
+    }
+
+    /// Sets the colors of text that has no ANSI colors. `None` uses the system
+    /// colors. This applies to text written after the call.
+    pub fn set_default_colors(&self, text: Option<ColorRef>, background: Option<ColorRef>) {
+        self.text_color.set(text);
+        self.back_color.set(background);
+        self.edit.set_background_color(background);
+    }
+
+    /// Sets the number of lines kept. When there are more, the oldest lines are
+    /// removed.
+    pub fn set_max_lines(&self, max_lines: usize) {
+        self.max_lines.set(max_lines.max(1));
+        self.trim_lines();
+    }
+
+    pub fn max_lines(&self) -> usize {
+        self.max_lines.get()
+    }
+
+    /// If true (the default), the view scrolls to the end when text is written.
+    pub fn set_auto_scroll(&self, value: bool) {
+        self.auto_scroll.set(value);
+    }
+
+    pub fn auto_scroll(&self) -> bool {
+        self.auto_scroll.get()
+    }
+
+    /// Removes all text, and resets the ANSI style.
+    pub fn clear(&self) {
+        self.edit.set_plain_text("");
+        self.parser.borrow_mut().reset();
+        self.partial_utf8.borrow_mut().clear();
+    }
+
+    /// Appends text, which may contain ANSI escape sequences. A sequence may be
+    /// split across calls.
+    pub fn write(&self, text: &str) {
+        let mut runs: Vec<(String, TextStyle)> = Vec::new();
+        self.parser.borrow_mut().feed(text, |run, style| {
+            // The rich edit control ends paragraphs with '\r', so CR LF would give
+            // blank lines.
+            let run = strip_controls(run);
+            if !run.is_empty() {
+                runs.push((run, *style));
+            }
+        });
+        if runs.is_empty() {
+            return;
+        }
+
+        unsafe {
+            SendMessageW(self.handle(), WM_SETREDRAW, Some(WPARAM(0)), None);
+        }
+        for (run, style) in runs.iter() {
+            self.edit.append_text(run, &self.char_format(style));
+        }
+        self.trim_lines();
+        unsafe {
+            SendMessageW(self.handle(), WM_SETREDRAW, Some(WPARAM(1)), None);
+            _ = InvalidateRect(Some(self.handle()), None, true);
+        }
+        if self.auto_scroll.get() {
+            self.edit.scroll_to_end();
+        }
+    }
+
+    /// Appends output that should be UTF-8, such as bytes read from a pipe.
+    /// A multi-byte character may be split across calls. Invalid bytes are shown
+    /// as U+FFFD.
+    pub fn write_bytes(&self, bytes: &[u8]) {
+        let mut partial = self.partial_utf8.borrow_mut();
+        partial.extend_from_slice(bytes);
+        let complete = match core::str::from_utf8(&partial) {
+            Ok(_) => partial.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => {
+                // Invalid bytes somewhere; keep only an incomplete sequence at the end.
+                let tail = incomplete_utf8_tail(&partial);
+                partial.len() - tail
+            }
+        };
+        let rest = partial.split_off(complete);
+        let text = String::from_utf8_lossy(&partial).into_owned();
+        *partial = rest;
+        drop(partial);
+        self.write(&text);
+    }
+
+    fn char_format(&self, style: &TextStyle) -> CharFormat {
+        let mut text_color = style
+            .foreground
+            .map(ansi_color_ref)
+            .or(self.text_color.get());
+        let mut back_color = style
+            .background
+            .map(ansi_color_ref)
+            .or(self.back_color.get());
+        if style.inverse {
+            let text = text_color.unwrap_or(ColorRef::from_sys_color(SysColor::WindowText));
+            let back = back_color.unwrap_or(ColorRef::from_sys_color(SysColor::Window));
+            text_color = Some(back);
+            back_color = Some(text);
+        }
+        CharFormat {
+            text_color,
+            back_color,
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+            strikeout: style.strikethrough,
+        }
+    }
+
+    fn trim_lines(&self) {
+        // The last line is the one being written, and may be empty.
+        let lines = self.edit.line_count();
+        let max_lines = self.max_lines.get() + 1;
+        if lines <= max_lines {
+            return;
+        }
+        if let Some(end) = self.edit.line_start(lines - max_lines) {
+            self.edit.set_selection(0, end as i32);
+            self.edit.replace_selection("");
+            self.edit.set_selection(-1, -1);
+        }
+    }
+}
+
+fn ansi_color_ref(color: AnsiColor) -> ColorRef {
+    let (r, g, b) = color.to_rgb();
+    ColorRef::from_u32_rgb(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+}
+
+/// The length of the incomplete UTF-8 sequence at the end of `bytes`, if any.
+fn incomplete_utf8_tail(bytes: &[u8]) -> usize {
+    for len in 1..=3.min(bytes.len()) {
+        let start = bytes.len() - len;
+        if let Err(e) = core::str::from_utf8(&bytes[start..]) {
+            if e.valid_up_to() == 0 && e.error_len().is_none() {
+                return len;
+            }
+        }
+    }
+    0
+}
@@ -0,0 +1,534 @@
+//! Parsing of ANSI escape sequences in program output. Only SGR ("select graphic
+//! rendition", `ESC [ ... m`) sequences change the style; all other escape
+//! sequences are recognized and removed. None of this needs a window.
+
+/// A color selected by an SGR sequence.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AnsiColor {
+    /// An entry in the 256-color palette. 0-7 are the standard colors, 8-15 the
+    /// bright colors, 16-231 a 6x6x6 color cube and 232-255 a gray ramp.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// The 16 standard colors, as in xterm.
+const STANDARD_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x00, 0x00),
+    (0x00, 0xcd, 0x00),
+    (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee),
+    (0xcd, 0x00, 0xcd),
+    (0x00, 0xcd, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+
+impl AnsiColor {
+    /// Converts the color to red, green and blue components.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            AnsiColor::Rgb(r, g, b) => (r, g, b),
+            AnsiColor::Indexed(i @ 0..=15) => STANDARD_COLORS[i as usize],
+            AnsiColor::Indexed(i @ 16..=231) => {
+                let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+                let i = i - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            AnsiColor::Indexed(i) => {
+                let gray = 8 + (i - 232) * 10;
+                (gray, gray, gray)
+            }
+        }
+    }
+}
+
+/// The character style selected by the SGR sequences seen so far.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct TextStyle {
+    /// `None` is the default text color.
+    pub foreground: Option<AnsiColor>,
+    /// `None` is the default background color.
+    pub background: Option<AnsiColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// The foreground and background colors are swapped when drawn.
+    pub inverse: bool,
+}
+
+impl TextStyle {
+    /// Applies the parameters of one SGR sequence. Unknown parameters are ignored.
+    /// An empty list is the same as `[0]`, which resets the style.
+    pub fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = TextStyle::default();
+            return;
+        }
+        let mut i = 0;
+        while i < params.len() {
+            let p = params[i];
+            i += 1;
+            match p {
+                0 => *self = TextStyle::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                9 => self.strikethrough = true,
+                21 => self.underline = true, // double underline
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.foreground = Some(AnsiColor::Indexed((p - 30) as u8)),
+                38 => self.foreground = extended_color(params, &mut i),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(AnsiColor::Indexed((p - 40) as u8)),
+                48 => self.background = extended_color(params, &mut i),
+                49 => self.background = None,
+                90..=97 => self.foreground = Some(AnsiColor::Indexed((p - 90 + 8) as u8)),
+                100..=107 => self.background = Some(AnsiColor::Indexed((p - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses the rest of a `38` or `48` parameter: either `5;n` or `2;r;g;b`.
+/// `i` is advanced past the parameters that were used. Malformed colors give `None`.
+fn extended_color(params: &[u16], i: &mut usize) -> Option<AnsiColor> {
+    let component = |n: u16| u8::try_from(n).ok();
+    match params.get(*i) {
+        Some(5) => {
+            let index = params.get(*i + 1).copied();
+            *i = (*i + 2).min(params.len());
+            Some(AnsiColor::Indexed(component(index?)?))
+        }
+        Some(2) => {
+            let rgb = params.get(*i + 1..*i + 4);
+            *i = (*i + 4).min(params.len());
+            let rgb = rgb?;
+            Some(AnsiColor::Rgb(
+                component(rgb[0])?,
+                component(rgb[1])?,
+                component(rgb[2])?,
+            ))
+        }
+        _ => {
+            *i = params.len();
+            None
+        }
+    }
+}
+
+/// Sequences with more parameters than this are still removed, but the extra
+/// parameters are ignored.
+const MAX_PARAMS: usize = 32;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State {
+    Ground,
+    /// After `ESC`.
+    Escape,
+    /// After `ESC` and an intermediate character, such as `ESC (`.
+    EscapeIntermediate,
+    /// After `ESC [`.
+    Csi,
+    /// After `ESC ]`, until `BEL` or `ESC \`.
+    Osc,
+    /// After `ESC` within an OSC string.
+    OscEscape,
+}
+
+/// Splits text into runs of plain text and the style of each run.
+///
+/// The parser keeps its state between calls to `feed`, so an escape sequence may be
+/// split across calls, as happens when reading the output of a process in chunks.
+pub struct AnsiParser {
+    state: State,
+    style: TextStyle,
+    params: Vec<u16>,
+    /// The value of the parameter being parsed, if it has any digits yet.
+    current: Option<u16>,
+    /// The CSI sequence has a private marker (`<`, `=`, `>` or `?`), or an
+    /// intermediate character, so it is not SGR.
+    private: bool,
+}
+
+impl Default for AnsiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnsiParser {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            style: TextStyle::default(),
+            params: Vec::new(),
+            current: None,
+            private: false,
+        }
+    }
+
+    /// The style that will be given to the next text.
+    pub fn style(&self) -> TextStyle {
+        self.style
+    }
+
+    /// Forgets any partial escape sequence and resets the style.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Parses `input`, calling `output` for each run of text with its style. Runs
+    /// are slices of `input`, and are never empty. Escape sequences are not
+    /// included in the output; other control characters are.
+    pub fn feed<'a>(&mut self, input: &'a str, mut output: impl FnMut(&'a str, &TextStyle)) {
+        let mut run_start: Option<usize> = None;
+        for (pos, c) in input.char_indices() {
+            if self.state == State::Ground {
+                if c == '\x1b' {
+                    if let Some(start) = run_start.take() {
+                        output(&input[start..pos], &self.style);
+                    }
+                    self.state = State::Escape;
+                } else if run_start.is_none() {
+                    run_start = Some(pos);
+                }
+            } else {
+                self.escape_char(c);
+            }
+        }
+        if let Some(start) = run_start {
+            output(&input[start..], &self.style);
+        }
+    }
+
+    fn escape_char(&mut self, c: char) {
+        match self.state {
+            State::Ground => {}
+            State::Escape => {
+                self.state = match c {
+                    '[' => {
+                        self.params.clear();
+                        self.current = None;
+                        self.private = false;
+                        State::Csi
+                    }
+                    ']' => State::Osc,
+                    ' '..='/' => State::EscapeIntermediate,
+                    '\x1b' => State::Escape,
+                    _ => State::Ground,
+                };
+            }
+            State::EscapeIntermediate => {
+                if !(' '..='/').contains(&c) {
+                    self.state = State::Ground;
+                }
+            }
+            State::Csi => match c {
+                '0'..='9' => {
+                    let digit = c as u16 - '0' as u16;
+                    let value = self.current.unwrap_or(0);
+                    self.current = Some(value.saturating_mul(10).saturating_add(digit));
+                }
+                ';' | ':' => self.end_param(),
+                '<' | '=' | '>' | '?' | ' '..='/' => self.private = true,
+                '@'..='~' => {
+                    self.end_param();
+                    if c == 'm' && !self.private {
+                        self.style.apply_sgr(&self.params);
+                    }
+                    self.state = State::Ground;
+                }
+                '\x1b' => self.state = State::Escape,
+                // Other characters are not valid here; drop the sequence.
+                _ => self.state = State::Ground,
+            },
+            State::Osc => match c {
+                '\x07' => self.state = State::Ground,
+                '\x1b' => self.state = State::OscEscape,
+                _ => {}
+            },
+            State::OscEscape => {
+                self.state = if c == '\\' { State::Ground } else { State::Osc };
+            }
+        }
+    }
+
+    fn end_param(&mut self) {
+        let value = self.current.take().unwrap_or(0);
+        if self.params.len() < MAX_PARAMS {
+            self.params.push(value);
+        }
+    }
+}
+
+/// Removes the control characters from a run of text, other than line feeds and
+/// tabs. Carriage returns are removed too, so CR LF line ends become LF, and a lone
+/// CR does not go back to the start of the line.
+pub fn strip_controls(run: &str) -> String {
+    run.chars()
+        .filter(|&c| c == '\n' || c == '\t' || !c.is_control())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut AnsiParser, input: &str) -> Vec<(String, TextStyle)> {
+        let mut runs = Vec::new();
+        parser.feed(input, |run, style| runs.push((run.to_string(), *style)));
+        runs
+    }
+
+    fn parse(input: &str) -> Vec<(String, TextStyle)> {
+        feed(&mut AnsiParser::new(), input)
+    }
+
+    fn sgr(params: &[u16]) -> TextStyle {
+        let mut style = TextStyle::default();
+        style.apply_sgr(params);
+        style
+    }
+
+    fn fg(index: u8) -> TextStyle {
+        TextStyle {
+            foreground: Some(AnsiColor::Indexed(index)),
+            ..Default::default()
+        }
+    }
+
+    const BOLD: TextStyle = TextStyle {
+        foreground: None,
+        background: None,
+        bold: true,
+        italic: false,
+        underline: false,
+        strikethrough: false,
+        inverse: false,
+    };
+
+    #[test]
+    fn plain_text_is_one_run() {
+        assert_eq!(parse("hello"), [("hello".into(), TextStyle::default())]);
+        assert_eq!(parse(""), []);
+    }
+
+    #[test]
+    fn sgr_bold_and_reset() {
+        assert_eq!(
+            parse("a\x1b[1mb\x1b[0mc\x1b[1md\x1b[me"),
+            [
+                ("a".into(), TextStyle::default()),
+                ("b".into(), BOLD),
+                ("c".into(), TextStyle::default()),
+                ("d".into(), BOLD),
+                ("e".into(), TextStyle::default()),
+            ]
+        );
+        assert!(sgr(&[1, 3, 4, 7, 9, 22, 23]).underline);
+        assert_eq!(sgr(&[1, 3, 22, 23]), TextStyle::default());
+        assert_eq!(sgr(&[4, 7, 9, 24, 27, 29]), TextStyle::default());
+        assert_eq!(sgr(&[1, 31, 0]), TextStyle::default());
+        assert_eq!(sgr(&[]), TextStyle::default());
+    }
+
+    #[test]
+    fn sgr_colors() {
+        let style = sgr(&[31, 42]);
+        assert_eq!(style.foreground, Some(AnsiColor::Indexed(1)));
+        assert_eq!(style.background, Some(AnsiColor::Indexed(2)));
+
+        let style = sgr(&[97, 100]);
+        assert_eq!(style.foreground, Some(AnsiColor::Indexed(15)));
+        assert_eq!(style.background, Some(AnsiColor::Indexed(8)));
+
+        assert_eq!(sgr(&[31, 42, 39, 49]), TextStyle::default());
+        // Unknown parameters are ignored.
+        assert_eq!(sgr(&[31, 50, 1000]), fg(1));
+        assert_eq!(parse("\x1b[32mx"), [("x".into(), fg(2))]);
+    }
+
+    #[test]
+    fn indexed_color() {
+        assert_eq!(sgr(&[38, 5, 208]), fg(208));
+        assert_eq!(sgr(&[48, 5, 17]).background, Some(AnsiColor::Indexed(17)));
+        // The parameters after the color still apply.
+        assert_eq!(
+            sgr(&[38, 5, 1, 1]),
+            TextStyle {
+                bold: true,
+                ..fg(1)
+            }
+        );
+        assert_eq!(parse("\x1b[38;5;208mx"), [("x".into(), fg(208))]);
+    }
+
+    #[test]
+    fn rgb_color() {
+        assert_eq!(
+            sgr(&[38, 2, 10, 20, 30]).foreground,
+            Some(AnsiColor::Rgb(10, 20, 30))
+        );
+        let style = sgr(&[48, 2, 1, 2, 3, 4]);
+        assert_eq!(style.background, Some(AnsiColor::Rgb(1, 2, 3)));
+        assert!(style.underline);
+        // Colons separate parameters, as in `ESC [ 38:2:r:g:b m`.
+        assert_eq!(
+            parse("\x1b[38:2:1:2:3mx")[0].1.foreground,
+            Some(AnsiColor::Rgb(1, 2, 3))
+        );
+    }
+
+    #[test]
+    fn extended_color_malformed() {
+        let cases: [&[u16]; 6] = [&[], &[5], &[5, 256], &[2, 1, 2], &[2, 1, 2, 300], &[7, 1]];
+        for params in cases {
+            let mut i = 0;
+            assert_eq!(extended_color(params, &mut i), None, "{params:?}");
+            assert!(i <= params.len(), "{params:?}");
+        }
+        // An unknown color kind uses up the rest of the parameters.
+        let mut i = 0;
+        assert_eq!(extended_color(&[7, 1], &mut i), None);
+        assert_eq!(i, 2);
+        // A component out of range still skips the whole color.
+        let mut i = 0;
+        assert_eq!(extended_color(&[2, 1, 300, 3, 1], &mut i), None);
+        assert_eq!(i, 4);
+
+        let mut i = 1;
+        assert_eq!(
+            extended_color(&[38, 5, 9, 1], &mut i),
+            Some(AnsiColor::Indexed(9))
+        );
+        assert_eq!(i, 3);
+        // A malformed color clears the color that was set.
+        assert_eq!(sgr(&[31, 38, 5]), TextStyle::default());
+        assert_eq!(sgr(&[38, 2, 1, 300, 3, 1]), BOLD);
+    }
+
+    #[test]
+    fn escape_split_across_writes() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(
+            feed(&mut parser, "ab\x1b[3"),
+            [("ab".into(), TextStyle::default())]
+        );
+        assert_eq!(feed(&mut parser, "1mcd"), [("cd".into(), fg(1))]);
+
+        let mut parser = AnsiParser::new();
+        assert_eq!(feed(&mut parser, "\x1b"), []);
+        assert_eq!(feed(&mut parser, "["), []);
+        assert_eq!(feed(&mut parser, "1;"), []);
+        assert_eq!(
+            feed(&mut parser, "38;5;2mx"),
+            [(
+                "x".into(),
+                TextStyle {
+                    bold: true,
+                    ..fg(2)
+                }
+            )]
+        );
+        assert_eq!(
+            parser.style(),
+            TextStyle {
+                bold: true,
+                ..fg(2)
+            }
+        );
+
+        parser.reset();
+        assert_eq!(parser.style(), TextStyle::default());
+    }
+
+    #[test]
+    fn reset_forgets_a_partial_sequence() {
+        let mut parser = AnsiParser::new();
+        feed(&mut parser, "\x1b[1");
+        parser.reset();
+        assert_eq!(
+            feed(&mut parser, "mx"),
+            [("mx".into(), TextStyle::default())]
+        );
+    }
+
+    #[test]
+    fn other_sequences_are_removed() {
+        let plain = |text: &str| {
+            parse(text)
+                .into_iter()
+                .map(|(run, style)| {
+                    assert_eq!(style, TextStyle::default());
+                    run
+                })
+                .collect::<String>()
+        };
+        assert_eq!(plain("a\x1b[2Kb"), "ab");
+        assert_eq!(plain("a\x1b[?25lb"), "ab");
+        // A private marker means that `m` is not SGR.
+        assert_eq!(plain("\x1b[>1mx"), "x");
+        assert_eq!(plain("\x1b]0;title\x07x"), "x");
+        assert_eq!(plain("\x1b]0;title\x1b\\x"), "x");
+        assert_eq!(plain("\x1b(Bx"), "x");
+        assert_eq!(plain("\x1b7x"), "x");
+    }
+
+    #[test]
+    fn malformed_escapes_are_dropped() {
+        // An invalid character ends the sequence, and is dropped with it.
+        assert_eq!(parse("\x1b[1\nx"), [("x".into(), TextStyle::default())]);
+        // A new escape starts over.
+        assert_eq!(parse("\x1b[31\x1b[1mx"), [("x".into(), BOLD)]);
+        // Extra parameters are ignored.
+        let many = format!("\x1b[{}1mx", "0;".repeat(MAX_PARAMS));
+        assert_eq!(parse(&many), [("x".into(), TextStyle::default())]);
+        // Huge numbers saturate.
+        assert_eq!(parse("\x1b[99999999mx")[0].1, TextStyle::default());
+    }
+
+    #[test]
+    fn parser_keeps_control_characters() {
+        assert_eq!(
+            parse("a\r\nb\tc\x07"),
+            [("a\r\nb\tc\x07".into(), TextStyle::default())]
+        );
+    }
+
+    #[test]
+    fn strip_controls_drops_carriage_returns() {
+        assert_eq!(strip_controls("a\r\nb"), "a\nb");
+        assert_eq!(strip_controls("50%\r100%"), "50%100%");
+        assert_eq!(strip_controls("a\tb\x07\x08c\x7f"), "a\tbc");
+        assert_eq!(strip_controls("é ü"), "é ü");
+        assert_eq!(strip_controls("\r"), "");
+    }
+
+    #[test]
+    fn palette_to_rgb() {
+        assert_eq!(AnsiColor::Indexed(1).to_rgb(), (0xcd, 0, 0));
+        assert_eq!(AnsiColor::Indexed(15).to_rgb(), (0xff, 0xff, 0xff));
+        assert_eq!(AnsiColor::Indexed(16).to_rgb(), (0, 0, 0));
+        assert_eq!(AnsiColor::Indexed(17).to_rgb(), (0, 0, 95));
+        assert_eq!(AnsiColor::Indexed(196).to_rgb(), (255, 0, 0));
+        assert_eq!(AnsiColor::Indexed(231).to_rgb(), (255, 255, 255));
+        assert_eq!(AnsiColor::Indexed(232).to_rgb(), (8, 8, 8));
+        assert_eq!(AnsiColor::Indexed(255).to_rgb(), (238, 238, 238));
+        assert_eq!(AnsiColor::Rgb(1, 2, 3).to_rgb(), (1, 2, 3));
+    }
+}
@@ -158,6 +158,22 @@ impl ControlState {
         }
     }
 
+    /// Shows `text` when the mouse hovers over the control. The text may contain line
+    /// breaks. See `Form::set_tooltip_options` for balloon style and delays.
+    pub fn set_tooltip(&self, text: &str) {
+        self.check_thread();
+        add_tool(self.hwnd, self.tooltip_owner(), Some(text));
+    }
+
+    pub fn clear_tooltip(&self) {
+        self.check_thread();
+        remove_tool(self.hwnd, self.tooltip_owner());
+    }
+
+    fn tooltip_owner(&self) -> HWND {
+        unsafe { GetParent(self.hwnd).unwrap_or(self.hwnd) }
+    }
+
     pub fn invalidate_all(&self) {
         unsafe {
             _ = InvalidateRect(Some(self.hwnd), None, true);
@@ -3,6 +3,9 @@ use crate::gdi::dc::Dc;
 use core::mem::MaybeUninit;
 use std::sync::Once;
 use windows::core::w;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    ReleaseCapture, SetCapture, SetFocus, TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT,
+};
 
 pub struct CustomControl<Inner>
 where
@@ -11,6 +14,12 @@ where
     control: ControlState,
     inner: Inner,
 
+    tooltip_registered: Cell<bool>,
+    tooltip_region: Cell<Option<usize>>,
+    tooltip_text: RefCell<WCString>,
+
+    tracking_mouse: Cell<bool>,
+
     bouncer: MaybeUninit<Bouncer>,
 }
 
@@ -48,6 +57,10 @@ where
             let mut me = Rc::new(Self {
                 control: ControlState::new(HWND(null_mut())),
                 inner,
+                tooltip_registered: Cell::new(false),
+                tooltip_region: Cell::new(None),
+                tooltip_text: RefCell::new(WCString::new()),
+                tracking_mouse: Cell::new(false),
                 bouncer: MaybeUninit::zeroed(),
             });
             let only_me = Rc::get_mut(&mut me).unwrap();
@@ -81,10 +94,92 @@ where
     }
 }
 
+impl<Inner> CustomControl<Inner>
+where
+    Inner: CustomInner,
+{
+    pub fn inner(&self) -> &Inner {
+        &self.inner
+    }
+}
+
 pub trait CustomInner: Sized {
     fn paint(&self, control: &CustomControl<Self>, dc: &Dc, rect: &Rect) {}
     fn mouse_move(&self, control: &CustomControl<Self>, pt: POINT) {}
     fn mouse_leave(&self, control: &CustomControl<Self>) {}
+
+    /// The left button was pressed. See `captures_mouse`.
+    fn mouse_down(&self, control: &CustomControl<Self>, pt: POINT) {}
+    fn mouse_up(&self, control: &CustomControl<Self>, pt: POINT) {}
+
+    /// Returns true if a click should give the control the focus, and capture the
+    /// mouse until the button is released, for controls that can be dragged in or
+    /// used with the keyboard. Otherwise the clicks also go to `DefWindowProc`.
+    fn captures_mouse(&self) -> bool {
+        false
+    }
+
+    /// `delta` is a multiple of `WHEEL_DELTA` (120) per notch; positive values
+    /// scroll up.
+    fn mouse_wheel(&self, control: &CustomControl<Self>, delta: i16) {}
+
+    /// Returns true if the key was handled.
+    fn key_down(&self, control: &CustomControl<Self>, key: u16) -> bool {
+        false
+    }
+
+    /// `code` is one of the `SB_*` values of `WM_VSCROLL`.
+    fn vertical_scroll(&self, control: &CustomControl<Self>, code: u16) {}
+
+    fn size(&self, control: &CustomControl<Self>, width: i32, height: i32) {}
+    fn focus_changed(&self, control: &CustomControl<Self>, has_focus: bool) {}
+
+    /// The `DLGC_*` flags for the keys that the control handles itself, rather
+    /// than letting the form use them for dialog navigation.
+    fn dialog_code(&self, control: &CustomControl<Self>) -> u32 {
+        0
+    }
+
+    /// Returns the tooltip for the part of the control under `pt`, if any.
+    fn tooltip_hit_test(&self, control: &CustomControl<Self>, pt: POINT) -> Option<CustomTooltip> {
+        None
+    }
+}
+
+/// A tooltip for one region of a `CustomControl`. See `CustomInner::tooltip_hit_test`.
+pub struct CustomTooltip {
+    /// Identifies the region. When the mouse moves to a region with a different id,
+    /// the old tooltip is hidden and the new one is shown after the usual delay.
+    pub region: usize,
+    pub text: String,
+}
+
+impl<Inner> CustomControl<Inner>
+where
+    Inner: CustomInner,
+{
+    fn update_tooltip(&self, hwnd: HWND, pt: POINT) {
+        let hit = self.inner.tooltip_hit_test(self, pt);
+        let region = hit.as_ref().map(|hit| hit.region);
+        if region == self.tooltip_region.get() {
+            return;
+        }
+
+        self.tooltip_region.set(region);
+        *self.tooltip_text.borrow_mut() = match hit {
+            Some(hit) => WCString::from_str_truncate(hit.text),
+            None => WCString::new(),
+        };
+
+        // The tooltip window is not created until some region actually has a tooltip.
+        if region.is_some() && !self.tooltip_registered.get() {
+            add_tool(hwnd, hwnd, None);
+            self.tooltip_registered.set(true);
+        }
+        if self.tooltip_registered.get() {
+            reset_tooltip(hwnd);
+        }
+    }
 }
 
 static REGISTER_CLASS_ONCE: Once = Once::new();
@@ -175,13 +270,97 @@ where
                 WM_MOUSEMOVE => {
                     let x = get_x_lparam(lparam) as i32;
                     let y = get_y_lparam(lparam) as i32;
+                    // WM_MOUSELEAVE is only sent after asking for it.
+                    if !self.tracking_mouse.get() {
+                        let mut tme = TRACKMOUSEEVENT {
+                            cbSize: size_of::<TRACKMOUSEEVENT>() as u32,
+                            dwFlags: TME_LEAVE,
+                            hwndTrack: hwnd,
+                            dwHoverTime: 0,
+                        };
+                        if TrackMouseEvent(&mut tme).is_ok() {
+                            self.tracking_mouse.set(true);
+                        }
+                    }
                     self.inner.mouse_move(self, POINT { x, y });
+                    self.update_tooltip(hwnd, POINT { x, y });
                 }
 
                 WM_MOUSELEAVE => {
+                    self.tracking_mouse.set(false);
                     self.inner.mouse_leave(self);
                 }
 
+                WM_LBUTTONDOWN => {
+                    let pt = POINT {
+                        x: get_x_lparam(lparam) as i32,
+                        y: get_y_lparam(lparam) as i32,
+                    };
+                    let captures = self.inner.captures_mouse();
+                    if captures {
+                        _ = SetFocus(Some(hwnd));
+                        SetCapture(hwnd);
+                    }
+                    self.inner.mouse_down(self, pt);
+                    if captures {
+                        return LRESULT(0);
+                    }
+                }
+
+                WM_LBUTTONUP => {
+                    let pt = POINT {
+                        x: get_x_lparam(lparam) as i32,
+                        y: get_y_lparam(lparam) as i32,
+                    };
+                    let captures = self.inner.captures_mouse();
+                    if captures {
+                        _ = ReleaseCapture();
+                    }
+                    self.inner.mouse_up(self, pt);
+                    if captures {
+                        return LRESULT(0);
+                    }
+                }
+
+                WM_MOUSEWHEEL => {
+                    self.inner.mouse_wheel(self, (wparam.0 >> 16) as i16);
+                    return LRESULT(0);
+                }
+
+                WM_KEYDOWN if self.inner.key_down(self, wparam.0 as u16) => {
+                    return LRESULT(0);
+                }
+
+                WM_VSCROLL => {
+                    self.inner.vertical_scroll(self, wparam.0 as u16);
+                    return LRESULT(0);
+                }
+
+                WM_SIZE => {
+                    let width = (lparam.0 & 0xffff) as i32;
+                    let height = ((lparam.0 >> 16) & 0xffff) as i32;
+                    self.inner.size(self, width, height);
+                }
+
+                WM_SETFOCUS | WM_KILLFOCUS => {
+                    self.inner.focus_changed(self, message == WM_SETFOCUS);
+                }
+
+                WM_GETDLGCODE => {
+                    return LRESULT(self.inner.dialog_code(self) as isize);
+                }
+
+                WM_NOTIFY => {
+                    let nmhdr = lparam.0 as *mut NMHDR;
+                    // https://learn.microsoft.com/en-us/windows/win32/controls/ttn-getdispinfo
+                    if (*nmhdr).code == TTN_GETDISPINFOW && (*nmhdr).idFrom == hwnd.0 as usize {
+                        let info = &mut *(nmhdr as *mut NMTTDISPINFOW);
+                        // The tooltip copies the text before this returns.
+                        info.lpszText = PWSTR(self.tooltip_text.borrow().as_ptr() as *mut u16);
+                        return LRESULT(0);
+                    }
+                }
+
                 _ => {}
             }
 
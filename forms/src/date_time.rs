@@ -0,0 +1,306 @@
+//! A plain calendar date and time, used by `DateTimePicker` and `MonthCalendar`.
+
+use super::*;
+use core::fmt;
+use windows::Win32::System::SystemInformation::GetLocalTime;
+
+/// A calendar date and time of day, with no time zone attached.
+///
+/// Fields are ordered from most to least significant, so the derived `Ord` is
+/// chronological order.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct DateTime {
+    pub year: u16,
+    /// 1 (January) through 12 (December)
+    pub month: u8,
+    /// 1 through 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+/// The day of the week. The values match `SYSTEMTIME::wDayOfWeek`.
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Weekday {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+}
+
+impl DateTime {
+    /// The current local date and time.
+    pub fn now() -> Self {
+        unsafe { GetLocalTime().into() }
+    }
+
+    /// Midnight at the start of the given date.
+    pub const fn date(year: u16, month: u8, day: u8) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            millisecond: 0,
+        }
+    }
+
+    pub const fn with_time(self, hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            hour,
+            minute,
+            second,
+            millisecond: 0,
+            ..self
+        }
+    }
+
+    /// The same date, at midnight.
+    pub const fn without_time(self) -> Self {
+        Self::date(self.year, self.month, self.day)
+    }
+
+    pub const fn is_leap_year(year: u16) -> bool {
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+    }
+
+    /// Returns 0 if `month` is not in `1..=12`.
+    pub const fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if Self::is_leap_year(year) => 29,
+            2 => 28,
+            _ => 0,
+        }
+    }
+
+    /// Checks that every field is in range. `SYSTEMTIME` cannot represent years
+    /// before 1601, so those are rejected too.
+    pub const fn is_valid(&self) -> bool {
+        self.year >= 1601
+            && self.year <= 30827
+            && self.month >= 1
+            && self.month <= 12
+            && self.day >= 1
+            && self.day <= Self::days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+            && self.millisecond < 1000
+    }
+
+    /// Computes the day of the week, using the proleptic Gregorian calendar.
+    pub const fn weekday(&self) -> Weekday {
+        // Sakamoto's method.
+        const T: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let y = if self.month < 3 {
+            self.year as u32 - 1
+        } else {
+            self.year as u32
+        };
+        let m = if self.month >= 1 && self.month <= 12 {
+            self.month as usize - 1
+        } else {
+            0
+        };
+        let dow = (y + y / 4 - y / 100 + y / 400 + T[m] as u32 + self.day as u32) % 7;
+        match dow {
+            0 => Weekday::Sunday,
+            1 => Weekday::Monday,
+            2 => Weekday::Tuesday,
+            3 => Weekday::Wednesday,
+            4 => Weekday::Thursday,
+            5 => Weekday::Friday,
+            _ => Weekday::Saturday,
+        }
+    }
+}
+
+impl From<DateTime> for SYSTEMTIME {
+    fn from(dt: DateTime) -> SYSTEMTIME {
+        SYSTEMTIME {
+            wYear: dt.year,
+            wMonth: dt.month as u16,
+            wDayOfWeek: dt.weekday() as u16,
+            wDay: dt.day as u16,
+            wHour: dt.hour as u16,
+            wMinute: dt.minute as u16,
+            wSecond: dt.second as u16,
+            wMilliseconds: dt.millisecond,
+        }
+    }
+}
+
+/// `wDayOfWeek` is ignored, since it is derived from the date.
+impl From<SYSTEMTIME> for DateTime {
+    fn from(st: SYSTEMTIME) -> DateTime {
+        DateTime {
+            year: st.wYear,
+            month: st.wMonth as u8,
+            day: st.wDay as u8,
+            hour: st.wHour as u8,
+            minute: st.wMinute as u8,
+            second: st.wSecond as u8,
+            millisecond: st.wMilliseconds,
+        }
+    }
+}
+
+/// Formats as `YYYY-MM-DD HH:MM:SS`, with `.mmm` appended if there are milliseconds.
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.millisecond != 0 {
+            write!(f, ".{:03}", self.millisecond)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the `[min, max]` array and `GDTR_*` flags used by `DTM_SETRANGE` and
+/// `MCM_SETRANGE`.
+pub(crate) fn systemtime_range(
+    min: Option<DateTime>,
+    max: Option<DateTime>,
+) -> (u32, [SYSTEMTIME; 2]) {
+    let mut flags = 0;
+    let mut range: [SYSTEMTIME; 2] = Default::default();
+    if let Some(min) = min {
+        flags |= GDTR_MIN;
+        range[0] = min.into();
+    }
+    if let Some(max) = max {
+        flags |= GDTR_MAX;
+        range[1] = max.into();
+    }
+    (flags, range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weekdays() {
+        let cases = [
+            (1601, 1, 1, Weekday::Monday),
+            (1900, 2, 28, Weekday::Wednesday),
+            (1900, 3, 1, Weekday::Thursday),
+            (1970, 1, 1, Weekday::Thursday),
+            (2000, 2, 29, Weekday::Tuesday),
+            (2000, 3, 1, Weekday::Wednesday),
+            (2023, 12, 31, Weekday::Sunday),
+            (2024, 2, 29, Weekday::Thursday),
+            (2100, 2, 28, Weekday::Sunday),
+            (2100, 3, 1, Weekday::Monday),
+        ];
+        for (year, month, day, weekday) in cases {
+            assert_eq!(
+                DateTime::date(year, month, day).weekday(),
+                weekday,
+                "{year}-{month}-{day}"
+            );
+        }
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(DateTime::is_leap_year(2024));
+        assert!(DateTime::is_leap_year(2000));
+        assert!(!DateTime::is_leap_year(1900));
+        assert!(!DateTime::is_leap_year(2100));
+        assert!(!DateTime::is_leap_year(2023));
+        assert_eq!(DateTime::days_in_month(2000, 2), 29);
+        assert_eq!(DateTime::days_in_month(1900, 2), 28);
+        assert_eq!(DateTime::days_in_month(2023, 13), 0);
+    }
+
+    #[test]
+    fn validity() {
+        assert!(DateTime::date(2000, 2, 29).is_valid());
+        assert!(!DateTime::date(1900, 2, 29).is_valid());
+        assert!(!DateTime::date(1600, 12, 31).is_valid());
+        assert!(DateTime::date(1601, 1, 1).is_valid());
+        assert!(!DateTime::date(2023, 4, 31).is_valid());
+        assert!(!DateTime::date(2023, 1, 1).with_time(24, 0, 0).is_valid());
+    }
+
+    #[test]
+    fn systemtime_round_trip() {
+        let dates = [
+            DateTime::date(1601, 1, 1),
+            DateTime::date(1900, 3, 1).with_time(23, 59, 59),
+            DateTime {
+                millisecond: 999,
+                ..DateTime::date(2000, 2, 29).with_time(12, 30, 15)
+            },
+            DateTime::date(2100, 12, 31).with_time(0, 0, 1),
+        ];
+        for date in dates {
+            let st: SYSTEMTIME = date.into();
+            assert_eq!(st.wDayOfWeek, date.weekday() as u16);
+            assert_eq!(DateTime::from(st), date);
+        }
+
+        let st: SYSTEMTIME = DateTime::date(2024, 2, 29).with_time(8, 5, 3).into();
+        assert_eq!(
+            (st.wYear, st.wMonth, st.wDay, st.wDayOfWeek),
+            (2024, 2, 29, Weekday::Thursday as u16)
+        );
+        assert_eq!((st.wHour, st.wMinute, st.wSecond), (8, 5, 3));
+    }
+
+    #[test]
+    fn from_systemtime_ignores_weekday() {
+        let st = SYSTEMTIME {
+            wYear: 2000,
+            wMonth: 2,
+            wDay: 29,
+            wDayOfWeek: 6,
+            ..Default::default()
+        };
+        let date = DateTime::from(st);
+        assert_eq!(date, DateTime::date(2000, 2, 29));
+        assert_eq!(SYSTEMTIME::from(date).wDayOfWeek, 2);
+    }
+
+    #[test]
+    fn range_flags() {
+        let min = DateTime::date(2000, 1, 1);
+        let max = DateTime::date(2100, 3, 1);
+        let (flags, range) = systemtime_range(Some(min), Some(max));
+        assert_eq!(flags, GDTR_MIN | GDTR_MAX);
+        assert_eq!(DateTime::from(range[0]), min);
+        assert_eq!(DateTime::from(range[1]), max);
+        let (flags, range) = systemtime_range(None, Some(max));
+        assert_eq!(flags, GDTR_MAX);
+        assert_eq!(range[0], SYSTEMTIME::default());
+        assert_eq!(systemtime_range(None, None).0, 0);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            DateTime::date(2024, 2, 9).with_time(7, 5, 0).to_string(),
+            "2024-02-09 07:05:00"
+        );
+        let date = DateTime {
+            millisecond: 42,
+            ..DateTime::date(1999, 12, 31)
+        };
+        assert_eq!(date.to_string(), "1999-12-31 00:00:00.042");
+    }
+}
@@ -0,0 +1,211 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/date-and-time-picker-controls
+
+use super::*;
+use std::cell::OnceCell;
+
+pub struct DateTimePicker {
+    control: ControlState,
+    value_changed_handler: OnceCell<Box<dyn Fn(Option<DateTime>)>>,
+}
+
+impl core::ops::Deref for DateTimePicker {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+/// Which parts of the value the picker shows and edits.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum DateTimePickerFormat {
+    /// The locale's short date format, with a drop-down calendar.
+    #[default]
+    ShortDate,
+    /// The locale's long date format, with a drop-down calendar.
+    LongDate,
+    /// The locale's time format.
+    Time,
+    /// Both date and time, as `yyyy-MM-dd HH:mm:ss`.
+    DateTime,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DateTimePickerOptions {
+    pub format: DateTimePickerFormat,
+    /// Shows a check box. When it is cleared, the control has no value.
+    pub show_none: bool,
+    /// Uses an up-down control to edit the value, instead of a drop-down calendar.
+    pub up_down: bool,
+    pub right_align: bool,
+}
+
+const DATE_TIME_FORMAT: &str = "yyyy-MM-dd HH:mm:ss";
+
+impl DateTimePicker {
+    pub fn new(parent: &ControlState) -> Rc<Self> {
+        Self::new_with_options(parent, &Default::default())
+    }
+
+    pub fn new_with_options(parent: &ControlState, options: &DateTimePickerOptions) -> Rc<Self> {
+        unsafe {
+            let mut style = WS_CHILD | WS_VISIBLE | WS_TABSTOP;
+            style.0 |= match options.format {
+                DateTimePickerFormat::ShortDate => DTS_SHORTDATECENTURYFORMAT,
+                DateTimePickerFormat::LongDate => DTS_LONGDATEFORMAT,
+                DateTimePickerFormat::Time => DTS_TIMEFORMAT,
+                DateTimePickerFormat::DateTime => DTS_SHORTDATECENTURYFORMAT,
+            };
+            if options.show_none {
+                style.0 |= DTS_SHOWNONE;
+            }
+            if options.up_down {
+                style.0 |= DTS_UPDOWN;
+            }
+            if options.right_align {
+                style.0 |= DTS_RIGHTALIGN;
+            }
+
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                DATETIMEPICK_CLASSW,
+                PCWSTR::null(),
+                style,
+                0,
+                0,
+                0,
+                0,
+                Some(parent.handle()),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            let this = Rc::new(DateTimePicker {
+                control: ControlState::new(hwnd),
+                value_changed_handler: OnceCell::new(),
+            });
+
+            if options.format == DateTimePickerFormat::DateTime {
+                this.set_format(Some(DATE_TIME_FORMAT));
+            }
+
+            register_notify_handler(hwnd, Rc::downgrade(&this) as Weak<dyn NotifyHandlerTrait>);
+
+            this
+        }
+    }
+
+    /// Gets the current value. Returns `None` if the control was created with
+    /// `show_none` and its check box is cleared.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/dtm-getsystemtime>
+    pub fn get_value(&self) -> Option<DateTime> {
+        unsafe {
+            let mut st: SYSTEMTIME = zeroed();
+            let result = SendMessageW(
+                self.handle(),
+                DTM_GETSYSTEMTIME,
+                None,
+                Some(LPARAM(&mut st as *mut SYSTEMTIME as isize)),
+            );
+            if result.0 == GDT_VALID.0 as isize {
+                Some(DateTime::from(st))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Sets the current value. `None` clears the check box, which is only possible
+    /// if the control was created with `show_none`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/dtm-setsystemtime>
+    pub fn set_value(&self, value: Option<DateTime>) {
+        unsafe {
+            let st: SYSTEMTIME = value.map(SYSTEMTIME::from).unwrap_or_default();
+            let flags = if value.is_some() { GDT_VALID } else { GDT_NONE };
+            if SendMessageW(
+                self.handle(),
+                DTM_SETSYSTEMTIME,
+                Some(WPARAM(flags.0 as usize)),
+                Some(LPARAM(&st as *const SYSTEMTIME as isize)),
+            )
+            .0 == 0
+            {
+                warn!("DTM_SETSYSTEMTIME failed, value: {:?}", value);
+            }
+        }
+    }
+
+    /// Limits the values that the user can choose. `None` leaves that end of the
+    /// range unbounded.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/dtm-setrange>
+    pub fn set_range(&self, min: Option<DateTime>, max: Option<DateTime>) {
+        unsafe {
+            let (flags, range) = systemtime_range(min, max);
+            SendMessageW(
+                self.handle(),
+                DTM_SETRANGE,
+                Some(WPARAM(flags as usize)),
+                Some(LPARAM(range.as_ptr() as isize)),
+            );
+        }
+    }
+
+    /// Sets a custom display format, such as `"dd MMM yyyy HH:mm"`. `None` restores
+    /// the format that the control was created with.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/date-and-time-picker-controls#format-strings>
+    pub fn set_format(&self, format: Option<&str>) {
+        unsafe {
+            let format_wstr: WCString;
+            let mut format_ptr: *const u16 = null();
+            if let Some(format) = format {
+                format_wstr = WCString::from_str_truncate(format);
+                format_ptr = format_wstr.as_ptr();
+            }
+            SendMessageW(
+                self.handle(),
+                DTM_SETFORMATW,
+                None,
+                Some(LPARAM(format_ptr as isize)),
+            );
+        }
+    }
+
+    /// Called when the user changes the value, or checks/clears the check box.
+    pub fn value_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(Option<DateTime>) + 'static,
+    {
+        let result = self.value_changed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call value_changed_handler() more than once"
+        );
+    }
+}
+
+impl NotifyHandlerTrait for DateTimePicker {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/dtn-datetimechange
+            DTN_DATETIMECHANGE => {
+                let change = &*(nmhdr as *const NMDATETIMECHANGE);
+                let value = if change.dwFlags == GDT_VALID {
+                    Some(DateTime::from(change.st))
+                } else {
+                    None
+                };
+                trace!("DTN_DATETIMECHANGE: {:?}", value);
+                if let Some(handler) = self.value_changed_handler.get() {
+                    handler(value);
+                }
+            }
+            _ => {}
+        }
+        NotifyResult::NotConsumed
+    }
+}
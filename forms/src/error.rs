@@ -5,6 +5,10 @@ pub enum Error {
     Windows(WIN32_ERROR),
     ItemDeleted,
     ParentDeleted,
+    /// Image data, such as an ICO or BMP file, could not be decoded.
+    InvalidImage(&'static str),
+    /// An error returned by a function of the `windows` crate.
+    Core(windows::core::Error),
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -14,3 +18,23 @@ impl From<WIN32_ERROR> for Error {
         Self::Windows(e)
     }
 }
+
+impl From<windows::core::Error> for Error {
+    fn from(e: windows::core::Error) -> Self {
+        Self::Core(e)
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Windows(e) => write!(f, "{}", windows::core::Error::from(*e).message()),
+            Self::ItemDeleted => f.write_str("The item has been deleted."),
+            Self::ParentDeleted => f.write_str("The parent window has been deleted."),
+            Self::InvalidImage(reason) => write!(f, "Invalid image: {reason}"),
+            Self::Core(e) => write!(f, "{}", e.message()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,16 +1,37 @@
 use super::*;
 use std::borrow::Cow;
-use widestring::{U16Str, U16String};
-use windows::Win32::UI::Controls::Dialogs::*;
+use std::path::{Path, PathBuf};
+use windows::core::{Interface, HRESULT};
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::Common::COMDLG_FILTERSPEC;
+use windows::Win32::UI::Shell::*;
 
+/// The Open and Save As common dialogs, and the folder picker.
+///
+/// These use `IFileDialog`, so COM must be initialized on the thread, as it is
+/// once a `Form` has been created.
 pub struct FileDialog {
     pub allow_multi_select: bool,
     /// A list of extensions, separated by `;`.
     pub filters: FileFilter,
     pub filter_all_files: bool,
+    /// The folder that the dialog opens in, even if the user last used another one.
     pub dir: Option<String>,
     pub title: Option<String>,
+    /// The folder that the dialog opens in, if the user has not used it before.
     pub initial_dir: Option<String>,
+    /// The file name that the dialog opens with.
+    pub file_name: Option<String>,
+    /// The extension added to file names that are typed without one, without the
+    /// dot. Once the user selects another filter, the dialog uses the extension of
+    /// that filter instead.
+    pub default_extension: Option<String>,
+    /// Asks the user before replacing a file in the Save As dialog. This is set by
+    /// default.
+    pub overwrite_prompt: bool,
+    /// The filter that is selected when the dialog opens, as an index into
+    /// `filters`.
+    pub filter_index: usize,
 }
 
 pub enum FileFilter {
@@ -25,6 +46,22 @@ pub enum FileDialogResult {
     Canceled,
 }
 
+/// The items chosen in a `FileDialog`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileDialogPaths {
+    pub paths: Vec<PathBuf>,
+    /// The filter that was selected, as an index into the list given by
+    /// `FileDialog::filter_specs`. This is 0 if there are no filters.
+    pub filter_index: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum DialogKind {
+    Open,
+    Save,
+    Folder,
+}
+
 impl Default for FileDialog {
     fn default() -> Self {
         Self::new()
@@ -40,143 +77,401 @@ impl FileDialog {
             dir: None,
             title: None,
             initial_dir: None,
+            file_name: None,
+            default_extension: None,
+            overwrite_prompt: true,
+            filter_index: 0,
         }
     }
 
     pub fn open(&self, parent: Option<&Form>) -> FileDialogResult {
-        self.show(parent, true)
+        match self.open_paths(parent) {
+            Some(selection) if self.allow_multi_select => multi_select_result(&selection.paths),
+            Some(selection) => single_result(&selection.paths),
+            None => FileDialogResult::Canceled,
+        }
     }
 
     pub fn save(&self, parent: Option<&Form>) -> FileDialogResult {
-        self.show(parent, false)
-    }
-
-    fn show(&self, parent: Option<&Form>, is_open: bool) -> FileDialogResult {
-        // https://docs.microsoft.com/en-us/windows/win32/api/commdlg/ns-commdlg-openfilenamew
-        unsafe {
-            let mut of: OPENFILENAMEW = zeroed();
-            of.lStructSize = size_of_val(&of) as u32;
-            of.hwndOwner = parent.map(|f| f.handle()).unwrap_or(HWND(null_mut()));
-
-            // Filename buffer
-            let mut filename_buffer: Vec<u16> = vec![0; 32768];
-            of.lpstrFile = PWSTR(filename_buffer.as_mut_ptr());
-            of.nMaxFile = filename_buffer.len() as u32;
-
-            // Handle filters
-            let mut filters = U16String::new();
-            let filters_ptr: *const u16;
-            match &self.filters {
-                FileFilter::None => {
-                    filters_ptr = null();
-                }
-                FileFilter::List(list) => {
-                    for (display, exts) in list.iter() {
-                        filters.push_str(&**display);
-                        filters.push_str("\0");
-                        filters.push_str(&**exts);
-                        filters.push_str("\0");
-                    }
-                    filters.push_str("All files\0*\0");
-                    filters.push_str("\0");
-                    filters_ptr = filters.as_ptr();
-                }
-                FileFilter::Static(list) => {
-                    for (display, exts) in list.iter() {
-                        filters.push_str(display);
-                        filters.push_str("\0");
-                        filters.push_str(exts);
-                        filters.push_str("\0");
-                    }
-                    filters.push_str("All files\0*\0");
-                    filters.push_str("\0");
-                    filters_ptr = filters.as_ptr();
-                }
+        match self.save_path(parent) {
+            Some(selection) => single_result(&selection.paths),
+            None => FileDialogResult::Canceled,
+        }
+    }
+
+    /// Shows the Open dialog. Returns the chosen files, or `None` if the user
+    /// canceled.
+    pub fn open_paths(&self, parent: Option<&Form>) -> Option<FileDialogPaths> {
+        self.show(parent, DialogKind::Open)
+    }
+
+    /// Shows the Save As dialog. Returns the chosen file, or `None` if the user
+    /// canceled.
+    pub fn save_path(&self, parent: Option<&Form>) -> Option<FileDialogPaths> {
+        self.show(parent, DialogKind::Save)
+    }
+
+    /// Shows a dialog for choosing folders instead of files. Filters are not used.
+    /// Returns the chosen folders, or `None` if the user canceled.
+    pub fn pick_folder(&self, parent: Option<&Form>) -> Option<FileDialogPaths> {
+        self.show(parent, DialogKind::Folder)
+    }
+
+    /// The filters that the dialog shows, as pairs of a display name and a list of
+    /// patterns, such as `("Text files", "*.txt;*.md")`. Extensions without a
+    /// wildcard are made into patterns, so `"txt; md"` gives `"*.txt;*.md"`. If
+    /// `filter_all_files` is set, an "All files" filter is added at the end.
+    pub fn filter_specs(&self) -> Vec<(String, String)> {
+        let mut specs: Vec<(String, String)> = match &self.filters {
+            FileFilter::None => Vec::new(),
+            FileFilter::List(list) => list
+                .iter()
+                .map(|(name, exts)| (name.to_string(), normalize_filter_pattern(exts)))
+                .collect(),
+            FileFilter::Static(list) => list
+                .iter()
+                .map(|(name, exts)| (name.to_string(), normalize_filter_pattern(exts)))
+                .collect(),
+        };
+        if !specs.is_empty() && self.filter_all_files {
+            specs.push(("All files".to_string(), "*.*".to_string()));
+        }
+        specs
+    }
+
+    fn show(&self, parent: Option<&Form>, kind: DialogKind) -> Option<FileDialogPaths> {
+        match unsafe { self.show_dialog(parent, kind) } {
+            Ok(selection) => selection,
+            Err(e) => {
+                warn!("file dialog failed: {e:?}");
+                None
             }
-            of.lpstrFilter = PCWSTR::from_raw(filters_ptr);
-            debug!("filter: {:?}", filters.as_slice());
-
-            // Handle title
-            let mut title: U16CString;
-            if let Some(t) = self.title.as_ref() {
-                title = U16CString::from_str(t).unwrap();
-                of.lpstrFileTitle = PWSTR::from_raw(title.as_mut_ptr());
+        }
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/shell/common-file-dialog
+    unsafe fn show_dialog(
+        &self,
+        parent: Option<&Form>,
+        kind: DialogKind,
+    ) -> windows::core::Result<Option<FileDialogPaths>> {
+        let dialog: IFileDialog = match kind {
+            DialogKind::Save => {
+                CoCreateInstance::<_, IFileSaveDialog>(&FileSaveDialog, None, CLSCTX_INPROC_SERVER)?
+                    .cast()?
+            }
+            _ => {
+                CoCreateInstance::<_, IFileOpenDialog>(&FileOpenDialog, None, CLSCTX_INPROC_SERVER)?
+                    .cast()?
             }
+        };
 
-            // Handle initial dir
-            let initial_dir: U16CString;
-            if let Some(s) = self.initial_dir.as_ref() {
-                initial_dir = U16CString::from_str(s).unwrap();
-                of.lpstrInitialDir = PCWSTR::from_raw(initial_dir.as_ptr());
+        let mut options = dialog.GetOptions()? | FOS_FORCEFILESYSTEM;
+        match kind {
+            DialogKind::Open => options |= FOS_FILEMUSTEXIST | FOS_PATHMUSTEXIST,
+            DialogKind::Save => {
+                options |= FOS_PATHMUSTEXIST;
+                if self.overwrite_prompt {
+                    options |= FOS_OVERWRITEPROMPT;
+                } else {
+                    options &= !FOS_OVERWRITEPROMPT;
+                }
             }
+            DialogKind::Folder => options |= FOS_PICKFOLDERS | FOS_PATHMUSTEXIST,
+        }
+        if self.allow_multi_select && kind != DialogKind::Save {
+            options |= FOS_ALLOWMULTISELECT;
+        }
+        dialog.SetOptions(options)?;
+
+        // The filter strings must live until the dialog is closed.
+        let filter_strings: Vec<(WCString, WCString)> = if kind == DialogKind::Folder {
+            Vec::new()
+        } else {
+            self.filter_specs()
+                .iter()
+                .map(|(name, spec)| {
+                    (
+                        WCString::from_str_truncate(name),
+                        WCString::from_str_truncate(spec),
+                    )
+                })
+                .collect()
+        };
+        if !filter_strings.is_empty() {
+            let filters: Vec<COMDLG_FILTERSPEC> = filter_strings
+                .iter()
+                .map(|(name, spec)| COMDLG_FILTERSPEC {
+                    pszName: PCWSTR(name.as_ptr()),
+                    pszSpec: PCWSTR(spec.as_ptr()),
+                })
+                .collect();
+            dialog.SetFileTypes(&filters)?;
+            // The dialog's file type index is 1-based.
+            let index = self.filter_index.min(filters.len() - 1);
+            dialog.SetFileTypeIndex(index as u32 + 1)?;
+        }
+
+        if let Some(title) = &self.title {
+            let title = WCString::from_str_truncate(title);
+            dialog.SetTitle(PCWSTR(title.as_ptr()))?;
+        }
+        if let Some(file_name) = &self.file_name {
+            let file_name = WCString::from_str_truncate(file_name);
+            dialog.SetFileName(PCWSTR(file_name.as_ptr()))?;
+        }
+        if let Some(ext) = &self.default_extension {
+            let ext = WCString::from_str_truncate(ext.trim_start_matches('.'));
+            dialog.SetDefaultExtension(PCWSTR(ext.as_ptr()))?;
+        }
+        if let Some(item) = self.initial_dir.as_deref().and_then(shell_item) {
+            dialog.SetDefaultFolder(&item)?;
+        }
+        if let Some(item) = self.dir.as_deref().and_then(shell_item) {
+            dialog.SetFolder(&item)?;
+        }
 
-            if self.allow_multi_select {
-                of.Flags |= OFN_ALLOWMULTISELECT;
+        if let Err(e) = dialog.Show(parent.map(|f| f.handle())) {
+            if e.code() == HRESULT::from_win32(ERROR_CANCELLED.0) {
+                debug!("canceled");
+                return Ok(None);
             }
+            return Err(e);
+        }
 
-            of.Flags |= OFN_EXPLORER;
+        let mut paths = Vec::new();
+        if kind == DialogKind::Save {
+            paths.push(item_path(&dialog.GetResult()?)?);
+        } else {
+            let items = dialog.cast::<IFileOpenDialog>()?.GetResults()?;
+            for i in 0..items.GetCount()? {
+                paths.push(item_path(&items.GetItemAt(i)?)?);
+            }
+        }
+        let filter_index = if filter_strings.is_empty() {
+            0
+        } else {
+            dialog.GetFileTypeIndex()?.saturating_sub(1) as usize
+        };
+        debug!("paths = {:?}, filter_index = {}", paths, filter_index);
+        Ok(Some(FileDialogPaths {
+            paths,
+            filter_index,
+        }))
+    }
+}
 
-            let succeeded = if is_open {
-                GetOpenFileNameW(&mut of).as_bool()
-            } else {
-                GetSaveFileNameW(&mut of).as_bool()
-            };
+fn shell_item(path: &str) -> Option<IShellItem> {
+    let path = WCString::from_str_truncate(path);
+    unsafe { SHCreateItemFromParsingName(PCWSTR(path.as_ptr()), None).ok() }
+}
+
+unsafe fn item_path(item: &IShellItem) -> windows::core::Result<PathBuf> {
+    let name = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+    let path = PathBuf::from(name.to_string().unwrap_or_default());
+    CoTaskMemFree(Some(name.0 as *const c_void));
+    Ok(path)
+}
 
-            if !succeeded {
-                debug!("failed");
-                return FileDialogResult::Canceled;
+/// Turns a list of extensions or patterns, separated by `;`, into the pattern list
+/// that a file dialog expects. `"txt; .md;*.rs"` gives `"*.txt;*.md;*.rs"`.
+///
+/// Only bare extensions (`txt` or `.txt`) get a `*.` prefix. Patterns with `*` or
+/// `?`, and file names such as `Cargo.toml`, are kept as they are.
+pub fn normalize_filter_pattern(exts: &str) -> String {
+    exts.split(';')
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| {
+            let is_pattern = ext.contains('*') || ext.contains('?');
+            let is_name = !ext.starts_with('.') && ext.contains('.');
+            if is_pattern || is_name {
+                ext.to_string()
+            } else {
+                format!("*.{}", ext.trim_start_matches('.'))
             }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
 
-            if self.allow_multi_select {
-                let mut filenames: Vec<String> = Vec::new();
+fn single_result(paths: &[PathBuf]) -> FileDialogResult {
+    match paths.first() {
+        Some(path) => FileDialogResult::Single(path.to_string_lossy().into_owned()),
+        None => FileDialogResult::Canceled,
+    }
+}
 
-                let mut dir_len = of.nFileOffset as usize;
-                while dir_len > 0 && filename_buffer[dir_len - 1] == 0 {
-                    dir_len -= 1;
+/// Splits the paths chosen in a multi-select dialog into the directory that holds
+/// them, and their names relative to it. The files are normally in one directory,
+/// but a library or search results can give files from several; the directory is
+/// then the deepest one that holds all of them.
+pub fn multi_select_result(paths: &[PathBuf]) -> FileDialogResult {
+    let Some(first) = paths.first() else {
+        return FileDialogResult::Canceled;
+    };
+    let mut dir: &Path = first.parent().unwrap_or(Path::new(""));
+    for path in paths[1..].iter() {
+        while !path.starts_with(dir) {
+            match dir.parent() {
+                Some(parent) => dir = parent,
+                None => {
+                    dir = Path::new("");
+                    break;
                 }
+            }
+        }
+    }
+    let files = paths
+        .iter()
+        .map(|path| {
+            path.strip_prefix(dir)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    FileDialogResult::Multi {
+        dir: dir.to_string_lossy().into_owned(),
+        files,
+    }
+}
 
-                let parent_dir = U16Str::from_slice(&filename_buffer[..dir_len]).to_string_lossy();
-
-                let mut pos = of.nFileOffset as usize;
-                loop {
-                    if pos >= filename_buffer.len() {
-                        break;
-                    }
-                    if filename_buffer[pos] == 0 {
-                        break;
-                    }
-                    let start = pos;
-                    pos += 1;
-                    let end;
-                    loop {
-                        if pos >= filename_buffer.len() {
-                            end = pos;
-                            break;
-                        }
-                        if filename_buffer[pos] == 0 {
-                            end = pos;
-                            pos += 1;
-                            break;
-                        }
-                        pos += 1;
-                    }
-
-                    let filename =
-                        U16Str::from_slice(&filename_buffer[start..end]).to_string_lossy();
-                    filenames.push(filename);
-                }
-                FileDialogResult::Multi {
-                    dir: parent_dir,
-                    files: filenames,
-                }
-            } else {
-                let single_filename = widestring::U16CStr::from_slice_truncate(&filename_buffer)
-                    .unwrap()
-                    .to_string_lossy();
-                debug!("filename = {:?}", single_filename);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(parts: &[&str]) -> PathBuf {
+        parts.iter().collect()
+    }
+
+    fn text(parts: &[&str]) -> String {
+        path(parts).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn normalize_patterns() {
+        assert_eq!(normalize_filter_pattern("txt; .md;*.rs"), "*.txt;*.md;*.rs");
+        assert_eq!(normalize_filter_pattern("txt;;  ; md;"), "*.txt;*.md");
+        assert_eq!(normalize_filter_pattern(""), "");
+        assert_eq!(normalize_filter_pattern(" ; "), "");
+        assert_eq!(normalize_filter_pattern("file?.log;*"), "file?.log;*");
+        assert_eq!(normalize_filter_pattern(".tar.gz"), "*.tar.gz");
+    }
+
+    #[test]
+    fn normalize_keeps_file_names() {
+        assert_eq!(normalize_filter_pattern("Cargo.toml"), "Cargo.toml");
+        assert_eq!(
+            normalize_filter_pattern("Cargo.toml; .lock; rs"),
+            "Cargo.toml;*.lock;*.rs"
+        );
+        // Without a leading dot, a dotted name is a file name, not an extension.
+        assert_eq!(normalize_filter_pattern("tar.gz"), "tar.gz");
+        assert_eq!(normalize_filter_pattern("*.tar.gz"), "*.tar.gz");
+        assert_eq!(normalize_filter_pattern("data??.csv"), "data??.csv");
+    }
+
+    #[test]
+    fn filter_specs_with_all_files() {
+        let dialog = FileDialog {
+            filters: FileFilter::Static(&[("Text", "txt; .md"), ("Rust", "*.rs")]),
+            ..FileDialog::new()
+        };
+        assert_eq!(
+            dialog.filter_specs(),
+            [
+                ("Text".to_string(), "*.txt;*.md".to_string()),
+                ("Rust".to_string(), "*.rs".to_string()),
+                ("All files".to_string(), "*.*".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_specs_without_all_files() {
+        let dialog = FileDialog {
+            filters: FileFilter::List(vec![(
+                Cow::Owned("Images".to_string()),
+                Cow::Owned("png;jpg".to_string()),
+            )]),
+            filter_all_files: false,
+            ..FileDialog::new()
+        };
+        assert_eq!(
+            dialog.filter_specs(),
+            [("Images".to_string(), "*.png;*.jpg".to_string())]
+        );
+    }
+
+    #[test]
+    fn filter_specs_without_filters() {
+        // "All files" is only added to other filters.
+        assert!(FileDialog::new().filter_specs().is_empty());
+    }
+
+    #[test]
+    fn multi_select_in_one_dir() {
+        let paths = [
+            path(&["data", "docs", "a.txt"]),
+            path(&["data", "docs", "b.txt"]),
+        ];
+        match multi_select_result(&paths) {
+            FileDialogResult::Multi { dir, files } => {
+                assert_eq!(dir, text(&["data", "docs"]));
+                assert_eq!(files, ["a.txt", "b.txt"]);
+            }
+            _ => panic!("expected Multi"),
+        }
+    }
+
+    #[test]
+    fn multi_select_in_several_dirs() {
+        let paths = [
+            path(&["data", "docs", "a.txt"]),
+            path(&["data", "src", "main.rs"]),
+            path(&["data", "docs", "old", "b.txt"]),
+        ];
+        match multi_select_result(&paths) {
+            FileDialogResult::Multi { dir, files } => {
+                assert_eq!(dir, "data");
+                assert_eq!(
+                    files,
+                    [
+                        text(&["docs", "a.txt"]),
+                        text(&["src", "main.rs"]),
+                        text(&["docs", "old", "b.txt"]),
+                    ]
+                );
+            }
+            _ => panic!("expected Multi"),
+        }
+    }
+
+    #[test]
+    fn multi_select_without_common_dir() {
+        let paths = [path(&["one", "a.txt"]), path(&["two", "b.txt"])];
+        match multi_select_result(&paths) {
+            FileDialogResult::Multi { dir, files } => {
+                assert_eq!(dir, "");
+                assert_eq!(files, [text(&["one", "a.txt"]), text(&["two", "b.txt"])]);
+            }
+            _ => panic!("expected Multi"),
+        }
+    }
 
-                FileDialogResult::Single(single_filename)
+    #[test]
+    fn multi_select_single_file() {
+        match multi_select_result(&[path(&["data", "docs", "a.txt"])]) {
+            FileDialogResult::Multi { dir, files } => {
+                assert_eq!(dir, text(&["data", "docs"]));
+                assert_eq!(files, ["a.txt"]);
             }
+            _ => panic!("expected Multi"),
         }
+        assert!(matches!(
+            multi_select_result(&[]),
+            FileDialogResult::Canceled
+        ));
     }
 }
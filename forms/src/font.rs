@@ -35,6 +35,7 @@ impl Font {
         FontBuilder {
             height,
             width: 0,
+            weight: 0,
             face_name,
             italic: false,
             underline: false,
@@ -47,6 +48,7 @@ impl Font {
 pub struct FontBuilder<'a> {
     height: i32,
     width: i32,
+    weight: i32,
     face_name: &'a str,
     italic: bool,
     underline: bool,
@@ -63,7 +65,7 @@ impl<'a> FontBuilder<'a> {
                 self.width,
                 0,                                            // escapement,
                 0,                                            // orientation,
-                0,                                            // weight,
+                self.weight,                                  // weight,
                 self.italic as u32,                           // italic,
                 self.underline as u32,                        // underline,
                 self.strikeout as u32,                        // strikeout,
@@ -93,6 +95,22 @@ impl<'a> FontBuilder<'a> {
         self.underline = true;
         self
     }
+
+    pub fn strikeout(&mut self) -> &mut Self {
+        self.strikeout = true;
+        self
+    }
+
+    /// Sets the weight, from 100 (thin) to 900 (black). 400 is normal, and 0 uses
+    /// the font's default weight.
+    pub fn weight(&mut self, weight: i32) -> &mut Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn bold(&mut self) -> &mut Self {
+        self.weight(FW_BOLD.0 as i32)
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
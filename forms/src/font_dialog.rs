@@ -0,0 +1,151 @@
+use super::*;
+use windows::Win32::UI::Controls::Dialogs::*;
+
+/// The font chooser common dialog.
+pub struct FontDialog {
+    /// The font that is selected when the dialog opens.
+    pub font: Option<FontDescription>,
+    /// Shows the underline, strikeout and color choices.
+    pub show_effects: bool,
+    /// Lists only fonts whose characters all have the same width.
+    pub fixed_pitch_only: bool,
+    /// Limits the sizes that can be chosen, in points.
+    pub size_range: Option<(i32, i32)>,
+}
+
+/// A font chosen in a `FontDialog`. Use `build` to create it.
+#[derive(Clone, Eq, PartialEq)]
+pub struct FontDescription {
+    pub face_name: String,
+    /// The height in pixels, as in `LOGFONTW::lfHeight`. A negative height is the
+    /// height of the characters; a positive height includes the space above them.
+    pub height: i32,
+    /// The size in tenths of a point, as shown in the dialog.
+    pub point_size_tenths: i32,
+    /// From 100 (thin) to 900 (black). 400 is normal, and 700 is bold.
+    pub weight: i32,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikeout: bool,
+    /// The text color, if `FontDialog::show_effects` was set.
+    pub color: Option<ColorRef>,
+}
+
+impl FontDescription {
+    /// Gets a builder for this font, to change it further before creating it.
+    pub fn builder(&self) -> FontBuilder<'_> {
+        let mut builder = Font::builder(&self.face_name, self.height);
+        builder.weight(self.weight);
+        if self.italic {
+            builder.italic();
+        }
+        if self.underline {
+            builder.underline();
+        }
+        if self.strikeout {
+            builder.strikeout();
+        }
+        builder
+    }
+
+    pub fn build(&self) -> Result<Rc<Font>> {
+        self.builder().build()
+    }
+
+    fn to_logfont(&self) -> LOGFONTW {
+        let mut logfont: LOGFONTW = unsafe { zeroed() };
+        logfont.lfHeight = self.height;
+        logfont.lfWeight = self.weight;
+        logfont.lfItalic = self.italic as u8;
+        logfont.lfUnderline = self.underline as u8;
+        logfont.lfStrikeOut = self.strikeout as u8;
+        // Leave room for the terminating nul.
+        let max_len = logfont.lfFaceName.len() - 1;
+        for (dst, src) in logfont.lfFaceName[..max_len]
+            .iter_mut()
+            .zip(self.face_name.encode_utf16())
+        {
+            *dst = src;
+        }
+        logfont
+    }
+
+    fn from_logfont(logfont: &LOGFONTW, point_size_tenths: i32, color: Option<ColorRef>) -> Self {
+        Self {
+            face_name: WCStr::from_slice_truncate(&logfont.lfFaceName)
+                .map(|s| s.to_string_lossy())
+                .unwrap_or_default(),
+            height: logfont.lfHeight,
+            point_size_tenths,
+            weight: logfont.lfWeight,
+            italic: logfont.lfItalic != 0,
+            underline: logfont.lfUnderline != 0,
+            strikeout: logfont.lfStrikeOut != 0,
+            color,
+        }
+    }
+}
+
+impl Default for FontDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontDialog {
+    pub fn new() -> Self {
+        Self {
+            font: None,
+            show_effects: false,
+            fixed_pitch_only: false,
+            size_range: None,
+        }
+    }
+
+    /// Shows the dialog. Returns the selected font, or `None` if the user canceled.
+    pub fn show(&self, parent: Option<&Form>) -> Option<FontDescription> {
+        // https://learn.microsoft.com/en-us/windows/win32/api/commdlg/ns-commdlg-choosefontw
+        let mut logfont: LOGFONTW = match &self.font {
+            Some(font) => font.to_logfont(),
+            None => unsafe { zeroed() },
+        };
+        let mut cf: CHOOSEFONTW = unsafe { zeroed() };
+        cf.lStructSize = size_of_val(&cf) as u32;
+        cf.hwndOwner = parent.map(|f| f.handle()).unwrap_or(HWND(null_mut()));
+        cf.lpLogFont = &mut logfont;
+        cf.Flags = CF_SCREENFONTS | CF_NOVERTFONTS;
+        if self.font.is_some() {
+            cf.Flags |= CF_INITTOLOGFONTSTRUCT;
+        }
+        if self.show_effects {
+            cf.Flags |= CF_EFFECTS;
+            if let Some(color) = self.font.as_ref().and_then(|f| f.color) {
+                cf.rgbColors = color.into();
+            }
+        }
+        if self.fixed_pitch_only {
+            cf.Flags |= CF_FIXEDPITCHONLY;
+        }
+        if let Some((min, max)) = self.size_range {
+            cf.Flags |= CF_LIMITSIZE;
+            cf.nSizeMin = min;
+            cf.nSizeMax = max;
+        }
+
+        let succeeded = unsafe { ChooseFontW(&mut cf).as_bool() };
+        if !succeeded {
+            debug!("font dialog canceled: {:?}", unsafe {
+                CommDlgExtendedError()
+            });
+            return None;
+        }
+        let color = self
+            .show_effects
+            .then(|| ColorRef::from_u32_bgr(cf.rgbColors.0));
+        Some(FontDescription::from_logfont(
+            &logfont,
+            cf.iPointSize,
+            color,
+        ))
+    }
+}
@@ -34,6 +34,7 @@ pub struct Form {
     notify_handler: OnceCell<Box<dyn Fn(&Notify)>>,
 
     status_bar: Cell<Option<Rc<StatusBar>>>,
+    toolbar: Cell<Option<Rc<Toolbar>>>,
 
     pub(crate) tab_controls: RefCell<Vec<std::rc::Weak<TabControl>>>,
 }
@@ -126,6 +127,33 @@ impl Form {
         }
     }
 
+    pub fn create_toolbar(self: &Rc<Self>) -> Rc<Toolbar> {
+        self.stuck.check();
+        let tb = if let Some(tb) = self.toolbar.take() {
+            tb
+        } else {
+            Toolbar::new(self)
+        };
+        self.toolbar.set(Some(tb.clone()));
+        tb
+    }
+
+    pub fn get_toolbar(&self) -> Option<Rc<Toolbar>> {
+        self.stuck.check();
+        if let Some(tb) = self.toolbar.take() {
+            self.toolbar.set(Some(tb.clone()));
+            Some(tb)
+        } else {
+            None
+        }
+    }
+
+    /// Changes how the tooltips of all controls on this form are shown.
+    pub fn set_tooltip_options(&self, options: &TooltipOptions) {
+        self.stuck.check();
+        apply_tooltip_options(get_tooltip_window(self.handle()), options);
+    }
+
     pub fn set_font(&self, font: Rc<Font>) {
         unsafe {
             SendMessageW(
@@ -184,11 +212,21 @@ impl Form {
             if let Some(sb) = self.status_bar.take() {
                 self.status_bar.set(Some(sb.clone()));
                 SendMessageW(sb.handle(), WM_SIZE, None, None);
+                sb.update_parts();
                 let mut sb_rect: RECT = zeroed();
                 _ = GetClientRect(sb.handle(), &mut sb_rect);
                 sb_height = sb_rect.bottom - sb_rect.top;
             }
 
+            let mut tb_height = 0;
+            if let Some(tb) = self.toolbar.take() {
+                self.toolbar.set(Some(tb.clone()));
+                tb.autosize();
+                let mut tb_rect: RECT = zeroed();
+                _ = GetWindowRect(tb.handle(), &mut tb_rect);
+                tb_height = tb_rect.bottom - tb_rect.top;
+            }
+
             let mut client_rect: RECT = zeroed();
             if GetClientRect(self.handle.get(), &mut client_rect).is_ok() {
                 trace!(
@@ -205,8 +243,8 @@ impl Form {
                     self.layout_min_size.set(min_size);
 
                     let mut layout_height = client_rect.bottom - client_rect.top;
-                    if layout_height >= sb_height {
-                        layout_height -= sb_height;
+                    if layout_height >= sb_height + tb_height {
+                        layout_height -= sb_height + tb_height;
                     }
 
                     let mut deferred_placer = DeferredLayoutPlacer::new(10);
@@ -214,7 +252,7 @@ impl Form {
                     layout.place(
                         &mut deferred_placer,
                         client_rect.left,
-                        client_rect.top,
+                        client_rect.top + tb_height,
                         client_rect.right - client_rect.left,
                         layout_height, // client_rect.bottom - client_rect.top,
                     );
@@ -392,6 +430,7 @@ extern "system" fn form_wndproc(
 
             wm::WM_DESTROY => {
                 debug!("WM_DESTROY");
+                forget_tooltip_window(window);
                 return LRESULT(0);
             }
 
@@ -405,6 +444,11 @@ extern "system" fn form_wndproc(
                     SendMessageW(sb.handle(), WM_SIZE, None, None);
                 }
 
+                if let Some(tb) = state.toolbar.take() {
+                    state.toolbar.set(Some(tb.clone()));
+                    tb.autosize();
+                }
+
                 state.invalidate_layout();
                 state.ensure_layout_valid();
 
@@ -417,6 +461,11 @@ extern "system" fn form_wndproc(
                 let control = ControlId(wparam_loword(wparam));
                 let command = Command(wparam_hiword(wparam) as u32);
 
+                // Give the control that sent the notification the first chance to handle it.
+                if lparam.0 != 0 && dispatch_command(HWND(lparam.0 as *mut c_void), command.0) {
+                    return LRESULT(0);
+                }
+
                 if let Some(handler) = state.command_handler.get() {
                     handler(control, command);
                 } else {
@@ -424,6 +473,18 @@ extern "system" fn form_wndproc(
                 }
             }
 
+            wm::WM_DRAWITEM => {
+                // https://learn.microsoft.com/en-us/windows/win32/controls/wm-drawitem
+                let dis = &*(lparam.0 as *const DRAWITEMSTRUCT);
+                if let Some(sb) = state.status_bar.take() {
+                    state.status_bar.set(Some(sb.clone()));
+                    if dis.hwndItem == sb.handle() {
+                        sb.draw_item(dis);
+                        return LRESULT(1);
+                    }
+                }
+            }
+
             // WM_NOTIFY is used by most of the Common Controls to communicate
             // with the app.
             // https://docs.microsoft.com/en-us/windows/win32/controls/wm-notify
@@ -431,6 +492,12 @@ extern "system" fn form_wndproc(
                 let nmhdr_ptr: *mut NMHDR = lparam.0 as *mut NMHDR;
                 let hwnd_from: HWND = (*nmhdr_ptr).hwndFrom;
                 let notify_code = (*nmhdr_ptr).code;
+
+                // Give the control that sent the notification the first chance to handle it.
+                if let NotifyResult::Consumed(result) = dispatch_notify(nmhdr_ptr) {
+                    return result;
+                }
+
                 let notify = Notify::from_nmhdr(nmhdr_ptr);
 
                 // For some notifications, we need to handle the notification directly.
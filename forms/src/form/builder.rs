@@ -120,6 +120,7 @@ impl<'a> FormBuilder<'a> {
                 background_brush: Default::default(),
                 background_color: Cell::new(ColorRef::from_sys_color(SysColor::Window)),
                 status_bar: Cell::new(None),
+                toolbar: Cell::new(None),
                 command_handler: Default::default(),
                 notify_handler: Default::default(),
                 tab_controls: Default::default(),
@@ -1,8 +1,9 @@
-use windows::Win32::Foundation::{COLORREF, POINT};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{COLORREF, POINT, RECT, SIZE};
 use windows::Win32::Graphics::Gdi::HDC;
 use windows::Win32::Graphics::Gdi::{self, HGDIOBJ};
 
-use crate::ColorRef;
+use crate::{ColorRef, Font};
 
 pub struct Dc {
     pub(crate) hdc: HDC,
@@ -161,6 +162,104 @@ impl Dc {
         }
     }
 
+    pub fn set_text_color(&self, c: ColorRef) {
+        unsafe {
+            Gdi::SetTextColor(self.hdc, COLORREF(c.as_u32()));
+        }
+    }
+
+    /// Text is drawn without filling the background of each character cell.
+    pub fn set_transparent_text(&self) {
+        unsafe {
+            Gdi::SetBkMode(self.hdc, Gdi::TRANSPARENT);
+        }
+    }
+
+    /// Fills a rectangle with a solid color. The right and bottom edges are excluded.
+    pub fn fill_rect(&self, left: i32, top: i32, right: i32, bottom: i32, c: ColorRef) {
+        unsafe {
+            // ExtTextOut with ETO_OPAQUE is the usual way to fill without a brush.
+            let rect = RECT {
+                left,
+                top,
+                right,
+                bottom,
+            };
+            Gdi::SetBkColor(self.hdc, COLORREF(c.as_u32()));
+            _ = Gdi::ExtTextOutW(
+                self.hdc,
+                0,
+                0,
+                Gdi::ETO_OPAQUE,
+                Some(&rect),
+                PCWSTR::null(),
+                0,
+                None,
+            );
+        }
+    }
+
+    pub fn select_font(&self, font: &Font) {
+        unsafe {
+            Gdi::SelectObject(self.hdc, HGDIOBJ(font.hfont.0));
+        }
+    }
+
+    pub fn text_out(&self, x: i32, y: i32, text: &str) {
+        let chars: Vec<u16> = text.encode_utf16().collect();
+        self.text_out_w(x, y, &chars);
+    }
+
+    /// Selects the stock `DC_PEN` and `DC_BRUSH`, whose colors are set with
+    /// `set_pen_color` and `set_brush_color`.
+    pub fn select_dc_pen_and_brush(&self) {
+        unsafe {
+            Gdi::SelectObject(self.hdc, Gdi::GetStockObject(Gdi::DC_PEN));
+            Gdi::SelectObject(self.hdc, Gdi::GetStockObject(Gdi::DC_BRUSH));
+        }
+    }
+
+    pub fn set_brush_color(&self, c: ColorRef) {
+        unsafe {
+            Gdi::SetDCBrushColor(self.hdc, COLORREF(c.as_u32()));
+        }
+    }
+
+    /// Selects no brush, so that shapes are drawn without being filled.
+    pub fn select_null_brush(&self) {
+        unsafe {
+            Gdi::SelectObject(self.hdc, Gdi::GetStockObject(Gdi::NULL_BRUSH));
+        }
+    }
+
+    /// Saves the state of the DC (selected objects, colors, clipping). See `restore`.
+    pub fn save(&self) -> i32 {
+        unsafe { Gdi::SaveDC(self.hdc) }
+    }
+
+    pub fn restore(&self, saved: i32) {
+        unsafe {
+            _ = Gdi::RestoreDC(self.hdc, saved);
+        }
+    }
+
+    /// Limits drawing to the intersection of the current clip region and a rectangle.
+    pub fn intersect_clip_rect(&self, left: i32, top: i32, right: i32, bottom: i32) {
+        unsafe {
+            Gdi::IntersectClipRect(self.hdc, left, top, right, bottom);
+        }
+    }
+
+    /// The width and height of `text` in the selected font.
+    pub fn text_extent(&self, text: &str) -> (i32, i32) {
+        unsafe {
+            let chars: Vec<u16> = text.encode_utf16().collect();
+            let mut size = SIZE::default();
+            _ = Gdi::GetTextExtentPoint32W(self.hdc, &chars, &mut size);
+            (size.cx, size.cy)
+        }
+    }
+
     // pub fn select_brush(&self, pen: HPEN) {
     // }
 }
@@ -0,0 +1,1157 @@
+//! A hex viewer for binary data of any size. Only the rows that are visible are
+//! read from the `ByteSource`, so large files can be viewed by reading them on
+//! demand, or through a memory mapping.
+//!
+//! Each row shows an offset, the bytes in hex, and the same bytes as ASCII:
+//!
+//! ```text
+//!  00000010  48 65 6C 6C 6F 2C 20 77  6F 72 6C 64 21 0A 00 00  Hello, world!...
+//! ```
+
+use super::*;
+use crate::custom::{CustomControl, CustomInner};
+use crate::gdi::dc::Dc;
+use core::ops::Range;
+use std::cell::OnceCell;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, VIRTUAL_KEY, VK_CONTROL, VK_DOWN, VK_END, VK_HOME, VK_LEFT, VK_NEXT, VK_PRIOR,
+    VK_RIGHT, VK_SHIFT, VK_UP,
+};
+
+/// The bytes shown by a `HexView`.
+pub trait ByteSource {
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads the bytes starting at `offset` into `buf`. Returns the number of bytes
+    /// read, which is less than `buf.len()` only at the end of the data.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+impl ByteSource for Vec<u8> {
+    fn len(&self) -> u64 {
+        self.as_slice().len() as u64
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.as_slice();
+        let start = offset.min(data.len() as u64) as usize;
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        Ok(n)
+    }
+}
+
+/// Reads a file on demand. The length of the file is read when it is opened.
+pub struct FileByteSource {
+    file: RefCell<File>,
+    len: u64,
+}
+
+impl FileByteSource {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            file: RefCell::new(file),
+            len,
+        })
+    }
+}
+
+impl ByteSource for FileByteSource {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut total = 0;
+        while total < buf.len() {
+            match file.read(&mut buf[total..])? {
+                0 => break,
+                n => total += n,
+            }
+        }
+        Ok(total)
+    }
+}
+
+/// A range of bytes drawn with a background color. See `HexView::add_highlight`.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Highlight {
+    pub range: Range<u64>,
+    pub color: ColorRef,
+}
+
+/// The selected bytes. The anchor is where the selection started, and the caret
+/// is the end that moves. Both bytes are selected.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct HexSelection {
+    pub anchor: u64,
+    pub caret: u64,
+}
+
+impl HexSelection {
+    pub fn at(offset: u64) -> Self {
+        Self {
+            anchor: offset,
+            caret: offset,
+        }
+    }
+
+    /// The selected bytes, clamped to the length of the data.
+    pub fn range(&self, len: u64) -> Range<u64> {
+        let start = self.anchor.min(self.caret);
+        let end = self.anchor.max(self.caret).saturating_add(1);
+        start.min(len)..end.min(len)
+    }
+}
+
+/// The column that a point is in. See `HexLayout::hit_test`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HexArea {
+    Offset,
+    Hex,
+    Ascii,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct HexHit {
+    /// The byte under the point. This may be past the end of the data, on the last
+    /// row.
+    pub offset: u64,
+    pub area: HexArea,
+}
+
+/// Where the columns of a `HexView` are. Positions are in characters of the
+/// (fixed-pitch) font, or in pixels where noted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct HexLayout {
+    pub bytes_per_row: usize,
+    pub offset_digits: usize,
+    /// Pixels
+    pub char_width: i32,
+    /// Pixels
+    pub line_height: i32,
+}
+
+/// Blank characters to the left of the offset column.
+const MARGIN_CHARS: usize = 1;
+/// Bytes in the hex column are split into groups with an extra space between them.
+const GROUP_BYTES: usize = 8;
+
+impl HexLayout {
+    pub fn new(bytes_per_row: usize, data_len: u64, char_width: i32, line_height: i32) -> Self {
+        Self {
+            bytes_per_row: bytes_per_row.max(1),
+            offset_digits: offset_digits(data_len),
+            char_width,
+            line_height,
+        }
+    }
+
+    /// The character position where the hex column starts.
+    pub fn hex_start(&self) -> usize {
+        MARGIN_CHARS + self.offset_digits + 2
+    }
+
+    /// The character position of the hex digits of byte `column` of a row.
+    pub fn hex_x(&self, column: usize) -> usize {
+        self.hex_start() + column * 3 + column / GROUP_BYTES
+    }
+
+    /// The character position where the ASCII column starts.
+    pub fn ascii_start(&self) -> usize {
+        self.hex_x(self.bytes_per_row - 1) + 2 + 2
+    }
+
+    pub fn ascii_x(&self, column: usize) -> usize {
+        self.ascii_start() + column
+    }
+
+    /// The width of a row, in characters.
+    pub fn row_chars(&self) -> usize {
+        self.ascii_x(self.bytes_per_row) + MARGIN_CHARS
+    }
+
+    pub fn row_count(&self, data_len: u64) -> u64 {
+        data_len.div_ceil(self.bytes_per_row as u64)
+    }
+
+    /// The number of rows that fit in `height` pixels, including a partial row at
+    /// the bottom.
+    pub fn visible_rows(&self, height: i32) -> u64 {
+        if self.line_height <= 0 {
+            return 0;
+        }
+        (height.max(0) as u64).div_ceil(self.line_height as u64)
+    }
+
+    /// The number of complete rows that fit in `height` pixels. At least 1.
+    pub fn page_rows(&self, height: i32) -> u64 {
+        if self.line_height <= 0 {
+            return 1;
+        }
+        ((height.max(0) / self.line_height) as u64).max(1)
+    }
+
+    /// Finds the byte at the point (`x`, `y`), in pixels, when `top_row` is the
+    /// first visible row. Points left of the hex column hit the first byte of the
+    /// row, and points beyond the end of a row hit its last byte.
+    pub fn hit_test(&self, x: i32, y: i32, top_row: u64) -> HexHit {
+        let row = top_row + (y.max(0) / self.line_height.max(1)) as u64;
+        let x_chars = (x.max(0) / self.char_width.max(1)) as usize;
+        let row_start = row * self.bytes_per_row as u64;
+
+        let (column, area) = if x_chars < self.hex_start() {
+            (0, HexArea::Offset)
+        } else if x_chars + 1 < self.ascii_start() {
+            let column = (0..self.bytes_per_row)
+                .rev()
+                .find(|&column| self.hex_x(column) <= x_chars)
+                .unwrap_or(0);
+            (column, HexArea::Hex)
+        } else {
+            let column = x_chars.saturating_sub(self.ascii_start());
+            (column.min(self.bytes_per_row - 1), HexArea::Ascii)
+        };
+
+        HexHit {
+            offset: row_start + column as u64,
+            area,
+        }
+    }
+}
+
+/// The number of hex digits used for offsets: enough for the largest offset, and
+/// at least 8.
+pub fn offset_digits(data_len: u64) -> usize {
+    let last = data_len.saturating_sub(1);
+    let digits = (64 - last.leading_zeros() as usize).div_ceil(4);
+    digits.max(8)
+}
+
+pub fn format_offset(offset: u64, digits: usize) -> String {
+    format!("{offset:0digits$X}")
+}
+
+/// The character shown for `b` in the ASCII column.
+pub fn printable_char(b: u8) -> char {
+    if (0x20..0x7f).contains(&b) {
+        b as char
+    } else {
+        '.'
+    }
+}
+
+/// Formats one row, exactly as it is drawn. `bytes` may be shorter than a full row.
+pub fn format_row(layout: &HexLayout, offset: u64, bytes: &[u8]) -> String {
+    let mut line = vec![b' '; layout.row_chars() - MARGIN_CHARS];
+    let offset_text = format_offset(offset, layout.offset_digits);
+    line[MARGIN_CHARS..MARGIN_CHARS + offset_text.len()].copy_from_slice(offset_text.as_bytes());
+    for (column, &b) in bytes.iter().take(layout.bytes_per_row).enumerate() {
+        let hex = format!("{b:02X}");
+        let x = layout.hex_x(column);
+        line[x..x + 2].copy_from_slice(hex.as_bytes());
+        line[layout.ascii_x(column)] = printable_char(b) as u8;
+    }
+    String::from_utf8(line).unwrap().trim_end().to_string()
+}
+
+/// Formats bytes as hex pairs separated by spaces, with a line break after every
+/// `bytes_per_line` bytes. This is the text that "copy as hex" puts on the clipboard.
+pub fn format_hex(bytes: &[u8], bytes_per_line: usize) -> String {
+    let mut text = String::with_capacity(bytes.len() * 3);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 {
+            if bytes_per_line > 0 && i % bytes_per_line == 0 {
+                text.push_str("\r\n");
+            } else {
+                text.push(' ');
+            }
+        }
+        text.push_str(&format!("{b:02X}"));
+    }
+    text
+}
+
+/// Parses an offset typed by the user, for "go to offset". Hex offsets are written
+/// as `0x1F00` or `1F00h`; anything else is decimal. Spaces and `_` are ignored.
+pub fn parse_offset(text: &str) -> Option<u64> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '_')
+        .collect();
+    if let Some(hex) = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_suffix('h'))
+        .or_else(|| text.strip_suffix('H'))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// A keyboard movement of the caret. See `move_caret`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CaretMove {
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    RowStart,
+    RowEnd,
+    Start,
+    End,
+}
+
+/// Moves the caret, keeping it within the data. `page_rows` is the number of rows
+/// that Page Up and Page Down move by.
+pub fn move_caret(
+    caret: u64,
+    movement: CaretMove,
+    bytes_per_row: usize,
+    page_rows: u64,
+    data_len: u64,
+) -> u64 {
+    if data_len == 0 {
+        return 0;
+    }
+    let last = data_len - 1;
+    let row_bytes = bytes_per_row as u64;
+    let caret = caret.min(last);
+    let moved = match movement {
+        CaretMove::Left => caret.saturating_sub(1),
+        CaretMove::Right => caret.saturating_add(1),
+        // Up and Down stay put when there is no row to move to.
+        CaretMove::Up => caret.checked_sub(row_bytes).unwrap_or(caret),
+        CaretMove::Down => match caret.checked_add(row_bytes) {
+            Some(next) if next <= last => next,
+            _ => caret,
+        },
+        CaretMove::PageUp => {
+            let rows = (caret / row_bytes).min(page_rows);
+            caret - rows * row_bytes
+        }
+        CaretMove::PageDown => {
+            let rows = (last / row_bytes - caret / row_bytes).min(page_rows);
+            caret + rows * row_bytes
+        }
+        CaretMove::RowStart => caret - caret % row_bytes,
+        CaretMove::RowEnd => caret - caret % row_bytes + row_bytes - 1,
+        CaretMove::Start => 0,
+        CaretMove::End => last,
+    };
+    moved.min(last)
+}
+
+/// Scroll bars are limited to `i32` positions. For data with more rows than that,
+/// each scroll bar unit is this many rows.
+pub fn scroll_scale(row_count: u64) -> u64 {
+    row_count / (i32::MAX as u64) + 1
+}
+
+/// The most bytes that `HexView::copy_selection_as_hex` copies to the clipboard.
+pub const MAX_COPY_BYTES: u64 = 16 << 20;
+
+/// Shows the bytes of a `ByteSource`. Click and drag (or use Shift with the arrow
+/// keys) to select bytes; Ctrl+C copies them as hex.
+pub struct HexView {
+    control: Rc<CustomControl<HexViewInner>>,
+}
+
+impl core::ops::Deref for HexView {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+struct HexViewInner {
+    source: RefCell<Option<Rc<dyn ByteSource>>>,
+    font: RefCell<Rc<Font>>,
+    bytes_per_row: Cell<usize>,
+    layout: Cell<HexLayout>,
+    top_row: Cell<u64>,
+    client_height: Cell<i32>,
+    selection: Cell<HexSelection>,
+    highlights: RefCell<Vec<Highlight>>,
+    dragging: Cell<bool>,
+    has_focus: Cell<bool>,
+    selection_changed_handler: OnceCell<Box<dyn Fn(Range<u64>)>>,
+}
+
+impl HexView {
+    pub fn new(parent: &ControlState) -> Rc<Self> {
+        let font = Font::new("Consolas", 16).unwrap();
+        let inner = HexViewInner {
+            source: RefCell::new(None),
+            font: RefCell::new(font),
+            bytes_per_row: Cell::new(16),
+            layout: Cell::new(HexLayout::new(16, 0, 8, 16)),
+            top_row: Cell::new(0),
+            client_height: Cell::new(0),
+            selection: Cell::new(HexSelection::default()),
+            highlights: RefCell::new(Vec::new()),
+            dragging: Cell::new(false),
+            has_focus: Cell::new(false),
+            selection_changed_handler: OnceCell::new(),
+        };
+
+        let control = CustomControl::new(parent, inner);
+        control.set_window_style_flag(WS_VSCROLL, true);
+        unsafe {
+            // The frame, which contains the scroll bar, is cached until this.
+            _ = SetWindowPos(
+                control.handle(),
+                None,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_FRAMECHANGED,
+            );
+        }
+
+        let this = Rc::new(HexView { control });
+        this.update_layout();
+        this
+    }
+
+    fn inner(&self) -> &HexViewInner {
+        self.control.inner()
+    }
+
+    pub fn set_source(&self, source: Rc<dyn ByteSource>) {
+        let inner = self.inner();
+        *inner.source.borrow_mut() = Some(source);
+        inner.top_row.set(0);
+        inner.selection.set(HexSelection::default());
+        self.update_layout();
+    }
+
+    pub fn clear_source(&self) {
+        *self.inner().source.borrow_mut() = None;
+        self.update_layout();
+    }
+
+    /// Sets the font. It should be a fixed-pitch font.
+    pub fn set_font(&self, font: Rc<Font>) {
+        *self.inner().font.borrow_mut() = font;
+        self.update_layout();
+    }
+
+    pub fn set_bytes_per_row(&self, bytes_per_row: usize) {
+        self.inner().bytes_per_row.set(bytes_per_row.max(1));
+        self.update_layout();
+    }
+
+    pub fn selection(&self) -> Range<u64> {
+        let inner = self.inner();
+        inner.selection.get().range(inner.len())
+    }
+
+    /// Selects `range`, and scrolls to show its start.
+    pub fn set_selection(&self, range: Range<u64>) {
+        let inner = self.inner();
+        let len = inner.len();
+        if len == 0 {
+            return;
+        }
+        let start = range.start.min(len - 1);
+        let end = range.end.saturating_sub(1).clamp(start, len - 1);
+        inner.set_selection(
+            &self.control,
+            HexSelection {
+                anchor: start,
+                caret: end,
+            },
+        );
+        inner.scroll_to_show(&self.control, start);
+    }
+
+    /// Moves the caret to `offset` and scrolls to show it. See `parse_offset`.
+    pub fn go_to(&self, offset: u64) {
+        self.set_selection(offset..offset.saturating_add(1));
+    }
+
+    pub fn add_highlight(&self, range: Range<u64>, color: ColorRef) {
+        self.inner()
+            .highlights
+            .borrow_mut()
+            .push(Highlight { range, color });
+        self.invalidate_all();
+    }
+
+    pub fn clear_highlights(&self) {
+        self.inner().highlights.borrow_mut().clear();
+        self.invalidate_all();
+    }
+
+    /// Copies the selected bytes to the clipboard, formatted with `format_hex`.
+    /// Fails with `ERROR_FILE_TOO_LARGE`, and copies nothing, if more than
+    /// `MAX_COPY_BYTES` are selected.
+    pub fn copy_selection_as_hex(&self) -> Result<()> {
+        self.inner().copy_selection_as_hex(&self.control)
+    }
+
+    /// Sets the handler that is called when the selection changes.
+    pub fn selection_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(Range<u64>) + 'static,
+    {
+        let result = self
+            .inner()
+            .selection_changed_handler
+            .set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call selection_changed_handler() more than once"
+        );
+    }
+
+    /// Measures the font, and updates the scroll bar.
+    fn update_layout(&self) {
+        let inner = self.inner();
+        unsafe {
+            let hwnd = self.control.handle();
+            let hdc = GetDC(Some(hwnd));
+            let old_font = SelectObject(hdc, HGDIOBJ(inner.font.borrow().hfont.0));
+            let mut metrics: TEXTMETRICW = zeroed();
+            _ = GetTextMetricsW(hdc, &mut metrics);
+            SelectObject(hdc, old_font);
+            ReleaseDC(Some(hwnd), hdc);
+
+            inner.layout.set(HexLayout::new(
+                inner.bytes_per_row.get(),
+                inner.len(),
+                metrics.tmAveCharWidth,
+                metrics.tmHeight,
+            ));
+        }
+        inner.update_scroll_bar(&self.control);
+        self.invalidate_all();
+    }
+}
+
+impl HexViewInner {
+    fn len(&self) -> u64 {
+        match &*self.source.borrow() {
+            Some(source) => source.len(),
+            None => 0,
+        }
+    }
+
+    fn max_top_row(&self) -> u64 {
+        let layout = self.layout.get();
+        let page = layout.page_rows(self.client_height.get());
+        layout.row_count(self.len()).saturating_sub(page)
+    }
+
+    fn scroll_to(&self, control: &CustomControl<Self>, row: u64) {
+        let row = row.min(self.max_top_row());
+        if row != self.top_row.get() {
+            self.top_row.set(row);
+            self.update_scroll_bar(control);
+            control.invalidate_all();
+        }
+    }
+
+    /// Scrolls as little as possible to make the row of `offset` fully visible.
+    fn scroll_to_show(&self, control: &CustomControl<Self>, offset: u64) {
+        let layout = self.layout.get();
+        let row = offset / layout.bytes_per_row as u64;
+        let page = layout.page_rows(self.client_height.get());
+        let top = self.top_row.get();
+        if row < top {
+            self.scroll_to(control, row);
+        } else if row >= top + page {
+            self.scroll_to(control, row + 1 - page);
+        }
+    }
+
+    fn update_scroll_bar(&self, control: &CustomControl<Self>) {
+        let layout = self.layout.get();
+        let rows = layout.row_count(self.len());
+        let scale = scroll_scale(rows);
+        unsafe {
+            let info = SCROLLINFO {
+                cbSize: size_of::<SCROLLINFO>() as u32,
+                fMask: SIF_RANGE | SIF_PAGE | SIF_POS,
+                nMin: 0,
+                nMax: (rows.saturating_sub(1) / scale) as i32,
+                nPage: (layout.page_rows(self.client_height.get()) / scale).max(1) as u32,
+                nPos: (self.top_row.get() / scale) as i32,
+                nTrackPos: 0,
+            };
+            SetScrollInfo(control.handle(), SB_VERT, &info, true);
+        }
+    }
+
+    fn set_selection(&self, control: &CustomControl<Self>, selection: HexSelection) {
+        if selection == self.selection.get() {
+            return;
+        }
+        self.selection.set(selection);
+        control.invalidate_all();
+        if let Some(handler) = self.selection_changed_handler.get() {
+            handler(selection.range(self.len()));
+        }
+    }
+
+    fn copy_selection_as_hex(&self, control: &CustomControl<Self>) -> Result<()> {
+        let Some(source) = self.source.borrow().clone() else {
+            return Ok(());
+        };
+        let range = self.selection.get().range(source.len());
+        let count = range.end - range.start;
+        if count > MAX_COPY_BYTES {
+            return Err(Error::Windows(ERROR_FILE_TOO_LARGE));
+        }
+        let mut bytes = vec![0; count as usize];
+        let n = source
+            .read_at(range.start, &mut bytes)
+            .map_err(|_| Error::Windows(ERROR_READ_FAULT))?;
+        if n < bytes.len() {
+            // The data has shrunk since the selection was made.
+            return Err(Error::Windows(ERROR_READ_FAULT));
+        }
+        set_clipboard_text(
+            control.handle(),
+            &format_hex(&bytes, self.layout.get().bytes_per_row),
+        )
+    }
+
+    fn background_color(&self, offset: u64, selection: &Range<u64>) -> Option<ColorRef> {
+        if selection.contains(&offset) {
+            let color = if self.has_focus.get() {
+                SysColor::Highlight
+            } else {
+                SysColor::GrayText
+            };
+            return Some(ColorRef::from_sys_color(color));
+        }
+        // Later highlights are drawn over earlier ones.
+        self.highlights
+            .borrow()
+            .iter()
+            .rev()
+            .find(|highlight| highlight.range.contains(&offset))
+            .map(|highlight| highlight.color)
+    }
+}
+
+impl CustomInner for HexViewInner {
+    fn captures_mouse(&self) -> bool {
+        true
+    }
+
+    fn paint(&self, control: &CustomControl<Self>, dc: &Dc, rect: &Rect) {
+        let layout = self.layout.get();
+        let cw = layout.char_width;
+        let lh = layout.line_height.max(1);
+        dc.select_font(&self.font.borrow());
+        dc.set_transparent_text();
+
+        let Some(source) = self.source.borrow().clone() else {
+            return;
+        };
+        let len = source.len();
+
+        // Read all of the rows that intersect the update rectangle at once.
+        let top = self.top_row.get();
+        let first_row = top + (rect.top.max(0) / lh) as u64;
+        let end_row =
+            (top + (rect.bottom.max(0) as u64).div_ceil(lh as u64)).min(layout.row_count(len));
+        if first_row >= end_row {
+            return;
+        }
+        let row_bytes = layout.bytes_per_row;
+        let mut buffer = vec![0; (end_row - first_row) as usize * row_bytes];
+        let n = match source.read_at(first_row * row_bytes as u64, &mut buffer) {
+            Ok(n) => n,
+            Err(e) => {
+                warn!("HexView: failed to read data: {e}");
+                0
+            }
+        };
+        buffer.truncate(n);
+
+        let selection = self.selection.get().range(len);
+        let window_text = ColorRef::from_sys_color(SysColor::WindowText);
+        let gray_text = ColorRef::from_sys_color(SysColor::GrayText);
+        let highlight_text = ColorRef::from_sys_color(SysColor::HighlightText);
+
+        for (i, bytes) in buffer.chunks(row_bytes).enumerate() {
+            let row = first_row + i as u64;
+            let y = ((row - top) as i32) * lh;
+            let row_offset = row * row_bytes as u64;
+
+            dc.set_text_color(gray_text);
+            dc.text_out(
+                MARGIN_CHARS as i32 * cw,
+                y,
+                &format_offset(row_offset, layout.offset_digits),
+            );
+
+            for (column, &b) in bytes.iter().enumerate() {
+                let offset = row_offset + column as u64;
+                let hex_x = layout.hex_x(column) as i32 * cw;
+                let ascii_x = layout.ascii_x(column) as i32 * cw;
+
+                let selected = selection.contains(&offset);
+                if let Some(color) = self.background_color(offset, &selection) {
+                    // Selected bytes that are next to each other are joined up.
+                    let extend = if selected
+                        && column + 1 < bytes.len()
+                        && selection.contains(&(offset + 1))
+                    {
+                        (layout.hex_x(column + 1) - layout.hex_x(column) - 2) as i32 * cw
+                    } else {
+                        0
+                    };
+                    dc.fill_rect(hex_x, y, hex_x + 2 * cw + extend, y + lh, color);
+                    dc.fill_rect(ascii_x, y, ascii_x + cw, y + lh, color);
+                }
+
+                dc.set_text_color(if selected && self.has_focus.get() {
+                    highlight_text
+                } else {
+                    window_text
+                });
+                dc.text_out(hex_x, y, &format!("{b:02X}"));
+                dc.text_out(ascii_x, y, &printable_char(b).to_string());
+            }
+        }
+    }
+
+    fn mouse_down(&self, control: &CustomControl<Self>, pt: POINT) {
+        let len = self.len();
+        if len == 0 {
+            return;
+        }
+        let hit = self.layout.get().hit_test(pt.x, pt.y, self.top_row.get());
+        let offset = hit.offset.min(len - 1);
+        let extend = unsafe { GetKeyState(VK_SHIFT.0 as i32) } < 0;
+        let selection = if extend {
+            HexSelection {
+                anchor: self.selection.get().anchor,
+                caret: offset,
+            }
+        } else {
+            HexSelection::at(offset)
+        };
+        self.dragging.set(true);
+        self.set_selection(control, selection);
+    }
+
+    fn mouse_move(&self, control: &CustomControl<Self>, pt: POINT) {
+        let len = self.len();
+        if !self.dragging.get() || len == 0 {
+            return;
+        }
+        // Dragging above or below the control scrolls.
+        if pt.y < 0 {
+            self.scroll_to(control, self.top_row.get().saturating_sub(1));
+        } else if pt.y >= self.client_height.get() {
+            self.scroll_to(control, self.top_row.get() + 1);
+        }
+        let hit = self.layout.get().hit_test(pt.x, pt.y, self.top_row.get());
+        let selection = HexSelection {
+            anchor: self.selection.get().anchor,
+            caret: hit.offset.min(len - 1),
+        };
+        self.set_selection(control, selection);
+    }
+
+    fn mouse_up(&self, control: &CustomControl<Self>, pt: POINT) {
+        self.dragging.set(false);
+    }
+
+    fn mouse_wheel(&self, control: &CustomControl<Self>, delta: i16) {
+        let rows = delta as i64 * 3 / WHEEL_DELTA as i64;
+        let top = self.top_row.get();
+        let row = if rows > 0 {
+            top.saturating_sub(rows as u64)
+        } else {
+            top.saturating_add(rows.unsigned_abs())
+        };
+        self.scroll_to(control, row);
+    }
+
+    fn key_down(&self, control: &CustomControl<Self>, key: u16) -> bool {
+        let ctrl = unsafe { GetKeyState(VK_CONTROL.0 as i32) } < 0;
+        let shift = unsafe { GetKeyState(VK_SHIFT.0 as i32) } < 0;
+
+        if ctrl && key == b'C' as u16 {
+            if let Err(e) = self.copy_selection_as_hex(control) {
+                warn!("HexView: failed to copy: {e:?}");
+            }
+            return true;
+        }
+        if ctrl && key == b'A' as u16 {
+            let len = self.len();
+            if len > 0 {
+                self.set_selection(
+                    control,
+                    HexSelection {
+                        anchor: 0,
+                        caret: len - 1,
+                    },
+                );
+            }
+            return true;
+        }
+
+        let movement = match VIRTUAL_KEY(key) {
+            VK_LEFT => CaretMove::Left,
+            VK_RIGHT => CaretMove::Right,
+            VK_UP => CaretMove::Up,
+            VK_DOWN => CaretMove::Down,
+            VK_PRIOR => CaretMove::PageUp,
+            VK_NEXT => CaretMove::PageDown,
+            VK_HOME if ctrl => CaretMove::Start,
+            VK_END if ctrl => CaretMove::End,
+            VK_HOME => CaretMove::RowStart,
+            VK_END => CaretMove::RowEnd,
+            _ => return false,
+        };
+
+        let layout = self.layout.get();
+        let old = self.selection.get();
+        let caret = move_caret(
+            old.caret,
+            movement,
+            layout.bytes_per_row,
+            layout.page_rows(self.client_height.get()),
+            self.len(),
+        );
+        let selection = if shift {
+            HexSelection {
+                anchor: old.anchor,
+                caret,
+            }
+        } else {
+            HexSelection::at(caret)
+        };
+        self.set_selection(control, selection);
+        self.scroll_to_show(control, caret);
+        true
+    }
+
+    fn vertical_scroll(&self, control: &CustomControl<Self>, code: u16) {
+        let layout = self.layout.get();
+        let page = layout.page_rows(self.client_height.get());
+        let top = self.top_row.get();
+        let row = match SCROLLBAR_COMMAND(code as i32) {
+            SB_LINEUP => top.saturating_sub(1),
+            SB_LINEDOWN => top + 1,
+            SB_PAGEUP => top.saturating_sub(page),
+            SB_PAGEDOWN => top + page,
+            SB_TOP => 0,
+            SB_BOTTOM => self.max_top_row(),
+            SB_THUMBTRACK | SB_THUMBPOSITION => unsafe {
+                // The position in WM_VSCROLL is only 16 bits.
+                let mut info = SCROLLINFO {
+                    cbSize: size_of::<SCROLLINFO>() as u32,
+                    fMask: SIF_TRACKPOS,
+                    ..Default::default()
+                };
+                _ = GetScrollInfo(control.handle(), SB_VERT, &mut info);
+                info.nTrackPos as u64 * scroll_scale(layout.row_count(self.len()))
+            },
+            _ => return,
+        };
+        self.scroll_to(control, row);
+    }
+
+    fn size(&self, control: &CustomControl<Self>, width: i32, height: i32) {
+        self.client_height.set(height);
+        // Growing the window can leave empty space below the last row.
+        self.top_row.set(self.top_row.get().min(self.max_top_row()));
+        self.update_scroll_bar(control);
+        control.invalidate_all();
+    }
+
+    fn focus_changed(&self, control: &CustomControl<Self>, has_focus: bool) {
+        self.has_focus.set(has_focus);
+        control.invalidate_all();
+    }
+
+    fn dialog_code(&self, control: &CustomControl<Self>) -> u32 {
+        DLGC_WANTARROWS | DLGC_WANTCHARS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> HexLayout {
+        HexLayout::new(16, 0x100, 8, 16)
+    }
+
+    #[test]
+    fn offset_digits_has_at_least_8() {
+        assert_eq!(offset_digits(0), 8);
+        assert_eq!(offset_digits(1), 8);
+        assert_eq!(offset_digits(0x1_0000_0000), 8);
+        assert_eq!(offset_digits(0x1_0000_0001), 9);
+        assert_eq!(offset_digits(u64::MAX), 16);
+    }
+
+    #[test]
+    fn format_offset_pads_with_zeros() {
+        assert_eq!(format_offset(0x1F, 8), "0000001F");
+        assert_eq!(format_offset(0x1_2345_6789, 9), "123456789");
+    }
+
+    #[test]
+    fn layout_columns() {
+        let layout = layout();
+        assert_eq!(layout.hex_start(), 11);
+        assert_eq!(layout.hex_x(0), 11);
+        assert_eq!(layout.hex_x(7), 32);
+        // The second group of 8 bytes has an extra space before it.
+        assert_eq!(layout.hex_x(8), 36);
+        assert_eq!(layout.hex_x(15), 57);
+        assert_eq!(layout.ascii_start(), 61);
+        assert_eq!(layout.row_chars(), 78);
+        assert_eq!(HexLayout::new(0, 0, 8, 16).bytes_per_row, 1);
+    }
+
+    #[test]
+    fn row_counts() {
+        let layout = layout();
+        assert_eq!(layout.row_count(0), 0);
+        assert_eq!(layout.row_count(16), 1);
+        assert_eq!(layout.row_count(17), 2);
+        assert_eq!(layout.visible_rows(40), 3);
+        assert_eq!(layout.visible_rows(-5), 0);
+        assert_eq!(layout.page_rows(40), 2);
+        assert_eq!(layout.page_rows(5), 1);
+
+        let empty = HexLayout::new(16, 0, 8, 0);
+        assert_eq!(empty.visible_rows(40), 0);
+        assert_eq!(empty.page_rows(40), 1);
+    }
+
+    #[test]
+    fn format_row_matches_the_module_docs() {
+        assert_eq!(
+            format_row(&layout(), 0x10, b"Hello, world!\n\0\0"),
+            " 00000010  48 65 6C 6C 6F 2C 20 77  6F 72 6C 64 21 0A 00 00  Hello, world!..."
+        );
+    }
+
+    #[test]
+    fn format_row_lines_up_a_partial_row() {
+        let row = format_row(&layout(), 0x20, b"AB");
+        assert_eq!(row, format!("{:<61}AB", " 00000020  41 42"));
+        assert_eq!(format_row(&layout(), 0, b""), " 00000000");
+    }
+
+    #[test]
+    fn format_row_ignores_extra_bytes() {
+        let layout = HexLayout::new(2, 0, 8, 16);
+        assert_eq!(format_row(&layout, 0, b"abc"), " 00000000  61 62  ab");
+    }
+
+    #[test]
+    fn printable_chars() {
+        assert_eq!(printable_char(b'A'), 'A');
+        assert_eq!(printable_char(b' '), ' ');
+        assert_eq!(printable_char(b'~'), '~');
+        assert_eq!(printable_char(0x1f), '.');
+        assert_eq!(printable_char(0x7f), '.');
+        assert_eq!(printable_char(0xe9), '.');
+    }
+
+    #[test]
+    fn format_hex_breaks_lines() {
+        assert_eq!(format_hex(&[], 16), "");
+        assert_eq!(format_hex(&[0, 0xAB, 0x10], 2), "00 AB\r\n10");
+        assert_eq!(format_hex(&[0, 0xAB, 0x10], 3), "00 AB 10");
+        assert_eq!(format_hex(&[0, 0xAB, 0x10], 0), "00 AB 10");
+    }
+
+    #[test]
+    fn parse_offset_hex_and_decimal() {
+        assert_eq!(parse_offset("0x1F00"), Some(0x1F00));
+        assert_eq!(parse_offset("0X10"), Some(0x10));
+        assert_eq!(parse_offset("1F00h"), Some(0x1F00));
+        assert_eq!(parse_offset("1f00H"), Some(0x1F00));
+        assert_eq!(parse_offset("1000"), Some(1000));
+        assert_eq!(parse_offset(" 1_000 "), Some(1000));
+        assert_eq!(parse_offset("0x 1F_00"), Some(0x1F00));
+    }
+
+    #[test]
+    fn parse_offset_rejects_invalid_text() {
+        assert_eq!(parse_offset(""), None);
+        assert_eq!(parse_offset("abc"), None);
+        assert_eq!(parse_offset("0x"), None);
+        assert_eq!(parse_offset("h"), None);
+        assert_eq!(parse_offset("-1"), None);
+        assert_eq!(parse_offset("0x1_0000_0000_0000_0000"), None);
+    }
+
+    #[test]
+    fn move_caret_by_bytes_and_rows() {
+        let mv = |caret, movement| move_caret(caret, movement, 16, 4, 100);
+        assert_eq!(mv(0, CaretMove::Left), 0);
+        assert_eq!(mv(5, CaretMove::Left), 4);
+        assert_eq!(mv(5, CaretMove::Right), 6);
+        assert_eq!(mv(99, CaretMove::Right), 99);
+        assert_eq!(mv(5, CaretMove::Up), 5);
+        assert_eq!(mv(20, CaretMove::Up), 4);
+        assert_eq!(mv(80, CaretMove::Down), 96);
+        // There is no byte below 90, so the caret stays on the last row.
+        assert_eq!(mv(90, CaretMove::Down), 90);
+    }
+
+    #[test]
+    fn move_caret_by_pages() {
+        let mv = |caret, movement| move_caret(caret, movement, 16, 4, 100);
+        assert_eq!(mv(70, CaretMove::PageUp), 6);
+        assert_eq!(mv(20, CaretMove::PageUp), 4);
+        assert_eq!(mv(5, CaretMove::PageDown), 69);
+        assert_eq!(mv(70, CaretMove::PageDown), 99);
+    }
+
+    #[test]
+    fn move_caret_to_ends() {
+        let mv = |caret, movement| move_caret(caret, movement, 16, 4, 100);
+        assert_eq!(mv(37, CaretMove::RowStart), 32);
+        assert_eq!(mv(37, CaretMove::RowEnd), 47);
+        assert_eq!(mv(97, CaretMove::RowEnd), 99);
+        assert_eq!(mv(37, CaretMove::Start), 0);
+        assert_eq!(mv(37, CaretMove::End), 99);
+    }
+
+    #[test]
+    fn move_caret_clamps_to_the_data() {
+        assert_eq!(move_caret(5, CaretMove::Right, 16, 4, 0), 0);
+        assert_eq!(move_caret(500, CaretMove::Left, 16, 4, 100), 98);
+        assert_eq!(move_caret(500, CaretMove::Start, 16, 4, 100), 0);
+    }
+
+    #[test]
+    fn scroll_scale_for_huge_data() {
+        assert_eq!(scroll_scale(0), 1);
+        assert_eq!(scroll_scale(i32::MAX as u64 - 1), 1);
+        assert_eq!(scroll_scale(i32::MAX as u64), 2);
+        assert_eq!(scroll_scale(u64::MAX), u64::MAX / i32::MAX as u64 + 1);
+    }
+
+    #[test]
+    fn hit_test_columns() {
+        let layout = layout();
+        let hit = |x_chars: i32| layout.hit_test(x_chars * 8, 0, 0);
+        assert_eq!(hit(0).area, HexArea::Offset);
+        assert_eq!(hit(0).offset, 0);
+        assert_eq!(hit(10).area, HexArea::Offset);
+        assert_eq!(
+            hit(11),
+            HexHit {
+                offset: 0,
+                area: HexArea::Hex
+            }
+        );
+        assert_eq!(
+            hit(13),
+            HexHit {
+                offset: 0,
+                area: HexArea::Hex
+            }
+        );
+        assert_eq!(
+            hit(14),
+            HexHit {
+                offset: 1,
+                area: HexArea::Hex
+            }
+        );
+        // The gap between the groups belongs to the byte before it.
+        assert_eq!(
+            hit(35),
+            HexHit {
+                offset: 7,
+                area: HexArea::Hex
+            }
+        );
+        assert_eq!(
+            hit(59),
+            HexHit {
+                offset: 15,
+                area: HexArea::Hex
+            }
+        );
+        assert_eq!(
+            hit(60),
+            HexHit {
+                offset: 0,
+                area: HexArea::Ascii
+            }
+        );
+        assert_eq!(
+            hit(63),
+            HexHit {
+                offset: 2,
+                area: HexArea::Ascii
+            }
+        );
+        assert_eq!(
+            hit(200),
+            HexHit {
+                offset: 15,
+                area: HexArea::Ascii
+            }
+        );
+    }
+
+    #[test]
+    fn hit_test_rows() {
+        let layout = layout();
+        assert_eq!(layout.hit_test(11 * 8, 40, 5).offset, 7 * 16);
+        assert_eq!(layout.hit_test(11 * 8 + 7, 47, 5).offset, 7 * 16);
+        assert_eq!(layout.hit_test(-10, -10, 5).offset, 5 * 16);
+    }
+
+    #[test]
+    fn selection_range() {
+        let selection = HexSelection {
+            anchor: 10,
+            caret: 5,
+        };
+        assert_eq!(selection.range(100), 5..11);
+        assert_eq!(selection.range(8), 5..8);
+        assert_eq!(selection.range(0), 0..0);
+        assert_eq!(HexSelection::at(3).range(100), 3..4);
+        assert_eq!(
+            HexSelection::at(u64::MAX).range(u64::MAX),
+            u64::MAX..u64::MAX
+        );
+    }
+
+    #[test]
+    fn vec_byte_source_reads_to_the_end() {
+        let data = vec![1u8, 2, 3, 4];
+        let mut buf = [0; 3];
+        assert_eq!(data.read_at(2, &mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [3, 4]);
+        assert_eq!(data.read_at(10, &mut buf).unwrap(), 0);
+    }
+}
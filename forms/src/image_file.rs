@@ -0,0 +1,651 @@
+//! Decoding of ICO and BMP files, for building an `ImageList`. This does not call
+//! into Windows, so it works on image data from any source.
+
+use super::*;
+use core::ops::Range;
+
+/// An image decoded into 32-bit pixels.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels in B, G, R, A byte order, with rows from top to bottom. Alpha is not
+    /// premultiplied.
+    pub bgra: Vec<u8>,
+}
+
+/// One image in the directory of an ICO file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IconEntry {
+    pub width: u32,
+    pub height: u32,
+    /// Bits per pixel, as recorded in the directory. This is 0 in some files.
+    pub bit_count: u16,
+    /// Location of the image within the file.
+    pub data: Range<usize>,
+}
+
+/// The image data of an `IconEntry`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IconImage<'a> {
+    /// A PNG image, which Windows decodes itself.
+    Png(&'a [u8]),
+    Bitmap(DecodedImage),
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Larger images are rejected, so that a bad header cannot cause a huge allocation.
+const MAX_DIMENSION: u32 = 0x4000;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+fn invalid(message: &'static str) -> Error {
+    Error::InvalidImage(message)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data.get(offset..offset + 2).ok_or(invalid("truncated"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or(invalid("truncated"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads the directory of an ICO file.
+///
+/// See <https://learn.microsoft.com/en-us/previous-versions/ms997538(v=msdn.10)>
+pub fn parse_ico_directory(data: &[u8]) -> Result<Vec<IconEntry>> {
+    if read_u16(data, 0)? != 0 || read_u16(data, 2)? != 1 {
+        return Err(invalid("not an ICO file"));
+    }
+    let count = read_u16(data, 4)? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        let header = data.get(entry..entry + 16).ok_or(invalid("truncated"))?;
+        let size = read_u32(data, entry + 8)? as usize;
+        let offset = read_u32(data, entry + 12)? as usize;
+        let end = offset.checked_add(size).ok_or(invalid("truncated"))?;
+        if end > data.len() {
+            return Err(invalid("truncated"));
+        }
+
+        // A width or height of 0 means 256.
+        let dimension = |b: u8| if b == 0 { 256 } else { b as u32 };
+        entries.push(IconEntry {
+            width: dimension(header[0]),
+            height: dimension(header[1]),
+            bit_count: read_u16(data, entry + 6)?,
+            data: offset..end,
+        });
+    }
+    Ok(entries)
+}
+
+/// Chooses the entry that is best displayed at `width` by `height`: an exact match
+/// with the most colors, or else the closest larger image (which will be shrunk),
+/// or else the largest image.
+pub fn choose_icon_entry(entries: &[IconEntry], width: u32, height: u32) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, e)| {
+            let exact = e.width == width && e.height == height;
+            let larger = e.width >= width && e.height >= height;
+            let distance = if larger { e.width } else { u32::MAX - e.width };
+            (!exact, !larger, distance, core::cmp::Reverse(e.bit_count))
+        })
+        .map(|(i, _)| i)
+}
+
+/// Decodes the data of one `IconEntry`, which is either a PNG image or a DIB
+/// followed by a 1-bit transparency mask.
+pub fn decode_icon_image(data: &[u8]) -> Result<IconImage<'_>> {
+    if data.starts_with(PNG_SIGNATURE) {
+        return Ok(IconImage::Png(data));
+    }
+
+    let header = DibHeader::parse(data, true)?;
+    let pixels_start = header.pixels_offset;
+    let pixels_len = header.stride() * header.height as usize;
+    let pixels = data
+        .get(pixels_start..pixels_start + pixels_len)
+        .ok_or(invalid("truncated"))?;
+    let mut image = header.decode(pixels)?;
+
+    // The AND mask gives transparency, unless the image has its own alpha channel.
+    let has_alpha = header.bit_count == 32 && image.bgra.chunks(4).any(|p| p[3] != 0);
+    if !has_alpha {
+        let mask_stride = (header.width as usize).div_ceil(32) * 4;
+        let mask_start = pixels_start + pixels_len;
+        let mask = data
+            .get(mask_start..mask_start + mask_stride * header.height as usize)
+            .ok_or(invalid("truncated"))?;
+        for y in 0..header.height as usize {
+            // Mask rows are bottom-up, like the pixels.
+            let mask_row = &mask[(header.height as usize - 1 - y) * mask_stride..];
+            for x in 0..header.width as usize {
+                let transparent = mask_row[x / 8] & (0x80 >> (x % 8)) != 0;
+                let pixel = (y * header.width as usize + x) * 4;
+                image.bgra[pixel + 3] = if transparent { 0 } else { 0xff };
+            }
+        }
+    }
+
+    Ok(IconImage::Bitmap(image))
+}
+
+/// Decodes a BMP file. Uncompressed images with 1, 4, 8, 16, 24 or 32 bits per
+/// pixel are supported.
+pub fn decode_bmp(data: &[u8]) -> Result<DecodedImage> {
+    if !data.starts_with(b"BM") {
+        return Err(invalid("not a BMP file"));
+    }
+    let pixels_offset = read_u32(data, 10)? as usize;
+    let dib = data.get(14..).ok_or(invalid("truncated"))?;
+    let header = DibHeader::parse(dib, false)?;
+
+    let pixels_len = header.stride() * header.height as usize;
+    let pixels = data
+        .get(pixels_offset..pixels_offset + pixels_len)
+        .ok_or(invalid("truncated"))?;
+    let mut image = header.decode(pixels)?;
+
+    // Many 32-bit BMP files leave the fourth byte as 0, which means opaque.
+    if image.bgra.chunks(4).all(|p| p[3] == 0) {
+        for pixel in image.bgra.chunks_mut(4) {
+            pixel[3] = 0xff;
+        }
+    }
+    Ok(image)
+}
+
+impl DecodedImage {
+    /// Splits an image strip, such as a set of toolbar images, into images that
+    /// are `tile_width` pixels wide.
+    pub fn split_strip(&self, tile_width: u32) -> Vec<DecodedImage> {
+        if tile_width == 0 {
+            return Vec::new();
+        }
+        let row_bytes = self.width as usize * 4;
+        let tile_bytes = tile_width as usize * 4;
+        (0..self.width / tile_width)
+            .map(|tile| {
+                let mut bgra = Vec::with_capacity(tile_bytes * self.height as usize);
+                for row in self.bgra.chunks(row_bytes) {
+                    let start = tile as usize * tile_bytes;
+                    bgra.extend_from_slice(&row[start..start + tile_bytes]);
+                }
+                DecodedImage {
+                    width: tile_width,
+                    height: self.height,
+                    bgra,
+                }
+            })
+            .collect()
+    }
+}
+
+// https://learn.microsoft.com/en-us/windows/win32/api/wingdi/ns-wingdi-bitmapinfoheader
+struct DibHeader {
+    width: u32,
+    height: u32,
+    top_down: bool,
+    bit_count: u16,
+    // masks for 16 and 32 bit images
+    red_mask: u32,
+    green_mask: u32,
+    blue_mask: u32,
+    alpha_mask: u32,
+    palette: Vec<[u8; 4]>,
+    // offset of the pixels from the start of the header, for icons
+    pixels_offset: usize,
+}
+
+impl DibHeader {
+    /// `is_icon` means that the height counts both the image and its mask.
+    fn parse(data: &[u8], is_icon: bool) -> Result<DibHeader> {
+        let header_size = read_u32(data, 0)? as usize;
+        if header_size < 40 {
+            return Err(invalid("unsupported bitmap header"));
+        }
+        let width = read_u32(data, 4)? as i32;
+        let mut height = read_u32(data, 8)? as i32;
+        let bit_count = read_u16(data, 14)?;
+        let compression = read_u32(data, 16)?;
+        let colors_used = read_u32(data, 32)?;
+
+        if is_icon {
+            height /= 2;
+        }
+        let top_down = height < 0;
+        let height = height.unsigned_abs();
+        if width <= 0 || height == 0 || width as u32 > MAX_DIMENSION || height > MAX_DIMENSION {
+            return Err(invalid("bad image size"));
+        }
+
+        let (mut red_mask, mut green_mask, mut blue_mask, mut alpha_mask) = match bit_count {
+            16 => (0x7c00, 0x03e0, 0x001f, 0),
+            32 => (0xff0000, 0xff00, 0xff, 0xff000000),
+            _ => (0, 0, 0, 0),
+        };
+
+        let mut end = header_size;
+        match compression {
+            BI_RGB => {}
+            BI_BITFIELDS | BI_ALPHABITFIELDS if bit_count == 16 || bit_count == 32 => {
+                // Version 4 and 5 headers contain the masks, otherwise they follow
+                // the header.
+                red_mask = read_u32(data, 40)?;
+                green_mask = read_u32(data, 44)?;
+                blue_mask = read_u32(data, 48)?;
+                if header_size >= 56 || compression == BI_ALPHABITFIELDS {
+                    alpha_mask = read_u32(data, 52)?;
+                } else {
+                    alpha_mask = 0;
+                }
+                if header_size == 40 {
+                    end += if compression == BI_ALPHABITFIELDS {
+                        16
+                    } else {
+                        12
+                    };
+                }
+            }
+            _ => return Err(invalid("compressed bitmaps are not supported")),
+        }
+
+        let mut palette = Vec::new();
+        match bit_count {
+            1 | 4 | 8 => {
+                let count = if colors_used == 0 || colors_used > 1 << bit_count {
+                    1 << bit_count
+                } else {
+                    colors_used as usize
+                };
+                let entries = data.get(end..end + count * 4).ok_or(invalid("truncated"))?;
+                palette.extend(entries.chunks(4).map(|c| [c[0], c[1], c[2], 0xff]));
+                end += count * 4;
+            }
+            16 | 24 | 32 => {}
+            _ => return Err(invalid("unsupported bits per pixel")),
+        }
+
+        Ok(DibHeader {
+            width: width as u32,
+            height,
+            top_down,
+            bit_count,
+            red_mask,
+            green_mask,
+            blue_mask,
+            alpha_mask,
+            palette,
+            pixels_offset: end,
+        })
+    }
+
+    /// Rows are padded to a multiple of 4 bytes.
+    fn stride(&self) -> usize {
+        (self.width as usize * self.bit_count as usize).div_ceil(32) * 4
+    }
+
+    fn decode(&self, pixels: &[u8]) -> Result<DecodedImage> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let stride = self.stride();
+        let mut bgra = Vec::with_capacity(width * height * 4);
+
+        for y in 0..height {
+            let src_y = if self.top_down { y } else { height - 1 - y };
+            let row = &pixels[src_y * stride..(src_y + 1) * stride];
+            for x in 0..width {
+                let pixel = match self.bit_count {
+                    1 | 4 | 8 => {
+                        let bits = self.bit_count as usize;
+                        let byte = row[x * bits / 8];
+                        let shift = 8 - bits - (x * bits % 8);
+                        let index = (byte >> shift) as usize & ((1 << bits) - 1);
+                        *self
+                            .palette
+                            .get(index)
+                            .ok_or(invalid("bad palette index"))?
+                    }
+                    16 => {
+                        let value = u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]) as u32;
+                        self.unpack(value)
+                    }
+                    24 => [row[x * 3], row[x * 3 + 1], row[x * 3 + 2], 0xff],
+                    _ => {
+                        let p = &row[x * 4..x * 4 + 4];
+                        self.unpack(u32::from_le_bytes([p[0], p[1], p[2], p[3]]))
+                    }
+                };
+                bgra.extend_from_slice(&pixel);
+            }
+        }
+
+        Ok(DecodedImage {
+            width: self.width,
+            height: self.height,
+            bgra,
+        })
+    }
+
+    fn unpack(&self, value: u32) -> [u8; 4] {
+        let alpha = if self.alpha_mask == 0 {
+            0xff
+        } else {
+            scale_channel(value, self.alpha_mask)
+        };
+        [
+            scale_channel(value, self.blue_mask),
+            scale_channel(value, self.green_mask),
+            scale_channel(value, self.red_mask),
+            alpha,
+        ]
+    }
+}
+
+/// Extracts the bits selected by `mask`, scaled to the range 0..=255.
+fn scale_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = (mask >> shift) as u64;
+    let bits = ((value & mask) >> shift) as u64;
+    (bits * 255 / max) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_invalid<T>(result: Result<T>, message: &str) -> bool {
+        matches!(result, Err(Error::InvalidImage(m)) if m == message)
+    }
+
+    /// A `BITMAPINFOHEADER`.
+    fn dib_header(
+        width: i32,
+        height: i32,
+        bit_count: u16,
+        compression: u32,
+        colors: u32,
+    ) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&bit_count.to_le_bytes());
+        header.extend_from_slice(&compression.to_le_bytes());
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(&colors.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header
+    }
+
+    /// A BMP file, given the DIB header with its masks or palette.
+    fn bmp(dib: &[u8], pixels: &[u8]) -> Vec<u8> {
+        let offset = 14 + dib.len() as u32;
+        let mut file = b"BM".to_vec();
+        file.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&offset.to_le_bytes());
+        file.extend_from_slice(dib);
+        file.extend_from_slice(pixels);
+        file
+    }
+
+    /// An ICO file with entries of the given width, height and bits per pixel.
+    fn ico(entries: &[(u8, u8, u16, &[u8])]) -> Vec<u8> {
+        let mut file = vec![0, 0, 1, 0];
+        file.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut offset = 6 + entries.len() * 16;
+        for &(width, height, bit_count, data) in entries {
+            file.extend_from_slice(&[width, height, 0, 0, 1, 0]);
+            file.extend_from_slice(&bit_count.to_le_bytes());
+            file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += data.len();
+        }
+        for &(_, _, _, data) in entries {
+            file.extend_from_slice(data);
+        }
+        file
+    }
+
+    // Red and green on the top row, blue and white on the bottom row, as BGR rows
+    // padded to 8 bytes.
+    const TOP_ROW_24: [u8; 8] = [0, 0, 0xff, 0, 0xff, 0, 0, 0];
+    const BOTTOM_ROW_24: [u8; 8] = [0xff, 0, 0, 0xff, 0xff, 0xff, 0, 0];
+    const EXPECTED_2X2: [u8; 16] = [
+        0, 0, 0xff, 0xff, 0, 0xff, 0, 0xff, // top
+        0xff, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, // bottom
+    ];
+
+    #[test]
+    fn ico_directory() {
+        let file = ico(&[(16, 16, 32, &[1; 8]), (0, 0, 8, &[2; 4])]);
+        let entries = parse_ico_directory(&file).unwrap();
+        assert_eq!(
+            entries,
+            [
+                IconEntry {
+                    width: 16,
+                    height: 16,
+                    bit_count: 32,
+                    data: 38..46,
+                },
+                IconEntry {
+                    width: 256,
+                    height: 256,
+                    bit_count: 8,
+                    data: 46..50,
+                },
+            ]
+        );
+        assert!(parse_ico_directory(&ico(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ico_directory_invalid() {
+        assert!(is_invalid(parse_ico_directory(&[]), "truncated"));
+        assert!(is_invalid(parse_ico_directory(&[0, 0, 1]), "truncated"));
+        // A cursor file has type 2.
+        let mut cursor = ico(&[(16, 16, 32, &[1; 8])]);
+        cursor[2] = 2;
+        assert!(is_invalid(parse_ico_directory(&cursor), "not an ICO file"));
+
+        let file = ico(&[(16, 16, 32, &[1; 8])]);
+        // The directory says there are two entries.
+        let mut extra = file.clone();
+        extra[4] = 2;
+        assert!(is_invalid(parse_ico_directory(&extra), "truncated"));
+        // The image data is cut short.
+        assert!(is_invalid(
+            parse_ico_directory(&file[..file.len() - 1]),
+            "truncated"
+        ));
+        // The offset overflows.
+        let mut overflow = file.clone();
+        overflow[18..22].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_ico_directory(&overflow).is_err());
+    }
+
+    #[test]
+    fn choose_entry() {
+        let entry = |width, bit_count| IconEntry {
+            width,
+            height: width,
+            bit_count,
+            data: 0..0,
+        };
+        let entries = [entry(16, 8), entry(16, 32), entry(48, 32), entry(32, 32)];
+        assert_eq!(choose_icon_entry(&entries, 16, 16), Some(1));
+        assert_eq!(choose_icon_entry(&entries, 24, 24), Some(3));
+        assert_eq!(choose_icon_entry(&entries, 64, 64), Some(2));
+        assert_eq!(choose_icon_entry(&[], 16, 16), None);
+    }
+
+    #[test]
+    fn bmp_bottom_up() {
+        let pixels = [BOTTOM_ROW_24, TOP_ROW_24].concat();
+        let image = decode_bmp(&bmp(&dib_header(2, 2, 24, BI_RGB, 0), &pixels)).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.bgra, EXPECTED_2X2);
+    }
+
+    #[test]
+    fn bmp_top_down() {
+        let pixels = [TOP_ROW_24, BOTTOM_ROW_24].concat();
+        let image = decode_bmp(&bmp(&dib_header(2, -2, 24, BI_RGB, 0), &pixels)).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.bgra, EXPECTED_2X2);
+    }
+
+    #[test]
+    fn bmp_bitfields() {
+        // 5-6-5 masks, after a version 1 header.
+        let mut dib = dib_header(3, 1, 16, BI_BITFIELDS, 0);
+        for mask in [0xf800u32, 0x07e0, 0x001f] {
+            dib.extend_from_slice(&mask.to_le_bytes());
+        }
+        let pixels = [0x00, 0xf8, 0x1f, 0x00, 0x00, 0x04, 0, 0];
+        let image = decode_bmp(&bmp(&dib, &pixels)).unwrap();
+        assert_eq!(
+            image.bgra,
+            [0, 0, 0xff, 0xff, 0xff, 0, 0, 0xff, 0, 129, 0, 0xff]
+        );
+    }
+
+    #[test]
+    fn bmp_alpha_bitfields() {
+        // The channels are in the opposite order to the usual one.
+        let mut dib = dib_header(1, 1, 32, BI_ALPHABITFIELDS, 0);
+        for mask in [0xffu32, 0xff00, 0xff0000, 0xff000000] {
+            dib.extend_from_slice(&mask.to_le_bytes());
+        }
+        let image = decode_bmp(&bmp(&dib, &[0x10, 0x20, 0x30, 0x40])).unwrap();
+        assert_eq!(image.bgra, [0x30, 0x20, 0x10, 0x40]);
+    }
+
+    #[test]
+    fn bmp_32_bit_alpha() {
+        let dib = dib_header(1, 1, 32, BI_RGB, 0);
+        // An alpha of 0 everywhere means the file has no alpha.
+        let image = decode_bmp(&bmp(&dib, &[1, 2, 3, 0])).unwrap();
+        assert_eq!(image.bgra, [1, 2, 3, 0xff]);
+        let image = decode_bmp(&bmp(&dib, &[1, 2, 3, 0x80])).unwrap();
+        assert_eq!(image.bgra, [1, 2, 3, 0x80]);
+    }
+
+    #[test]
+    fn bmp_palette() {
+        let mut dib = dib_header(3, 1, 1, BI_RGB, 0);
+        dib.extend_from_slice(&[0, 0, 0, 0, 0xff, 0xff, 0xff, 0]);
+        let image = decode_bmp(&bmp(&dib, &[0b1010_0000, 0, 0, 0])).unwrap();
+        assert_eq!(
+            image.bgra,
+            [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+
+        // The palette has one color, and the pixel uses the second.
+        let mut dib = dib_header(1, 1, 8, BI_RGB, 1);
+        dib.extend_from_slice(&[1, 2, 3, 0]);
+        assert!(is_invalid(
+            decode_bmp(&bmp(&dib, &[1, 0, 0, 0])),
+            "bad palette index"
+        ));
+    }
+
+    #[test]
+    fn bmp_invalid() {
+        let pixels = [BOTTOM_ROW_24, TOP_ROW_24].concat();
+        let file = bmp(&dib_header(2, 2, 24, BI_RGB, 0), &pixels);
+        assert!(is_invalid(decode_bmp(b"XX"), "not a BMP file"));
+        assert!(is_invalid(decode_bmp(b"BM"), "truncated"));
+        assert!(is_invalid(decode_bmp(&file[..30]), "truncated"));
+        assert!(is_invalid(decode_bmp(&file[..file.len() - 1]), "truncated"));
+
+        let mut small_header = file.clone();
+        small_header[14..18].copy_from_slice(&12u32.to_le_bytes());
+        assert!(is_invalid(
+            decode_bmp(&small_header),
+            "unsupported bitmap header"
+        ));
+
+        // BI_RLE8
+        let rle = bmp(&dib_header(2, 2, 8, 1, 0), &pixels);
+        assert!(is_invalid(
+            decode_bmp(&rle),
+            "compressed bitmaps are not supported"
+        ));
+        let bits = bmp(&dib_header(2, 2, 2, BI_RGB, 0), &pixels);
+        assert!(is_invalid(decode_bmp(&bits), "unsupported bits per pixel"));
+        for (width, height) in [(0, 2), (-2, 2), (2, 0), (0x4001, 1)] {
+            let file = bmp(&dib_header(width, height, 24, BI_RGB, 0), &pixels);
+            assert!(is_invalid(decode_bmp(&file), "bad image size"));
+        }
+    }
+
+    #[test]
+    fn icon_with_mask() {
+        // The height counts the image and its mask.
+        let mut data = dib_header(2, 4, 24, BI_RGB, 0);
+        data.extend_from_slice(&BOTTOM_ROW_24);
+        data.extend_from_slice(&TOP_ROW_24);
+        // The top left pixel is transparent. Mask rows are bottom-up too.
+        data.extend_from_slice(&[0, 0, 0, 0, 0x80, 0, 0, 0]);
+        let Ok(IconImage::Bitmap(image)) = decode_icon_image(&data) else {
+            panic!("expected a bitmap");
+        };
+        let mut expected = EXPECTED_2X2;
+        expected[3] = 0;
+        assert_eq!(image.bgra, expected);
+
+        // The mask is missing.
+        assert!(is_invalid(
+            decode_icon_image(&data[..data.len() - 8]),
+            "truncated"
+        ));
+    }
+
+    #[test]
+    fn icon_with_alpha() {
+        // The mask is not used, or needed, when the image has alpha.
+        let mut data = dib_header(1, 2, 32, BI_RGB, 0);
+        data.extend_from_slice(&[1, 2, 3, 0x80]);
+        let Ok(IconImage::Bitmap(image)) = decode_icon_image(&data) else {
+            panic!("expected a bitmap");
+        };
+        assert_eq!(image.bgra, [1, 2, 3, 0x80]);
+    }
+
+    #[test]
+    fn icon_png() {
+        let data = [PNG_SIGNATURE, b"rest"].concat();
+        assert_eq!(decode_icon_image(&data).unwrap(), IconImage::Png(&data));
+    }
+
+    #[test]
+    fn split_strip() {
+        let image = DecodedImage {
+            width: 4,
+            height: 1,
+            bgra: (0..16).collect(),
+        };
+        let tiles = image.split_strip(2);
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles[1].bgra, (8..16).collect::<Vec<u8>>());
+        assert!(image.split_strip(0).is_empty());
+    }
+}
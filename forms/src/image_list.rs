@@ -0,0 +1,315 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/image-lists
+
+use super::*;
+use windows::Win32::UI::Shell::{SHGetStockIconInfo, SHGSI_ICON, SHGSI_LARGEICON, SHGSI_SMALLICON};
+use windows::Win32::UI::Shell::{SHSTOCKICONID, SHSTOCKICONINFO};
+
+/// A collection of same-sized images, which controls such as `Toolbar` and
+/// `ListView` refer to by index.
+pub struct ImageList {
+    pub(crate) himagelist: HIMAGELIST,
+    width: i32,
+    height: i32,
+}
+
+impl Drop for ImageList {
+    fn drop(&mut self) {
+        unsafe {
+            _ = ImageList_Destroy(Some(self.himagelist));
+        }
+    }
+}
+
+/// Icons that are provided by the system.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SystemIcon {
+    Application,
+    Error,
+    Information,
+    Question,
+    Shield,
+    Warning,
+}
+
+impl SystemIcon {
+    fn to_native(self) -> PCWSTR {
+        match self {
+            Self::Application => IDI_APPLICATION,
+            Self::Error => IDI_ERROR,
+            Self::Information => IDI_INFORMATION,
+            Self::Question => IDI_QUESTION,
+            Self::Shield => IDI_SHIELD,
+            Self::Warning => IDI_WARNING,
+        }
+    }
+}
+
+/// Icons that are used by the Windows shell, such as in Explorer.
+///
+/// See <https://learn.microsoft.com/en-us/windows/win32/api/shellapi/ne-shellapi-shstockiconid>
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StockIcon {
+    /// A document that has no associated application.
+    Document,
+    Application,
+    Folder,
+    FolderOpen,
+    Drive,
+    NetworkDrive,
+    Computer,
+    Printer,
+    Find,
+    Help,
+    Delete,
+    Lock,
+    Settings,
+    RecycleBin,
+    Info,
+    Warning,
+    Error,
+    Shield,
+    Link,
+}
+
+impl StockIcon {
+    fn to_native(self) -> SHSTOCKICONID {
+        use windows::Win32::UI::Shell as shell;
+        match self {
+            Self::Document => shell::SIID_DOCNOASSOC,
+            Self::Application => shell::SIID_APPLICATION,
+            Self::Folder => shell::SIID_FOLDER,
+            Self::FolderOpen => shell::SIID_FOLDEROPEN,
+            Self::Drive => shell::SIID_DRIVEFIXED,
+            Self::NetworkDrive => shell::SIID_DRIVENET,
+            Self::Computer => shell::SIID_DESKTOPPC,
+            Self::Printer => shell::SIID_PRINTER,
+            Self::Find => shell::SIID_FIND,
+            Self::Help => shell::SIID_HELP,
+            Self::Delete => shell::SIID_DELETE,
+            Self::Lock => shell::SIID_LOCK,
+            Self::Settings => shell::SIID_SETTINGS,
+            Self::RecycleBin => shell::SIID_RECYCLER,
+            Self::Info => shell::SIID_INFO,
+            Self::Warning => shell::SIID_WARNING,
+            Self::Error => shell::SIID_ERROR,
+            Self::Shield => shell::SIID_SHIELD,
+            Self::Link => shell::SIID_LINK,
+        }
+    }
+}
+
+impl ImageList {
+    /// Creates an empty image list, whose images are `width` by `height` pixels.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/api/commctrl/nf-commctrl-imagelist_create>
+    pub fn new(width: i32, height: i32) -> Result<Rc<ImageList>> {
+        unsafe {
+            let himagelist = ImageList_Create(width, height, ILC_COLOR32 | ILC_MASK, 0, 8);
+            if himagelist.is_invalid() {
+                warn!("failed to create image list");
+                return Err(Error::Windows(GetLastError()));
+            }
+            Ok(Rc::new(ImageList {
+                himagelist,
+                width,
+                height,
+            }))
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { ImageList_GetImageCount(self.himagelist) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an icon and returns its index. The image list keeps its own copy of the
+    /// icon, so the caller still owns `hicon`.
+    pub(crate) fn add_hicon(&self, hicon: HICON) -> Result<u32> {
+        unsafe {
+            let index = ImageList_ReplaceIcon(self.himagelist, -1, hicon);
+            if index < 0 {
+                return Err(Error::Windows(GetLastError()));
+            }
+            Ok(index as u32)
+        }
+    }
+
+    /// Creates an icon from the image at `index`. The caller owns the icon, and must
+    /// destroy it with `DestroyIcon`.
+    pub(crate) fn get_hicon(&self, index: u32) -> Result<HICON> {
+        unsafe {
+            let hicon = ImageList_GetIcon(self.himagelist, index as i32, ILD_NORMAL);
+            if hicon.is_invalid() {
+                return Err(Error::Windows(GetLastError()));
+            }
+            Ok(hicon)
+        }
+    }
+
+    /// Adds an icon that we created, then destroys it.
+    fn add_owned_hicon(&self, hicon: HICON) -> Result<u32> {
+        let result = self.add_hicon(hicon);
+        unsafe {
+            _ = DestroyIcon(hicon);
+        }
+        result
+    }
+
+    /// Adds a decoded image and returns its index. The image is scaled to the size
+    /// of this image list.
+    pub fn add_image(&self, image: &DecodedImage) -> Result<u32> {
+        let hicon = create_hicon(image)?;
+        self.add_owned_hicon(hicon)
+    }
+
+    /// Adds the image from an ICO file that best fits the size of this image list,
+    /// and returns its index.
+    pub fn add_ico(&self, data: &[u8]) -> Result<u32> {
+        let entries = parse_ico_directory(data)?;
+        let best = choose_icon_entry(&entries, self.width as u32, self.height as u32)
+            .ok_or(Error::InvalidImage("ICO file has no images"))?;
+        match decode_icon_image(&data[entries[best].data.clone()])? {
+            IconImage::Png(png) => unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createiconfromresourceex
+                let hicon = CreateIconFromResourceEx(
+                    png,
+                    true,
+                    0x0003_0000,
+                    self.width,
+                    self.height,
+                    LR_DEFAULTCOLOR,
+                )
+                .map_err(|_| Error::Windows(GetLastError()))?;
+                self.add_owned_hicon(hicon)
+            },
+            IconImage::Bitmap(image) => self.add_image(&image),
+        }
+    }
+
+    /// Adds the images in a BMP file and returns the index of the first one. If the
+    /// bitmap is a strip of images that are as wide as this image list, each of
+    /// them is added.
+    pub fn add_bmp(&self, data: &[u8]) -> Result<u32> {
+        let image = decode_bmp(data)?;
+        let tile_width = self.width as u32;
+        if image.width > tile_width && image.width % tile_width == 0 {
+            let mut first = None;
+            for tile in image.split_strip(tile_width) {
+                let index = self.add_image(&tile)?;
+                first.get_or_insert(index);
+            }
+            Ok(first.unwrap_or(0))
+        } else {
+            self.add_image(&image)
+        }
+    }
+
+    /// Adds an icon resource from the executable, and returns its index.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-loadimagew>
+    pub fn add_icon_resource(&self, id: u16) -> Result<u32> {
+        unsafe {
+            let handle = LoadImageW(
+                Some(get_instance()),
+                PCWSTR(id as usize as *const u16),
+                IMAGE_ICON,
+                self.width,
+                self.height,
+                LR_DEFAULTCOLOR,
+            )
+            .map_err(|_| Error::Windows(GetLastError()))?;
+            self.add_owned_hicon(HICON(handle.0))
+        }
+    }
+
+    /// Adds one of the shell's icons and returns its index.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-shgetstockiconinfo>
+    pub fn add_stock_icon(&self, icon: StockIcon) -> Result<u32> {
+        unsafe {
+            let mut info: SHSTOCKICONINFO = zeroed();
+            info.cbSize = size_of::<SHSTOCKICONINFO>() as u32;
+            let size = if self.width <= GetSystemMetrics(SM_CXSMICON) {
+                SHGSI_SMALLICON
+            } else {
+                SHGSI_LARGEICON
+            };
+            SHGetStockIconInfo(icon.to_native(), SHGSI_ICON | size, &mut info)
+                .map_err(|_| Error::Windows(GetLastError()))?;
+            self.add_owned_hicon(info.hIcon)
+        }
+    }
+
+    /// Adds one of the system icons, scaled to the size of this image list, and
+    /// returns its index.
+    pub fn add_system_icon(&self, icon: SystemIcon) -> Result<u32> {
+        unsafe {
+            // LR_SHARED icons are owned by the system, so they are not destroyed here.
+            let handle = LoadImageW(
+                None,
+                icon.to_native(),
+                IMAGE_ICON,
+                self.width,
+                self.height,
+                LR_SHARED,
+            )
+            .map_err(|_| Error::Windows(GetLastError()))?;
+            self.add_hicon(HICON(handle.0))
+        }
+    }
+}
+
+/// Creates an icon from 32-bit pixels. The alpha channel gives the transparency.
+fn create_hicon(image: &DecodedImage) -> Result<HICON> {
+    if image.width == 0
+        || image.height == 0
+        || image.bgra.len() != image.width as usize * image.height as usize * 4
+    {
+        return Err(Error::InvalidImage(
+            "pixel data does not match the image size",
+        ));
+    }
+
+    unsafe {
+        let mut bmi: BITMAPINFO = zeroed();
+        bmi.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as u32;
+        bmi.bmiHeader.biWidth = image.width as i32;
+        // negative height means that rows are top-down
+        bmi.bmiHeader.biHeight = -(image.height as i32);
+        bmi.bmiHeader.biPlanes = 1;
+        bmi.bmiHeader.biBitCount = 32;
+        bmi.bmiHeader.biCompression = BI_RGB.0;
+
+        let mut bits: *mut c_void = null_mut();
+        let color = CreateDIBSection(None, &bmi, DIB_RGB_COLORS, &mut bits, None, 0)
+            .map_err(|_| Error::Windows(GetLastError()))?;
+        core::ptr::copy_nonoverlapping(image.bgra.as_ptr(), bits as *mut u8, image.bgra.len());
+
+        // The mask is ignored when the color bitmap has an alpha channel.
+        let mask = CreateBitmap(image.width as i32, image.height as i32, 1, 1, None);
+
+        let info = ICONINFO {
+            fIcon: true.into(),
+            xHotspot: 0,
+            yHotspot: 0,
+            hbmMask: mask,
+            hbmColor: color,
+        };
+        let result = CreateIconIndirect(&info).map_err(|_| Error::Windows(GetLastError()));
+
+        _ = DeleteObject(color.into());
+        _ = DeleteObject(mask.into());
+        result
+    }
+}
@@ -9,7 +9,12 @@ pub fn init_common_controls() {
         debug!("calling InitCommonControls (in once)");
         let mut icc: INITCOMMONCONTROLSEX = zeroed();
         icc.dwSize = size_of::<INITCOMMONCONTROLSEX>() as u32;
-        icc.dwICC = ICC_LISTVIEW_CLASSES | ICC_TREEVIEW_CLASSES | ICC_BAR_CLASSES | ICC_TAB_CLASSES;
+        icc.dwICC = ICC_LISTVIEW_CLASSES
+            | ICC_TREEVIEW_CLASSES
+            | ICC_BAR_CLASSES
+            | ICC_TAB_CLASSES
+            | ICC_DATE_CLASSES
+            | ICC_LINK_CLASS;
 
         let icc_result = InitCommonControlsEx(&icc).ok();
         debug!("icc_result: {:?}", icc_result);
@@ -43,6 +43,22 @@ impl Layout {
             Self::Stack(stack) => stack.min_size(),
         }
     }
+
+    /// Calls `f` for each control in the layout, including those in nested layouts.
+    pub(crate) fn for_each_control(&self, f: &mut dyn FnMut(&ControlState)) {
+        match self {
+            Self::Grid(grid) => {
+                for item in grid.items.iter() {
+                    item.item.for_each_control(f);
+                }
+            }
+            Self::Stack(stack) => {
+                for item in stack.items.iter() {
+                    item.for_each_control(f);
+                }
+            }
+        }
+    }
 }
 
 /// An item that participates in a Layout.
@@ -87,6 +103,13 @@ impl LayoutItem {
             }
         }
     }
+
+    pub(crate) fn for_each_control(&self, f: &mut dyn FnMut(&ControlState)) {
+        match self {
+            Self::Layout(nested_layout) => nested_layout.for_each_control(f),
+            Self::Control(control) => f(control),
+        }
+    }
 }
 
 pub(crate) trait LayoutPlacer {
@@ -10,61 +10,113 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::comparison_chain)]
 
+// Lets the tests use the derive macros, which refer to `::forms`.
+#[cfg(test)]
+extern crate self as forms;
+
 mod app;
 mod brush;
 mod button;
+pub mod chart;
+mod clipboard;
 mod color;
+pub mod color_dialog;
 mod command;
+pub mod console_view;
 mod control;
 pub mod custom;
+mod date_time;
+mod date_time_picker;
 mod edit;
 mod error;
 mod executor;
 mod ffi;
 pub mod file_dialog;
 mod font;
+pub mod font_dialog;
 mod form;
 pub mod gdi;
+mod hex_view;
+mod image_file;
+mod image_list;
 pub mod init;
 mod label;
 pub mod layout;
+mod link_label;
 pub mod list_view;
+mod log_view;
 mod menu;
+mod message_box;
 mod messenger;
+mod month_calendar;
 mod msg;
 mod notify;
+mod prop_sheet;
+mod properties;
+mod property_grid;
 mod rich_edit;
+mod search_box;
 mod status_bar;
 mod style;
 mod system_params;
 mod tab;
+mod table_view;
+mod task_dialog;
+mod toolbar;
+mod tooltip;
+mod tray_icon;
 pub mod tree_view;
 
 pub use app::*;
 pub use brush::{Brush, SysColor};
 pub use button::*;
+pub use chart::{Chart, Series, SeriesKind};
+pub use clipboard::*;
 pub use color::*;
 pub use command::*;
+pub use console_view::ConsoleView;
 pub use control::*;
+pub use date_time::*;
+pub use date_time_picker::*;
 pub use edit::*;
 pub use error::{Error, Result};
 pub use executor::*;
 pub use font::*;
 pub use form::*;
+pub use forms_derive::{Properties, PropertyField, TableRow};
+pub use hex_view::*;
+pub use image_file::*;
+pub use image_list::*;
 pub use label::Label;
 pub use layout::grid::*;
 pub use layout::*;
-pub use list_view::{ListView, Mode};
+pub use link_label::*;
+pub use list_view::{
+    group_by_key, CellStyle, Column, ColumnAlign, ColumnAutoSize, ColumnState, Comparator, Group,
+    ListSource, ListView, Mode, SortOrder,
+};
+pub use log_view::*;
 pub use menu::*;
+pub use message_box::*;
 pub use messenger::{Messenger, Sender};
+pub use month_calendar::*;
 pub use msg::*;
 pub use notify::*;
+pub use prop_sheet::*;
+pub use properties::*;
+pub use property_grid::PropertyGrid;
 pub use rich_edit::RichEdit;
 pub use rich_edit::*;
+pub use search_box::*;
 pub use status_bar::*;
 pub use style::*;
 pub use tab::*;
-pub use tree_view::{TreeNode, TreeView, TreeViewOptions};
+pub use table_view::*;
+pub use task_dialog::*;
+pub use toolbar::*;
+pub use tooltip::TooltipOptions;
+pub use tray_icon::*;
+pub use tree_view::{NewItem, TreeNode, TreeView, TreeViewOptions};
 pub use windows::Win32::Foundation::RECTL as Rect;
 
 use core::cell::UnsafeCell;
@@ -77,6 +129,7 @@ use static_assertions::assert_not_impl_any;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::{Rc, Weak};
+use tooltip::*;
 use tracing::{debug, error, trace, warn};
 use widestring::U16CStr as WCStr;
 use widestring::U16CString as WCString;
@@ -107,6 +160,23 @@ pub(crate) fn set_window_text(hwnd: HWND, text: &str) {
     }
 }
 
+/// Copies `text` into a buffer that a control provided in a notification, such as
+/// the `pszText` / `cchTextMax` pair of `TBN_GETINFOTIP`. The text is truncated if
+/// it does not fit, and is always null-terminated.
+pub(crate) unsafe fn copy_to_text_buffer(text: &str, buffer: PWSTR, buffer_len: i32) {
+    if buffer.is_null() || buffer_len <= 0 {
+        return;
+    }
+    let buffer = core::slice::from_raw_parts_mut(buffer.0, buffer_len as usize);
+    let (chars, _) = buffer.split_at_mut(buffer_len as usize - 1);
+    let mut n = 0;
+    for (dst, src) in chars.iter_mut().zip(text.encode_utf16()) {
+        *dst = src;
+        n += 1;
+    }
+    buffer[n] = 0;
+}
+
 pub(crate) fn get_window_text(hwnd: HWND) -> String {
     unsafe {
         let len_lresult = SendMessageW(hwnd, WM_GETTEXTLENGTH, None, None).0;
@@ -0,0 +1,390 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/syslink-overview
+
+use super::*;
+use core::ops::Range;
+use std::cell::OnceCell;
+use windows::core::w;
+use windows::Win32::UI::Controls::{NM_CLICK, NM_RETURN};
+use windows::Win32::UI::Shell::{
+    ShellExecuteW, SE_ERR_ASSOCINCOMPLETE, SE_ERR_DDEBUSY, SE_ERR_DDEFAIL, SE_ERR_DDETIMEOUT,
+    SE_ERR_DLLNOTFOUND, SE_ERR_NOASSOC, SE_ERR_OOM, SE_ERR_SHARE,
+};
+
+/// A label whose text can contain links, written as `<a href="...">text</a>`.
+///
+/// ```text
+/// Written to <a href="C:\logs\app.log">the log file</a>. See the <a id="help">documentation</a>.
+/// ```
+pub struct LinkLabel {
+    control: ControlState,
+    markup: RefCell<LinkMarkup>,
+    link_clicked_handler: OnceCell<Box<dyn Fn(&Link)>>,
+}
+
+impl core::ops::Deref for LinkLabel {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+/// One link in the text of a `LinkLabel`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+    /// Position of the link among all of the links in the text, starting at 0.
+    pub index: usize,
+    pub id: Option<String>,
+    pub href: Option<String>,
+    /// The text of the link, as it is displayed.
+    pub text: String,
+    /// Byte range of the link within `LinkMarkup::text`.
+    pub range: Range<usize>,
+}
+
+/// Link markup, split into the displayed text and the links within it.
+/// See `parse_link_markup`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LinkMarkup {
+    /// The text as it is displayed, with the `<a>` tags removed.
+    pub text: String,
+    pub links: Vec<Link>,
+}
+
+/// Parses `<a>` tags in the same way that the SysLink control does.
+///
+/// Tag and attribute names are not case sensitive. Attribute values are quoted with
+/// `"` or `'`; the `href` and `id` attributes are recognized and the rest are
+/// ignored. A `<` that does not start a well-formed tag is ordinary text. A link
+/// without a closing `</a>` continues to the end of the text.
+pub fn parse_link_markup(markup: &str) -> LinkMarkup {
+    let mut result = LinkMarkup::default();
+    let mut rest = markup;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            result.text.push_str(rest);
+            break;
+        };
+        result.text.push_str(&rest[..lt]);
+        rest = &rest[lt..];
+
+        let Some((id, href, after_tag)) = parse_open_tag(rest) else {
+            result.text.push('<');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let (link_text, after_link) = match find_ignore_ascii_case(after_tag, "</a>") {
+            Some(end) => (&after_tag[..end], &after_tag[end + "</a>".len()..]),
+            None => (after_tag, ""),
+        };
+
+        let start = result.text.len();
+        result.text.push_str(link_text);
+        result.links.push(Link {
+            index: result.links.len(),
+            id,
+            href,
+            text: link_text.to_string(),
+            range: start..result.text.len(),
+        });
+        rest = after_link;
+    }
+
+    result
+}
+
+/// Parses `<a attr="value" ...>` at the start of `s`. Returns the `id` and `href`
+/// attributes, and the text after the tag.
+fn parse_open_tag(s: &str) -> Option<(Option<String>, Option<String>, &str)> {
+    let bytes = s.as_bytes();
+    if bytes.len() < 3 || bytes[0] != b'<' || !bytes[1].eq_ignore_ascii_case(&b'a') {
+        return None;
+    }
+    let mut rest = &s[2..];
+    if !(rest.starts_with('>') || rest.starts_with(|c: char| c.is_ascii_whitespace())) {
+        return None;
+    }
+
+    let mut id = None;
+    let mut href = None;
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('>') {
+            return Some((id, href, after));
+        }
+
+        let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric())?;
+        if name_len == 0 {
+            return None;
+        }
+        let name = &rest[..name_len];
+        rest = rest[name_len..]
+            .trim_start()
+            .strip_prefix('=')?
+            .trim_start();
+
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        rest = &rest[1..];
+        let value_len = rest.find(quote)?;
+        let value = rest[..value_len].to_string();
+        rest = &rest[value_len + 1..];
+
+        if name.eq_ignore_ascii_case("href") {
+            href = Some(value);
+        } else if name.eq_ignore_ascii_case("id") {
+            id = Some(value);
+        }
+    }
+}
+
+fn find_ignore_ascii_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+impl LinkLabel {
+    pub fn new(form: &Rc<Form>) -> Rc<Self> {
+        unsafe {
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                WC_LINK,
+                w!(""),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(LWS_TRANSPARENT),
+                0,
+                0,
+                0,
+                0,
+                Some(form.handle()),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            let this = Rc::new(LinkLabel {
+                control: ControlState::new(hwnd),
+                markup: RefCell::new(LinkMarkup::default()),
+                link_clicked_handler: OnceCell::new(),
+            });
+
+            SendMessageW(
+                hwnd,
+                WM_SETFONT,
+                Some(WPARAM(form.style.static_font.hfont.0 as usize)),
+                Some(LPARAM(1)),
+            );
+
+            register_notify_handler(hwnd, Rc::downgrade(&this) as Weak<dyn NotifyHandlerTrait>);
+            this
+        }
+    }
+
+    /// Sets the text, including its `<a>` tags. See `parse_link_markup`.
+    pub fn set_markup(&self, markup: &str) {
+        *self.markup.borrow_mut() = parse_link_markup(markup);
+        set_window_text(self.handle(), markup);
+    }
+
+    pub fn links(&self) -> Vec<Link> {
+        self.markup.borrow().links.clone()
+    }
+
+    /// Sets the handler that is called when a link is clicked, or activated with
+    /// the keyboard. Use `open_with_shell` to open the link's `href`.
+    pub fn link_clicked_handler<F>(&self, handler: F)
+    where
+        F: Fn(&Link) + 'static,
+    {
+        let result = self.link_clicked_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call link_clicked_handler() more than once"
+        );
+    }
+}
+
+impl NotifyHandlerTrait for LinkLabel {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/nm-click-syslink
+            NM_CLICK | NM_RETURN => {
+                let nmlink = &*(nmhdr as *const NMLINK);
+                let link = self
+                    .markup
+                    .borrow()
+                    .links
+                    .get(nmlink.item.iLink as usize)
+                    .cloned();
+                if let (Some(link), Some(handler)) = (link, self.link_clicked_handler.get()) {
+                    handler(&link);
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
+
+/// Opens a URL, document or folder with the program that the user has chosen for
+/// it, as if it was double-clicked in Explorer.
+///
+/// See <https://learn.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-shellexecutew>
+pub fn open_with_shell(target: &str) -> Result<()> {
+    unsafe {
+        let target_wstr = WCString::from_str_truncate(target);
+        let result = ShellExecuteW(
+            None,
+            w!("open"),
+            PCWSTR::from_raw(target_wstr.as_ptr()),
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+        // Values of 32 or less are errors. The last error is not set reliably.
+        let code = result.0 as isize;
+        if code > 32 {
+            Ok(())
+        } else {
+            Err(Error::Windows(shell_execute_error(code as u32)))
+        }
+    }
+}
+
+/// Converts an error returned by `ShellExecuteW` (`SE_ERR_*`, or one of the
+/// Win32 errors that it shares numbers with) to a Win32 error.
+fn shell_execute_error(code: u32) -> WIN32_ERROR {
+    match code {
+        0 | SE_ERR_OOM => ERROR_NOT_ENOUGH_MEMORY,
+        SE_ERR_SHARE => ERROR_SHARING_VIOLATION,
+        SE_ERR_ASSOCINCOMPLETE | SE_ERR_NOASSOC => ERROR_NO_ASSOCIATION,
+        SE_ERR_DDETIMEOUT | SE_ERR_DDEFAIL | SE_ERR_DDEBUSY => ERROR_DDE_FAIL,
+        SE_ERR_DLLNOTFOUND => ERROR_DLL_NOT_FOUND,
+        // SE_ERR_FNF, SE_ERR_PNF, SE_ERR_ACCESSDENIED and ERROR_BAD_FORMAT have the
+        // same numbers as the Win32 errors.
+        code => WIN32_ERROR(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text() {
+        let markup = parse_link_markup("No links here.");
+        assert_eq!(markup.text, "No links here.");
+        assert!(markup.links.is_empty());
+        assert_eq!(parse_link_markup(""), LinkMarkup::default());
+    }
+
+    #[test]
+    fn several_links() {
+        let markup = parse_link_markup(r#"See <a href="a.txt">one</a> and <a>two</a>."#);
+        assert_eq!(markup.text, "See one and two.");
+        assert_eq!(
+            markup.links,
+            [
+                Link {
+                    index: 0,
+                    id: None,
+                    href: Some("a.txt".to_string()),
+                    text: "one".to_string(),
+                    range: 4..7,
+                },
+                Link {
+                    index: 1,
+                    id: None,
+                    href: None,
+                    text: "two".to_string(),
+                    range: 12..15,
+                },
+            ]
+        );
+        assert_eq!(&markup.text[markup.links[1].range.clone()], "two");
+    }
+
+    #[test]
+    fn attributes() {
+        let markup =
+            parse_link_markup(r#"<A ID='help' class="x"  HREF = "C:\logs\app.log" >log</A>"#);
+        assert_eq!(markup.text, "log");
+        let link = &markup.links[0];
+        assert_eq!(link.id.as_deref(), Some("help"));
+        assert_eq!(link.href.as_deref(), Some(r"C:\logs\app.log"));
+
+        // The other quote can be used inside a value.
+        let markup = parse_link_markup(r#"<a href='say "hi"'>x</a>"#);
+        assert_eq!(markup.links[0].href.as_deref(), Some(r#"say "hi""#));
+    }
+
+    #[test]
+    fn malformed_tags_are_text() {
+        for text in [
+            "a < b",
+            "<abbr>x</abbr>",
+            "<a href=x>unquoted</a>",
+            "<a href=\"open>x</a>",
+            "<a =\"x\">x</a>",
+            "<",
+            "<a",
+        ] {
+            let markup = parse_link_markup(text);
+            assert_eq!(markup.text, text, "{text}");
+            assert!(markup.links.is_empty(), "{text}");
+        }
+    }
+
+    #[test]
+    fn unterminated_link() {
+        let markup = parse_link_markup("Open <a href=\"x\">the rest");
+        assert_eq!(markup.text, "Open the rest");
+        assert_eq!(markup.links[0].range, 5..13);
+
+        // A closing tag without an opening tag is text.
+        assert_eq!(parse_link_markup("a</a>b").text, "a</a>b");
+    }
+
+    #[test]
+    fn nested_link() {
+        // The first `</a>` closes the link, and the inner tag is part of its text.
+        let markup = parse_link_markup("<a id=\"outer\">x <a>y</a> z</a>");
+        assert_eq!(markup.text, "x <a>y z</a>");
+        assert_eq!(markup.links.len(), 1);
+        assert_eq!(markup.links[0].text, "x <a>y");
+    }
+
+    #[test]
+    fn entities_are_not_decoded() {
+        // SysLink shows entities as they are written, so they are kept.
+        let markup = parse_link_markup("Tom &amp; <a href=\"?a=1&amp;b=2\">&lt;Jerry&gt;</a>");
+        assert_eq!(markup.text, "Tom &amp; &lt;Jerry&gt;");
+        assert_eq!(markup.links[0].href.as_deref(), Some("?a=1&amp;b=2"));
+        assert_eq!(markup.links[0].text, "&lt;Jerry&gt;");
+    }
+
+    #[test]
+    fn non_ascii_ranges_are_bytes() {
+        let markup = parse_link_markup("Größe: <a>über</a>");
+        let range = markup.links[0].range.clone();
+        assert_eq!(&markup.text[range], "über");
+    }
+
+    #[test]
+    fn shell_execute_errors() {
+        assert_eq!(shell_execute_error(0), ERROR_NOT_ENOUGH_MEMORY);
+        assert_eq!(shell_execute_error(2), ERROR_FILE_NOT_FOUND);
+        assert_eq!(shell_execute_error(3), ERROR_PATH_NOT_FOUND);
+        assert_eq!(shell_execute_error(5), ERROR_ACCESS_DENIED);
+        assert_eq!(shell_execute_error(8), ERROR_NOT_ENOUGH_MEMORY);
+        assert_eq!(shell_execute_error(11), ERROR_BAD_FORMAT);
+        assert_eq!(shell_execute_error(26), ERROR_SHARING_VIOLATION);
+        assert_eq!(shell_execute_error(27), ERROR_NO_ASSOCIATION);
+        assert_eq!(shell_execute_error(29), ERROR_DDE_FAIL);
+        assert_eq!(shell_execute_error(31), ERROR_NO_ASSOCIATION);
+        assert_eq!(shell_execute_error(32), ERROR_DLL_NOT_FOUND);
+    }
+}
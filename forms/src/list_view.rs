@@ -1,4 +1,21 @@
 use super::*;
+use core::ops::{Bound, RangeBounds};
+use std::cell::OnceCell;
+use windows::Win32::UI::Controls::{NM_CLICK, NM_CUSTOMDRAW};
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+
+mod cell_style;
+mod column;
+mod group;
+mod sort;
+mod source;
+
+pub use cell_style::*;
+pub use column::*;
+pub use group::*;
+pub use sort::*;
+pub use source::*;
 
 impl core::ops::Deref for ListView {
     type Target = ControlState;
@@ -9,23 +26,80 @@ impl core::ops::Deref for ListView {
 
 pub struct ListView {
     control: ControlState,
+    small_images: RefCell<Option<Rc<ImageList>>>,
+    large_images: RefCell<Option<Rc<ImageList>>>,
+    item_tooltip_handler: OnceCell<Box<dyn Fn(usize, usize) -> Option<String>>>,
+    begin_label_edit_handler: OnceCell<Box<dyn Fn(usize) -> bool>>,
+    end_label_edit_handler: OnceCell<Box<dyn Fn(usize, Option<&str>) -> bool>>,
+    cell_style_handler: OnceCell<Box<dyn Fn(usize, usize) -> Option<CellStyle>>>,
+    /// The font that the items are drawn with, before they are styled.
+    draw_font: Cell<HFONT>,
+    /// The text colors that an item is drawn with, before it is styled.
+    draw_colors: Cell<(COLORREF, COLORREF)>,
+    styled_fonts: RefCell<StyledFonts>,
+    /// The rows of a virtual list view.
+    source: Option<Rc<dyn ListSource>>,
+    /// Reused for the text of each cell of a virtual list view.
+    cell_text: Cell<String>,
+    sort: RefCell<SortState>,
+    columns: RefCell<Vec<ColumnInfo>>,
+    groups: RefCell<Vec<GroupInfo>>,
+    next_group_id: Cell<i32>,
+    group_header_click_handler: OnceCell<Box<dyn Fn(i32)>>,
+    group_collapsed_handler: OnceCell<Box<dyn Fn(i32, bool)>>,
 }
 
+const LIST_VIEW_SUBCLASS_ID: usize = 1;
+
 const WC_LISTVIEW: &str = "SysListView32";
 
 impl ListView {
     pub fn new(parent_control: &ControlState) -> Rc<ListView> {
+        Self::create(parent_control, None)
+    }
+
+    /// Creates a virtual list view, which gets its rows from `source` as it shows
+    /// them, so that it can have millions of rows. The methods that insert, delete
+    /// or change items cannot be used with it.
+    pub fn new_virtual(parent_control: &ControlState, source: Rc<dyn ListSource>) -> Rc<ListView> {
+        let len = source.len();
+        let this = Self::create(parent_control, Some(source));
+        unsafe {
+            // Without this, the list view does not ask the source for state images.
+            SendMessageW(
+                this.handle(),
+                LVM_SETCALLBACKMASK,
+                Some(WPARAM(LVIS_STATEIMAGEMASK.0 as usize)),
+                None,
+            );
+        }
+        this.set_len(len);
+        this
+    }
+
+    fn create(parent_control: &ControlState, source: Option<Rc<dyn ListSource>>) -> Rc<ListView> {
         unsafe {
             let parent_window = parent_control.handle();
             let window_name = WCString::from_str_truncate("");
             let class_name_wstr = WCString::from_str_truncate(WC_LISTVIEW);
             let ex_style = WINDOW_EX_STYLE(0);
+            let mut style = LVS_SHAREIMAGELISTS;
+            if source.is_some() {
+                style |= LVS_OWNERDATA;
+            }
 
             let hwnd = match CreateWindowExW(
                 ex_style,
                 PCWSTR::from_raw(class_name_wstr.as_ptr()),
                 PCWSTR::from_raw(window_name.as_ptr()),
-                WS_CHILD | WS_VISIBLE | WS_CHILDWINDOW | WS_BORDER | WS_TABSTOP,
+                // The image lists are owned by ImageList, so the list view must not
+                // destroy them.
+                WS_CHILD
+                    | WS_VISIBLE
+                    | WS_CHILDWINDOW
+                    | WS_BORDER
+                    | WS_TABSTOP
+                    | WINDOW_STYLE(style),
                 0,
                 0,
                 0,
@@ -41,13 +115,184 @@ impl ListView {
 
             let state: Rc<ListView> = Rc::new(ListView {
                 control: ControlState::new(hwnd),
+                small_images: RefCell::new(None),
+                large_images: RefCell::new(None),
+                item_tooltip_handler: OnceCell::new(),
+                begin_label_edit_handler: OnceCell::new(),
+                end_label_edit_handler: OnceCell::new(),
+                cell_style_handler: OnceCell::new(),
+                draw_font: Cell::new(HFONT::default()),
+                draw_colors: Cell::new(Default::default()),
+                styled_fonts: RefCell::new(StyledFonts::default()),
+                source,
+                cell_text: Cell::new(String::new()),
+                sort: RefCell::new(SortState::default()),
+                columns: RefCell::new(Vec::new()),
+                groups: RefCell::new(Vec::new()),
+                next_group_id: Cell::new(1),
+                group_header_click_handler: OnceCell::new(),
+                group_collapsed_handler: OnceCell::new(),
             });
+            // The header control sends its notifications to the list view, rather than
+            // to the form. The subclass also sees the clicks and keys that collapse
+            // groups.
+            let state_ptr: *const ListView = &*state;
+            _ = SetWindowSubclass(
+                hwnd,
+                Some(list_view_subclass_proc),
+                LIST_VIEW_SUBCLASS_ID,
+                state_ptr as usize,
+            );
+            register_notify_handler(hwnd, Rc::downgrade(&state) as Weak<dyn NotifyHandlerTrait>);
             // form.invalidate_layout();
 
             state
         }
     }
 
+    /// The source of the rows, if this is a virtual list view.
+    pub fn source(&self) -> Option<&Rc<dyn ListSource>> {
+        self.source.as_ref()
+    }
+
+    /// Sets the number of rows of a virtual list view, after rows were added to or
+    /// removed from its source. The selection and scroll position are kept.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-setitemcount>
+    pub fn set_len(&self, len: usize) {
+        debug_assert!(self.source.is_some(), "set_len() needs a virtual list view");
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETITEMCOUNT,
+                Some(WPARAM(len)),
+                Some(LPARAM(LVSICF_NOSCROLL as isize)),
+            );
+        }
+    }
+
+    /// Redraws `rows`, after they have changed in the source of a virtual list view.
+    /// Use `..` to redraw all of them.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-redrawitems>
+    pub fn refresh<R: RangeBounds<usize>>(&self, rows: R) {
+        let len = self.items_len();
+        let first = match rows.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match rows.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => len,
+        }
+        .min(len);
+        if first >= end {
+            return;
+        }
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_REDRAWITEMS,
+                Some(WPARAM(first)),
+                Some(LPARAM(end as isize - 1)),
+            );
+        }
+    }
+
+    /// Sets how the column at `col` is sorted when its header is clicked. Clicking
+    /// the header again sorts the column in the other direction. Columns without a
+    /// comparator are not sorted.
+    pub fn set_column_comparator(&self, col: usize, comparator: Comparator) {
+        self.sort.borrow_mut().set_comparator(col, Some(comparator));
+    }
+
+    /// The column that the list view was last sorted by, and in which direction.
+    pub fn sorted_by(&self) -> Option<(usize, SortOrder)> {
+        self.sort.borrow().sorted_by
+    }
+
+    /// Sorts the items by the column at `col`, using its comparator, or comparing
+    /// the text if it has none, and shows the sort arrow in its header.
+    ///
+    /// A virtual list view is sorted by its source, with `ListSource::sort`. As the
+    /// rows then move, the selection is cleared.
+    pub fn sort_by_column(&self, col: usize, order: SortOrder) {
+        let comparator = self
+            .sort
+            .borrow()
+            .comparator(col)
+            .cloned()
+            .unwrap_or(Comparator::Text);
+
+        if let Some(source) = &self.source {
+            if !source.sort(col, order, &comparator) {
+                debug!("the list source cannot be sorted by column {}", col);
+                return;
+            }
+            self.set_item_selected(usize::MAX, false);
+            self.refresh(..);
+        } else {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-sortitemsex
+            let context = SortContext {
+                list_view: self,
+                col,
+                order,
+                comparator: &comparator,
+                buffer: RefCell::new(Vec::new()),
+                texts: RefCell::new((String::new(), String::new())),
+            };
+            let compare: unsafe extern "system" fn(LPARAM, LPARAM, LPARAM) -> i32 = compare_items;
+            unsafe {
+                SendMessageW(
+                    self.handle(),
+                    LVM_SORTITEMSEX,
+                    Some(WPARAM(&context as *const SortContext as usize)),
+                    Some(LPARAM(compare as usize as isize)),
+                );
+            }
+        }
+
+        self.sort.borrow_mut().sorted_by = Some((col, order));
+        self.set_sort_arrow(col, order);
+    }
+
+    /// Shows the sort arrow in the header of `col`, and removes it from the others.
+    fn set_sort_arrow(&self, col: usize, order: SortOrder) {
+        unsafe {
+            let header =
+                HWND(SendMessageW(self.handle(), LVM_GETHEADER, None, None).0 as *mut c_void);
+            if header.is_invalid() {
+                return;
+            }
+            let count = SendMessageW(header, HDM_GETITEMCOUNT, None, None).0;
+            for i in 0..count.max(0) as usize {
+                let mut item: HDITEMW = zeroed();
+                item.mask = HDI_FORMAT;
+                SendMessageW(
+                    header,
+                    HDM_GETITEMW,
+                    Some(WPARAM(i)),
+                    Some(LPARAM(&mut item as *mut HDITEMW as isize)),
+                );
+                item.fmt.0 &= !(HDF_SORTUP.0 | HDF_SORTDOWN.0);
+                if i == col {
+                    item.fmt.0 |= match order {
+                        SortOrder::Ascending => HDF_SORTUP.0,
+                        SortOrder::Descending => HDF_SORTDOWN.0,
+                    };
+                }
+                SendMessageW(
+                    header,
+                    HDM_SETITEMW,
+                    Some(WPARAM(i)),
+                    Some(LPARAM(&item as *const HDITEMW as isize)),
+                );
+            }
+        }
+    }
+
     /// Iterates the indices of the selected items.
     pub fn iter_selected_items(&self) -> IterSelectedItems<'_> {
         IterSelectedItems {
@@ -80,23 +325,269 @@ impl ListView {
 
     // Columns
     pub fn add_column(&self, index: u32, width: i32, text: &str) {
+        self.insert_column(index as usize, &Column::new(text).width(width));
+    }
+
+    /// Inserts a column at `index`, described by `column`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-insertcolumn>
+    pub fn insert_column(&self, index: usize, column: &Column) {
+        let info = ColumnInfo::new(column);
+        let width = info.constrain_width(column.width);
+        let index = {
+            let mut columns = self.columns.borrow_mut();
+            let index = index.min(columns.len());
+            columns.insert(index, info);
+            index
+        };
+        self.sort.borrow_mut().insert_column(index);
         unsafe {
-            let textw = WCString::from_str_truncate(text);
+            let textw = WCString::from_str_truncate(&column.title);
             let mut col: LVCOLUMNW = zeroed();
-            col.mask |= LVCF_TEXT | LVCF_WIDTH;
+            col.mask = LVCF_TEXT | LVCF_WIDTH | LVCF_FMT;
+            col.fmt = column.align.to_native();
             col.cx = width;
             col.pszText = PWSTR(textw.as_ptr() as *mut u16);
             SendMessageW(
                 self.control.handle(),
                 LVM_INSERTCOLUMNW,
-                Some(WPARAM(index as usize)),
+                Some(WPARAM(index)),
                 Some(LPARAM(&col as *const LVCOLUMNW as isize)),
             );
         }
+        if let Some(autosize) = column.autosize {
+            self.autosize_column(index, autosize);
+        }
+    }
+
+    /// The number of columns.
+    pub fn num_columns(&self) -> usize {
+        self.columns.borrow().len()
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-getcolumnwidth
+    pub fn column_width(&self, col: usize) -> i32 {
+        unsafe { SendMessageW(self.handle(), LVM_GETCOLUMNWIDTH, Some(WPARAM(col)), None).0 as i32 }
+    }
+
+    /// Sets the width of a column, within its minimum and maximum widths. If the
+    /// column is hidden, this is the width it gets when it is shown.
+    pub fn set_column_width(&self, col: usize, width: i32) {
+        let width = {
+            let mut columns = self.columns.borrow_mut();
+            let Some(info) = columns.get_mut(col) else {
+                return;
+            };
+            if info.hidden {
+                info.shown_width = width;
+                return;
+            }
+            info.constrain_width(width)
+        };
+        self.send_column_width(col, width);
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-setcolumnwidth
+    fn send_column_width(&self, col: usize, width: i32) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETCOLUMNWIDTH,
+                Some(WPARAM(col)),
+                Some(LPARAM(width as isize)),
+            );
+        }
+    }
+
+    /// Sizes a column to fit its cells, within its minimum and maximum widths. A
+    /// virtual list view only measures the rows that are shown.
+    pub fn autosize_column(&self, col: usize, autosize: ColumnAutoSize) {
+        let info = match self.columns.borrow().get(col) {
+            Some(info) if !info.hidden => info.clone(),
+            _ => return,
+        };
+        self.send_column_width(
+            col,
+            match autosize {
+                ColumnAutoSize::Content => LVSCW_AUTOSIZE,
+                ColumnAutoSize::ContentAndHeader => LVSCW_AUTOSIZE_USEHEADER,
+            },
+        );
+        let width = self.column_width(col);
+        let constrained = info.constrain_width(width);
+        if constrained != width {
+            self.send_column_width(col, constrained);
+        }
+    }
+
+    /// Sizes each column that was inserted with `Column::autosize` to fit its cells,
+    /// such as after the rows have been added.
+    pub fn autosize_columns(&self) {
+        let autosizes: Vec<Option<ColumnAutoSize>> =
+            self.columns.borrow().iter().map(|c| c.autosize).collect();
+        for (col, autosize) in autosizes.into_iter().enumerate() {
+            if let Some(autosize) = autosize {
+                self.autosize_column(col, autosize);
+            }
+        }
+    }
+
+    /// Hides or shows a column. A hidden column has no width, and the user cannot
+    /// resize it. It keeps its index, so the cells of the other columns do not move.
+    pub fn set_column_hidden(&self, col: usize, hidden: bool) {
+        let current_width = self.column_width(col);
+        let width = {
+            let mut columns = self.columns.borrow_mut();
+            let Some(info) = columns.get_mut(col) else {
+                return;
+            };
+            if info.hidden == hidden {
+                return;
+            }
+            if hidden {
+                info.shown_width = current_width;
+            }
+            info.hidden = hidden;
+            info.constrain_width(info.shown_width)
+        };
+        self.send_column_width(col, width);
+    }
+
+    pub fn is_column_hidden(&self, col: usize) -> bool {
+        self.columns.borrow().get(col).is_some_and(|c| c.hidden)
+    }
+
+    /// The columns, in the order they are shown. The user can change the order by
+    /// dragging the headers, if `set_header_drag_drop` is set.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-getcolumnorderarray>
+    pub fn column_order(&self) -> Vec<usize> {
+        let mut order: Vec<i32> = vec![0; self.num_columns()];
+        if order.is_empty() {
+            return Vec::new();
+        }
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_GETCOLUMNORDERARRAY,
+                Some(WPARAM(order.len())),
+                Some(LPARAM(order.as_mut_ptr() as isize)),
+            );
+        }
+        order.into_iter().map(|col| col as usize).collect()
+    }
+
+    /// Sets the order the columns are shown in. `order` must hold each column once.
+    pub fn set_column_order(&self, order: &[usize]) {
+        let num_columns = self.num_columns();
+        let mut seen = vec![false; num_columns];
+        let is_valid = order.len() == num_columns
+            && order
+                .iter()
+                .all(|&col| col < num_columns && !std::mem::replace(&mut seen[col], true));
+        if !is_valid {
+            warn!(
+                "set_column_order: {:?} is not an order of the columns",
+                order
+            );
+            return;
+        }
+        let order: Vec<i32> = order.iter().map(|&col| col as i32).collect();
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETCOLUMNORDERARRAY,
+                Some(WPARAM(order.len())),
+                Some(LPARAM(order.as_ptr() as isize)),
+            );
+            _ = InvalidateRect(Some(self.handle()), None, true);
+        }
+    }
+
+    /// Gets the widths, order, hidden columns and sort column, to save them.
+    pub fn column_state(&self) -> ColumnState {
+        let columns: Vec<(bool, i32)> = self
+            .columns
+            .borrow()
+            .iter()
+            .map(|c| (c.hidden, c.shown_width))
+            .collect();
+        let (widths, hidden) = columns
+            .into_iter()
+            .enumerate()
+            .map(|(col, (hidden, shown_width))| {
+                if hidden {
+                    (shown_width, true)
+                } else {
+                    (self.column_width(col), false)
+                }
+            })
+            .unzip();
+        ColumnState {
+            widths,
+            order: self.column_order(),
+            hidden,
+            sorted_by: self.sorted_by(),
+        }
+    }
+
+    /// Restores the state from `column_state`. Returns false, and changes nothing,
+    /// if the state does not fit the columns, such as when the app has added a
+    /// column since it was saved.
+    pub fn set_column_state(&self, state: &ColumnState) -> bool {
+        if !state.is_valid_for(self.num_columns()) {
+            return false;
+        }
+        for (col, (&width, &hidden)) in state.widths.iter().zip(state.hidden.iter()).enumerate() {
+            self.set_column_hidden(col, false);
+            self.set_column_width(col, width);
+            self.set_column_hidden(col, hidden);
+        }
+        self.set_column_order(&state.order);
+        if let Some((col, order)) = state.sorted_by {
+            self.sort_by_column(col, order);
+        }
+        true
+    }
+
+    unsafe fn header_notify(&self, nmhdr: *mut NMHDR) -> Option<LRESULT> {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/hdn-itemchanging
+            HDN_ITEMCHANGINGW => {
+                let header = &*(nmhdr as *const NMHEADERW);
+                if !header.pitem.is_null() && (*header.pitem).mask.0 & HDI_WIDTH.0 != 0 {
+                    if let Some(info) = self.columns.borrow().get(header.iItem as usize) {
+                        let item = &mut *header.pitem;
+                        item.cxy = info.constrain_width(item.cxy);
+                    }
+                }
+                None
+            }
+
+            // Keep the user from resizing hidden columns, by dragging or by
+            // double-clicking the divider.
+            HDN_BEGINTRACKW | HDN_DIVIDERDBLCLICKW => {
+                let header = &*(nmhdr as *const NMHEADERW);
+                if self.is_column_hidden(header.iItem as usize) {
+                    Some(LRESULT(1))
+                } else {
+                    None
+                }
+            }
+
+            _ => None,
+        }
     }
 
     // https://docs.microsoft.com/en-us/windows/win32/controls/lvm-deletecolumn
     pub fn delete_column(&self, index: usize) {
+        {
+            let mut columns = self.columns.borrow_mut();
+            if index < columns.len() {
+                columns.remove(index);
+                self.sort.borrow_mut().delete_column(index);
+            }
+        }
         unsafe {
             SendMessageW(
                 self.control.handle(),
@@ -107,6 +598,329 @@ impl ListView {
         }
     }
 
+    // Groups
+
+    /// Shows the items in their groups, under a header for each group. Items that
+    /// are not in a group are not shown. A virtual list view cannot show groups.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-enablegroupview>
+    pub fn set_group_view(&self, value: bool) {
+        debug_assert!(
+            self.source.is_none(),
+            "a virtual list view cannot show groups"
+        );
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_ENABLEGROUPVIEW,
+                Some(WPARAM(value as usize)),
+                None,
+            );
+        }
+    }
+
+    pub fn is_group_view(&self) -> bool {
+        unsafe { SendMessageW(self.handle(), LVM_ISGROUPVIEWENABLED, None, None).0 != 0 }
+    }
+
+    /// Adds a group after the others, and returns its id, which is used to put
+    /// items in it. Returns `None` if the group could not be added.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-insertgroup>
+    pub fn add_group(&self, group: &Group) -> Option<i32> {
+        let id = self.next_group_id.get();
+        unsafe {
+            let header = WCString::from_str_truncate(&group.header);
+            let footer = WCString::from_str_truncate(&group.footer);
+            let mut lv_group: LVGROUP = zeroed();
+            lv_group.cbSize = size_of::<LVGROUP>() as u32;
+            lv_group.mask = LVGF_HEADER | LVGF_GROUPID | LVGF_STATE;
+            lv_group.pszHeader = PWSTR(header.as_ptr() as *mut u16);
+            if !group.footer.is_empty() {
+                lv_group.mask |= LVGF_FOOTER;
+                lv_group.pszFooter = PWSTR(footer.as_ptr() as *mut u16);
+            }
+            lv_group.iGroupId = id;
+            lv_group.stateMask = LVGS_COLLAPSIBLE | LVGS_COLLAPSED;
+            lv_group.state = group.state();
+            let index = SendMessageW(
+                self.handle(),
+                LVM_INSERTGROUP,
+                Some(WPARAM(usize::MAX)),
+                Some(LPARAM(&lv_group as *const LVGROUP as isize)),
+            );
+            if index.0 < 0 {
+                warn!("failed to add list view group {:?}", group.header);
+                return None;
+            }
+        }
+        self.next_group_id.set(id + 1);
+        self.groups.borrow_mut().push(GroupInfo {
+            id,
+            collapsed: group.collapsed,
+        });
+        Some(id)
+    }
+
+    /// Removes a group. Its items are not removed, but are not shown in group view
+    /// until they are put in another group.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-removegroup>
+    pub fn remove_group(&self, group: i32) {
+        self.groups.borrow_mut().retain(|info| info.id != group);
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_REMOVEGROUP,
+                Some(WPARAM(group as usize)),
+                None,
+            );
+        }
+    }
+
+    pub fn remove_all_groups(&self) {
+        self.groups.borrow_mut().clear();
+        unsafe {
+            SendMessageW(self.handle(), LVM_REMOVEALLGROUPS, None, None);
+        }
+    }
+
+    /// The ids of the groups, in the order they are shown.
+    pub fn groups(&self) -> Vec<i32> {
+        self.groups.borrow().iter().map(|info| info.id).collect()
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-setgroupinfo
+    fn set_group_info(&self, group: i32, lv_group: &mut LVGROUP) {
+        lv_group.cbSize = size_of::<LVGROUP>() as u32;
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETGROUPINFO,
+                Some(WPARAM(group as usize)),
+                Some(LPARAM(lv_group as *mut LVGROUP as isize)),
+            );
+        }
+    }
+
+    pub fn set_group_header(&self, group: i32, header: &str) {
+        let header = WCString::from_str_truncate(header);
+        let mut lv_group: LVGROUP = unsafe { zeroed() };
+        lv_group.mask = LVGF_HEADER;
+        lv_group.pszHeader = PWSTR(header.as_ptr() as *mut u16);
+        self.set_group_info(group, &mut lv_group);
+    }
+
+    pub fn set_group_footer(&self, group: i32, footer: &str) {
+        let footer = WCString::from_str_truncate(footer);
+        let mut lv_group: LVGROUP = unsafe { zeroed() };
+        lv_group.mask = LVGF_FOOTER;
+        lv_group.pszFooter = PWSTR(footer.as_ptr() as *mut u16);
+        self.set_group_info(group, &mut lv_group);
+    }
+
+    /// Collapses or expands a group. A group that is collapsed becomes collapsible,
+    /// so that the user can expand it again. This does not call the handler set by
+    /// `group_collapsed_handler`.
+    pub fn set_group_collapsed(&self, group: i32, collapsed: bool) {
+        let mut lv_group: LVGROUP = unsafe { zeroed() };
+        lv_group.mask = LVGF_STATE;
+        if collapsed {
+            lv_group.stateMask = LVGS_COLLAPSIBLE | LVGS_COLLAPSED;
+            lv_group.state = LVGS_COLLAPSIBLE | LVGS_COLLAPSED;
+        } else {
+            lv_group.stateMask = LVGS_COLLAPSED;
+            lv_group.state = LVGS_NORMAL;
+        }
+        self.set_group_info(group, &mut lv_group);
+        if let Some(info) = self
+            .groups
+            .borrow_mut()
+            .iter_mut()
+            .find(|info| info.id == group)
+        {
+            info.collapsed = collapsed;
+        }
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-getgroupstate
+    pub fn is_group_collapsed(&self, group: i32) -> bool {
+        unsafe {
+            let state = SendMessageW(
+                self.handle(),
+                LVM_GETGROUPSTATE,
+                Some(WPARAM(group as usize)),
+                Some(LPARAM(LVGS_COLLAPSED.0 as isize)),
+            );
+            state.0 as u32 & LVGS_COLLAPSED.0 != 0
+        }
+    }
+
+    /// Inserts an item at the end, in `group`.
+    pub fn insert_item_in_group(&self, text: &str, group: i32) -> usize {
+        unsafe {
+            let len = self.items_len();
+
+            let textw = WCString::from_str_truncate(text);
+            let mut lv_item: LVITEMW = zeroed();
+            lv_item.iItem = len as i32;
+            lv_item.iSubItem = 0;
+            lv_item.mask = LVIF_TEXT | LVIF_GROUPID;
+            lv_item.pszText = PWSTR(textw.as_ptr() as *mut u16);
+            lv_item.iGroupId = group;
+            SendMessageW(
+                self.handle(),
+                LVM_INSERTITEMW,
+                None,
+                Some(LPARAM(&lv_item as *const _ as isize)),
+            )
+            .0 as usize
+        }
+    }
+
+    /// Moves an item to `group`.
+    pub fn set_item_group(&self, item: usize, group: i32) {
+        unsafe {
+            let mut lv_item: LVITEMW = zeroed();
+            lv_item.iItem = item as i32;
+            lv_item.mask = LVIF_GROUPID;
+            lv_item.iGroupId = group;
+            SendMessageW(
+                self.handle(),
+                LVM_SETITEMW,
+                None,
+                Some(LPARAM(&lv_item as *const _ as isize)),
+            );
+        }
+    }
+
+    /// Inserts `rows` at the end, in a group for each key, with the groups in the
+    /// order of the keys, and turns on group view. `group` describes the group for
+    /// a key, given the number of rows with that key. Returns the id of each
+    /// group, with its key.
+    ///
+    /// The columns must already be inserted, such as from `T::columns()`.
+    ///
+    /// ```ignore
+    /// list_view.insert_grouped_rows(&files, |file| file.folder.clone(), |folder, count| {
+    ///     Group::new(&format!("{folder} ({count})")).collapsible(true)
+    /// });
+    /// ```
+    pub fn insert_grouped_rows<T, K, F, G>(&self, rows: &[T], key: F, mut group: G) -> Vec<(K, i32)>
+    where
+        T: TableRow,
+        K: Ord,
+        F: FnMut(&T) -> K,
+        G: FnMut(&K, usize) -> Group,
+    {
+        let num_columns = self.num_columns().max(1);
+        let mut text = String::new();
+        let mut ids = Vec::new();
+        for (key, indices) in group_by_key(rows, key) {
+            let Some(id) = self.add_group(&group(&key, indices.len())) else {
+                continue;
+            };
+            for index in indices {
+                let row = &rows[index];
+                text.clear();
+                row.cell_text(0, &mut text);
+                let item = self.insert_item_in_group(&text, id);
+                for col in 1..num_columns {
+                    text.clear();
+                    row.cell_text(col, &mut text);
+                    self.set_subitem_text(item, col, &text);
+                }
+            }
+            ids.push((key, id));
+        }
+        self.set_group_view(true);
+        ids
+    }
+
+    /// Sets the handler that is called when the user clicks the header of a group,
+    /// given its id. Clicking the button that collapses the group calls the handler
+    /// set by `group_collapsed_handler` instead.
+    pub fn group_header_click_handler<F>(&self, handler: F)
+    where
+        F: Fn(i32) + 'static,
+    {
+        let result = self.group_header_click_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call group_header_click_handler() more than once"
+        );
+    }
+
+    /// Sets the handler that is called when the user collapses or expands a group,
+    /// given its id and whether it is now collapsed.
+    pub fn group_collapsed_handler<F>(&self, handler: F)
+    where
+        F: Fn(i32, bool) + 'static,
+    {
+        let result = self.group_collapsed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call group_collapsed_handler() more than once"
+        );
+    }
+
+    /// Finds the group whose header is at `point`, not counting its collapse
+    /// button.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-hittest>
+    unsafe fn group_header_at(&self, point: POINT) -> Option<i32> {
+        let mut info: LVHITTESTINFO = zeroed();
+        info.pt = point;
+        // With -1, the list view also finds the group.
+        SendMessageW(
+            self.handle(),
+            LVM_HITTEST,
+            Some(WPARAM(usize::MAX)),
+            Some(LPARAM(&mut info as *mut LVHITTESTINFO as isize)),
+        );
+        if !info.flags.contains(LVHT_EX_GROUP_HEADER)
+            || info.flags.contains(LVHT_EX_GROUP_COLLAPSE)
+            || info.iGroup < 0
+        {
+            return None;
+        }
+        let mut lv_group: LVGROUP = zeroed();
+        lv_group.cbSize = size_of::<LVGROUP>() as u32;
+        lv_group.mask = LVGF_GROUPID;
+        let found = SendMessageW(
+            self.handle(),
+            LVM_GETGROUPINFOBYINDEX,
+            Some(WPARAM(info.iGroup as usize)),
+            Some(LPARAM(&mut lv_group as *mut LVGROUP as isize)),
+        );
+        (found.0 != 0).then_some(lv_group.iGroupId)
+    }
+
+    /// Calls the handler set by `group_collapsed_handler` for each group that the
+    /// user has collapsed or expanded. The list view does not send a notification
+    /// for this, so the groups are checked after each click and key.
+    fn check_groups_collapsed(&self) {
+        let Some(handler) = self.group_collapsed_handler.get() else {
+            return;
+        };
+        let changed: Vec<(i32, bool)> = self
+            .groups
+            .borrow_mut()
+            .iter_mut()
+            .filter_map(|info| {
+                let collapsed = self.is_group_collapsed(info.id);
+                if collapsed == info.collapsed {
+                    return None;
+                }
+                info.collapsed = collapsed;
+                Some((info.id, collapsed))
+            })
+            .collect();
+        for (group, collapsed) in changed {
+            handler(group, collapsed);
+        }
+    }
+
     // Appearance properties
 
     // https://docs.microsoft.com/en-us/windows/win32/controls/extended-list-view-styles
@@ -185,6 +999,162 @@ impl ListView {
             .set_window_style_flag(WINDOW_STYLE(LVS_EDITLABELS), value);
     }
 
+    /// Sets the handler that provides the tooltip for an item, given the item and
+    /// subitem indices. If the handler returns `None`, the tooltip shows the item's
+    /// text when it is too wide to be displayed in full.
+    pub fn item_tooltip_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize, usize) -> Option<String> + 'static,
+    {
+        let result = self.item_tooltip_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call item_tooltip_handler() more than once"
+        );
+        self.set_ex_style_bool(LVS_EX_INFOTIP, true);
+    }
+
+    /// Sets the handler that is called when the user starts editing the label of an
+    /// item, given its index. If the handler returns false, the label is not edited.
+    pub fn begin_label_edit_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize) -> bool + 'static,
+    {
+        let result = self.begin_label_edit_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call begin_label_edit_handler() more than once"
+        );
+    }
+
+    /// Sets the handler that is called when the user has finished editing the label
+    /// of an item, given its index and the new text, or `None` if the edit was
+    /// cancelled. If the handler returns true, the item gets the new text; if it
+    /// returns false, the item keeps its old text.
+    ///
+    /// A virtual list view does not store the text, so the handler must change the
+    /// row in the source. The row is redrawn if the handler returns true.
+    ///
+    /// Without this handler, the item always keeps its old text.
+    pub fn end_label_edit_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize, Option<&str>) -> bool + 'static,
+    {
+        let result = self.end_label_edit_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call end_label_edit_handler() more than once"
+        );
+    }
+
+    /// Starts editing the label of an item, as if the user had clicked it. This
+    /// moves the focus to the list view. Returns false if the edit did not start,
+    /// such as when the begin-edit handler refused it.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-editlabel>
+    pub fn edit_label(&self, item: usize) -> bool {
+        unsafe {
+            _ = SetFocus(Some(self.handle()));
+            let edit = SendMessageW(self.handle(), LVM_EDITLABELW, Some(WPARAM(item)), None);
+            edit.0 != 0
+        }
+    }
+
+    /// Stops editing the label of an item, if one is being edited. The item keeps
+    /// its old text.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/lvm-canceleditlabel>
+    pub fn cancel_label_edit(&self) {
+        unsafe {
+            SendMessageW(self.handle(), LVM_CANCELEDITLABEL, None, None);
+        }
+    }
+
+    /// Sets the handler that gives the colors and font of a cell, given the item and
+    /// subitem indices, such as to show errors in red, or to shade every other row.
+    /// The handler is called for each cell as it is drawn, so it should be fast. If
+    /// it returns `None`, the cell is drawn as usual.
+    ///
+    /// For a virtual list view, this is used instead of `ListSource::cell_style`.
+    pub fn cell_style_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize, usize) -> Option<CellStyle> + 'static,
+    {
+        let result = self.cell_style_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call cell_style_handler() more than once"
+        );
+        unsafe {
+            _ = InvalidateRect(Some(self.handle()), None, true);
+        }
+    }
+
+    fn cell_style(&self, row: usize, col: usize) -> Option<CellStyle> {
+        if let Some(handler) = self.cell_style_handler.get() {
+            handler(row, col)
+        } else if let Some(source) = &self.source {
+            source.cell_style(row, col)
+        } else {
+            None
+        }
+    }
+
+    /// Applies the cell styles, while the list view draws its items.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/nm-customdraw-list-view>
+    unsafe fn custom_draw(&self, draw: &mut NMLVCUSTOMDRAW) -> Option<u32> {
+        let stage = draw.nmcd.dwDrawStage.0;
+        if stage == CDDS_PREPAINT.0 {
+            if self.cell_style_handler.get().is_none() && self.source.is_none() {
+                return None;
+            }
+            // The font that is selected for one item stays selected for the next.
+            self.draw_font
+                .set(HFONT(GetCurrentObject(draw.nmcd.hdc, OBJ_FONT).0));
+            return Some(CDRF_NOTIFYITEMDRAW);
+        }
+
+        let is_item = stage == CDDS_ITEMPREPAINT.0;
+        if !is_item && stage != CDDS_ITEMPREPAINT.0 | CDDS_SUBITEM.0 {
+            return None;
+        }
+        // In group view, the group headers are drawn too, and their dwItemSpec is
+        // the group id rather than a row.
+        if draw.dwItemType != LVCDI_ITEM {
+            return None;
+        }
+        if is_item {
+            // The colors that are set for one subitem stay set for the next, so keep
+            // the defaults to restore them.
+            self.draw_colors.set((draw.clrText, draw.clrTextBk));
+        }
+        let (text_color, back_color) = self.draw_colors.get();
+
+        // In the modes other than Details, only the item is drawn, with the style
+        // of its first column.
+        let row = draw.nmcd.dwItemSpec;
+        let col = if is_item {
+            0
+        } else {
+            draw.iSubItem.max(0) as usize
+        };
+        let style = self.cell_style(row, col).unwrap_or_default();
+        draw.clrText = style.foreground.map_or(text_color, COLORREF::from);
+        draw.clrTextBk = style.background.map_or(back_color, COLORREF::from);
+        let styled_font =
+            self.styled_fonts
+                .borrow_mut()
+                .get(self.draw_font.get(), style.bold, style.italic);
+        SelectObject(draw.nmcd.hdc, HGDIOBJ(styled_font.0));
+
+        Some(if is_item {
+            CDRF_NEWFONT | CDRF_NOTIFYSUBITEMDRAW
+        } else {
+            CDRF_NEWFONT
+        })
+    }
+
     /// Sets all items to the not-selected state.
     pub fn clear_selection(&self) {}
 
@@ -208,40 +1178,69 @@ impl ListView {
     }
 
     pub fn get_item_text(&self, item: usize, subitem: usize) -> String {
-        unsafe {
-            let mut buffer: Vec<u16> = vec![0; 0x100];
-            let mut lv_item: LVITEMW = zeroed();
-            lv_item.iItem = item as i32;
-            lv_item.iSubItem = subitem as i32;
-            lv_item.pszText = PWSTR(buffer.as_mut_ptr());
-            lv_item.cchTextMax = buffer.len() as i32;
-
-            let mut len = SendMessageW(
-                self.handle(),
-                LVM_GETITEMTEXT,
-                Some(WPARAM(item)),
-                Some(LPARAM(&mut lv_item as *mut _ as isize)),
-            )
-            .0;
-            assert!(len >= 0);
-            if len as usize > buffer.len() {
-                // Resize the buffer, try again.
+        let mut text = String::new();
+        self.read_item_text(item, subitem, &mut Vec::new(), &mut text);
+        text
+    }
 
-                buffer.resize(len as usize, 0);
+    /// Gets the text of an item or subitem into `text`, using `buffer` to receive it
+    /// from the control. Both are reused, so that sorting does not allocate for each
+    /// comparison.
+    ///
+    /// This uses `LVM_GETITEMW`, because it is the only message that may be sent to
+    /// the list view while it is sorting. It must not panic, as it is called from
+    /// the sort callback.
+    fn read_item_text(
+        &self,
+        item: usize,
+        subitem: usize,
+        buffer: &mut Vec<u16>,
+        text: &mut String,
+    ) {
+        text.clear();
+        if buffer.len() < 0x100 {
+            buffer.resize(0x100, 0);
+        }
+        loop {
+            unsafe {
+                let mut lv_item: LVITEMW = zeroed();
+                lv_item.mask = LVIF_TEXT;
+                lv_item.iItem = item as i32;
+                lv_item.iSubItem = subitem as i32;
                 lv_item.pszText = PWSTR(buffer.as_mut_ptr());
                 lv_item.cchTextMax = buffer.len() as i32;
-
-                len = SendMessageW(
+                buffer[0] = 0;
+                let ok = SendMessageW(
                     self.handle(),
-                    LVM_GETITEMTEXT,
-                    Some(WPARAM(item)),
+                    LVM_GETITEMW,
+                    None,
                     Some(LPARAM(&mut lv_item as *mut _ as isize)),
                 )
-                .0;
-                assert!(len >= 0);
+                .0 != 0;
+                if !ok || lv_item.pszText.is_null() {
+                    return;
+                }
+                // The control may point `pszText` at its own copy of the text,
+                // rather than copying it into the buffer.
+                let chars = if lv_item.pszText.0 == buffer.as_mut_ptr() {
+                    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+                    // If the text filled the buffer, it may have been truncated:
+                    // resize the buffer and try again.
+                    if len + 1 >= buffer.len() && buffer.len() < 0x10000 {
+                        let new_len = buffer.len() * 2;
+                        buffer.resize(new_len, 0);
+                        continue;
+                    }
+                    &buffer[..len]
+                } else {
+                    lv_item.pszText.as_wide()
+                };
+                text.extend(
+                    char::decode_utf16(chars.iter().copied())
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+                );
+                return;
             }
-
-            WStr::from_slice(&buffer[..len as usize]).to_string_lossy()
         }
     }
 
@@ -273,6 +1272,69 @@ impl ListView {
         }
     }
 
+    /// Inserts an item at the end, showing image `image` from the list view's image
+    /// lists.
+    pub fn insert_item_with_image(&self, text: &str, image: u32) -> usize {
+        unsafe {
+            let len = self.items_len();
+
+            let textw = WCString::from_str_truncate(text);
+            let mut lv_item: LVITEMW = zeroed();
+            lv_item.iItem = len as i32;
+            lv_item.iSubItem = 0;
+            lv_item.mask |= LVIF_TEXT | LVIF_IMAGE;
+            lv_item.pszText = PWSTR(textw.as_ptr() as *mut u16);
+            lv_item.iImage = image as i32;
+            SendMessageW(
+                self.handle(),
+                LVM_INSERTITEMW,
+                None,
+                Some(LPARAM(&lv_item as *const _ as isize)),
+            )
+            .0 as usize
+        }
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-setitem
+    pub fn set_item_image(&self, item: usize, image: u32) {
+        unsafe {
+            let mut lv_item: LVITEMW = zeroed();
+            lv_item.iItem = item as i32;
+            lv_item.mask = LVIF_IMAGE;
+            lv_item.iImage = image as i32;
+            SendMessageW(
+                self.handle(),
+                LVM_SETITEMW,
+                None,
+                Some(LPARAM(&lv_item as *const _ as isize)),
+            );
+        }
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/lvm-setimagelist
+    fn set_image_list(&self, which: u32, images: &ImageList) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETIMAGELIST,
+                Some(WPARAM(which as usize)),
+                Some(LPARAM(images.himagelist.0)),
+            );
+        }
+    }
+
+    /// Sets the images used in the `Details`, `List` and `SmallIcon` modes.
+    pub fn set_small_image_list(&self, images: Rc<ImageList>) {
+        self.set_image_list(LVSIL_SMALL, &images);
+        *self.small_images.borrow_mut() = Some(images);
+    }
+
+    /// Sets the images used in the `Icon` and `Tile` modes.
+    pub fn set_large_image_list(&self, images: Rc<ImageList>) {
+        self.set_image_list(LVSIL_NORMAL, &images);
+        *self.large_images.borrow_mut() = Some(images);
+    }
+
     // https://docs.microsoft.com/en-us/windows/win32/controls/lvm-setitemtext
     pub fn set_subitem_text(&self, item: usize, subitem: usize, s: &str) {
         unsafe {
@@ -348,6 +1410,224 @@ impl ListView {
     }
 }
 
+impl NotifyHandlerTrait for ListView {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-getinfotip
+            LVN_GETINFOTIPW => {
+                let info = &mut *(nmhdr as *mut NMLVGETINFOTIPW);
+                if let Some(handler) = self.item_tooltip_handler.get() {
+                    if let Some(text) = handler(info.iItem as usize, info.iSubItem as usize) {
+                        copy_to_text_buffer(&text, info.pszText, info.cchTextMax);
+                    }
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-beginlabeledit
+            LVN_BEGINLABELEDITW => {
+                let info = &*(nmhdr as *const NMLVDISPINFOW);
+                match self.begin_label_edit_handler.get() {
+                    Some(handler) if info.item.iItem >= 0 => {
+                        // Returning TRUE cancels the edit.
+                        let allow = handler(info.item.iItem as usize);
+                        NotifyResult::Consumed(LRESULT(!allow as isize))
+                    }
+                    _ => NotifyResult::NotConsumed,
+                }
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-endlabeledit
+            LVN_ENDLABELEDITW => {
+                let info = &*(nmhdr as *const NMLVDISPINFOW);
+                match self.end_label_edit_handler.get() {
+                    Some(handler) if info.item.iItem >= 0 => {
+                        let item = info.item.iItem as usize;
+                        // The text is null if the user cancelled the edit.
+                        let text = if info.item.pszText.is_null() {
+                            None
+                        } else {
+                            Some(info.item.pszText.to_string().unwrap_or_default())
+                        };
+                        let accept = handler(item, text.as_deref()) && text.is_some();
+                        if accept && self.source.is_some() {
+                            self.refresh(item..=item);
+                        }
+                        NotifyResult::Consumed(LRESULT(accept as isize))
+                    }
+                    _ => NotifyResult::NotConsumed,
+                }
+            }
+
+            NM_CUSTOMDRAW => match self.custom_draw(&mut *(nmhdr as *mut NMLVCUSTOMDRAW)) {
+                Some(result) => NotifyResult::Consumed(LRESULT(result as isize)),
+                None => NotifyResult::NotConsumed,
+            },
+
+            // The app's notify_handler still sees the click.
+            NM_CLICK => {
+                if let Some(handler) = self.group_header_click_handler.get() {
+                    let click = &*(nmhdr as *const NMITEMACTIVATE);
+                    if let Some(group) = self.group_header_at(click.ptAction) {
+                        handler(group);
+                    }
+                }
+                NotifyResult::NotConsumed
+            }
+
+            // The app's notify_handler still sees the click.
+            LVN_COLUMNCLICK => {
+                let col = (*(nmhdr as *const NMLISTVIEW)).iSubItem;
+                let order = {
+                    let sort = self.sort.borrow();
+                    match sort.comparator(col as usize) {
+                        Some(_) if col >= 0 => Some(sort.order_after_click(col as usize)),
+                        _ => None,
+                    }
+                };
+                if let Some(order) = order {
+                    self.sort_by_column(col as usize, order);
+                }
+                NotifyResult::NotConsumed
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-getdispinfo
+            LVN_GETDISPINFOW => {
+                if let Some(source) = &self.source {
+                    let item = &mut (*(nmhdr as *mut NMLVDISPINFOW)).item;
+                    self.get_disp_info(&**source, item);
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-odcachehint
+            LVN_ODCACHEHINT => {
+                if let Some(source) = &self.source {
+                    let hint = &*(nmhdr as *const NMLVCACHEHINT);
+                    if hint.iFrom >= 0 && hint.iTo >= hint.iFrom {
+                        source.cache_hint(hint.iFrom as usize..hint.iTo as usize + 1);
+                    }
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-odfinditem
+            LVN_ODFINDITEMW => {
+                let find = &*(nmhdr as *const NMLVFINDITEMW);
+                let found = match &self.source {
+                    Some(source)
+                        if find.lvfi.flags.0 & (LVFI_STRING.0 | LVFI_PARTIAL.0) != 0
+                            && !find.lvfi.psz.is_null() =>
+                    {
+                        let text = find.lvfi.psz.to_string().unwrap_or_default();
+                        source.find_row(
+                            &text,
+                            find.iStart.max(0) as usize,
+                            find.lvfi.flags.contains(LVFI_PARTIAL),
+                            find.lvfi.flags.contains(LVFI_WRAP),
+                        )
+                    }
+                    _ => None,
+                };
+                NotifyResult::Consumed(LRESULT(found.map(|row| row as isize).unwrap_or(-1)))
+            }
+
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
+
+impl Drop for ListView {
+    fn drop(&mut self) {
+        unsafe {
+            _ = RemoveWindowSubclass(
+                self.handle(),
+                Some(list_view_subclass_proc),
+                LIST_VIEW_SUBCLASS_ID,
+            );
+        }
+    }
+}
+
+unsafe extern "system" fn list_view_subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    ref_data: usize,
+) -> LRESULT {
+    let this: &ListView = &*(ref_data as *const ListView);
+    match message {
+        WM_NOTIFY => {
+            if let Some(result) = this.header_notify(lparam.0 as *mut NMHDR) {
+                return result;
+            }
+        }
+        WM_LBUTTONDOWN | WM_LBUTTONUP | WM_LBUTTONDBLCLK | WM_KEYDOWN => {
+            let result = DefSubclassProc(hwnd, message, wparam, lparam);
+            this.check_groups_collapsed();
+            return result;
+        }
+        _ => {}
+    }
+    DefSubclassProc(hwnd, message, wparam, lparam)
+}
+
+struct SortContext<'a> {
+    list_view: &'a ListView,
+    col: usize,
+    order: SortOrder,
+    comparator: &'a Comparator,
+    /// Reused by each comparison: the buffer that the text is read into, and the
+    /// text of the two items.
+    buffer: RefCell<Vec<u16>>,
+    texts: RefCell<(String, String)>,
+}
+
+/// With `LVM_SORTITEMSEX`, the items are given by their indices.
+unsafe extern "system" fn compare_items(item1: LPARAM, item2: LPARAM, context: LPARAM) -> i32 {
+    let context = &*(context.0 as *const SortContext);
+    let mut buffer = context.buffer.borrow_mut();
+    let mut texts = context.texts.borrow_mut();
+    let (a, b) = &mut *texts;
+    let list_view = context.list_view;
+    list_view.read_item_text(item1.0 as usize, context.col, &mut buffer, a);
+    list_view.read_item_text(item2.0 as usize, context.col, &mut buffer, b);
+    context.order.apply(context.comparator.compare(a, b)) as i32
+}
+
+impl ListView {
+    unsafe fn get_disp_info(&self, source: &dyn ListSource, item: &mut LVITEMW) {
+        if item.iItem < 0 || item.iSubItem < 0 {
+            return;
+        }
+        let row = item.iItem as usize;
+        let col = item.iSubItem as usize;
+        if item.mask.contains(LVIF_TEXT) {
+            // The buffer is taken out while the source writes to it, in case the
+            // source calls back into the list view.
+            let mut text = self.cell_text.take();
+            text.clear();
+            source.cell_text(row, col, &mut text);
+            copy_to_text_buffer(&text, item.pszText, item.cchTextMax);
+            self.cell_text.set(text);
+        }
+        if item.mask.contains(LVIF_IMAGE) && col == 0 {
+            if let Some(image) = source.image(row) {
+                item.iImage = image as i32;
+            }
+        }
+        if item.mask.contains(LVIF_STATE) {
+            if let Some(state_image) = source.state_image(row) {
+                // INDEXTOSTATEIMAGEMASK
+                item.state = LIST_VIEW_ITEM_STATE_FLAGS(state_image << 12);
+                item.stateMask = LVIS_STATEIMAGEMASK;
+            }
+        }
+    }
+}
+
 /*
 impl NotifyHandlerTrait for ListView {
     unsafe fn wm_notify(&self, _control_id: WPARAM, nmhdr: *mut NMHDR) -> NotifyResult {
@@ -0,0 +1,69 @@
+use super::*;
+
+/// How a cell of a `ListView` is drawn, for `ListView::cell_style_handler` and
+/// `ListSource::cell_style`. Each part that is not set is drawn as usual, so the
+/// default style changes nothing.
+///
+/// ```ignore
+/// CellStyle {
+///     foreground: Some(ColorRef::RED),
+///     bold: true,
+///     ..Default::default()
+/// }
+/// ```
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub struct CellStyle {
+    /// The color of the text.
+    pub foreground: Option<ColorRef>,
+    /// The color behind the text. Selected rows are drawn with the selection
+    /// colors instead.
+    pub background: Option<ColorRef>,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// The bold and italic fonts that styled cells are drawn with, made from the font
+/// of the list view.
+#[derive(Default)]
+pub(crate) struct StyledFonts {
+    base: HFONT,
+    /// Bold, italic, and bold italic.
+    fonts: [Option<Font>; 3],
+}
+
+impl StyledFonts {
+    /// Gets the font for a cell drawn with `base`. This is `base` itself for a cell
+    /// that is neither bold nor italic, or if the font cannot be made.
+    pub(crate) unsafe fn get(&mut self, base: HFONT, bold: bool, italic: bool) -> HFONT {
+        let index = match (bold, italic) {
+            (false, false) => return base,
+            (true, false) => 0,
+            (false, true) => 1,
+            (true, true) => 2,
+        };
+        if self.base != base {
+            // The list view's font has changed.
+            self.base = base;
+            self.fonts = Default::default();
+        }
+        if self.fonts[index].is_none() {
+            let mut logfont: LOGFONTW = zeroed();
+            let len = GetObjectW(
+                HGDIOBJ(base.0),
+                size_of::<LOGFONTW>() as i32,
+                Some(&mut logfont as *mut LOGFONTW as *mut c_void),
+            );
+            if len == 0 {
+                return base;
+            }
+            if bold {
+                logfont.lfWeight = FW_BOLD.0 as i32;
+            }
+            if italic {
+                logfont.lfItalic = 1;
+            }
+            self.fonts[index] = Font::from_logfont(&logfont).ok();
+        }
+        self.fonts[index].as_ref().map_or(base, |font| font.hfont)
+    }
+}
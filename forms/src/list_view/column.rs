@@ -0,0 +1,370 @@
+use super::*;
+use core::fmt;
+use core::str::FromStr;
+
+/// How the text of a `ListView` column is aligned. The first column is always
+/// aligned to the left.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum ColumnAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl ColumnAlign {
+    pub(crate) fn to_native(self) -> LVCOLUMNW_FORMAT {
+        match self {
+            Self::Left => LVCFMT_LEFT,
+            Self::Center => LVCFMT_CENTER,
+            Self::Right => LVCFMT_RIGHT,
+        }
+    }
+}
+
+/// What a column is sized to fit, by `ListView::autosize_column`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ColumnAutoSize {
+    /// The widest cell.
+    Content,
+    /// The widest cell or the header, whichever is wider.
+    ContentAndHeader,
+}
+
+/// Describes a column of a `ListView`, for `ListView::insert_column`.
+///
+/// ```ignore
+/// list_view.insert_column(1, &Column::new("Size").width(80).align(ColumnAlign::Right));
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Column {
+    pub(crate) title: String,
+    pub(crate) width: i32,
+    pub(crate) align: ColumnAlign,
+    pub(crate) min_width: i32,
+    pub(crate) max_width: Option<i32>,
+    pub(crate) autosize: Option<ColumnAutoSize>,
+    pub(crate) hidden: bool,
+}
+
+/// The width of a column, if none is given.
+pub const DEFAULT_COLUMN_WIDTH: i32 = 100;
+
+impl Column {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            width: DEFAULT_COLUMN_WIDTH,
+            align: ColumnAlign::Left,
+            min_width: 0,
+            max_width: None,
+            autosize: None,
+            hidden: false,
+        }
+    }
+
+    /// Sets the width, in pixels.
+    pub fn width(mut self, width: i32) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn align(mut self, align: ColumnAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Keeps the user from making the column narrower than `width`.
+    pub fn min_width(mut self, width: i32) -> Self {
+        self.min_width = width;
+        self
+    }
+
+    /// Keeps the user from making the column wider than `width`.
+    pub fn max_width(mut self, width: i32) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// Sizes the column to fit its content when it is inserted, and whenever
+    /// `ListView::autosize_columns` is called, within the minimum and maximum
+    /// widths.
+    pub fn autosize(mut self, autosize: ColumnAutoSize) -> Self {
+        self.autosize = Some(autosize);
+        self
+    }
+
+    /// Hides the column. See `ListView::set_column_hidden`.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+}
+
+/// What a `ListView` remembers about each of its columns, to enforce the limits
+/// that the header control does not know about.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ColumnInfo {
+    pub(crate) min_width: i32,
+    pub(crate) max_width: Option<i32>,
+    pub(crate) autosize: Option<ColumnAutoSize>,
+    pub(crate) hidden: bool,
+    /// The width to restore when a hidden column is shown.
+    pub(crate) shown_width: i32,
+}
+
+impl ColumnInfo {
+    pub(crate) fn new(column: &Column) -> Self {
+        Self {
+            min_width: column.min_width,
+            max_width: column.max_width,
+            autosize: column.autosize,
+            hidden: column.hidden,
+            shown_width: column.width,
+        }
+    }
+
+    /// The width that the column gets, if `width` is asked for. A hidden column
+    /// has no width.
+    pub(crate) fn constrain_width(&self, width: i32) -> i32 {
+        if self.hidden {
+            return 0;
+        }
+        let width = width.max(self.min_width);
+        match self.max_width {
+            Some(max) => width.min(max.max(self.min_width)),
+            None => width,
+        }
+    }
+}
+
+/// The layout of the columns of a `ListView`, which the user can change: their
+/// widths, the order they are shown in, which are hidden, and which column the
+/// rows are sorted by. Apps can save it with `to_string`, and restore it with
+/// `parse` and `ListView::set_column_state`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ColumnState {
+    /// The width of each column. For a hidden column, this is the width it has when
+    /// it is shown.
+    pub widths: Vec<i32>,
+    /// The columns, in the order they are shown.
+    pub order: Vec<usize>,
+    /// Whether each column is hidden.
+    pub hidden: Vec<bool>,
+    pub sorted_by: Option<(usize, SortOrder)>,
+}
+
+impl ColumnState {
+    /// Checks that the state describes `num_columns` columns, and that `order` holds
+    /// each of them once.
+    pub fn is_valid_for(&self, num_columns: usize) -> bool {
+        if self.widths.len() != num_columns
+            || self.order.len() != num_columns
+            || self.hidden.len() != num_columns
+        {
+            return false;
+        }
+        let mut seen = vec![false; num_columns];
+        for &col in self.order.iter() {
+            if col >= num_columns || seen[col] {
+                return false;
+            }
+            seen[col] = true;
+        }
+        self.sorted_by.is_none_or(|(col, _)| col < num_columns)
+    }
+}
+
+/// Writes the state as `widths=120,80;order=1,0;hidden=1;sort=0:asc`.
+impl fmt::Display for ColumnState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: fmt::Display>(items: impl Iterator<Item = T>) -> String {
+            items.map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+        }
+        write!(f, "widths={}", list(self.widths.iter()))?;
+        write!(f, ";order={}", list(self.order.iter()))?;
+        let hidden = self
+            .hidden
+            .iter()
+            .enumerate()
+            .filter(|(_, &hidden)| hidden)
+            .map(|(col, _)| col);
+        write!(f, ";hidden={}", list(hidden))?;
+        if let Some((col, order)) = self.sorted_by {
+            let order = match order {
+                SortOrder::Ascending => "asc",
+                SortOrder::Descending => "desc",
+            };
+            write!(f, ";sort={}:{}", col, order)?;
+        }
+        Ok(())
+    }
+}
+
+/// The error for text that is not a `ColumnState`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseColumnStateError;
+
+impl fmt::Display for ParseColumnStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid column state")
+    }
+}
+
+impl std::error::Error for ParseColumnStateError {}
+
+impl FromStr for ColumnState {
+    type Err = ParseColumnStateError;
+
+    /// Reads the text written by `to_string`. The `widths`, `order` and `hidden` keys
+    /// are required. Unknown keys are ignored, so that state saved by a later
+    /// version can still be read.
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        fn list<T: FromStr>(s: &str) -> core::result::Result<Vec<T>, ParseColumnStateError> {
+            s.split(',')
+                .filter(|item| !item.is_empty())
+                .map(|item| item.trim().parse().map_err(|_| ParseColumnStateError))
+                .collect()
+        }
+
+        let mut state = ColumnState::default();
+        let mut widths: Option<Vec<i32>> = None;
+        let mut order: Option<Vec<usize>> = None;
+        let mut hidden: Option<Vec<usize>> = None;
+        for part in s.split(';').filter(|part| !part.trim().is_empty()) {
+            let (key, value) = part.split_once('=').ok_or(ParseColumnStateError)?;
+            match key.trim() {
+                "widths" => widths = Some(list(value)?),
+                "order" => order = Some(list(value)?),
+                "hidden" => hidden = Some(list(value)?),
+                "sort" => {
+                    let (col, order) = value.split_once(':').ok_or(ParseColumnStateError)?;
+                    let col = col.trim().parse().map_err(|_| ParseColumnStateError)?;
+                    let order = match order.trim() {
+                        "asc" => SortOrder::Ascending,
+                        "desc" => SortOrder::Descending,
+                        _ => return Err(ParseColumnStateError),
+                    };
+                    state.sorted_by = Some((col, order));
+                }
+                _ => {}
+            }
+        }
+        let (Some(widths), Some(order), Some(hidden)) = (widths, order, hidden) else {
+            return Err(ParseColumnStateError);
+        };
+        state.widths = widths;
+        state.order = order;
+        state.hidden = vec![false; state.widths.len()];
+        for col in hidden {
+            *state.hidden.get_mut(col).ok_or(ParseColumnStateError)? = true;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> ColumnState {
+        ColumnState {
+            widths: vec![120, 80, 200],
+            order: vec![2, 0, 1],
+            hidden: vec![false, true, false],
+            sorted_by: Some((2, SortOrder::Descending)),
+        }
+    }
+
+    fn parse(s: &str) -> core::result::Result<ColumnState, ParseColumnStateError> {
+        s.parse()
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = state().to_string();
+        assert_eq!(text, "widths=120,80,200;order=2,0,1;hidden=1;sort=2:desc");
+        assert_eq!(parse(&text), Ok(state()));
+
+        let unsorted = ColumnState {
+            widths: vec![50, 60],
+            order: vec![0, 1],
+            hidden: vec![false, false],
+            sorted_by: None,
+        };
+        assert_eq!(unsorted.to_string(), "widths=50,60;order=0,1;hidden=");
+        assert_eq!(parse(&unsorted.to_string()), Ok(unsorted));
+
+        let ascending = ColumnState {
+            sorted_by: Some((0, SortOrder::Ascending)),
+            ..state()
+        };
+        assert_eq!(parse(&ascending.to_string()), Ok(ascending));
+
+        let empty = ColumnState::default();
+        assert_eq!(parse(&empty.to_string()), Ok(empty));
+    }
+
+    #[test]
+    fn parse_ignores_spaces_and_unknown_keys() {
+        assert_eq!(
+            parse(" widths = 120, 80,200 ; order=2,0,1;hidden= 1 ;sort= 2 : desc;later=x;"),
+            Ok(state())
+        );
+    }
+
+    #[test]
+    fn parse_rejects_missing_fields() {
+        assert!(parse("").is_err());
+        assert!(parse("order=0;hidden=").is_err());
+        assert!(parse("widths=10;hidden=").is_err());
+        assert!(parse("widths=10;order=0").is_err());
+        assert!(parse("widths;order=0;hidden=").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_values() {
+        assert!(parse("widths=abc;order=0;hidden=").is_err());
+        assert!(parse("widths=10.5;order=0;hidden=").is_err());
+        assert!(parse("widths=10;order=-1;hidden=").is_err());
+        assert!(parse("widths=10;order=0;hidden=x").is_err());
+        // A hidden column that does not exist.
+        assert!(parse("widths=10;order=0;hidden=1").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_sort() {
+        assert!(parse("widths=10;order=0;hidden=;sort=0:up").is_err());
+        assert!(parse("widths=10;order=0;hidden=;sort=0").is_err());
+        assert!(parse("widths=10;order=0;hidden=;sort=x:asc").is_err());
+        assert!(parse("widths=10;order=0;hidden=;sort=:asc").is_err());
+    }
+
+    #[test]
+    fn is_valid_for_checks_the_columns() {
+        assert!(state().is_valid_for(3));
+        assert!(!state().is_valid_for(2));
+        assert!(!state().is_valid_for(4));
+        assert!(ColumnState::default().is_valid_for(0));
+
+        let repeated = ColumnState {
+            order: vec![0, 0, 1],
+            ..state()
+        };
+        assert!(!repeated.is_valid_for(3));
+        let out_of_range = ColumnState {
+            order: vec![0, 1, 3],
+            ..state()
+        };
+        assert!(!out_of_range.is_valid_for(3));
+        let sorted_out_of_range = ColumnState {
+            sorted_by: Some((3, SortOrder::Ascending)),
+            ..state()
+        };
+        assert!(!sorted_out_of_range.is_valid_for(3));
+    }
+}
@@ -0,0 +1,84 @@
+use super::*;
+use std::collections::BTreeMap;
+
+/// Describes a group of items in a `ListView`, for `ListView::add_group`.
+///
+/// ```ignore
+/// let errors = list_view.add_group(&Group::new("Errors").footer("3 errors").collapsible(true));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Group {
+    pub(crate) header: String,
+    pub(crate) footer: String,
+    pub(crate) collapsible: bool,
+    pub(crate) collapsed: bool,
+}
+
+impl Group {
+    pub fn new(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the text shown below the items of the group.
+    pub fn footer(mut self, footer: &str) -> Self {
+        self.footer = footer.to_string();
+        self
+    }
+
+    /// Shows a button in the header, which hides or shows the items of the group.
+    pub fn collapsible(mut self, collapsible: bool) -> Self {
+        self.collapsible = collapsible;
+        self
+    }
+
+    /// Hides the items of the group, until the user expands it. A collapsed group
+    /// is always collapsible.
+    pub fn collapsed(mut self, collapsed: bool) -> Self {
+        self.collapsed = collapsed;
+        self
+    }
+
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    pub(crate) fn state(&self) -> LIST_VIEW_GROUP_STATE_FLAGS {
+        let mut state = LVGS_NORMAL;
+        if self.collapsible || self.collapsed {
+            state |= LVGS_COLLAPSIBLE;
+        }
+        if self.collapsed {
+            state |= LVGS_COLLAPSED;
+        }
+        state
+    }
+}
+
+/// What a `ListView` remembers about each of its groups, to tell when the user has
+/// collapsed or expanded one.
+#[derive(Clone, Debug)]
+pub(crate) struct GroupInfo {
+    pub(crate) id: i32,
+    pub(crate) collapsed: bool,
+}
+
+/// Sorts the indices of `rows` into groups of the rows with the same key, in the
+/// order of the keys. The rows of each group keep their order.
+///
+/// ```ignore
+/// let groups = group_by_key(&files, |file| file.extension.clone());
+/// ```
+pub fn group_by_key<T, K, F>(rows: &[T], mut key: F) -> Vec<(K, Vec<usize>)>
+where
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    let mut groups: BTreeMap<K, Vec<usize>> = BTreeMap::new();
+    for (index, row) in rows.iter().enumerate() {
+        groups.entry(key(row)).or_default().push(index);
+    }
+    groups.into_iter().collect()
+}
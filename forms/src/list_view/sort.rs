@@ -0,0 +1,326 @@
+use core::cmp::Ordering;
+use std::rc::Rc;
+
+/// The direction in which a `ListView` column is sorted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// The other direction.
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::Ascending => Self::Descending,
+            Self::Descending => Self::Ascending,
+        }
+    }
+
+    /// Applies this direction to the result of comparing two values in ascending
+    /// order.
+    pub fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            Self::Ascending => ordering,
+            Self::Descending => ordering.reverse(),
+        }
+    }
+}
+
+/// Compares the text of two cells of a `ListView` column, to sort it.
+#[derive(Clone)]
+pub enum Comparator {
+    /// Compares the text, ignoring case.
+    Text,
+    /// Compares the text, ignoring case, but compares runs of digits by their
+    /// value, so that "file2" comes before "file10". See `natural_cmp`.
+    Natural,
+    /// Compares the text with a function.
+    Custom(Rc<dyn Fn(&str, &str) -> Ordering>),
+}
+
+impl Comparator {
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&str, &str) -> Ordering + 'static,
+    {
+        Self::Custom(Rc::new(f))
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Text => text_cmp(a, b),
+            Self::Natural => natural_cmp(a, b),
+            Self::Custom(f) => f(a, b),
+        }
+    }
+
+    /// Sorts `rows` by the text that `cell_text` writes for each of them. This is
+    /// for a `ListSource` that keeps its rows in a sorted order of indices. The
+    /// sort is stable, so rows with the same text keep their order.
+    pub fn sort_rows<F>(&self, rows: &mut [usize], order: SortOrder, mut cell_text: F)
+    where
+        F: FnMut(usize, &mut String),
+    {
+        // Get the text of each row once, rather than once per comparison.
+        let mut keys: Vec<(String, usize)> = rows
+            .iter()
+            .map(|&row| {
+                let mut text = String::new();
+                cell_text(row, &mut text);
+                (text, row)
+            })
+            .collect();
+        keys.sort_by(|(a, _), (b, _)| order.apply(self.compare(a, b)));
+        for (slot, (_, row)) in rows.iter_mut().zip(keys) {
+            *slot = row;
+        }
+    }
+}
+
+/// Compares two strings, ignoring case. Strings that differ only in case are
+/// ordered by their code points, so that the order does not depend on the input.
+pub fn text_cmp(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+        .then_with(|| a.cmp(b))
+}
+
+/// Compares two strings in "natural" order: runs of ASCII digits are compared by
+/// their value, and everything else is compared ignoring case. "file2" comes
+/// before "file10", and "v1.9" before "v1.10". Strings that differ only in case
+/// or in leading zeros are ordered by their code points.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a_rest.chars().next(), b_rest.chars().next()) else {
+            // The shorter string comes first.
+            break a_rest.is_empty().cmp(&b_rest.is_empty()).reverse();
+        };
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let (a_digits, a_next) = split_digits(a_rest);
+            let (b_digits, b_next) = split_digits(b_rest);
+            let ordering = digits_cmp(a_digits, b_digits);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a_rest = a_next;
+            b_rest = b_next;
+        } else {
+            let ordering = ca.to_lowercase().cmp(cb.to_lowercase());
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a_rest = &a_rest[ca.len_utf8()..];
+            b_rest = &b_rest[cb.len_utf8()..];
+        }
+    }
+    .then_with(|| a.cmp(b))
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compares two runs of digits by value, however long they are.
+fn digits_cmp(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// The comparators of the columns of a `ListView`, and the column it is sorted by.
+#[derive(Default)]
+pub(crate) struct SortState {
+    pub(crate) comparators: Vec<Option<Comparator>>,
+    pub(crate) sorted_by: Option<(usize, SortOrder)>,
+}
+
+impl SortState {
+    pub(crate) fn comparator(&self, col: usize) -> Option<&Comparator> {
+        self.comparators.get(col).and_then(|c| c.as_ref())
+    }
+
+    pub(crate) fn set_comparator(&mut self, col: usize, comparator: Option<Comparator>) {
+        if self.comparators.len() <= col {
+            self.comparators.resize(col + 1, None);
+        }
+        self.comparators[col] = comparator;
+    }
+
+    /// Moves the comparators and the sorted column along with a column that is
+    /// inserted at `index`.
+    pub(crate) fn insert_column(&mut self, index: usize) {
+        if index < self.comparators.len() {
+            self.comparators.insert(index, None);
+        }
+        if let Some((col, _)) = &mut self.sorted_by {
+            if *col >= index {
+                *col += 1;
+            }
+        }
+    }
+
+    /// Removes the comparator of a column that is deleted, and moves the ones after
+    /// it. If the list view was sorted by the column, it is no longer sorted.
+    pub(crate) fn delete_column(&mut self, index: usize) {
+        if index < self.comparators.len() {
+            self.comparators.remove(index);
+        }
+        match &mut self.sorted_by {
+            Some((col, _)) if *col == index => self.sorted_by = None,
+            Some((col, _)) if *col > index => *col -= 1,
+            _ => {}
+        }
+    }
+
+    /// The order to sort `col` in when its header is clicked: ascending, unless it
+    /// is already sorted ascending.
+    pub(crate) fn order_after_click(&self, col: usize) -> SortOrder {
+        match self.sorted_by {
+            Some((sorted_col, order)) if sorted_col == col => order.reversed(),
+            _ => SortOrder::Ascending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Ordering::{Equal, Greater, Less};
+
+    #[test]
+    fn natural_cmp_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Less);
+        assert_eq!(natural_cmp("file10", "file2"), Greater);
+        assert_eq!(natural_cmp("v1.9", "v1.10"), Less);
+        assert_eq!(natural_cmp("v1.10", "v1.10"), Equal);
+        assert_eq!(natural_cmp("2", "10"), Less);
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Less
+        );
+    }
+
+    #[test]
+    fn natural_cmp_leading_zeros() {
+        // The same value, so the text breaks the tie.
+        assert_eq!(natural_cmp("file007", "file7"), Less);
+        assert_eq!(natural_cmp("file7", "file007"), Greater);
+        assert_eq!(natural_cmp("file007", "file8"), Less);
+        assert_eq!(natural_cmp("file010", "file9"), Greater);
+        assert_eq!(digits_cmp("000", "0"), Equal);
+    }
+
+    #[test]
+    fn natural_cmp_ignores_case_until_tie() {
+        assert_eq!(natural_cmp("apple", "Banana"), Less);
+        assert_eq!(natural_cmp("Apple", "banana"), Less);
+        assert_eq!(natural_cmp("File1", "file1"), Less);
+        assert_eq!(natural_cmp("file1", "File1"), Greater);
+        assert_eq!(natural_cmp("file1", "file1"), Equal);
+    }
+
+    #[test]
+    fn natural_cmp_shorter_first() {
+        assert_eq!(natural_cmp("file", "file1"), Less);
+        assert_eq!(natural_cmp("file1", "file"), Greater);
+        assert_eq!(natural_cmp("", "a"), Less);
+        assert_eq!(natural_cmp("", ""), Equal);
+        assert_eq!(natural_cmp("abc", "ab"), Greater);
+    }
+
+    #[test]
+    fn natural_cmp_non_ascii() {
+        assert_eq!(natural_cmp("Émile2", "émile10"), Less);
+        assert_eq!(natural_cmp("Ärger", "ärger"), Less);
+        assert_eq!(natural_cmp("日本1", "日本01"), Greater);
+        // Digits other than ASCII are compared as text.
+        assert_eq!(natural_cmp("x٢", "x١٠"), Greater);
+    }
+
+    #[test]
+    fn text_cmp_ignores_case_until_tie() {
+        assert_eq!(text_cmp("apple", "Banana"), Less);
+        assert_eq!(text_cmp("B", "a"), Greater);
+        assert_eq!(text_cmp("A", "a"), Less);
+        assert_eq!(text_cmp("a", "a"), Equal);
+        assert_eq!(text_cmp("file10", "file2"), Less);
+        assert_eq!(text_cmp("ab", "abc"), Less);
+        assert_eq!(text_cmp("Straße", "STRASSE"), Greater);
+    }
+
+    #[test]
+    fn sort_order_apply() {
+        assert_eq!(SortOrder::Ascending.apply(Less), Less);
+        assert_eq!(SortOrder::Descending.apply(Less), Greater);
+        assert_eq!(SortOrder::Descending.apply(Equal), Equal);
+        assert_eq!(SortOrder::Ascending.reversed(), SortOrder::Descending);
+        assert_eq!(SortOrder::Descending.reversed(), SortOrder::Ascending);
+    }
+
+    #[test]
+    fn sort_rows_is_stable() {
+        let cells = ["b", "a10", "a2", "b", "A2"];
+        let mut rows: Vec<usize> = (0..cells.len()).collect();
+        Comparator::Natural.sort_rows(&mut rows, SortOrder::Ascending, |row, text| {
+            text.push_str(cells[row])
+        });
+        assert_eq!(rows, [4, 2, 1, 0, 3]);
+        Comparator::Natural.sort_rows(&mut rows, SortOrder::Descending, |row, text| {
+            text.push_str(cells[row])
+        });
+        assert_eq!(rows, [0, 3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn order_after_click() {
+        let mut state = SortState::default();
+        assert_eq!(state.order_after_click(1), SortOrder::Ascending);
+        state.sorted_by = Some((1, SortOrder::Ascending));
+        assert_eq!(state.order_after_click(1), SortOrder::Descending);
+        assert_eq!(state.order_after_click(0), SortOrder::Ascending);
+        state.set_comparator(3, Some(Comparator::Text));
+        assert!(state.comparator(3).is_some());
+        assert!(state.comparator(2).is_none());
+        assert!(state.comparator(9).is_none());
+    }
+
+    #[test]
+    fn insert_column_moves_comparators() {
+        let mut state = SortState::default();
+        state.set_comparator(1, Some(Comparator::Natural));
+        state.sorted_by = Some((1, SortOrder::Descending));
+        state.insert_column(1);
+        assert!(state.comparator(1).is_none());
+        assert!(matches!(state.comparator(2), Some(Comparator::Natural)));
+        assert_eq!(state.sorted_by, Some((2, SortOrder::Descending)));
+
+        // Columns after the last comparator, and after the sorted column, change
+        // nothing.
+        state.insert_column(5);
+        state.insert_column(3);
+        assert_eq!(state.comparators.len(), 3);
+        assert_eq!(state.sorted_by, Some((2, SortOrder::Descending)));
+    }
+
+    #[test]
+    fn delete_column_moves_comparators() {
+        let mut state = SortState::default();
+        state.set_comparator(0, Some(Comparator::Text));
+        state.set_comparator(2, Some(Comparator::Natural));
+        state.sorted_by = Some((2, SortOrder::Ascending));
+        state.delete_column(0);
+        assert!(matches!(state.comparator(1), Some(Comparator::Natural)));
+        assert!(state.comparator(2).is_none());
+        assert_eq!(state.sorted_by, Some((1, SortOrder::Ascending)));
+
+        state.delete_column(7);
+        assert_eq!(state.sorted_by, Some((1, SortOrder::Ascending)));
+        state.delete_column(1);
+        assert!(state.comparator(1).is_none());
+        assert_eq!(state.sorted_by, None);
+    }
+}
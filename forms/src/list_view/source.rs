@@ -0,0 +1,93 @@
+use super::{CellStyle, Comparator, SortOrder};
+use core::ops::Range;
+
+/// Provides the rows of a virtual `ListView`, which asks for the cells that it
+/// shows instead of storing a copy of every row.
+///
+/// The list view only knows how many rows there are. After rows are added or
+/// removed, call `ListView::set_len`, and after rows change, call
+/// `ListView::refresh`.
+pub trait ListSource {
+    /// The number of rows.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the text of the cell at `row` and `col` to `text`, which is empty.
+    /// The same buffer is reused for each cell, so formatting a cell with
+    /// `write!(text, ...)` does not allocate.
+    fn cell_text(&self, row: usize, col: usize, text: &mut String);
+
+    /// The image of the row, from the list view's image lists.
+    fn image(&self, _row: usize) -> Option<u32> {
+        None
+    }
+
+    /// The state image of the row, such as 1 for an unchecked and 2 for a checked
+    /// check box. 0 shows no state image.
+    fn state_image(&self, _row: usize) -> Option<u32> {
+        None
+    }
+
+    /// The colors and font of the cell at `row` and `col`, for a source that does
+    /// not need `ListView::cell_style_handler`. `None`, the default, draws the cell
+    /// as usual.
+    fn cell_style(&self, _row: usize, _col: usize) -> Option<CellStyle> {
+        None
+    }
+
+    /// Called before the list view asks for the cells of `rows`, so that a source
+    /// that loads rows from elsewhere can load them all at once.
+    fn cache_hint(&self, _rows: Range<usize>) {}
+
+    /// Reorders the rows by the column at `col`, for `ListView::sort_by_column`.
+    /// `comparator` is the one set for the column, which compares the text of the
+    /// cells; a source can use it with `Comparator::sort_rows`, or compare its own
+    /// data instead. Returns false if the source cannot be sorted, which is the
+    /// default.
+    fn sort(&self, _col: usize, _order: SortOrder, _comparator: &Comparator) -> bool {
+        false
+    }
+
+    /// Finds the row whose first column matches `text`, ignoring case, for
+    /// incremental search with the keyboard. The search begins at `start`, and goes
+    /// around to the first row if `wrap` is true. If `partial` is true, the text
+    /// only needs to match the start of the cell.
+    ///
+    /// The default searches the rows in order, using `cell_text`.
+    fn find_row(&self, text: &str, start: usize, partial: bool, wrap: bool) -> Option<usize> {
+        find_row_by_text(self, text, start, partial, wrap)
+    }
+}
+
+/// Searches the first column of `source`, as described by `ListSource::find_row`.
+pub(crate) fn find_row_by_text<S: ListSource + ?Sized>(
+    source: &S,
+    text: &str,
+    start: usize,
+    partial: bool,
+    wrap: bool,
+) -> Option<usize> {
+    let len = source.len();
+    let start = start.min(len);
+    let wrapped = if wrap { 0..start } else { 0..0 };
+    let mut cell = String::new();
+    (start..len).chain(wrapped).find(|&row| {
+        cell.clear();
+        source.cell_text(row, 0, &mut cell);
+        text_matches(&cell, text, partial)
+    })
+}
+
+/// Compares `cell` with `text`, ignoring case.
+fn text_matches(cell: &str, text: &str, partial: bool) -> bool {
+    let mut cell_chars = cell.chars().flat_map(char::to_lowercase);
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if cell_chars.next() != Some(c) {
+            return false;
+        }
+    }
+    partial || cell_chars.next().is_none()
+}
@@ -0,0 +1,796 @@
+//! A log viewer control, and a `tracing` layer that feeds it.
+//!
+//! `LogView` is a virtual list view (`LVS_OWNERDATA`) over a bounded ring buffer of
+//! `LogRecord`s. `LogLayer` is a `tracing_subscriber::Layer` that captures events on
+//! any thread and sends them to the UI thread through a `Messenger`.
+//!
+//! ```ignore
+//! let log_view = LogView::new(&form);
+//! tracing_subscriber::registry()
+//!     .with(log_view.layer(&messenger))
+//!     .init();
+//! ```
+
+use super::*;
+use core::fmt::{self, Write as _};
+use std::collections::VecDeque;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// One event captured by `LogLayer`.
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    /// The local time at which the event was recorded.
+    pub time: DateTime,
+    pub level: Level,
+    pub target: String,
+    /// The event's message, followed by its other fields as ` name=value`.
+    pub message: String,
+}
+
+impl LogRecord {
+    pub fn new(level: Level, target: &str, message: &str) -> Self {
+        Self {
+            time: DateTime::now(),
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Selects which records a `LogView` shows.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogFilter {
+    /// The most verbose level shown. `Level::INFO` shows `INFO`, `WARN` and `ERROR`.
+    pub max_level: Level,
+    /// Only records whose message or target contains this text are shown. The
+    /// comparison ignores ASCII case. An empty string matches every record.
+    pub search: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            max_level: Level::TRACE,
+            search: String::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        record.level <= self.max_level
+            && (contains_ignore_ascii_case(&record.message, &self.search)
+                || contains_ignore_ascii_case(&record.target, &self.search))
+    }
+}
+
+fn contains_ignore_ascii_case(haystack: &str, needle: &str) -> bool {
+    let needle = needle.as_bytes();
+    needle.is_empty()
+        || haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+/// How a `LogBuffer::push` changed the visible records.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LogPush {
+    /// The new record passed the filter, and was appended to the visible records.
+    pub appended: bool,
+    /// The number of visible records that were evicted from the front.
+    pub evicted: usize,
+}
+
+/// A ring buffer of log records, with a filtered view of them. This is the model
+/// behind `LogView`, and does not need a window.
+///
+/// Every record gets a sequence number. The visible records are kept as a list of
+/// sequence numbers, so evicting the oldest record never requires a rescan.
+pub struct LogBuffer {
+    records: VecDeque<LogRecord>,
+    /// The sequence number of `records[0]`.
+    first_seq: u64,
+    capacity: usize,
+    filter: LogFilter,
+    visible: VecDeque<u64>,
+}
+
+impl LogBuffer {
+    /// Creates a buffer that holds at most `capacity` records (at least 1).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::new(),
+            first_seq: 0,
+            capacity: capacity.max(1),
+            filter: LogFilter::default(),
+            visible: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of records held, including the ones hidden by the filter.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn filter(&self) -> &LogFilter {
+        &self.filter
+    }
+
+    /// Iterates all of the records held, oldest first, ignoring the filter.
+    pub fn iter(&self) -> impl Iterator<Item = &LogRecord> {
+        self.records.iter()
+    }
+
+    /// The number of records that pass the filter.
+    pub fn visible_len(&self) -> usize {
+        self.visible.len()
+    }
+
+    /// Gets the record at `index` among the records that pass the filter.
+    pub fn visible(&self, index: usize) -> Option<&LogRecord> {
+        let seq = *self.visible.get(index)?;
+        self.records.get((seq - self.first_seq) as usize)
+    }
+
+    /// Appends a record, evicting the oldest one if the buffer is full.
+    pub fn push(&mut self, record: LogRecord) -> LogPush {
+        let mut evicted = 0;
+        if self.records.len() == self.capacity {
+            evicted = self.evict_front(1);
+        }
+        let seq = self.first_seq + self.records.len() as u64;
+        let appended = self.filter.matches(&record);
+        if appended {
+            self.visible.push_back(seq);
+        }
+        self.records.push_back(record);
+        LogPush { appended, evicted }
+    }
+
+    /// Changes the capacity, evicting the oldest records if there are too many.
+    /// Returns the number of visible records that were evicted.
+    pub fn set_capacity(&mut self, capacity: usize) -> usize {
+        self.capacity = capacity.max(1);
+        let excess = self.records.len().saturating_sub(self.capacity);
+        self.evict_front(excess)
+    }
+
+    fn evict_front(&mut self, count: usize) -> usize {
+        let end = self.first_seq + count as u64;
+        self.records.drain(..count);
+        self.first_seq = end;
+        let mut evicted = 0;
+        while self.visible.front().is_some_and(|&seq| seq < end) {
+            self.visible.pop_front();
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// Replaces the filter, and recomputes the visible records.
+    pub fn set_filter(&mut self, filter: LogFilter) {
+        self.filter = filter;
+        self.visible.clear();
+        for (i, record) in self.records.iter().enumerate() {
+            if self.filter.matches(record) {
+                self.visible.push_back(self.first_seq + i as u64);
+            }
+        }
+    }
+
+    /// Removes all records. The filter and capacity are kept.
+    pub fn clear(&mut self) {
+        self.first_seq += self.records.len() as u64;
+        self.records.clear();
+        self.visible.clear();
+    }
+}
+
+/// Formats the time of day, as shown in the time column of a `LogView`.
+pub fn format_log_time(time: &DateTime) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        time.hour, time.minute, time.second, time.millisecond
+    )
+}
+
+/// A `tracing_subscriber::Layer` that sends each event to a `LogView`. Create one
+/// with `LogView::layer`.
+///
+/// Events from this crate below `WARN` are dropped. The message loop logs at
+/// `DEBUG` and `TRACE` for every window message, so showing those events would
+/// cause more messages, and more events, without end.
+pub struct LogLayer {
+    sender: Sender<LogRecord>,
+    max_level: Level,
+}
+
+impl LogLayer {
+    /// Sets the most verbose level that is sent to the view. Filtering here, rather
+    /// than in the view, avoids the cost of formatting events that would never be
+    /// shown. The default is `Level::TRACE`.
+    pub fn with_max_level(self, max_level: Level) -> Self {
+        Self { max_level, ..self }
+    }
+
+    fn accepts(&self, level: Level, target: &str) -> bool {
+        let own_target = target == "forms" || target.starts_with("forms::");
+        level <= self.max_level && !(own_target && level > Level::WARN)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !self.accepts(*metadata.level(), metadata.target()) {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.sender.send(LogRecord {
+            time: DateTime::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        });
+    }
+}
+
+/// Collects the `message` field of an event, and the other fields as `name=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        if self.message.is_empty() {
+            self.fields.trim_start().to_string()
+        } else {
+            self.message.push_str(&self.fields);
+            self.message
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            _ = write!(self.message, "{value:?}");
+        } else {
+            _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+impl core::ops::Deref for LogView {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+/// Shows log records in a list view, with columns for the time, level, target and
+/// message.
+///
+/// When auto-scroll is on and the last row is in view, the view scrolls to show each
+/// new record. Scrolling up to read older records stops this until the last row is
+/// back in view. While paused, new records are held back and the rows do not
+/// change.
+pub struct LogView {
+    control: ControlState,
+    buffer: RefCell<LogBuffer>,
+    /// Records received while paused. Bounded by the buffer's capacity.
+    pending: RefCell<VecDeque<LogRecord>>,
+    paused: Cell<bool>,
+    auto_scroll: Cell<bool>,
+}
+
+const WC_LISTVIEW: &str = "SysListView32";
+
+/// The capacity of a new `LogView`'s buffer.
+pub const DEFAULT_LOG_CAPACITY: usize = 10_000;
+
+const COLUMN_TIME: i32 = 0;
+const COLUMN_LEVEL: i32 = 1;
+const COLUMN_TARGET: i32 = 2;
+const COLUMN_MESSAGE: i32 = 3;
+
+impl LogView {
+    pub fn new(parent: &ControlState) -> Rc<LogView> {
+        unsafe {
+            let window_name = WCString::from_str_truncate("");
+            let class_name_wstr = WCString::from_str_truncate(WC_LISTVIEW);
+
+            let hwnd = match CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                PCWSTR::from_raw(class_name_wstr.as_ptr()),
+                PCWSTR::from_raw(window_name.as_ptr()),
+                WS_CHILD
+                    | WS_VISIBLE
+                    | WS_BORDER
+                    | WS_TABSTOP
+                    | WINDOW_STYLE(LVS_REPORT | LVS_OWNERDATA | LVS_SHOWSELALWAYS),
+                0,
+                0,
+                0,
+                0,
+                Some(parent.handle()),
+                None,
+                Some(HINSTANCE(get_instance().0)),
+                None,
+            ) {
+                Ok(h) => h,
+                Err(e) => panic!("failed to create LogView window: {e:?}"),
+            };
+
+            let ex_style = LVS_EX_FULLROWSELECT | LVS_EX_DOUBLEBUFFER;
+            SendMessageW(
+                hwnd,
+                LVM_SETEXTENDEDLISTVIEWSTYLE,
+                Some(WPARAM(ex_style as usize)),
+                Some(LPARAM(ex_style as isize)),
+            );
+
+            let this = Rc::new(LogView {
+                control: ControlState::new(hwnd),
+                buffer: RefCell::new(LogBuffer::new(DEFAULT_LOG_CAPACITY)),
+                pending: RefCell::new(VecDeque::new()),
+                paused: Cell::new(false),
+                auto_scroll: Cell::new(true),
+            });
+            for (index, width, text) in [
+                (COLUMN_TIME, 90, "Time"),
+                (COLUMN_LEVEL, 60, "Level"),
+                (COLUMN_TARGET, 160, "Target"),
+                (COLUMN_MESSAGE, 600, "Message"),
+            ] {
+                this.insert_column(index, width, text);
+            }
+            register_notify_handler(hwnd, Rc::downgrade(&this) as Weak<dyn NotifyHandlerTrait>);
+            this
+        }
+    }
+
+    fn insert_column(&self, index: i32, width: i32, text: &str) {
+        unsafe {
+            let textw = WCString::from_str_truncate(text);
+            let mut col: LVCOLUMNW = zeroed();
+            col.mask = LVCF_TEXT | LVCF_WIDTH;
+            col.cx = width;
+            col.pszText = PWSTR(textw.as_ptr() as *mut u16);
+            SendMessageW(
+                self.handle(),
+                LVM_INSERTCOLUMNW,
+                Some(WPARAM(index as usize)),
+                Some(LPARAM(&col as *const LVCOLUMNW as isize)),
+            );
+        }
+    }
+
+    /// Creates a layer that sends `tracing` events to this view. Events can be
+    /// recorded on any thread; they are added to the view on the UI thread.
+    pub fn layer(self: &Rc<Self>, messenger: &Messenger) -> LogLayer {
+        let weak = Rc::downgrade(self);
+        let sender = messenger.register_receiver_func("LogView", move |record: LogRecord| {
+            if let Some(this) = weak.upgrade() {
+                this.push(record);
+            }
+        });
+        LogLayer {
+            sender,
+            max_level: Level::TRACE,
+        }
+    }
+
+    /// Adds a record. This must be called on the UI thread; use `layer` to log from
+    /// other threads.
+    pub fn push(&self, record: LogRecord) {
+        if self.paused.get() {
+            let mut pending = self.pending.borrow_mut();
+            if pending.len() == self.buffer.borrow().capacity() {
+                pending.pop_front();
+            }
+            pending.push_back(record);
+            return;
+        }
+        let follow = self.auto_scroll.get() && self.is_last_row_visible();
+        let result = self.buffer.borrow_mut().push(record);
+        if result.appended || result.evicted != 0 {
+            self.update_rows(result.evicted == 0);
+            if follow {
+                self.scroll_to_end();
+            }
+        }
+    }
+
+    /// Removes all records.
+    pub fn clear(&self) {
+        self.buffer.borrow_mut().clear();
+        self.pending.borrow_mut().clear();
+        self.update_rows(false);
+    }
+
+    /// Sets the maximum number of records kept. The oldest records are discarded
+    /// first.
+    pub fn set_capacity(&self, capacity: usize) {
+        let evicted = self.buffer.borrow_mut().set_capacity(capacity);
+        if evicted != 0 {
+            self.update_rows(false);
+        }
+    }
+
+    /// Shows only the records at `max_level` or more severe.
+    pub fn set_max_level(&self, max_level: Level) {
+        let mut filter = self.buffer.borrow().filter().clone();
+        filter.max_level = max_level;
+        self.set_filter(filter);
+    }
+
+    /// Shows only the records whose message or target contains `text`, ignoring
+    /// ASCII case. An empty string shows all records.
+    pub fn set_search(&self, text: &str) {
+        let mut filter = self.buffer.borrow().filter().clone();
+        filter.search = text.to_string();
+        self.set_filter(filter);
+    }
+
+    pub fn set_filter(&self, filter: LogFilter) {
+        self.buffer.borrow_mut().set_filter(filter);
+        self.update_rows(false);
+        if self.auto_scroll.get() {
+            self.scroll_to_end();
+        }
+    }
+
+    pub fn filter(&self) -> LogFilter {
+        self.buffer.borrow().filter().clone()
+    }
+
+    /// Turns auto-scroll on or off. Turning it on scrolls to the last row.
+    pub fn set_auto_scroll(&self, value: bool) {
+        self.auto_scroll.set(value);
+        if value {
+            self.scroll_to_end();
+        }
+    }
+
+    pub fn auto_scroll(&self) -> bool {
+        self.auto_scroll.get()
+    }
+
+    /// Pauses or resumes the view. Records received while paused are added when
+    /// the view is resumed.
+    pub fn set_paused(&self, value: bool) {
+        if self.paused.replace(value) && !value {
+            let pending = core::mem::take(&mut *self.pending.borrow_mut());
+            let follow = self.auto_scroll.get() && self.is_last_row_visible();
+            {
+                let mut buffer = self.buffer.borrow_mut();
+                for record in pending {
+                    buffer.push(record);
+                }
+            }
+            self.update_rows(false);
+            if follow {
+                self.scroll_to_end();
+            }
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    /// The number of records held, including the ones hidden by the filter.
+    pub fn len(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.borrow().is_empty()
+    }
+
+    /// Gets the record shown in `row`.
+    pub fn record(&self, row: usize) -> Option<LogRecord> {
+        self.buffer.borrow().visible(row).cloned()
+    }
+
+    /// Sets the number of rows to match the buffer. If `appended_only` is true, the
+    /// existing rows did not change, so only the new rows are repainted.
+    fn update_rows(&self, appended_only: bool) {
+        let len = self.buffer.borrow().visible_len();
+        let mut flags = LVSICF_NOSCROLL;
+        if appended_only {
+            flags |= LVSICF_NOINVALIDATEALL;
+        }
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                LVM_SETITEMCOUNT,
+                Some(WPARAM(len)),
+                Some(LPARAM(flags as isize)),
+            );
+            if !appended_only {
+                _ = InvalidateRect(Some(self.handle()), None, false);
+            }
+        }
+    }
+
+    fn is_last_row_visible(&self) -> bool {
+        unsafe {
+            let len = SendMessageW(self.handle(), LVM_GETITEMCOUNT, None, None).0 as usize;
+            let top = SendMessageW(self.handle(), LVM_GETTOPINDEX, None, None).0 as usize;
+            let per_page = SendMessageW(self.handle(), LVM_GETCOUNTPERPAGE, None, None).0 as usize;
+            top + per_page >= len
+        }
+    }
+
+    fn scroll_to_end(&self) {
+        let len = self.buffer.borrow().visible_len();
+        if len != 0 {
+            unsafe {
+                SendMessageW(
+                    self.handle(),
+                    LVM_ENSUREVISIBLE,
+                    Some(WPARAM(len - 1)),
+                    Some(LPARAM(0)),
+                );
+            }
+        }
+    }
+}
+
+impl NotifyHandlerTrait for LogView {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-getdispinfo
+            LVN_GETDISPINFOW => {
+                let info = &mut *(nmhdr as *mut NMLVDISPINFOW);
+                let item = &mut info.item;
+                if item.mask.contains(LVIF_TEXT) {
+                    let buffer = self.buffer.borrow();
+                    if let Some(record) = buffer.visible(item.iItem as usize) {
+                        match item.iSubItem {
+                            COLUMN_TIME => copy_to_text_buffer(
+                                &format_log_time(&record.time),
+                                item.pszText,
+                                item.cchTextMax,
+                            ),
+                            COLUMN_LEVEL => copy_to_text_buffer(
+                                record.level.as_str(),
+                                item.pszText,
+                                item.cchTextMax,
+                            ),
+                            COLUMN_TARGET => {
+                                copy_to_text_buffer(&record.target, item.pszText, item.cchTextMax)
+                            }
+                            COLUMN_MESSAGE => {
+                                copy_to_text_buffer(&record.message, item.pszText, item.cchTextMax)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: Level, target: &str, message: &str) -> LogRecord {
+        LogRecord {
+            time: DateTime::date(2024, 1, 1),
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn info(message: &str) -> LogRecord {
+        record(Level::INFO, "app", message)
+    }
+
+    fn visible(buffer: &LogBuffer) -> Vec<&str> {
+        (0..buffer.visible_len())
+            .map(|i| buffer.visible(i).unwrap().message.as_str())
+            .collect()
+    }
+
+    fn all(buffer: &LogBuffer) -> Vec<&str> {
+        buffer.iter().map(|r| r.message.as_str()).collect()
+    }
+
+    fn warn_only() -> LogFilter {
+        LogFilter {
+            max_level: Level::WARN,
+            search: String::new(),
+        }
+    }
+
+    #[test]
+    fn push_until_full() {
+        let mut buffer = LogBuffer::new(3);
+        for message in ["a", "b", "c"] {
+            let push = buffer.push(info(message));
+            assert_eq!(
+                push,
+                LogPush {
+                    appended: true,
+                    evicted: 0
+                }
+            );
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(visible(&buffer), ["a", "b", "c"]);
+        assert!(buffer.visible(3).is_none());
+    }
+
+    #[test]
+    fn push_evicts_the_oldest_at_capacity() {
+        let mut buffer = LogBuffer::new(3);
+        for message in ["a", "b", "c"] {
+            buffer.push(info(message));
+        }
+        assert_eq!(
+            buffer.push(info("d")),
+            LogPush {
+                appended: true,
+                evicted: 1
+            }
+        );
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(all(&buffer), ["b", "c", "d"]);
+        assert_eq!(visible(&buffer), ["b", "c", "d"]);
+        buffer.push(info("e"));
+        assert_eq!(visible(&buffer), ["c", "d", "e"]);
+    }
+
+    #[test]
+    fn evicting_a_hidden_record_does_not_shift_visible_rows() {
+        let mut buffer = LogBuffer::new(2);
+        buffer.set_filter(warn_only());
+        assert!(buffer.push(record(Level::WARN, "app", "w1")).appended);
+        assert!(!buffer.push(info("i1")).appended);
+        assert_eq!(
+            buffer.push(record(Level::ERROR, "app", "e1")),
+            LogPush {
+                appended: true,
+                evicted: 1
+            }
+        );
+        assert_eq!(visible(&buffer), ["e1"]);
+        // The hidden record is evicted next.
+        assert_eq!(buffer.push(record(Level::WARN, "app", "w2")).evicted, 0);
+        assert_eq!(visible(&buffer), ["e1", "w2"]);
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn filter_by_level() {
+        let mut buffer = LogBuffer::new(10);
+        buffer.push(record(Level::ERROR, "app", "error"));
+        buffer.push(record(Level::WARN, "app", "warn"));
+        buffer.push(record(Level::INFO, "app", "info"));
+        buffer.push(record(Level::DEBUG, "app", "debug"));
+        buffer.push(record(Level::TRACE, "app", "trace"));
+        assert_eq!(buffer.visible_len(), 5);
+
+        buffer.set_filter(warn_only());
+        assert_eq!(visible(&buffer), ["error", "warn"]);
+        buffer.set_filter(LogFilter {
+            max_level: Level::DEBUG,
+            search: String::new(),
+        });
+        assert_eq!(visible(&buffer), ["error", "warn", "info", "debug"]);
+        // The records hidden by a filter are still held.
+        assert_eq!(buffer.len(), 5);
+        buffer.set_filter(LogFilter::default());
+        assert_eq!(buffer.visible_len(), 5);
+    }
+
+    #[test]
+    fn filter_by_search_text() {
+        let mut buffer = LogBuffer::new(10);
+        buffer.push(record(Level::INFO, "net::http", "GET /index.html"));
+        buffer.push(record(Level::INFO, "db", "query took 5 ms"));
+        buffer.push(record(Level::WARN, "net::dns", "lookup failed"));
+
+        let search = |text: &str| LogFilter {
+            search: text.to_string(),
+            ..LogFilter::default()
+        };
+        buffer.set_filter(search("NET"));
+        assert_eq!(visible(&buffer), ["GET /index.html", "lookup failed"]);
+        buffer.set_filter(search("Query"));
+        assert_eq!(visible(&buffer), ["query took 5 ms"]);
+        buffer.set_filter(search("nothing"));
+        assert_eq!(buffer.visible_len(), 0);
+        assert_eq!(buffer.filter(), &search("nothing"));
+
+        // New records are checked against the filter as they are pushed.
+        assert!(!buffer.push(info("still empty here")).appended);
+        assert!(buffer.push(info("NOTHING")).appended);
+        assert_eq!(visible(&buffer), ["NOTHING"]);
+    }
+
+    #[test]
+    fn set_capacity_shrinks_below_the_length() {
+        let mut buffer = LogBuffer::new(5);
+        buffer.set_filter(warn_only());
+        buffer.push(record(Level::WARN, "app", "w1"));
+        buffer.push(info("i1"));
+        buffer.push(record(Level::WARN, "app", "w2"));
+        buffer.push(info("i2"));
+        buffer.push(record(Level::WARN, "app", "w3"));
+
+        // w1, i1 and w2 are evicted, two of which were visible.
+        assert_eq!(buffer.set_capacity(2), 2);
+        assert_eq!(buffer.capacity(), 2);
+        assert_eq!(all(&buffer), ["i2", "w3"]);
+        assert_eq!(visible(&buffer), ["w3"]);
+
+        assert_eq!(buffer.set_capacity(10), 0);
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.set_capacity(0), 0);
+        assert_eq!(buffer.capacity(), 1);
+        assert_eq!(all(&buffer), ["w3"]);
+        assert_eq!(LogBuffer::new(0).capacity(), 1);
+    }
+
+    #[test]
+    fn clear_keeps_the_filter_and_capacity() {
+        let mut buffer = LogBuffer::new(3);
+        buffer.set_filter(warn_only());
+        buffer.push(record(Level::WARN, "app", "w1"));
+        buffer.push(info("i1"));
+        buffer.clear();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.visible_len(), 0);
+        assert!(buffer.visible(0).is_none());
+        assert_eq!(buffer.capacity(), 3);
+        assert_eq!(buffer.filter(), &warn_only());
+
+        buffer.push(record(Level::ERROR, "app", "e1"));
+        buffer.push(info("i2"));
+        assert_eq!(visible(&buffer), ["e1"]);
+        assert_eq!(all(&buffer), ["e1", "i2"]);
+    }
+
+    #[test]
+    fn search_ignores_ascii_case() {
+        assert!(contains_ignore_ascii_case("Hello World", "WORLD"));
+        assert!(contains_ignore_ascii_case("anything", ""));
+        assert!(contains_ignore_ascii_case("", ""));
+        assert!(!contains_ignore_ascii_case("", "a"));
+        assert!(!contains_ignore_ascii_case("abc", "abcd"));
+    }
+}
@@ -39,9 +39,15 @@ impl Menu {
     }
 
     pub fn track_popup_menu(&self, form: &Form, x: i32, y: i32) {
+        self.track_popup_menu_owned_by(form.handle.get(), x, y);
+    }
+
+    /// Shows the popup menu at screen coordinates `x`, `y`. `WM_COMMAND` for the
+    /// chosen item is sent to `owner`.
+    pub(crate) fn track_popup_menu_owned_by(&self, owner: HWND, x: i32, y: i32) {
         unsafe {
             let flags = TRACK_POPUP_MENU_FLAGS(0);
-            if TrackPopupMenu(self.hmenu, flags, x, y, None, form.handle.get(), None).into() {
+            if TrackPopupMenu(self.hmenu, flags, x, y, None, owner, None).into() {
                 debug!("TrackPopupMenu succeeded");
             } else {
                 // debug!("TrackPopupMenu failed");
@@ -0,0 +1,284 @@
+//! Message boxes: short modal messages with a fixed set of buttons.
+//!
+//! ```ignore
+//! let result = MessageBox::builder()
+//!     .title("Save Changes")
+//!     .text("Save changes to the document?")
+//!     .icon(MessageBoxIcon::Question)
+//!     .buttons(MessageBoxButtons::YesNoCancel)
+//!     .default_button(MessageBoxResult::Yes)
+//!     .parent(&form)
+//!     .show();
+//! ```
+
+use super::*;
+
+/// The icon shown in a message box. This also selects the system sound.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MessageBoxIcon {
+    #[default]
+    None,
+    Information,
+    Question,
+    Warning,
+    Error,
+}
+
+/// The buttons of a message box.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MessageBoxButtons {
+    #[default]
+    Ok,
+    OkCancel,
+    YesNo,
+    YesNoCancel,
+    RetryCancel,
+    AbortRetryIgnore,
+    CancelTryContinue,
+}
+
+/// The button that closed a message box.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MessageBoxResult {
+    Ok,
+    Cancel,
+    Yes,
+    No,
+    Retry,
+    Abort,
+    Ignore,
+    TryAgain,
+    Continue,
+}
+
+impl MessageBoxButtons {
+    /// The buttons, from left to right.
+    pub fn results(self) -> &'static [MessageBoxResult] {
+        use MessageBoxResult as R;
+        match self {
+            Self::Ok => &[R::Ok],
+            Self::OkCancel => &[R::Ok, R::Cancel],
+            Self::YesNo => &[R::Yes, R::No],
+            Self::YesNoCancel => &[R::Yes, R::No, R::Cancel],
+            Self::RetryCancel => &[R::Retry, R::Cancel],
+            Self::AbortRetryIgnore => &[R::Abort, R::Retry, R::Ignore],
+            Self::CancelTryContinue => &[R::Cancel, R::TryAgain, R::Continue],
+        }
+    }
+
+    /// The position of `button` in this set, if it is part of it.
+    pub fn position(self, button: MessageBoxResult) -> Option<usize> {
+        self.results().iter().position(|&b| b == button)
+    }
+
+    fn style(self) -> MESSAGEBOX_STYLE {
+        match self {
+            Self::Ok => MB_OK,
+            Self::OkCancel => MB_OKCANCEL,
+            Self::YesNo => MB_YESNO,
+            Self::YesNoCancel => MB_YESNOCANCEL,
+            Self::RetryCancel => MB_RETRYCANCEL,
+            Self::AbortRetryIgnore => MB_ABORTRETRYIGNORE,
+            Self::CancelTryContinue => MB_CANCELTRYCONTINUE,
+        }
+    }
+}
+
+impl MessageBoxIcon {
+    fn style(self) -> MESSAGEBOX_STYLE {
+        match self {
+            Self::None => MESSAGEBOX_STYLE(0),
+            Self::Information => MB_ICONINFORMATION,
+            Self::Question => MB_ICONQUESTION,
+            Self::Warning => MB_ICONWARNING,
+            Self::Error => MB_ICONERROR,
+        }
+    }
+}
+
+impl MessageBoxResult {
+    fn from_id(id: MESSAGEBOX_RESULT) -> Option<Self> {
+        Some(match id {
+            IDOK => Self::Ok,
+            IDCANCEL => Self::Cancel,
+            IDYES => Self::Yes,
+            IDNO => Self::No,
+            IDRETRY => Self::Retry,
+            IDABORT => Self::Abort,
+            IDIGNORE => Self::Ignore,
+            IDTRYAGAIN => Self::TryAgain,
+            IDCONTINUE => Self::Continue,
+            _ => return None,
+        })
+    }
+}
+
+/// Shows message boxes. Use `MessageBox::builder()`, or one of the helpers.
+pub struct MessageBox;
+
+pub struct MessageBoxBuilder<'a> {
+    title: String,
+    text: String,
+    icon: MessageBoxIcon,
+    buttons: MessageBoxButtons,
+    default_button: Option<MessageBoxResult>,
+    parent: Option<&'a Form>,
+}
+
+impl<'a> MessageBoxBuilder<'a> {
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_string();
+        self
+    }
+
+    pub fn icon(mut self, icon: MessageBoxIcon) -> Self {
+        self.icon = icon;
+        self
+    }
+
+    pub fn buttons(mut self, buttons: MessageBoxButtons) -> Self {
+        self.buttons = buttons;
+        self
+    }
+
+    /// Sets the button that has the focus, and is chosen by Enter. This is ignored
+    /// if the button is not one of `buttons`. By default it is the first button.
+    pub fn default_button(mut self, button: MessageBoxResult) -> Self {
+        self.default_button = Some(button);
+        self
+    }
+
+    /// Sets the window that owns the message box. It is disabled while the message
+    /// box is shown.
+    pub fn parent(mut self, parent: &'a Form) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Shows the message box, and waits until the user closes it.
+    ///
+    /// If the message box is closed without a button, such as with Escape or the
+    /// close box, the result is `Cancel`. (Only sets that have a Cancel button can be
+    /// closed that way; for `Ok`, the result is then `Ok`.)
+    pub fn show(self) -> MessageBoxResult {
+        let default_button = match self
+            .default_button
+            .and_then(|button| self.buttons.position(button))
+        {
+            Some(1) => MB_DEFBUTTON2,
+            Some(2) => MB_DEFBUTTON3,
+            _ => MB_DEFBUTTON1,
+        };
+        let mut style = self.buttons.style() | self.icon.style() | default_button;
+        if self.parent.is_none() {
+            style |= MB_TASKMODAL;
+        }
+
+        let text = WCString::from_str_truncate(&self.text);
+        let title = WCString::from_str_truncate(&self.title);
+        let id = unsafe {
+            MessageBoxW(
+                self.parent.map(|form| form.handle()),
+                PCWSTR::from_raw(text.as_ptr()),
+                PCWSTR::from_raw(title.as_ptr()),
+                style,
+            )
+        };
+        match MessageBoxResult::from_id(id) {
+            Some(result) => result,
+            None => {
+                // 0 means the message box could not be created.
+                warn!("MessageBoxW failed: {:?}", unsafe { GetLastError() });
+                MessageBoxResult::Cancel
+            }
+        }
+    }
+}
+
+impl MessageBox {
+    pub fn builder<'a>() -> MessageBoxBuilder<'a> {
+        MessageBoxBuilder {
+            title: String::new(),
+            text: String::new(),
+            icon: MessageBoxIcon::None,
+            buttons: MessageBoxButtons::Ok,
+            default_button: None,
+            parent: None,
+        }
+    }
+
+    /// Shows a message with an information icon and an OK button.
+    pub fn info(parent: Option<&Form>, title: &str, text: &str) {
+        Self::with_parent(parent)
+            .title(title)
+            .text(text)
+            .icon(MessageBoxIcon::Information)
+            .show();
+    }
+
+    /// Shows an error, with an error icon and an OK button. The text is the error's
+    /// message, followed by the messages of its sources.
+    pub fn error(parent: Option<&Form>, title: &str, error: &dyn std::error::Error) {
+        Self::with_parent(parent)
+            .title(title)
+            .text(&Self::error_text(error))
+            .icon(MessageBoxIcon::Error)
+            .show();
+    }
+
+    /// Asks a question, with Yes and No buttons. Returns true if the user chose Yes.
+    pub fn confirm(parent: Option<&Form>, title: &str, question: &str) -> bool {
+        Self::with_parent(parent)
+            .title(title)
+            .text(question)
+            .icon(MessageBoxIcon::Question)
+            .buttons(MessageBoxButtons::YesNo)
+            .show()
+            == MessageBoxResult::Yes
+    }
+
+    /// Shows an error that stopped an operation, and asks whether to try again, with
+    /// Retry and Cancel buttons. Returns true if the user chose Retry.
+    pub fn confirm_retry(
+        parent: Option<&Form>,
+        title: &str,
+        error: &dyn std::error::Error,
+    ) -> bool {
+        Self::with_parent(parent)
+            .title(title)
+            .text(&Self::error_text(error))
+            .icon(MessageBoxIcon::Error)
+            .buttons(MessageBoxButtons::RetryCancel)
+            .show()
+            == MessageBoxResult::Retry
+    }
+
+    /// Formats an error and its sources, one per line, as shown by `MessageBox::error`.
+    pub fn error_text(error: &dyn std::error::Error) -> String {
+        let mut text = error.to_string();
+        let mut source = error.source();
+        while let Some(e) = source {
+            let message = e.to_string();
+            // Some errors include the message of their source in their own message.
+            if !text.ends_with(&message) {
+                text.push_str("\n\n");
+                text.push_str(&message);
+            }
+            source = e.source();
+        }
+        text
+    }
+
+    fn with_parent(parent: Option<&Form>) -> MessageBoxBuilder<'_> {
+        let builder = Self::builder();
+        match parent {
+            Some(parent) => builder.parent(parent),
+            None => builder,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/month-calendar-controls
+
+use super::*;
+use std::cell::OnceCell;
+
+pub struct MonthCalendar {
+    control: ControlState,
+    multi_select: bool,
+    value_changed_handler: OnceCell<Box<dyn Fn(DateTime, DateTime)>>,
+}
+
+impl core::ops::Deref for MonthCalendar {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MonthCalendarOptions {
+    /// Allows the user to select a range of contiguous days.
+    pub multi_select: bool,
+    pub week_numbers: bool,
+    /// Hides the "Today" date at the bottom of the control.
+    pub no_today: bool,
+}
+
+impl MonthCalendar {
+    pub fn new(parent: &ControlState) -> Rc<Self> {
+        Self::new_with_options(parent, &Default::default())
+    }
+
+    pub fn new_with_options(parent: &ControlState, options: &MonthCalendarOptions) -> Rc<Self> {
+        unsafe {
+            let mut style = WS_CHILD | WS_VISIBLE | WS_TABSTOP;
+            if options.multi_select {
+                style.0 |= MCS_MULTISELECT;
+            }
+            if options.week_numbers {
+                style.0 |= MCS_WEEKNUMBERS;
+            }
+            if options.no_today {
+                style.0 |= MCS_NOTODAY;
+            }
+
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                MONTHCAL_CLASSW,
+                PCWSTR::null(),
+                style,
+                0,
+                0,
+                0,
+                0,
+                Some(parent.handle()),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            let this = Rc::new(MonthCalendar {
+                control: ControlState::new(hwnd),
+                multi_select: options.multi_select,
+                value_changed_handler: OnceCell::new(),
+            });
+
+            register_notify_handler(hwnd, Rc::downgrade(&this) as Weak<dyn NotifyHandlerTrait>);
+
+            this
+        }
+    }
+
+    /// Gets the selected date. For a multi-select calendar, this is the first day
+    /// of the selected range.
+    pub fn get_value(&self) -> DateTime {
+        self.get_selection_range().0
+    }
+
+    /// Selects a single date.
+    pub fn set_value(&self, value: DateTime) {
+        self.set_selection_range(value, value);
+    }
+
+    /// Gets the first and last selected days. Unless the calendar is multi-select,
+    /// they are the same day.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/mcm-getselrange>
+    pub fn get_selection_range(&self) -> (DateTime, DateTime) {
+        unsafe {
+            let mut range: [SYSTEMTIME; 2] = Default::default();
+            if self.multi_select {
+                SendMessageW(
+                    self.handle(),
+                    MCM_GETSELRANGE,
+                    None,
+                    Some(LPARAM(range.as_mut_ptr() as isize)),
+                );
+            } else {
+                SendMessageW(
+                    self.handle(),
+                    MCM_GETCURSEL,
+                    None,
+                    Some(LPARAM(range.as_mut_ptr() as isize)),
+                );
+                range[1] = range[0];
+            }
+            // The time fields are undefined.
+            (
+                DateTime::from(range[0]).without_time(),
+                DateTime::from(range[1]).without_time(),
+            )
+        }
+    }
+
+    /// Selects a range of days. Unless the calendar is multi-select, only `first`
+    /// is used.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/mcm-setselrange>
+    pub fn set_selection_range(&self, first: DateTime, last: DateTime) {
+        unsafe {
+            let range: [SYSTEMTIME; 2] = [first.into(), last.into()];
+            let result = if self.multi_select {
+                SendMessageW(
+                    self.handle(),
+                    MCM_SETSELRANGE,
+                    None,
+                    Some(LPARAM(range.as_ptr() as isize)),
+                )
+            } else {
+                SendMessageW(
+                    self.handle(),
+                    MCM_SETCURSEL,
+                    None,
+                    Some(LPARAM(range.as_ptr() as isize)),
+                )
+            };
+            if result.0 == 0 {
+                warn!("failed to set month calendar selection: {first} - {last}");
+            }
+        }
+    }
+
+    /// Sets the largest number of days that can be selected in a multi-select
+    /// calendar. The default is 7.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/mcm-setmaxselcount>
+    pub fn set_max_selection_days(&self, days: u32) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                MCM_SETMAXSELCOUNT,
+                Some(WPARAM(days as usize)),
+                None,
+            );
+        }
+    }
+
+    /// Limits the dates that the user can choose. `None` leaves that end of the
+    /// range unbounded.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/mcm-setrange>
+    pub fn set_range(&self, min: Option<DateTime>, max: Option<DateTime>) {
+        unsafe {
+            let (flags, range) = systemtime_range(min, max);
+            SendMessageW(
+                self.handle(),
+                MCM_SETRANGE,
+                Some(WPARAM(flags as usize)),
+                Some(LPARAM(range.as_ptr() as isize)),
+            );
+        }
+    }
+
+    /// Overrides the date that the control shows as "Today".
+    pub fn set_today(&self, today: DateTime) {
+        unsafe {
+            let st: SYSTEMTIME = today.into();
+            SendMessageW(
+                self.handle(),
+                MCM_SETTODAY,
+                None,
+                Some(LPARAM(&st as *const SYSTEMTIME as isize)),
+            );
+        }
+    }
+
+    /// The smallest size (width, height) that shows one full month.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/mcm-getminreqrect>
+    pub fn get_min_size(&self) -> (i32, i32) {
+        unsafe {
+            let mut rect: RECT = zeroed();
+            SendMessageW(
+                self.handle(),
+                MCM_GETMINREQRECT,
+                None,
+                Some(LPARAM(&mut rect as *mut RECT as isize)),
+            );
+            (rect.right - rect.left, rect.bottom - rect.top)
+        }
+    }
+
+    /// Called when the selection changes, with the first and last selected days.
+    pub fn value_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(DateTime, DateTime) + 'static,
+    {
+        let result = self.value_changed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call value_changed_handler() more than once"
+        );
+    }
+}
+
+impl NotifyHandlerTrait for MonthCalendar {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/mcn-selchange
+            MCN_SELCHANGE => {
+                let change = &*(nmhdr as *const NMSELCHANGE);
+                let first = DateTime::from(change.stSelStart).without_time();
+                let last = DateTime::from(change.stSelEnd).without_time();
+                trace!("MCN_SELCHANGE: {first} - {last}");
+                if let Some(handler) = self.value_changed_handler.get() {
+                    handler(first, last);
+                }
+            }
+            _ => {}
+        }
+        NotifyResult::NotConsumed
+    }
+}
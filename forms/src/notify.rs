@@ -24,3 +24,49 @@ impl<'a> Notify<'a> {
         }
     }
 }
+
+/// Implemented by controls that handle their own `WM_NOTIFY` notifications, such as
+/// value-changed events. The parent window routes each notification to the control
+/// that sent it, before the app's `notify_handler` sees it.
+pub(crate) trait NotifyHandlerTrait {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult;
+}
+
+pub(crate) enum NotifyResult {
+    /// The notification should continue on to the form's `notify_handler`.
+    NotConsumed,
+    /// The control handled the notification. The value is returned from `WM_NOTIFY`.
+    Consumed(LRESULT),
+}
+
+thread_local! {
+    // key is HWND of the control that sends the notifications
+    static NOTIFY_HANDLERS: RefCell<HashMap<isize, Weak<dyn NotifyHandlerTrait>>> =
+        RefCell::new(HashMap::new());
+}
+
+pub(crate) fn register_notify_handler(hwnd: HWND, handler: Weak<dyn NotifyHandlerTrait>) {
+    NOTIFY_HANDLERS.with_borrow_mut(|handlers| {
+        handlers.retain(|_, h| h.strong_count() != 0);
+        handlers.insert(hwnd.0 as isize, handler);
+    });
+}
+
+/// Routes a `WM_NOTIFY` notification to the control that sent it, if that control
+/// registered a handler.
+pub(crate) unsafe fn dispatch_notify(nmhdr: *mut NMHDR) -> NotifyResult {
+    let hwnd_from = (*nmhdr).hwndFrom;
+    let handler = NOTIFY_HANDLERS.with_borrow(|handlers| {
+        handlers
+            .get(&(hwnd_from.0 as isize))
+            .and_then(|h| h.upgrade())
+    });
+
+    // The registry is not borrowed while the handler runs, so the handler can
+    // create (and register) new controls.
+    if let Some(handler) = handler {
+        handler.wm_notify(nmhdr)
+    } else {
+        NotifyResult::NotConsumed
+    }
+}
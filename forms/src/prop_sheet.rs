@@ -1,29 +1,1059 @@
+//! Property sheets and wizards: dialogs made of several pages.
+//!
+//! A property sheet shows its pages as tabs, with OK, Cancel and Apply buttons. A
+//! wizard shows one page at a time, with Back, Next, Finish and Cancel buttons.
+//! Each page owns a `Layout` of controls, created by a closure when the sheet is
+//! built. The order in which pages are visited is tracked by `PageSequence`, which
+//! does not need a window.
+//!
+//! ```ignore
+//! let sheet = PropertySheet::builder()
+//!     .title("Options")
+//!     .page(PropertyPage::builder("General", |page| {
+//!         let name = Edit::new(page.form());
+//!         let check_name = name.clone();
+//!         page.validate_handler(move || {
+//!             if check_name.get_text().is_empty() {
+//!                 Err("Enter a name.".to_string())
+//!             } else {
+//!                 Ok(())
+//!             }
+//!         });
+//!         Layout::Stack(StackLayout::vertical(24).control(name))
+//!     }))
+//!     .build();
+//! let result = sheet.show_modal_under(Some(&form));
+//! ```
+
 use super::*;
+use std::cell::OnceCell;
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
 
-pub struct PropertySheet {
+/// How the pages of a sheet are presented.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SheetKind {
+    /// Pages are tabs, and can be visited in any order. The buttons are OK, Cancel
+    /// and Apply.
+    PropertySheet,
+    /// Pages are visited in order. The buttons are Back, Next, Finish and Cancel.
+    Wizard,
 }
 
-pub struct PropertyPage {
+/// How the user closed a sheet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SheetResult {
+    /// OK was clicked in a property sheet. The pages were applied.
+    Ok,
+    /// Finish was clicked in a wizard. The pages were applied.
+    Finish,
+    /// Cancel was clicked, or the sheet was closed. Changes applied earlier with the
+    /// Apply button are not undone.
+    Cancel,
 }
 
-pub struct PropertySheetBuilder {
-    pages: Vec<PropertyPageBuilder>,
+/// A request to change the current page.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PageMove {
+    /// Return to the page that was shown before the current one.
+    Back,
+    /// Go to the next page that is not skipped.
+    Next,
+    /// Go to a specific page, such as when a tab is clicked.
+    Select(usize),
+}
+
+/// Which of the wizard buttons are enabled.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WizardButtons {
+    pub back: bool,
+    pub next: bool,
+    pub finish: bool,
+}
+
+/// Tracks the current page of a sheet, the pages visited to get there, and the
+/// state of the buttons. This is the model behind `PropertySheet`, and does not
+/// need a window.
+///
+/// The Back button returns along the path the user took, so a wizard page that is
+/// skipped after it was visited is still returned to. Button states set with
+/// `set_back_enabled` (and similar) apply only to the current page; they are
+/// cleared when the page changes.
+pub struct PageSequence {
+    kind: SheetKind,
+    len: usize,
+    current: usize,
+    history: Vec<usize>,
+    skipped: Vec<bool>,
+    modified: bool,
+    back_enabled: Option<bool>,
+    next_enabled: Option<bool>,
+    finish_enabled: Option<bool>,
+}
+
+impl PageSequence {
+    /// Creates a sequence of `len` pages, starting at the first page.
+    pub fn new(kind: SheetKind, len: usize) -> Self {
+        Self {
+            kind,
+            len,
+            current: 0,
+            history: Vec::new(),
+            skipped: vec![false; len],
+            modified: false,
+            back_enabled: None,
+            next_enabled: None,
+            finish_enabled: None,
+        }
+    }
+
+    pub fn kind(&self) -> SheetKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Skips a page when moving forward through a wizard. This is how a wizard
+    /// branches: an earlier page can skip the pages that do not apply. The current
+    /// page cannot be skipped.
+    pub fn set_skipped(&mut self, page: usize, skipped: bool) {
+        if page == self.current && skipped {
+            return;
+        }
+        if let Some(value) = self.skipped.get_mut(page) {
+            *value = skipped;
+        }
+    }
+
+    pub fn is_skipped(&self, page: usize) -> bool {
+        self.skipped.get(page).copied().unwrap_or(false)
+    }
+
+    /// The page that Next would go to.
+    pub fn next_page(&self) -> Option<usize> {
+        (self.current + 1..self.len).find(|&page| !self.skipped[page])
+    }
+
+    /// The page that Back would go to.
+    pub fn previous_page(&self) -> Option<usize> {
+        self.history.last().copied()
+    }
+
+    /// Finds the page that `page_move` would go to. Returns `None` if the move is not
+    /// possible, or would not change the page.
+    pub fn target(&self, page_move: PageMove) -> Option<usize> {
+        let target = match page_move {
+            PageMove::Back => self.previous_page()?,
+            PageMove::Next => self.next_page()?,
+            PageMove::Select(page) => {
+                if page >= self.len || self.is_skipped(page) {
+                    return None;
+                }
+                page
+            }
+        };
+        (target != self.current).then_some(target)
+    }
+
+    /// Whether the current page must be validated before `page_move`. Going back
+    /// in a wizard does not validate, so that the user can leave a page that they
+    /// cannot complete.
+    pub fn needs_validation(&self, page_move: PageMove) -> bool {
+        !(self.kind == SheetKind::Wizard && page_move == PageMove::Back)
+    }
+
+    /// Makes the move, if it is possible. Returns the new current page.
+    pub fn apply_move(&mut self, page_move: PageMove) -> Option<usize> {
+        let target = self.target(page_move)?;
+        if page_move == PageMove::Back {
+            self.history.pop();
+        } else {
+            self.history.push(self.current);
+        }
+        self.current = target;
+        self.back_enabled = None;
+        self.next_enabled = None;
+        self.finish_enabled = None;
+        Some(target)
+    }
+
+    /// The pages that apply: the ones that are not skipped.
+    pub fn active_pages(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|&page| !self.skipped[page])
+    }
+
+    /// The state of the wizard buttons. By default, Back is enabled if there is a
+    /// page to go back to, Next if there is a next page, and Finish on the last page.
+    pub fn buttons(&self) -> WizardButtons {
+        let next = self.next_page().is_some();
+        WizardButtons {
+            back: self.back_enabled.unwrap_or(self.previous_page().is_some()),
+            next: next && self.next_enabled.unwrap_or(true),
+            finish: self.finish_enabled.unwrap_or(!next),
+        }
+    }
+
+    /// Enables or disables Back on the current page. `None` restores the default.
+    pub fn set_back_enabled(&mut self, value: Option<bool>) {
+        self.back_enabled = value;
+    }
+
+    /// Enables or disables Next on the current page. Next is never enabled on the
+    /// last page. `None` restores the default.
+    pub fn set_next_enabled(&mut self, value: Option<bool>) {
+        self.next_enabled = value;
+    }
+
+    /// Enables or disables Finish on the current page, such as to allow finishing a
+    /// wizard early. `None` restores the default.
+    pub fn set_finish_enabled(&mut self, value: Option<bool>) {
+        self.finish_enabled = value;
+    }
+
+    /// Whether a page has changes that have not been applied. This enables the Apply
+    /// button of a property sheet.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn set_modified(&mut self, value: bool) {
+        self.modified = value;
+    }
+}
+
+/// One page of a `PropertySheet`.
+pub struct PropertyPage {
+    title: String,
+    description: String,
+    form: Rc<Form>,
+    sheet: Weak<PropertySheet>,
+    index: usize,
+    /// `None` while the page is shown, because the layout is then part of the
+    /// sheet's layout.
+    layout: RefCell<Option<Layout>>,
+    validate_handler: OnceCell<Box<dyn Fn() -> core::result::Result<(), String>>>,
+    apply_handler: OnceCell<Box<dyn Fn()>>,
+    activate_handler: OnceCell<Box<dyn Fn()>>,
+    command_handler: OnceCell<Box<dyn Fn(ControlId, Command)>>,
+    notify_handler: OnceCell<Box<dyn Fn(&Notify)>>,
 }
 
 pub struct PropertyPageBuilder {
+    title: String,
+    description: String,
+    build: Box<dyn FnOnce(&PropertyPage) -> Layout>,
+}
+
+impl PropertyPageBuilder {
+    /// Sets the text shown under the title, in the header of a wizard page.
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+}
+
+impl PropertyPage {
+    /// Describes a page. When the sheet is built, `build` is called to create the
+    /// page's controls and set its handlers. Controls are created on
+    /// `PropertyPage::form`, and `build` returns the layout that places them.
+    pub fn builder<F>(title: &str, build: F) -> PropertyPageBuilder
+    where
+        F: FnOnce(&PropertyPage) -> Layout + 'static,
+    {
+        PropertyPageBuilder {
+            title: title.to_string(),
+            description: String::new(),
+            build: Box::new(build),
+        }
+    }
+
+    /// The window of the sheet. Controls on this page are created with it as their
+    /// parent. The control IDs 1, 2 and 0x3021 through 0x3025 are used by the
+    /// sheet's buttons.
+    pub fn form(&self) -> &Rc<Form> {
+        &self.form
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The sheet that this page is part of. This is `None` while the sheet is being
+    /// built.
+    pub fn sheet(&self) -> Option<Rc<PropertySheet>> {
+        self.sheet.upgrade()
+    }
+
+    /// Sets the handler that checks the page before the user leaves it, or clicks
+    /// OK, Apply or Finish. If it returns an error, the message is shown and the
+    /// page stays.
+    pub fn validate_handler<F>(&self, handler: F)
+    where
+        F: Fn() -> core::result::Result<(), String> + 'static,
+    {
+        let result = self.validate_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call validate_handler() more than once"
+        );
+    }
+
+    /// Sets the handler that saves the page's values, when the user clicks OK,
+    /// Apply or Finish. In a wizard, skipped pages are not applied.
+    pub fn apply_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.apply_handler.set(Box::new(handler));
+        assert!(result.is_ok(), "cannot call apply_handler() more than once");
+    }
+
+    /// Sets the handler that is called each time the page is shown.
+    pub fn activate_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.activate_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call activate_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for `WM_COMMAND` from the controls on this page.
+    pub fn command_handler<F>(&self, handler: F)
+    where
+        F: Fn(ControlId, Command) + 'static,
+    {
+        let result = self.command_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call command_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for `WM_NOTIFY` from the controls on this page.
+    pub fn notify_handler<F>(&self, handler: F)
+    where
+        F: Fn(&Notify) + 'static,
+    {
+        let result = self.notify_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call notify_handler() more than once"
+        );
+    }
+
+    /// Marks the page as having changes that have not been applied, which enables
+    /// the Apply button.
+    pub fn set_modified(&self) {
+        if let Some(sheet) = self.sheet() {
+            sheet.sequence.borrow_mut().set_modified(true);
+            sheet.update_buttons();
+        }
+    }
+
+    fn validate(&self) -> core::result::Result<(), String> {
+        match self.validate_handler.get() {
+            Some(handler) => handler(),
+            None => Ok(()),
+        }
+    }
+
+    fn apply(&self) {
+        if let Some(handler) = self.apply_handler.get() {
+            handler();
+        }
+    }
+
+    fn set_controls_visible(&self, layout: &Layout, visible: bool) {
+        layout.for_each_control(&mut |control| {
+            if visible {
+                control.show();
+            } else {
+                control.hide();
+            }
+        });
+    }
+}
+
+/// A dialog with several pages, shown as tabs or as the steps of a wizard.
+pub struct PropertySheet {
+    form: Rc<Form>,
+    kind: SheetKind,
+    pages: Vec<Rc<PropertyPage>>,
+    sequence: RefCell<PageSequence>,
+    tabs: Option<Rc<TabControl>>,
+    header: Option<(Rc<Label>, Rc<Label>)>,
+    buttons: Vec<(ControlId, Rc<Button>)>,
+    /// The page whose layout is part of the form's layout.
+    shown_page: Cell<Option<usize>>,
+    result: Cell<SheetResult>,
+    applied: Cell<bool>,
+}
+
+impl core::ops::Deref for PropertySheet {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.form
+    }
+}
+
+const ID_APPLY: ControlId = ControlId(0x3021);
+const ID_BACK: ControlId = ControlId(0x3023);
+const ID_NEXT: ControlId = ControlId(0x3024);
+const ID_FINISH: ControlId = ControlId(0x3025);
+// Enter and Escape send these, through IsDialogMessageW.
+const ID_OK: ControlId = ControlId(IDOK.0 as u16);
+const ID_CANCEL: ControlId = ControlId(IDCANCEL.0 as u16);
+
+const PAGE_ROW: u16 = 1;
+const BUTTON_WIDTH: i32 = 85;
+const BUTTON_HEIGHT: i32 = 26;
+
+pub struct PropertySheetBuilder<'a> {
+    title: String,
+    kind: SheetKind,
+    size: (i32, i32),
+    parent: Option<&'a Form>,
+    pages: Vec<PropertyPageBuilder>,
 }
 
-impl PropertySheetBuilder {
+impl<'a> PropertySheetBuilder<'a> {
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Shows the pages as the steps of a wizard, instead of as tabs.
+    pub fn wizard(mut self) -> Self {
+        self.kind = SheetKind::Wizard;
+        self
+    }
+
+    /// Sets the size of the sheet's window, in pixels.
+    pub fn size(mut self, width: i32, height: i32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Sets the window that owns the sheet.
+    pub fn parent(mut self, parent: &'a Form) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
     pub fn page(mut self, page: PropertyPageBuilder) -> Self {
         self.pages.push(page);
+        self
+    }
+
+    /// Creates the sheet's window and all of its pages. The first page is shown.
+    pub fn build(self) -> Rc<PropertySheet> {
+        let mut form_builder = Form::builder();
+        form_builder
+            .text(&self.title)
+            .size(self.size.0, self.size.1);
+        if let Some(parent) = self.parent {
+            form_builder.parent(parent);
+        }
+        let form = form_builder.build();
+
+        let kind = self.kind;
+        let tabs = (kind == SheetKind::PropertySheet).then(|| TabControl::new(&form));
+        let header = (kind == SheetKind::Wizard).then(|| (Label::new(&form), Label::new(&form)));
+
+        let button_specs: &[(ControlId, &str)] = match kind {
+            SheetKind::PropertySheet => {
+                &[(ID_OK, "OK"), (ID_CANCEL, "Cancel"), (ID_APPLY, "Apply")]
+            }
+            SheetKind::Wizard => &[
+                (ID_BACK, "< Back"),
+                (ID_NEXT, "Next >"),
+                (ID_FINISH, "Finish"),
+                (ID_CANCEL, "Cancel"),
+            ],
+        };
+        let buttons = button_specs
+            .iter()
+            .map(|&(id, text)| (id, Button::builder(&form, id).text(text).build()))
+            .collect();
+
+        let num_pages = self.pages.len();
+        let sheet = Rc::new_cyclic(|weak: &Weak<PropertySheet>| {
+            let pages = self
+                .pages
+                .into_iter()
+                .enumerate()
+                .map(|(index, builder)| {
+                    let page = PropertyPage {
+                        title: builder.title,
+                        description: builder.description,
+                        form: form.clone(),
+                        sheet: weak.clone(),
+                        index,
+                        layout: RefCell::new(None),
+                        validate_handler: OnceCell::new(),
+                        apply_handler: OnceCell::new(),
+                        activate_handler: OnceCell::new(),
+                        command_handler: OnceCell::new(),
+                        notify_handler: OnceCell::new(),
+                    };
+                    let layout = (builder.build)(&page);
+                    page.set_controls_visible(&layout, false);
+                    *page.layout.borrow_mut() = Some(layout);
+                    Rc::new(page)
+                })
+                .collect::<Vec<_>>();
+
+            PropertySheet {
+                form: form.clone(),
+                kind,
+                pages,
+                sequence: RefCell::new(PageSequence::new(kind, num_pages)),
+                tabs,
+                header,
+                buttons,
+                shown_page: Cell::new(None),
+                result: Cell::new(SheetResult::Cancel),
+                applied: Cell::new(false),
+            }
+        });
+
+        if let Some(tabs) = &sheet.tabs {
+            for (i, page) in sheet.pages.iter().enumerate() {
+                tabs.add_tab(i as u32, &page.title);
+            }
+            register_notify_handler(
+                tabs.handle(),
+                Rc::downgrade(&sheet) as Weak<dyn NotifyHandlerTrait>,
+            );
+        }
+
+        let weak = Rc::downgrade(&sheet);
+        form.command_handler(move |id, command| {
+            if let Some(sheet) = weak.upgrade() {
+                sheet.on_command(id, command);
+            }
+        });
+        let weak = Rc::downgrade(&sheet);
+        form.notify_handler(move |notify| {
+            if let Some(sheet) = weak.upgrade() {
+                if let Some(page) = sheet.current_page() {
+                    if let Some(handler) = page.notify_handler.get() {
+                        handler(notify);
+                    }
+                }
+            }
+        });
+
+        sheet.show_page(0);
+        sheet
     }
 }
 
 impl PropertySheet {
-    pub fn builder() -> PropertySheetBuilder {
+    pub fn builder<'a>() -> PropertySheetBuilder<'a> {
         PropertySheetBuilder {
+            title: String::new(),
+            kind: SheetKind::PropertySheet,
+            size: (520, 420),
+            parent: None,
             pages: Vec::with_capacity(10),
         }
     }
 
+    pub fn kind(&self) -> SheetKind {
+        self.kind
+    }
+
+    pub fn form(&self) -> &Rc<Form> {
+        &self.form
+    }
+
+    pub fn pages(&self) -> &[Rc<PropertyPage>] {
+        &self.pages
+    }
+
+    pub fn current_page(&self) -> Option<&Rc<PropertyPage>> {
+        self.pages.get(self.sequence.borrow().current())
+    }
+
+    /// Runs the sheet until the user closes it.
+    pub fn show_modal(&self) -> SheetResult {
+        self.show_modal_under(None)
+    }
+
+    /// Runs the sheet until the user closes it. `parent` is disabled meanwhile.
+    pub fn show_modal_under(&self, parent: Option<&Form>) -> SheetResult {
+        self.form.show_modal_under(parent);
+        self.result.get()
+    }
+
+    /// How the sheet was closed. This is `SheetResult::Cancel` until it is closed.
+    pub fn result(&self) -> SheetResult {
+        self.result.get()
+    }
+
+    /// Whether the pages were applied, by OK, Finish or Apply.
+    pub fn applied(&self) -> bool {
+        self.applied.get()
+    }
+
+    /// Moves to another page, as if the user had clicked Back or Next, or selected
+    /// a tab. Returns false if the move is not possible, or the current page is not
+    /// valid.
+    pub fn move_to(&self, page_move: PageMove) -> bool {
+        let (target, validate) = {
+            let sequence = self.sequence.borrow();
+            (
+                sequence.target(page_move),
+                sequence.needs_validation(page_move),
+            )
+        };
+        if target.is_none() || (validate && !self.validate_current()) {
+            return false;
+        }
+        self.sequence.borrow_mut().apply_move(page_move);
+        self.sync_page();
+        true
+    }
+
+    /// Skips a wizard page when moving forward. See `PageSequence::set_skipped`.
+    pub fn set_page_skipped(&self, page: usize, skipped: bool) {
+        self.sequence.borrow_mut().set_skipped(page, skipped);
+        self.update_buttons();
+    }
+
+    /// Enables or disables Back on the current page. `None` restores the default.
+    pub fn set_back_enabled(&self, value: Option<bool>) {
+        self.sequence.borrow_mut().set_back_enabled(value);
+        self.update_buttons();
+    }
+
+    /// Enables or disables Next on the current page. `None` restores the default.
+    pub fn set_next_enabled(&self, value: Option<bool>) {
+        self.sequence.borrow_mut().set_next_enabled(value);
+        self.update_buttons();
+    }
+
+    /// Enables or disables Finish on the current page. `None` restores the default.
+    pub fn set_finish_enabled(&self, value: Option<bool>) {
+        self.sequence.borrow_mut().set_finish_enabled(value);
+        self.update_buttons();
+    }
+
+    /// Closes the sheet with the given result, without validating or applying.
+    pub fn close(&self, result: SheetResult) {
+        self.result.set(result);
+        unsafe {
+            _ = PostMessageW(Some(self.form.handle()), WM_CLOSE, WPARAM(0), LPARAM(0));
+        }
+    }
+
+    fn on_command(&self, id: ControlId, command: Command) {
+        let is_sheet_button = id == ID_OK || self.buttons.iter().any(|(b, _)| *b == id);
+        if !is_sheet_button {
+            if let Some(page) = self.current_page() {
+                if let Some(handler) = page.command_handler.get() {
+                    handler(id, command);
+                }
+            }
+            return;
+        }
+        if command != Command::ButtonClicked {
+            return;
+        }
+
+        match id {
+            ID_CANCEL => self.close(SheetResult::Cancel),
+            ID_BACK => {
+                self.move_to(PageMove::Back);
+            }
+            ID_NEXT => {
+                self.move_to(PageMove::Next);
+            }
+            ID_FINISH => self.finish(),
+            ID_APPLY if self.validate_current() => self.apply_pages(),
+            ID_OK => match self.kind {
+                SheetKind::PropertySheet => {
+                    if self.validate_current() {
+                        self.apply_pages();
+                        self.close(SheetResult::Ok);
+                    }
+                }
+                SheetKind::Wizard => {
+                    let buttons = self.sequence.borrow().buttons();
+                    if buttons.next {
+                        self.move_to(PageMove::Next);
+                    } else if buttons.finish {
+                        self.finish();
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn finish(&self) {
+        if !self.sequence.borrow().buttons().finish {
+            return;
+        }
+        if self.validate_current() {
+            self.apply_pages();
+            self.close(SheetResult::Finish);
+        }
+    }
+
+    fn validate_current(&self) -> bool {
+        let Some(page) = self.current_page() else {
+            return true;
+        };
+        match page.validate() {
+            Ok(()) => true,
+            Err(message) => {
+                MessageBox::builder()
+                    .title("Invalid Value")
+                    .text(&message)
+                    .icon(MessageBoxIcon::Warning)
+                    .parent(&self.form)
+                    .show();
+                false
+            }
+        }
+    }
+
+    fn apply_pages(&self) {
+        let active: Vec<usize> = self.sequence.borrow().active_pages().collect();
+        for index in active {
+            self.pages[index].apply();
+        }
+        self.sequence.borrow_mut().set_modified(false);
+        self.applied.set(true);
+        self.update_buttons();
+    }
+
+    /// Shows `index` after the sequence has moved to it.
+    fn sync_page(&self) {
+        let index = self.sequence.borrow().current();
+        self.show_page(index);
+        if let Some(tabs) = &self.tabs {
+            tabs.select_tab(index as u32);
+        }
+        if let Some(handler) = self.pages[index].activate_handler.get() {
+            handler();
+        }
+    }
+
+    /// Replaces the page in the sheet's layout with page `index`.
+    fn show_page(&self, index: usize) {
+        if self.shown_page.get() == Some(index) || index >= self.pages.len() {
+            return;
+        }
+
+        // Take the previous page's layout back out of the form's layout.
+        if let Some(Layout::Grid(mut grid)) = self.form.layout.borrow_mut().take() {
+            for item in grid.items.drain(..) {
+                if item.row != PAGE_ROW {
+                    continue;
+                }
+                if let (LayoutItem::Layout(layout), Some(previous)) =
+                    (item.item, self.shown_page.get())
+                {
+                    let previous = &self.pages[previous];
+                    previous.set_controls_visible(&layout, false);
+                    *previous.layout.borrow_mut() = Some(*layout);
+                }
+            }
+        }
+
+        let page = &self.pages[index];
+        let page_layout = page.layout.borrow_mut().take();
+        if let Some(page_layout) = &page_layout {
+            page.set_controls_visible(page_layout, true);
+        }
+        if let Some((title, description)) = &self.header {
+            title.set_text(&page.title);
+            description.set_text(&page.description);
+        }
+
+        self.form.set_layout(self.sheet_layout(page_layout));
+        self.shown_page.set(Some(index));
+        self.update_buttons();
+
+        // Keep the focus out of the hidden controls.
+        if let Some(layout) = &*self.form.layout.borrow() {
+            let mut first: Option<HWND> = None;
+            layout.for_each_control(&mut |control| {
+                first.get_or_insert(control.handle());
+            });
+            if let Some(hwnd) = first {
+                unsafe {
+                    _ = SetFocus(Some(hwnd));
+                }
+            }
+        }
+    }
+
+    /// Builds the layout of the whole sheet: the tabs or wizard header, then the
+    /// page, then a row of buttons aligned to the right.
+    fn sheet_layout(&self, page_layout: Option<Layout>) -> Layout {
+        let header_height = match self.kind {
+            SheetKind::PropertySheet => 28,
+            SheetKind::Wizard => 48,
+        };
+        let mut items = Vec::new();
+        if let Some(tabs) = &self.tabs {
+            items.push(GridItem::control(0, 0, tabs.clone()));
+        }
+        if let Some((title, description)) = &self.header {
+            let header = StackLayout::vertical(20)
+                .control(title.clone())
+                .control(description.clone());
+            items.push(GridItem::new(
+                0,
+                0,
+                LayoutItem::Layout(Box::new(Layout::Stack(header))),
+            ));
+        }
+        if let Some(page_layout) = page_layout {
+            items.push(GridItem::new(
+                PAGE_ROW,
+                0,
+                LayoutItem::Layout(Box::new(page_layout)),
+            ));
+        }
+
+        let mut button_cols = GridAxis::new().padding(6).scaled(1.0, 0);
+        let mut button_items = Vec::new();
+        for (i, (_, button)) in self.buttons.iter().enumerate() {
+            button_cols = button_cols.fixed(BUTTON_WIDTH);
+            button_items.push(GridItem::control(0, i as u16 + 1, button.clone()));
+        }
+        let button_row = GridLayout {
+            cols: button_cols,
+            rows: GridAxis::new().fixed(BUTTON_HEIGHT),
+            items: button_items,
+        };
+        items.push(GridItem::new(
+            PAGE_ROW + 1,
+            0,
+            LayoutItem::Layout(Box::new(Layout::Grid(button_row))),
+        ));
+
+        Layout::Grid(GridLayout {
+            cols: GridAxis::new().lead_margin(8).tail_margin(8).scaled(1.0, 0),
+            rows: GridAxis::new()
+                .lead_margin(8)
+                .tail_margin(8)
+                .padding(8)
+                .fixed(header_height)
+                .scaled(1.0, 0)
+                .fixed(BUTTON_HEIGHT + 4),
+            items,
+        })
+    }
+
+    fn update_buttons(&self) {
+        let sequence = self.sequence.borrow();
+        let buttons = sequence.buttons();
+        for (id, button) in self.buttons.iter() {
+            let enabled = match *id {
+                ID_BACK => buttons.back,
+                ID_NEXT => buttons.next,
+                ID_FINISH => buttons.finish,
+                ID_APPLY => sequence.is_modified(),
+                _ => true,
+            };
+            button.set_enabled(enabled);
+        }
+    }
+}
+
+impl NotifyHandlerTrait for PropertySheet {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tcn-selchanging
+            TCN_SELCHANGING => {
+                // Returning TRUE keeps the current tab.
+                let valid = self.validate_current();
+                NotifyResult::Consumed(LRESULT(!valid as isize))
+            }
+            TCN_SELCHANGE => {
+                let Some(tabs) = &self.tabs else {
+                    return NotifyResult::NotConsumed;
+                };
+                let selected = SendMessageW(tabs.handle(), TCM_GETCURSEL, None, None).0;
+                if selected >= 0 {
+                    // The page was validated by TCN_SELCHANGING.
+                    let moved = self
+                        .sequence
+                        .borrow_mut()
+                        .apply_move(PageMove::Select(selected as usize));
+                    if moved.is_some() {
+                        self.sync_page();
+                    }
+                }
+                NotifyResult::NotConsumed
+            }
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wizard(len: usize) -> PageSequence {
+        PageSequence::new(SheetKind::Wizard, len)
+    }
+
+    fn buttons(back: bool, next: bool, finish: bool) -> WizardButtons {
+        WizardButtons { back, next, finish }
+    }
+
+    #[test]
+    fn next_and_back() {
+        let mut sequence = wizard(3);
+        assert_eq!(sequence.current(), 0);
+        assert_eq!(sequence.target(PageMove::Back), None);
+        assert_eq!(sequence.apply_move(PageMove::Back), None);
+        assert_eq!(sequence.apply_move(PageMove::Next), Some(1));
+        assert_eq!(sequence.apply_move(PageMove::Next), Some(2));
+        assert_eq!(sequence.target(PageMove::Next), None);
+        assert_eq!(sequence.apply_move(PageMove::Next), None);
+        assert_eq!(sequence.current(), 2);
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(1));
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(0));
+        assert_eq!(sequence.previous_page(), None);
+    }
+
+    #[test]
+    fn next_skips_pages() {
+        let mut sequence = wizard(5);
+        sequence.set_skipped(1, true);
+        sequence.set_skipped(2, true);
+        assert!(sequence.is_skipped(1));
+        assert_eq!(sequence.active_pages().collect::<Vec<_>>(), [0, 3, 4]);
+        assert_eq!(sequence.target(PageMove::Next), Some(3));
+        assert_eq!(sequence.apply_move(PageMove::Next), Some(3));
+        // Back returns to the page the user came from.
+        assert_eq!(sequence.target(PageMove::Back), Some(0));
+
+        sequence.set_skipped(4, true);
+        assert_eq!(sequence.target(PageMove::Next), None);
+        sequence.set_skipped(4, false);
+        assert_eq!(sequence.target(PageMove::Next), Some(4));
+    }
+
+    #[test]
+    fn back_returns_to_pages_skipped_after_they_were_visited() {
+        let mut sequence = wizard(4);
+        sequence.apply_move(PageMove::Next);
+        sequence.apply_move(PageMove::Next);
+        assert_eq!(sequence.current(), 2);
+        sequence.set_skipped(1, true);
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(1));
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(0));
+        assert_eq!(sequence.apply_move(PageMove::Next), Some(2));
+    }
+
+    #[test]
+    fn select_pages() {
+        let mut sequence = PageSequence::new(SheetKind::PropertySheet, 4);
+        sequence.set_skipped(2, true);
+        assert_eq!(sequence.target(PageMove::Select(0)), None);
+        assert_eq!(sequence.target(PageMove::Select(2)), None);
+        assert_eq!(sequence.target(PageMove::Select(4)), None);
+        assert_eq!(sequence.apply_move(PageMove::Select(3)), Some(3));
+        assert_eq!(sequence.apply_move(PageMove::Select(1)), Some(1));
+        assert_eq!(sequence.previous_page(), Some(3));
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(3));
+        assert_eq!(sequence.apply_move(PageMove::Back), Some(0));
+    }
+
+    #[test]
+    fn current_page_cannot_be_skipped() {
+        let mut sequence = wizard(3);
+        sequence.set_skipped(0, true);
+        assert!(!sequence.is_skipped(0));
+        sequence.set_skipped(7, true);
+        assert!(!sequence.is_skipped(7));
+        assert_eq!(sequence.active_pages().count(), 3);
+    }
+
+    #[test]
+    fn buttons_at_first_and_last_page() {
+        let mut sequence = wizard(3);
+        assert_eq!(sequence.buttons(), buttons(false, true, false));
+        sequence.apply_move(PageMove::Next);
+        assert_eq!(sequence.buttons(), buttons(true, true, false));
+        sequence.apply_move(PageMove::Next);
+        assert_eq!(sequence.buttons(), buttons(true, false, true));
+
+        // A page is the last one when all of the pages after it are skipped.
+        let mut sequence = wizard(3);
+        sequence.set_skipped(1, true);
+        sequence.set_skipped(2, true);
+        assert_eq!(sequence.buttons(), buttons(false, false, true));
+
+        assert_eq!(wizard(1).buttons(), buttons(false, false, true));
+        assert_eq!(wizard(0).buttons(), buttons(false, false, true));
+    }
+
+    #[test]
+    fn button_overrides() {
+        let mut sequence = wizard(3);
+        sequence.set_back_enabled(Some(true));
+        sequence.set_next_enabled(Some(false));
+        sequence.set_finish_enabled(Some(true));
+        assert_eq!(sequence.buttons(), buttons(true, false, true));
+        sequence.set_next_enabled(None);
+        assert_eq!(sequence.buttons(), buttons(true, true, true));
+
+        // The overrides are cleared when the page changes.
+        sequence.apply_move(PageMove::Next);
+        assert_eq!(sequence.buttons(), buttons(true, true, false));
+        sequence.set_back_enabled(Some(false));
+        assert_eq!(sequence.buttons(), buttons(false, true, false));
+        sequence.set_back_enabled(None);
+        assert_eq!(sequence.buttons(), buttons(true, true, false));
+
+        // Next is never enabled on the last page.
+        sequence.apply_move(PageMove::Next);
+        sequence.set_next_enabled(Some(true));
+        sequence.set_finish_enabled(Some(false));
+        assert_eq!(sequence.buttons(), buttons(true, false, false));
+    }
+
+    #[test]
+    fn validation_and_modified() {
+        let wizard = wizard(2);
+        assert!(!wizard.needs_validation(PageMove::Back));
+        assert!(wizard.needs_validation(PageMove::Next));
+        assert!(wizard.needs_validation(PageMove::Select(1)));
+
+        let mut sheet = PageSequence::new(SheetKind::PropertySheet, 2);
+        assert!(sheet.needs_validation(PageMove::Back));
+        assert!(!sheet.is_modified());
+        sheet.set_modified(true);
+        assert!(sheet.is_modified());
+        // Changing the page does not apply the changes.
+        sheet.apply_move(PageMove::Select(1));
+        assert!(sheet.is_modified());
+    }
 }
@@ -0,0 +1,733 @@
+//! The model behind `PropertyGrid`: objects describe their fields as a list of
+//! `Property` values, and accept edits through `Properties::set_property`.
+//!
+//! Nothing in this module needs a window, so objects and their validation can be
+//! exercised directly.
+
+use std::collections::HashSet;
+
+/// An object whose fields can be shown and edited in a `PropertyGrid`.
+///
+/// Usually this is derived:
+///
+/// ```ignore
+/// #[derive(Properties)]
+/// struct Connection {
+///     #[property(category = "Server", description = "Name or address of the server")]
+///     host: String,
+///     #[property(category = "Server")]
+///     port: u16,
+///     #[property(category = "Options", label = "Use TLS")]
+///     tls: bool,
+///     #[property(skip)]
+///     session: Option<Session>,
+/// }
+/// ```
+///
+/// Field types must implement `PropertyField`. `#[derive(PropertyField)]` implements
+/// it for enums without fields, which are edited with a drop-down list.
+pub trait Properties {
+    /// Describes the fields, in the order they are shown.
+    fn properties(&self) -> Vec<Property>;
+
+    /// Changes the field called `name`. Returns a message for the user if the value
+    /// is rejected, in which case the object must be left unchanged.
+    fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), String>;
+}
+
+/// One field of an object. See `Properties`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    /// Identifies the field in `Properties::set_property`.
+    pub name: String,
+    /// The name shown in the grid.
+    pub label: String,
+    /// Properties with the same category are grouped under a heading. Properties
+    /// without a category are shown first.
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub value: PropertyValue,
+    pub read_only: bool,
+}
+
+/// The value of a property. The variant also chooses the editor that is used for it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// Edited with a text box.
+    Text(String),
+    /// Toggled by clicking it, or by pressing Space.
+    Bool(bool),
+    /// Edited with a text box that only accepts whole numbers.
+    Integer(i64),
+    /// Edited with a text box that only accepts numbers.
+    Float(f64),
+    /// Edited with a drop-down list. `index` is the position of the current value
+    /// in `choices`.
+    Choice { index: usize, choices: Vec<String> },
+}
+
+impl PropertyValue {
+    /// The text shown in the value column.
+    pub fn display(&self) -> String {
+        match self {
+            Self::Text(s) => s.clone(),
+            Self::Bool(b) => if *b { "True" } else { "False" }.to_string(),
+            Self::Integer(n) => n.to_string(),
+            Self::Float(f) => f.to_string(),
+            Self::Choice { index, choices } => choices.get(*index).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Converts text typed into an editor to a value of the same kind as `self`.
+    pub fn parse_edit(&self, text: &str) -> Result<PropertyValue, String> {
+        let trimmed = text.trim();
+        match self {
+            Self::Text(_) => Ok(Self::Text(text.to_string())),
+            Self::Bool(_) => match trimmed.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Self::Bool(true)),
+                "false" | "no" | "0" => Ok(Self::Bool(false)),
+                _ => Err(format!("'{trimmed}' is not True or False.")),
+            },
+            Self::Integer(_) => trimmed
+                .parse()
+                .map(Self::Integer)
+                .map_err(|_| format!("'{trimmed}' is not a whole number.")),
+            Self::Float(_) => trimmed
+                .parse()
+                .map(Self::Float)
+                .map_err(|_| format!("'{trimmed}' is not a number.")),
+            Self::Choice { choices, .. } => choices
+                .iter()
+                .position(|choice| choice.eq_ignore_ascii_case(trimmed))
+                .map(|index| Self::Choice {
+                    index,
+                    choices: choices.clone(),
+                })
+                .ok_or_else(|| format!("'{trimmed}' is not one of the choices.")),
+        }
+    }
+
+    /// The value that `Bool` toggles to, or `None` for other kinds.
+    pub fn toggled(&self) -> Option<PropertyValue> {
+        match self {
+            Self::Bool(b) => Some(Self::Bool(!b)),
+            _ => None,
+        }
+    }
+}
+
+/// Converts a field to and from a `PropertyValue`. Used by `#[derive(Properties)]`.
+pub trait PropertyField: Sized {
+    fn to_property_value(&self) -> PropertyValue;
+    fn from_property_value(value: PropertyValue) -> Result<Self, String>;
+}
+
+fn wrong_kind(value: &PropertyValue) -> String {
+    format!("'{}' is not a valid value.", value.display())
+}
+
+impl PropertyField for String {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Text(self.clone())
+    }
+    fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+        match value {
+            PropertyValue::Text(s) => Ok(s),
+            other => Err(wrong_kind(&other)),
+        }
+    }
+}
+
+impl PropertyField for bool {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Bool(*self)
+    }
+    fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+        match value {
+            PropertyValue::Bool(b) => Ok(b),
+            other => Err(wrong_kind(&other)),
+        }
+    }
+}
+
+macro_rules! integer_property_fields {
+    ($($t:ty),*) => {
+        $(
+            impl PropertyField for $t {
+                fn to_property_value(&self) -> PropertyValue {
+                    // All of these types fit in an `i64`.
+                    PropertyValue::Integer(*self as i64)
+                }
+                fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+                    match value {
+                        PropertyValue::Integer(n) => <$t>::try_from(n).map_err(|_| {
+                            format!(
+                                "{n} is out of range. The value must be between {} and {}.",
+                                <$t>::MIN,
+                                <$t>::MAX
+                            )
+                        }),
+                        other => Err(wrong_kind(&other)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_property_fields!(i8, i16, i32, i64, isize, u8, u16, u32);
+
+// `u64` and `usize` can hold values that `PropertyValue::Integer` cannot, so they
+// are edited as text.
+macro_rules! unsigned_text_property_fields {
+    ($($t:ty),*) => {
+        $(
+            impl PropertyField for $t {
+                fn to_property_value(&self) -> PropertyValue {
+                    PropertyValue::Text(self.to_string())
+                }
+                fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+                    let out_of_range = |n: &dyn core::fmt::Display| {
+                        format!(
+                            "{n} is out of range. The value must be between 0 and {}.",
+                            <$t>::MAX
+                        )
+                    };
+                    match value {
+                        PropertyValue::Text(s) => {
+                            let trimmed = s.trim();
+                            if !trimmed.is_empty() && trimmed.bytes().all(|b| b.is_ascii_digit()) {
+                                trimmed.parse().map_err(|_| out_of_range(&trimmed))
+                            } else {
+                                Err(format!("'{trimmed}' is not a whole number."))
+                            }
+                        }
+                        PropertyValue::Integer(n) => <$t>::try_from(n).map_err(|_| out_of_range(&n)),
+                        other => Err(wrong_kind(&other)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+unsigned_text_property_fields!(u64, usize);
+
+impl PropertyField for f64 {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Float(*self)
+    }
+    fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+        match value {
+            PropertyValue::Float(f) => Ok(f),
+            PropertyValue::Integer(n) => Ok(n as f64),
+            other => Err(wrong_kind(&other)),
+        }
+    }
+}
+
+impl PropertyField for f32 {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Float(*self as f64)
+    }
+    fn from_property_value(value: PropertyValue) -> Result<Self, String> {
+        f64::from_property_value(value).map(|f| f as f32)
+    }
+}
+
+/// A row of a `PropertyGrid`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PropertyRow {
+    /// The heading of a category, which can be collapsed to hide its properties.
+    Category { name: String, collapsed: bool },
+    /// Index into `PropertyGridModel::properties`.
+    Property(usize),
+}
+
+/// The rows shown by a `PropertyGrid`: properties grouped by category, with some
+/// categories collapsed.
+#[derive(Clone, Debug, Default)]
+pub struct PropertyGridModel {
+    properties: Vec<Property>,
+    collapsed: HashSet<String>,
+    rows: Vec<PropertyRow>,
+}
+
+impl PropertyGridModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the properties. Categories that were collapsed stay collapsed.
+    pub fn set_properties(&mut self, properties: Vec<Property>) {
+        self.properties = properties;
+        self.build_rows();
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    pub fn rows(&self) -> &[PropertyRow] {
+        &self.rows
+    }
+
+    /// The property shown in `row`, if that row is not a category heading.
+    pub fn property_at(&self, row: usize) -> Option<&Property> {
+        match self.rows.get(row)? {
+            PropertyRow::Property(index) => self.properties.get(*index),
+            PropertyRow::Category { .. } => None,
+        }
+    }
+
+    /// The row that shows the property called `name`, if it is visible.
+    pub fn find_row(&self, name: &str) -> Option<usize> {
+        self.rows.iter().position(|row| match row {
+            PropertyRow::Property(index) => self.properties[*index].name == name,
+            PropertyRow::Category { .. } => false,
+        })
+    }
+
+    pub fn is_collapsed(&self, category: &str) -> bool {
+        self.collapsed.contains(category)
+    }
+
+    pub fn set_collapsed(&mut self, category: &str, value: bool) {
+        if value {
+            self.collapsed.insert(category.to_string());
+        } else {
+            self.collapsed.remove(category);
+        }
+        self.build_rows();
+    }
+
+    /// Collapses or expands the category whose heading is in `row`. Returns false
+    /// if `row` is not a category heading.
+    pub fn toggle_row(&mut self, row: usize) -> bool {
+        let Some(PropertyRow::Category { name, collapsed }) = self.rows.get(row).cloned() else {
+            return false;
+        };
+        self.set_collapsed(&name, !collapsed);
+        true
+    }
+
+    /// The text of the name and value columns of `row`.
+    pub fn row_text(&self, row: usize) -> (String, String) {
+        match &self.rows[row] {
+            PropertyRow::Category { name, collapsed } => {
+                let marker = if *collapsed { '+' } else { '-' };
+                (format!("{marker} {name}"), String::new())
+            }
+            PropertyRow::Property(index) => {
+                let property = &self.properties[*index];
+                (property.label.clone(), property.value.display())
+            }
+        }
+    }
+
+    fn build_rows(&mut self) {
+        self.rows.clear();
+
+        // Categories are shown in the order in which they first appear.
+        let mut categories: Vec<&str> = Vec::new();
+        for (index, property) in self.properties.iter().enumerate() {
+            match &property.category {
+                None => self.rows.push(PropertyRow::Property(index)),
+                Some(category) => {
+                    if !categories.contains(&category.as_str()) {
+                        categories.push(category);
+                    }
+                }
+            }
+        }
+
+        for category in categories {
+            let collapsed = self.collapsed.contains(category);
+            self.rows.push(PropertyRow::Category {
+                name: category.to_string(),
+                collapsed,
+            });
+            if collapsed {
+                continue;
+            }
+            for (index, property) in self.properties.iter().enumerate() {
+                if property.category.as_deref() == Some(category) {
+                    self.rows.push(PropertyRow::Property(index));
+                }
+            }
+        }
+    }
+}
+
+/// Applies text typed into an editor to the property called `name`: parses it as
+/// the same kind of value as the property's current value, and passes it to
+/// `Properties::set_property`.
+pub fn apply_property_edit(
+    object: &mut dyn Properties,
+    name: &str,
+    text: &str,
+) -> Result<(), String> {
+    let properties = object.properties();
+    let property = properties
+        .iter()
+        .find(|property| property.name == name)
+        .ok_or_else(|| format!("There is no property called '{name}'."))?;
+    if property.read_only {
+        return Err(format!("'{}' cannot be changed.", property.label));
+    }
+    let value = property.value.parse_edit(text)?;
+    object.set_property(name, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_round_trip() {
+        assert_eq!(
+            i64::MIN.to_property_value(),
+            PropertyValue::Integer(i64::MIN)
+        );
+        assert_eq!(
+            u32::MAX.to_property_value(),
+            PropertyValue::Integer(u32::MAX as i64)
+        );
+        assert_eq!(
+            u8::from_property_value(PropertyValue::Integer(255)),
+            Ok(255)
+        );
+        assert!(u8::from_property_value(PropertyValue::Integer(256)).is_err());
+        assert!(u16::from_property_value(PropertyValue::Integer(-1)).is_err());
+        assert!(i32::from_property_value(PropertyValue::Text("1".into())).is_err());
+    }
+
+    #[test]
+    fn large_unsigned_integers_are_text() {
+        let value = u64::MAX.to_property_value();
+        assert_eq!(value, PropertyValue::Text(u64::MAX.to_string()));
+        assert_eq!(u64::from_property_value(value), Ok(u64::MAX));
+        assert_eq!(
+            usize::MAX.to_property_value().display(),
+            usize::MAX.to_string()
+        );
+
+        assert_eq!(
+            u64::from_property_value(PropertyValue::Text(" 42 ".into())),
+            Ok(42)
+        );
+        assert_eq!(usize::from_property_value(PropertyValue::Integer(7)), Ok(7));
+    }
+
+    #[test]
+    fn large_unsigned_integers_reject_invalid_values() {
+        let text = |s: &str| u64::from_property_value(PropertyValue::Text(s.into()));
+        assert!(text("").is_err());
+        assert!(text("-1").is_err());
+        assert!(text("+1").is_err());
+        assert!(text("1.5").is_err());
+        assert!(text("18446744073709551616")
+            .unwrap_err()
+            .contains("out of range"));
+        assert!(u64::from_property_value(PropertyValue::Integer(-1))
+            .unwrap_err()
+            .contains("out of range"));
+        assert!(u64::from_property_value(PropertyValue::Bool(true)).is_err());
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, crate::PropertyField)]
+    enum Mode {
+        Fast,
+        #[property(label = "Very safe")]
+        Safe,
+    }
+
+    #[derive(crate::Properties)]
+    struct Connection {
+        #[property(category = "Server", description = "Name of the server")]
+        host: String,
+        #[property(category = "Server")]
+        port: u16,
+        name_only: bool,
+        #[property(category = "Options", label = "Use TLS")]
+        tls: bool,
+        #[property(category = "Options")]
+        mode: Mode,
+        #[property(read_only)]
+        id: u32,
+        #[property(skip)]
+        #[allow(dead_code)]
+        session: Option<()>,
+    }
+
+    fn connection() -> Connection {
+        Connection {
+            host: "example.com".into(),
+            port: 443,
+            name_only: false,
+            tls: true,
+            mode: Mode::Fast,
+            id: 7,
+            session: None,
+        }
+    }
+
+    fn model() -> PropertyGridModel {
+        let mut model = PropertyGridModel::new();
+        model.set_properties(connection().properties());
+        model
+    }
+
+    fn category(name: &str, collapsed: bool) -> PropertyRow {
+        PropertyRow::Category {
+            name: name.into(),
+            collapsed,
+        }
+    }
+
+    #[test]
+    fn derive_describes_fields() {
+        let properties = connection().properties();
+        let names: Vec<&str> = properties.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["host", "port", "name_only", "tls", "mode", "id"]);
+        let labels: Vec<&str> = properties.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            ["Host", "Port", "Name only", "Use TLS", "Mode", "Id"]
+        );
+
+        assert_eq!(
+            properties[0],
+            Property {
+                name: "host".into(),
+                label: "Host".into(),
+                category: Some("Server".into()),
+                description: Some("Name of the server".into()),
+                value: PropertyValue::Text("example.com".into()),
+                read_only: false,
+            }
+        );
+        assert_eq!(properties[1].value, PropertyValue::Integer(443));
+        assert_eq!(properties[2].category, None);
+        assert_eq!(
+            properties[4].value,
+            PropertyValue::Choice {
+                index: 0,
+                choices: vec!["Fast".into(), "Very safe".into()],
+            }
+        );
+        assert!(properties[5].read_only);
+    }
+
+    #[test]
+    fn derive_sets_fields() {
+        let mut object = connection();
+        assert_eq!(
+            object.set_property("port", PropertyValue::Integer(8080)),
+            Ok(())
+        );
+        assert_eq!(object.port, 8080);
+        assert!(object
+            .set_property("port", PropertyValue::Integer(70000))
+            .is_err());
+        assert!(object
+            .set_property("port", PropertyValue::Text("80".into()))
+            .is_err());
+        assert_eq!(object.port, 8080);
+
+        let safe = Mode::Safe.to_property_value();
+        assert_eq!(object.set_property("mode", safe), Ok(()));
+        assert_eq!(object.mode, Mode::Safe);
+        let invalid = PropertyValue::Choice {
+            index: 5,
+            choices: Vec::new(),
+        };
+        assert!(object.set_property("mode", invalid).is_err());
+        assert_eq!(object.mode, Mode::Safe);
+
+        assert_eq!(
+            object.set_property("id", PropertyValue::Integer(1)),
+            Err("'Id' cannot be changed.".into())
+        );
+        assert_eq!(object.id, 7);
+        assert_eq!(
+            object.set_property("session", PropertyValue::Bool(true)),
+            Err("There is no property called 'session'.".into())
+        );
+    }
+
+    #[test]
+    fn rows_show_uncategorized_properties_first() {
+        let model = model();
+        assert_eq!(
+            model.rows(),
+            [
+                PropertyRow::Property(2),
+                PropertyRow::Property(5),
+                category("Server", false),
+                PropertyRow::Property(0),
+                PropertyRow::Property(1),
+                category("Options", false),
+                PropertyRow::Property(3),
+                PropertyRow::Property(4),
+            ]
+        );
+        assert_eq!(model.property_at(3).unwrap().name, "host");
+        assert!(model.property_at(2).is_none());
+        assert!(model.property_at(99).is_none());
+    }
+
+    #[test]
+    fn rows_keep_categories_in_order_of_first_appearance() {
+        let property = |name: &str, category: Option<&str>| Property {
+            name: name.into(),
+            label: name.into(),
+            category: category.map(Into::into),
+            description: None,
+            value: PropertyValue::Bool(false),
+            read_only: false,
+        };
+        let mut model = PropertyGridModel::new();
+        model.set_properties(vec![
+            property("a", Some("B")),
+            property("b", Some("A")),
+            property("c", Some("B")),
+            property("d", None),
+        ]);
+        assert_eq!(
+            model.rows(),
+            [
+                PropertyRow::Property(3),
+                category("B", false),
+                PropertyRow::Property(0),
+                PropertyRow::Property(2),
+                category("A", false),
+                PropertyRow::Property(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn toggle_row_collapses_categories() {
+        let mut model = model();
+        assert!(!model.toggle_row(0));
+        assert!(!model.toggle_row(99));
+        assert!(model.toggle_row(2));
+        assert!(model.is_collapsed("Server"));
+        assert_eq!(model.rows().len(), 6);
+        assert_eq!(model.rows()[2], category("Server", true));
+        assert_eq!(model.find_row("host"), None);
+        assert_eq!(model.find_row("tls"), Some(4));
+
+        // Collapsed categories stay collapsed when the properties change.
+        model.set_properties(connection().properties());
+        assert!(model.is_collapsed("Server"));
+        assert_eq!(model.find_row("host"), None);
+
+        assert!(model.toggle_row(2));
+        assert!(!model.is_collapsed("Server"));
+        assert_eq!(model.find_row("host"), Some(3));
+    }
+
+    #[test]
+    fn find_row_by_name() {
+        let model = model();
+        assert_eq!(model.find_row("name_only"), Some(0));
+        assert_eq!(model.find_row("mode"), Some(7));
+        assert_eq!(model.find_row("Server"), None);
+        assert_eq!(model.find_row("session"), None);
+    }
+
+    #[test]
+    fn row_text_of_headings_and_properties() {
+        let mut model = model();
+        assert_eq!(model.row_text(2), ("- Server".into(), String::new()));
+        assert_eq!(model.row_text(6), ("Use TLS".into(), "True".into()));
+        assert_eq!(model.row_text(7), ("Mode".into(), "Fast".into()));
+        model.set_collapsed("Server", true);
+        assert_eq!(model.row_text(2), ("+ Server".into(), String::new()));
+    }
+
+    #[test]
+    fn parse_edit_accepts_values_of_the_same_kind() {
+        let text = PropertyValue::Text(String::new());
+        assert_eq!(
+            text.parse_edit(" a "),
+            Ok(PropertyValue::Text(" a ".into()))
+        );
+        let boolean = PropertyValue::Bool(false);
+        assert_eq!(boolean.parse_edit(" YES "), Ok(PropertyValue::Bool(true)));
+        assert_eq!(boolean.parse_edit("0"), Ok(PropertyValue::Bool(false)));
+        let integer = PropertyValue::Integer(0);
+        assert_eq!(integer.parse_edit(" -12 "), Ok(PropertyValue::Integer(-12)));
+        let float = PropertyValue::Float(0.0);
+        assert_eq!(float.parse_edit("1e3"), Ok(PropertyValue::Float(1000.0)));
+        let choice = Mode::Fast.to_property_value();
+        assert_eq!(
+            choice.parse_edit("very SAFE"),
+            Ok(Mode::Safe.to_property_value())
+        );
+    }
+
+    #[test]
+    fn parse_edit_errors() {
+        assert_eq!(
+            PropertyValue::Bool(false).parse_edit("maybe"),
+            Err("'maybe' is not True or False.".into())
+        );
+        assert_eq!(
+            PropertyValue::Integer(0).parse_edit(" 12x "),
+            Err("'12x' is not a whole number.".into())
+        );
+        assert!(PropertyValue::Integer(0).parse_edit("1.5").is_err());
+        assert!(PropertyValue::Integer(0)
+            .parse_edit("99999999999999999999")
+            .is_err());
+        assert_eq!(
+            PropertyValue::Float(0.0).parse_edit("abc"),
+            Err("'abc' is not a number.".into())
+        );
+        assert_eq!(
+            Mode::Fast.to_property_value().parse_edit("Slow"),
+            Err("'Slow' is not one of the choices.".into())
+        );
+    }
+
+    #[test]
+    fn toggled_only_changes_bools() {
+        assert_eq!(
+            PropertyValue::Bool(true).toggled(),
+            Some(PropertyValue::Bool(false))
+        );
+        assert_eq!(PropertyValue::Integer(1).toggled(), None);
+    }
+
+    #[test]
+    fn apply_property_edit_parses_and_sets() {
+        let mut object = connection();
+        assert_eq!(apply_property_edit(&mut object, "port", " 8080 "), Ok(()));
+        assert_eq!(object.port, 8080);
+        assert_eq!(
+            apply_property_edit(&mut object, "mode", "very safe"),
+            Ok(())
+        );
+        assert_eq!(object.mode, Mode::Safe);
+        assert!(apply_property_edit(&mut object, "port", "x").is_err());
+        assert!(apply_property_edit(&mut object, "port", "-1").is_err());
+        assert_eq!(object.port, 8080);
+    }
+
+    #[test]
+    fn apply_property_edit_rejects_read_only_and_unknown_properties() {
+        let mut object = connection();
+        assert_eq!(
+            apply_property_edit(&mut object, "id", "not even a number"),
+            Err("'Id' cannot be changed.".into())
+        );
+        assert_eq!(object.id, 7);
+        assert_eq!(
+            apply_property_edit(&mut object, "nope", "1"),
+            Err("There is no property called 'nope'.".into())
+        );
+    }
+}
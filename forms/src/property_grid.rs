@@ -0,0 +1,600 @@
+//! A two-column list of the properties of an object, with in-place editors.
+//! The model is in `properties.rs`.
+
+use super::*;
+use std::cell::OnceCell;
+use std::sync::Once;
+use windows::core::w;
+use windows::Win32::UI::Controls::{NM_CLICK, NM_DBLCLK, NM_RETURN};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetFocus, SetFocus, VK_ESCAPE, VK_F2, VK_RETURN, VK_SPACE,
+};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+
+/// Posted to the grid by an editor, so that the editor is not destroyed while it
+/// is still handling a message. wParam is the editor's window, so that a message
+/// that arrives after another editor has opened is ignored.
+const WM_COMMIT_EDIT: u32 = WM_APP + 1;
+const WM_CANCEL_EDIT: u32 = WM_APP + 2;
+
+const EDITOR_SUBCLASS_ID: usize = 1;
+
+/// Shows the properties of an object that implements `Properties`, grouped by
+/// category, and lets the user edit them.
+///
+/// Click a value (or press F2) to edit it. Bool values are toggled by clicking them
+/// or pressing Space. Click a category heading to collapse or expand it. A
+/// property's description is shown as the tooltip of its row.
+pub struct PropertyGrid {
+    control: ControlState,
+    list: Rc<ListView>,
+    model: RefCell<PropertyGridModel>,
+    object: RefCell<Option<Rc<RefCell<dyn Properties>>>>,
+    editor: Cell<Option<Editor>>,
+    property_changed_handler: OnceCell<Box<dyn Fn(&str)>>,
+}
+
+impl core::ops::Deref for PropertyGrid {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Editor {
+    hwnd: HWND,
+    row: usize,
+}
+
+impl PropertyGrid {
+    pub fn new(parent: &ControlState) -> Rc<Self> {
+        let atom = register_class_lazy();
+
+        unsafe {
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                PCWSTR(atom as *const u16),
+                w!(""),
+                WS_CHILD | WS_VISIBLE | WS_CLIPCHILDREN,
+                0,
+                0,
+                0,
+                0,
+                Some(parent.handle()),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            let control = ControlState::new(hwnd);
+            let list = ListView::new(&control);
+            list.set_mode(Mode::Details);
+            // Editors are siblings of the list, placed on top of it.
+            list.set_window_style_flag(
+                WS_CLIPSIBLINGS
+                    | WINDOW_STYLE(LVS_SINGLESEL | LVS_SHOWSELALWAYS | LVS_NOSORTHEADER),
+                true,
+            );
+            list.set_full_row_select(true);
+            list.set_grid_lines(true);
+            list.set_double_buffer(true);
+            list.add_column(0, 150, "Property");
+            list.add_column(1, 200, "Value");
+
+            let this = Rc::new_cyclic(|this: &Weak<PropertyGrid>| {
+                let this = this.clone();
+                list.item_tooltip_handler(move |row, _| {
+                    let this = this.upgrade()?;
+                    let model = this.model.borrow();
+                    model.property_at(row)?.description.clone()
+                });
+
+                PropertyGrid {
+                    control,
+                    list,
+                    model: RefCell::new(PropertyGridModel::new()),
+                    object: RefCell::new(None),
+                    editor: Cell::new(None),
+                    property_changed_handler: OnceCell::new(),
+                }
+            });
+
+            SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0), Rc::as_ptr(&this) as isize);
+            this
+        }
+    }
+
+    /// Shows the properties of `object`. The grid keeps a reference to it, and
+    /// changes it when the user edits a value.
+    pub fn set_object(&self, object: Rc<RefCell<dyn Properties>>) {
+        *self.object.borrow_mut() = Some(object);
+        self.refresh();
+    }
+
+    pub fn clear_object(&self) {
+        *self.object.borrow_mut() = None;
+        self.refresh();
+    }
+
+    /// Reads the properties of the object again. Call this after changing the
+    /// object in code.
+    pub fn refresh(&self) {
+        self.end_edit(false);
+        let object = self.object.borrow().clone();
+        let properties = match object {
+            Some(object) => object.borrow().properties(),
+            None => Vec::new(),
+        };
+        self.model.borrow_mut().set_properties(properties);
+        self.fill_list();
+    }
+
+    pub fn set_category_collapsed(&self, category: &str, value: bool) {
+        self.end_edit(true);
+        self.model.borrow_mut().set_collapsed(category, value);
+        self.fill_list();
+    }
+
+    /// The name of the selected property, if a property (rather than a category
+    /// heading) is selected.
+    pub fn selected_property(&self) -> Option<String> {
+        let row = self.list.iter_selected_items().next()?;
+        let model = self.model.borrow();
+        Some(model.property_at(row)?.name.clone())
+    }
+
+    /// Sets the handler that is called with the name of a property after the user
+    /// has changed it, and the object has accepted the new value.
+    pub fn property_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(&str) + 'static,
+    {
+        let result = self.property_changed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call property_changed_handler() more than once"
+        );
+    }
+
+    fn fill_list(&self) {
+        let selected = self.list.iter_selected_items().next();
+        unsafe {
+            SendMessageW(self.list.handle(), WM_SETREDRAW, Some(WPARAM(0)), None);
+        }
+        self.list.delete_all_items();
+        let len = {
+            let model = self.model.borrow();
+            for row in 0..model.rows().len() {
+                let (name, value) = model.row_text(row);
+                let item = self.list.insert_item(&name);
+                self.list.set_subitem_text(item, 1, &value);
+            }
+            model.rows().len()
+        };
+        if let Some(selected) = selected.filter(|&row| row < len) {
+            self.list.set_item_selected(selected, true);
+        }
+        unsafe {
+            SendMessageW(self.list.handle(), WM_SETREDRAW, Some(WPARAM(1)), None);
+            _ = InvalidateRect(Some(self.list.handle()), None, true);
+        }
+    }
+
+    fn layout(&self, width: i32, height: i32) {
+        unsafe {
+            self.end_edit(true);
+            _ = MoveWindow(self.list.handle(), 0, 0, width, height, true);
+            // The value column fills the space that the name column leaves.
+            SendMessageW(
+                self.list.handle(),
+                LVM_SETCOLUMNWIDTH,
+                Some(WPARAM(0)),
+                Some(LPARAM((width * 2 / 5) as isize)),
+            );
+            SendMessageW(
+                self.list.handle(),
+                LVM_SETCOLUMNWIDTH,
+                Some(WPARAM(1)),
+                Some(LPARAM(LVSCW_AUTOSIZE_USEHEADER as isize)),
+            );
+        }
+    }
+
+    /// Called when a row is clicked, or activated with the keyboard.
+    fn activate_row(&self, row: usize, value_column: bool) {
+        self.end_edit(true);
+        if self.model.borrow_mut().toggle_row(row) {
+            self.fill_list();
+            return;
+        }
+
+        let Some(property) = self.model.borrow().property_at(row).cloned() else {
+            return;
+        };
+        if property.read_only {
+            return;
+        }
+        if let Some(value) = property.value.toggled() {
+            if value_column {
+                self.set_value(&property.name, Ok(value));
+            }
+            return;
+        }
+        if value_column {
+            self.begin_edit(row, &property);
+        }
+    }
+
+    fn begin_edit(&self, row: usize, property: &Property) {
+        unsafe {
+            let list = self.list.handle();
+            let mut rect = RECT {
+                top: 1,
+                left: LVIR_BOUNDS as i32,
+                ..Default::default()
+            };
+            SendMessageW(
+                list,
+                LVM_GETSUBITEMRECT,
+                Some(WPARAM(row)),
+                Some(LPARAM(&mut rect as *mut RECT as isize)),
+            );
+            let mut points = [
+                POINT {
+                    x: rect.left,
+                    y: rect.top,
+                },
+                POINT {
+                    x: rect.right,
+                    y: rect.bottom,
+                },
+            ];
+            MapWindowPoints(Some(list), Some(self.handle()), &mut points);
+            let [top_left, bottom_right] = points;
+            let width = bottom_right.x - top_left.x;
+            let height = bottom_right.y - top_left.y;
+
+            let hwnd = match &property.value {
+                PropertyValue::Choice { index, choices } => {
+                    // The height of a combo box includes its drop-down list.
+                    let hwnd = CreateWindowExW(
+                        WINDOW_EX_STYLE(0),
+                        w!("ComboBox"),
+                        PCWSTR::null(),
+                        WS_CHILD | WS_VISIBLE | WS_VSCROLL | WINDOW_STYLE(CBS_DROPDOWNLIST as u32),
+                        top_left.x,
+                        top_left.y,
+                        width,
+                        height * 10,
+                        Some(self.handle()),
+                        None,
+                        Some(get_instance()),
+                        None,
+                    )
+                    .unwrap();
+                    for choice in choices {
+                        let choice_wstr = WCString::from_str_truncate(choice);
+                        SendMessageW(
+                            hwnd,
+                            CB_ADDSTRING,
+                            None,
+                            Some(LPARAM(choice_wstr.as_ptr() as isize)),
+                        );
+                    }
+                    SendMessageW(hwnd, CB_SETCURSEL, Some(WPARAM(*index)), None);
+                    hwnd
+                }
+
+                _ => {
+                    let hwnd = CreateWindowExW(
+                        WINDOW_EX_STYLE(0),
+                        w!("Edit"),
+                        PCWSTR::null(),
+                        WS_CHILD | WS_VISIBLE | WS_BORDER | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+                        top_left.x,
+                        top_left.y,
+                        width,
+                        height,
+                        Some(self.handle()),
+                        None,
+                        Some(get_instance()),
+                        None,
+                    )
+                    .unwrap();
+                    set_window_text(hwnd, &property.value.display());
+                    SendMessageW(hwnd, EM_SETSEL, Some(WPARAM(0)), Some(LPARAM(-1)));
+                    hwnd
+                }
+            };
+
+            let font = SendMessageW(list, WM_GETFONT, None, None);
+            SendMessageW(hwnd, WM_SETFONT, Some(WPARAM(font.0 as usize)), None);
+            _ = SetWindowSubclass(hwnd, Some(editor_subclass_proc), EDITOR_SUBCLASS_ID, 0);
+            _ = SetWindowPos(hwnd, Some(HWND_TOP), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE);
+
+            self.editor.set(Some(Editor { hwnd, row }));
+            _ = SetFocus(Some(hwnd));
+            if matches!(property.value, PropertyValue::Choice { .. }) {
+                SendMessageW(hwnd, CB_SHOWDROPDOWN, Some(WPARAM(1)), None);
+            }
+        }
+    }
+
+    /// Closes the editor, if one is open. If `commit` is true, the edited value is
+    /// given to the object.
+    fn end_edit(&self, commit: bool) {
+        let Some(editor) = self.editor.take() else {
+            return;
+        };
+
+        let (name, value) = {
+            let model = self.model.borrow();
+            let Some(property) = model.property_at(editor.row) else {
+                return;
+            };
+            let value = match &property.value {
+                PropertyValue::Choice { choices, .. } => unsafe {
+                    let index = SendMessageW(editor.hwnd, CB_GETCURSEL, None, None).0;
+                    if index < 0 {
+                        Err("Choose one of the values in the list.".to_string())
+                    } else {
+                        Ok(PropertyValue::Choice {
+                            index: index as usize,
+                            choices: choices.clone(),
+                        })
+                    }
+                },
+                value => value.parse_edit(&get_window_text(editor.hwnd)),
+            };
+            (property.name.clone(), value)
+        };
+
+        unsafe {
+            if GetFocus() == editor.hwnd {
+                _ = SetFocus(Some(self.list.handle()));
+            }
+            _ = RemoveWindowSubclass(editor.hwnd, Some(editor_subclass_proc), EDITOR_SUBCLASS_ID);
+            _ = DestroyWindow(editor.hwnd);
+        }
+
+        if commit {
+            self.set_value(&name, value);
+        }
+    }
+
+    fn set_value(&self, name: &str, value: core::result::Result<PropertyValue, String>) {
+        let Some(object) = self.object.borrow().clone() else {
+            return;
+        };
+        let result = value.and_then(|value| object.borrow_mut().set_property(name, value));
+        match result {
+            Ok(()) => {
+                self.refresh();
+                if let Some(handler) = self.property_changed_handler.get() {
+                    handler(name);
+                }
+            }
+            Err(message) => self.show_error(&message),
+        }
+    }
+
+    fn show_error(&self, message: &str) {
+        unsafe {
+            let message_wstr = WCString::from_str_truncate(message);
+            let owner = GetAncestor(self.handle(), GA_ROOT);
+            MessageBoxW(
+                Some(owner),
+                PCWSTR::from_raw(message_wstr.as_ptr()),
+                w!("Invalid Property Value"),
+                MB_OK | MB_ICONWARNING,
+            );
+        }
+    }
+
+    unsafe fn list_notify(&self, nmhdr: *mut NMHDR) -> Option<LRESULT> {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/nm-click-list-view
+            NM_CLICK => {
+                let item = &*(nmhdr as *const NMITEMACTIVATE);
+                if item.iItem >= 0 {
+                    self.activate_row(item.iItem as usize, item.iSubItem == 1);
+                }
+                Some(LRESULT(0))
+            }
+
+            NM_DBLCLK => {
+                // The first click has already handled the value column and
+                // category headings.
+                let item = &*(nmhdr as *const NMITEMACTIVATE);
+                let is_property = self
+                    .model
+                    .borrow()
+                    .property_at(item.iItem as usize)
+                    .is_some();
+                if item.iItem >= 0 && item.iSubItem == 0 && is_property {
+                    self.activate_row(item.iItem as usize, true);
+                }
+                Some(LRESULT(0))
+            }
+
+            NM_RETURN => {
+                if let Some(row) = self.list.iter_selected_items().next() {
+                    self.activate_row(row, true);
+                }
+                Some(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-keydown
+            LVN_KEYDOWN => {
+                let key = &*(nmhdr as *const NMLVKEYDOWN);
+                let row = self.list.iter_selected_items().next();
+                if let Some(row) = row {
+                    if key.wVKey == VK_F2.0 {
+                        self.activate_row(row, true);
+                    } else if key.wVKey == VK_SPACE.0 {
+                        let is_bool = matches!(
+                            self.model.borrow().property_at(row),
+                            Some(Property {
+                                value: PropertyValue::Bool(_),
+                                ..
+                            })
+                        );
+                        if is_bool {
+                            self.activate_row(row, true);
+                        }
+                    }
+                }
+                Some(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/lvn-beginscroll
+            LVN_BEGINSCROLL => {
+                self.end_edit(true);
+                Some(LRESULT(0))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+impl Drop for PropertyGrid {
+    fn drop(&mut self) {
+        unsafe {
+            SetWindowLongPtrW(self.control.hwnd, WINDOW_LONG_PTR_INDEX(0), 0);
+        }
+    }
+}
+
+static REGISTER_CLASS_ONCE: Once = Once::new();
+static mut CLASS_ATOM: ATOM = 0;
+
+fn register_class_lazy() -> ATOM {
+    REGISTER_CLASS_ONCE.call_once(|| unsafe {
+        let instance = get_instance();
+
+        let mut class_ex: WNDCLASSEXW = zeroed();
+        class_ex.cbSize = size_of::<WNDCLASSEXW>() as u32;
+        class_ex.hInstance = instance;
+        class_ex.lpszClassName = w!("rust_forms.property_grid");
+        class_ex.style = CS_HREDRAW | CS_VREDRAW;
+        class_ex.hbrBackground = HBRUSH((COLOR_WINDOW.0 + 1) as _);
+        class_ex.lpfnWndProc = Some(property_grid_wndproc);
+        class_ex.hCursor = LoadCursorW(None, IDC_ARROW).unwrap();
+        class_ex.cbWndExtra = size_of::<*mut c_void>() as i32;
+
+        let atom = RegisterClassExW(&class_ex);
+        if atom == 0 {
+            panic!("Failed to register window class");
+        }
+        CLASS_ATOM = atom;
+    });
+
+    unsafe { CLASS_ATOM }
+}
+
+unsafe extern "system" fn property_grid_wndproc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let this_ptr = GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as *const PropertyGrid;
+    if this_ptr.is_null() {
+        return DefWindowProcW(hwnd, message, wparam, lparam);
+    }
+    let this = &*this_ptr;
+
+    match message {
+        WM_SIZE => {
+            let width = (lparam.0 & 0xffff) as i32;
+            let height = ((lparam.0 >> 16) & 0xffff) as i32;
+            this.layout(width, height);
+            LRESULT(0)
+        }
+
+        WM_SETFOCUS => {
+            _ = SetFocus(Some(this.list.handle()));
+            LRESULT(0)
+        }
+
+        WM_NOTIFY => {
+            let nmhdr = lparam.0 as *mut NMHDR;
+            if (*nmhdr).hwndFrom == this.list.handle() {
+                if let Some(result) = this.list_notify(nmhdr) {
+                    return result;
+                }
+            }
+            match dispatch_notify(nmhdr) {
+                NotifyResult::Consumed(result) => result,
+                NotifyResult::NotConsumed => LRESULT(0),
+            }
+        }
+
+        WM_COMMAND => {
+            let editor = this.editor.get();
+            if editor.is_some_and(|editor| editor.hwnd.0 == lparam.0 as *mut c_void) {
+                let code = (wparam.0 >> 16) as u32;
+                if code == EN_KILLFOCUS || code == CBN_KILLFOCUS || code == CBN_SELENDOK {
+                    // lParam is the editor's window.
+                    _ = PostMessageW(
+                        Some(hwnd),
+                        WM_COMMIT_EDIT,
+                        WPARAM(lparam.0 as usize),
+                        LPARAM(0),
+                    );
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_COMMIT_EDIT | WM_CANCEL_EDIT => {
+            let editor = this.editor.get();
+            if editor.is_some_and(|editor| editor.hwnd.0 as usize == wparam.0) {
+                this.end_edit(message == WM_COMMIT_EDIT);
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, message, wparam, lparam),
+    }
+}
+
+/// Handles Enter and Escape in an editor.
+unsafe extern "system" fn editor_subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    _ref_data: usize,
+) -> LRESULT {
+    match message {
+        // Keep Enter and Escape away from IsDialogMessage.
+        WM_GETDLGCODE => {
+            let result = DefSubclassProc(hwnd, message, wparam, lparam);
+            return LRESULT(result.0 | DLGC_WANTALLKEYS as isize);
+        }
+
+        WM_KEYDOWN if wparam.0 == VK_RETURN.0 as usize || wparam.0 == VK_ESCAPE.0 as usize => {
+            let parent = GetParent(hwnd).unwrap_or_default();
+            let message = if wparam.0 == VK_RETURN.0 as usize {
+                WM_COMMIT_EDIT
+            } else {
+                WM_CANCEL_EDIT
+            };
+            _ = PostMessageW(Some(parent), message, WPARAM(hwnd.0 as usize), LPARAM(0));
+            return LRESULT(0);
+        }
+
+        // Without this, the edit control beeps.
+        WM_CHAR if wparam.0 == '\r' as usize || wparam.0 == 0x1b => {
+            return LRESULT(0);
+        }
+
+        _ => {}
+    }
+    DefSubclassProc(hwnd, message, wparam, lparam)
+}
@@ -1,20 +1,31 @@
+use crate::ColorRef;
 use crate::ControlState;
+use crate::Font;
 use crate::Form;
-use std::mem::zeroed;
+use std::mem::{size_of, zeroed};
 use std::rc::Rc;
 use std::sync::Once;
 use widestring::U16CString;
 use windows::core::w;
 use windows::core::PCWSTR;
+use windows::Win32::Foundation::COLORREF;
 use windows::Win32::Foundation::LPARAM;
 use windows::Win32::Foundation::WPARAM;
 use windows::Win32::System::LibraryLoader::LoadLibraryW;
+use windows::Win32::UI::Controls::RichEdit::{
+    CFE_AUTOBACKCOLOR, CFE_AUTOCOLOR, CFE_BOLD, CFE_EFFECTS, CFE_ITALIC, CFE_STRIKEOUT,
+    CFE_UNDERLINE, CFM_BACKCOLOR, CFM_BOLD, CFM_COLOR, CFM_ITALIC, CFM_STRIKEOUT, CFM_UNDERLINE,
+    CHARFORMAT2W, CHARRANGE, EM_EXLIMITTEXT, EM_EXSETSEL, EM_SETBKGNDCOLOR, EM_SETCHARFORMAT,
+    EM_SETTARGETDEVICE, SCF_SELECTION, ST_SELECTION,
+};
+use windows::Win32::UI::Controls::{EM_GETLINECOUNT, EM_LINEINDEX};
 use windows::Win32::UI::WindowsAndMessaging::SendMessageW;
 use windows::Win32::UI::WindowsAndMessaging::WINDOW_STYLE;
 use windows::Win32::UI::WindowsAndMessaging::WM_USER;
 use windows::Win32::UI::WindowsAndMessaging::{
     CreateWindowExW, ES_MULTILINE, WINDOW_EX_STYLE, WS_BORDER, WS_CHILD, WS_TABSTOP, WS_VISIBLE,
 };
+use windows::Win32::UI::WindowsAndMessaging::{SB_BOTTOM, WM_SETFONT, WM_VSCROLL};
 
 pub struct RichEdit {
     control: ControlState,
@@ -27,13 +38,35 @@ impl core::ops::Deref for RichEdit {
     }
 }
 
+/// The format of characters in a `RichEdit`. Colors that are `None` use the
+/// control's default colors.
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub struct CharFormat {
+    pub text_color: Option<ColorRef>,
+    pub back_color: Option<ColorRef>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikeout: bool,
+}
+
 impl RichEdit {
     pub fn new(parent: &ControlState) -> Rc<RichEdit> {
+        Self::with_style(parent, WINDOW_STYLE(0))
+    }
+
+    /// Creates the control with `extra_style` added to the usual styles, for styles
+    /// such as `WS_VSCROLL` that cannot be changed after the control is created.
+    pub(crate) fn with_style(parent: &ControlState, extra_style: WINDOW_STYLE) -> Rc<RichEdit> {
         load_rich_edit_dll();
 
         unsafe {
-            let style =
-                WINDOW_STYLE(ES_MULTILINE as _) | WS_VISIBLE | WS_CHILD | WS_BORDER | WS_TABSTOP;
+            let style = WINDOW_STYLE(ES_MULTILINE as _)
+                | WS_VISIBLE
+                | WS_CHILD
+                | WS_BORDER
+                | WS_TABSTOP
+                | extra_style;
             let ex_style = WINDOW_EX_STYLE(0);
 
             let handle = CreateWindowExW(
@@ -75,6 +108,169 @@ impl RichEdit {
         }
     }
 
+    pub fn set_font(&self, font: &Font) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                WM_SETFONT,
+                Some(WPARAM(font.hfont.0 as usize)),
+                Some(LPARAM(1)),
+            );
+        }
+    }
+
+    /// Sets the maximum number of characters the control holds. The default is
+    /// 32,767, which is too small for a log or console.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/em-exlimittext>
+    pub fn set_text_limit(&self, limit: usize) {
+        unsafe {
+            _ = SendMessageW(
+                self.handle(),
+                EM_EXLIMITTEXT,
+                None,
+                Some(LPARAM(limit as isize)),
+            );
+        }
+    }
+
+    /// Turns word wrapping on or off. When it is off, each paragraph is one line.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/em-settargetdevice>
+    pub fn set_word_wrap(&self, value: bool) {
+        unsafe {
+            _ = SendMessageW(
+                self.handle(),
+                EM_SETTARGETDEVICE,
+                Some(WPARAM(0)),
+                Some(LPARAM(if value { 0 } else { 1 })),
+            );
+        }
+    }
+
+    /// Sets the background color, or restores the system window color if `None`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/em-setbkgndcolor>
+    pub fn set_background_color(&self, color: Option<ColorRef>) {
+        unsafe {
+            _ = SendMessageW(
+                self.handle(),
+                EM_SETBKGNDCOLOR,
+                Some(WPARAM(color.is_none() as usize)),
+                Some(LPARAM(color.map(|c| c.as_u32()).unwrap_or(0) as isize)),
+            );
+        }
+    }
+
+    /// Selects the characters from `start` up to `end`. An `end` of -1 selects to
+    /// the end of the text, and a `start` of -1 puts the caret at the end.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/em-exsetsel>
+    pub fn set_selection(&self, start: i32, end: i32) {
+        unsafe {
+            let range = CHARRANGE {
+                cpMin: start,
+                cpMax: end,
+            };
+            _ = SendMessageW(
+                self.handle(),
+                EM_EXSETSEL,
+                None,
+                Some(LPARAM(&range as *const CHARRANGE as isize)),
+            );
+        }
+    }
+
+    /// Replaces the selected text with `s`, which takes the selection's format.
+    pub fn replace_selection(&self, s: &str) {
+        let ws = U16CString::from_str_truncate(s);
+        unsafe {
+            let set_text: SETTEXTEX = SETTEXTEX {
+                flags: ST_SELECTION,
+                codepage: SETTEXT_CODEPAGE_UNICODE,
+            };
+
+            _ = SendMessageW(
+                self.handle(),
+                EM_SETTEXTEX,
+                Some(WPARAM(&set_text as *const _ as usize)),
+                Some(LPARAM(ws.as_ptr() as isize)),
+            );
+        }
+    }
+
+    /// Sets the format of the selected text. If the selection is empty, this sets
+    /// the format of text inserted at the caret.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/em-setcharformat>
+    pub fn set_selection_format(&self, format: &CharFormat) {
+        unsafe {
+            let mut cf: CHARFORMAT2W = zeroed();
+            cf.Base.cbSize = size_of::<CHARFORMAT2W>() as u32;
+            cf.Base.dwMask =
+                CFM_COLOR | CFM_BACKCOLOR | CFM_BOLD | CFM_ITALIC | CFM_UNDERLINE | CFM_STRIKEOUT;
+            let mut effects = CFE_EFFECTS(0);
+            for (flag, value) in [
+                (CFE_BOLD, format.bold),
+                (CFE_ITALIC, format.italic),
+                (CFE_UNDERLINE, format.underline),
+                (CFE_STRIKEOUT, format.strikeout),
+                (CFE_AUTOCOLOR, format.text_color.is_none()),
+                (CFE_AUTOBACKCOLOR, format.back_color.is_none()),
+            ] {
+                if value {
+                    effects |= flag;
+                }
+            }
+            cf.Base.dwEffects = effects;
+            if let Some(color) = format.text_color {
+                cf.Base.crTextColor = COLORREF(color.as_u32());
+            }
+            if let Some(color) = format.back_color {
+                cf.crBackColor = COLORREF(color.as_u32());
+            }
+            _ = SendMessageW(
+                self.handle(),
+                EM_SETCHARFORMAT,
+                Some(WPARAM(SCF_SELECTION as usize)),
+                Some(LPARAM(&cf as *const CHARFORMAT2W as isize)),
+            );
+        }
+    }
+
+    /// Adds `s` at the end of the text, with the given format. The selection is left
+    /// at the end of the text.
+    pub fn append_text(&self, s: &str, format: &CharFormat) {
+        self.set_selection(-1, -1);
+        self.set_selection_format(format);
+        self.replace_selection(s);
+    }
+
+    /// The number of lines. With word wrapping on, this counts wrapped lines.
+    pub fn line_count(&self) -> usize {
+        unsafe { SendMessageW(self.handle(), EM_GETLINECOUNT, None, None).0 as usize }
+    }
+
+    /// The character index of the start of `line`, or `None` if there is no such
+    /// line.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        unsafe {
+            let index = SendMessageW(self.handle(), EM_LINEINDEX, Some(WPARAM(line)), None).0;
+            usize::try_from(index).ok()
+        }
+    }
+
+    pub fn scroll_to_end(&self) {
+        unsafe {
+            _ = SendMessageW(
+                self.handle(),
+                WM_VSCROLL,
+                Some(WPARAM(SB_BOTTOM.0 as usize)),
+                None,
+            );
+        }
+    }
+
     // https://learn.microsoft.com/en-us/windows/win32/controls/em-setoptions
     fn set_option_bool(&self, mask: u32, value: bool) {
         unsafe {
@@ -0,0 +1,537 @@
+//! A text box for typing a search query, which reports the query once the user
+//! stops typing.
+
+use super::*;
+use std::cell::OnceCell;
+use std::time::{Duration, Instant};
+use windows::core::w;
+use windows::Win32::UI::Input::KeyboardAndMouse::{SetFocus, VK_ESCAPE, VK_RETURN};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+
+/// An `Edit` for search queries, with a cue banner and a button that clears it.
+///
+/// Changes to the text are reported to the `query_changed_handler` once the user
+/// has stopped typing for the debounce delay, using a timer on the UI thread.
+/// Enter reports the query at once and calls the `submit_handler`. Escape clears
+/// the text, or calls the `escape_handler` if there is no text.
+pub struct SearchBox {
+    edit: Rc<Edit>,
+    clear_button: HWND,
+    debouncer: RefCell<QueryDebouncer>,
+    query_changed_handler: OnceCell<Box<dyn Fn(&str)>>,
+    submit_handler: OnceCell<Box<dyn Fn(&str)>>,
+    escape_handler: OnceCell<Box<dyn Fn()>>,
+}
+
+impl core::ops::Deref for SearchBox {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.edit
+    }
+}
+
+/// The debounce delay that a `SearchBox` starts with.
+pub const DEFAULT_SEARCH_DELAY: Duration = Duration::from_millis(300);
+
+const SEARCH_TIMER_ID: usize = 1;
+const SEARCH_SUBCLASS_ID: usize = 1;
+
+impl SearchBox {
+    pub fn new(parent: &Rc<Form>) -> Rc<SearchBox> {
+        let edit = Edit::new(parent);
+        unsafe {
+            let hwnd = edit.handle();
+            // The clear button is a child of the edit control, so keep the edit
+            // control from painting over it.
+            let style = GetWindowLongPtrW(hwnd, GWL_STYLE);
+            SetWindowLongPtrW(hwnd, GWL_STYLE, style | WS_CLIPCHILDREN.0 as isize);
+
+            // "×", which every font has.
+            let clear_button = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                w!("Button"),
+                w!("\u{00d7}"),
+                WS_CHILD | WINDOW_STYLE(BS_PUSHBUTTON as u32 | BS_FLAT as u32),
+                0,
+                0,
+                0,
+                0,
+                Some(hwnd),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+            let font = SendMessageW(hwnd, WM_GETFONT, None, None);
+            SendMessageW(
+                clear_button,
+                WM_SETFONT,
+                Some(WPARAM(font.0 as usize)),
+                None,
+            );
+
+            let this = Rc::new(SearchBox {
+                edit,
+                clear_button,
+                debouncer: RefCell::new(QueryDebouncer::new(DEFAULT_SEARCH_DELAY)),
+                query_changed_handler: OnceCell::new(),
+                submit_handler: OnceCell::new(),
+                escape_handler: OnceCell::new(),
+            });
+
+            let this_ptr: *const SearchBox = &*this;
+            _ = SetWindowSubclass(
+                hwnd,
+                Some(search_box_subclass_proc),
+                SEARCH_SUBCLASS_ID,
+                this_ptr as usize,
+            );
+            register_command_handler(hwnd, Rc::downgrade(&this) as Weak<dyn CommandHandlerTrait>);
+            this
+        }
+    }
+
+    /// The `Edit` that the search box is built on.
+    pub fn edit(&self) -> &Rc<Edit> {
+        &self.edit
+    }
+
+    /// Sets the text that is shown in gray while the search box is empty, such as
+    /// "Search".
+    pub fn set_cue_banner(&self, text: &str) {
+        let text = WCString::from_str_truncate(text);
+        unsafe {
+            // wparam = 1 keeps the banner shown while the search box has the focus.
+            SendMessageW(
+                self.handle(),
+                EM_SETCUEBANNER,
+                Some(WPARAM(1)),
+                Some(LPARAM(text.as_ptr() as isize)),
+            );
+        }
+    }
+
+    /// Sets how long the user must stop typing before the query is reported.
+    pub fn set_delay(&self, delay: Duration) {
+        self.debouncer.borrow_mut().set_delay(delay);
+    }
+
+    pub fn text(&self) -> String {
+        self.edit.get_text()
+    }
+
+    /// Sets the text. The query is reported after the debounce delay, as if it had
+    /// been typed.
+    pub fn set_text(&self, text: &str) {
+        self.edit.set_text(text);
+    }
+
+    /// The query that was last reported to the `query_changed_handler`.
+    pub fn query(&self) -> String {
+        self.debouncer.borrow().query().to_string()
+    }
+
+    /// Clears the text, and reports the empty query at once.
+    pub fn clear(&self) {
+        self.edit.set_text("");
+        self.flush();
+    }
+
+    /// Sets the handler that is given the query, once the user stops typing. It is
+    /// not called if the text ends up the same as the last query.
+    pub fn query_changed_handler<F>(&self, handler: F)
+    where
+        F: Fn(&str) + 'static,
+    {
+        let result = self.query_changed_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call query_changed_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for Enter. The query has already been reported when it is
+    /// called.
+    pub fn submit_handler<F>(&self, handler: F)
+    where
+        F: Fn(&str) + 'static,
+    {
+        let result = self.submit_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call submit_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for Escape, when the search box is already empty.
+    pub fn escape_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.escape_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call escape_handler() more than once"
+        );
+    }
+
+    fn text_changed(&self) {
+        let text = self.text();
+        unsafe {
+            _ = ShowWindow(
+                self.clear_button,
+                if text.is_empty() { SW_HIDE } else { SW_SHOWNA },
+            );
+        }
+        let delay = self
+            .debouncer
+            .borrow_mut()
+            .text_changed(&text, Instant::now());
+        self.set_timer(delay);
+    }
+
+    fn set_timer(&self, delay: Duration) {
+        let ms = delay
+            .as_millis()
+            .clamp(USER_TIMER_MINIMUM as u128, u32::MAX as u128);
+        unsafe {
+            // This replaces the timer, if it is already set.
+            SetTimer(Some(self.handle()), SEARCH_TIMER_ID, ms as u32, None);
+        }
+    }
+
+    fn timer_elapsed(&self) {
+        unsafe {
+            _ = KillTimer(Some(self.handle()), SEARCH_TIMER_ID);
+        }
+        // The debouncer is not borrowed while the handler runs, so that it can
+        // change the text.
+        let poll = self.debouncer.borrow_mut().poll(Instant::now());
+        match poll {
+            DebouncePoll::Idle => {}
+            DebouncePoll::Wait(delay) => self.set_timer(delay),
+            DebouncePoll::Changed(query) => self.report(&query),
+        }
+    }
+
+    /// Reports the pending query now, instead of when the timer elapses.
+    fn flush(&self) {
+        unsafe {
+            _ = KillTimer(Some(self.handle()), SEARCH_TIMER_ID);
+        }
+        let text = self.text();
+        let changed = {
+            let mut debouncer = self.debouncer.borrow_mut();
+            debouncer.text_changed(&text, Instant::now());
+            debouncer.flush()
+        };
+        if let Some(query) = changed {
+            self.report(&query);
+        }
+    }
+
+    fn report(&self, query: &str) {
+        debug!("search query: {:?}", query);
+        if let Some(handler) = self.query_changed_handler.get() {
+            handler(query);
+        }
+    }
+
+    fn submit(&self) {
+        self.flush();
+        if let Some(handler) = self.submit_handler.get() {
+            handler(&self.query());
+        }
+    }
+
+    fn escape(&self) {
+        if !self.text().is_empty() {
+            self.clear();
+        } else if let Some(handler) = self.escape_handler.get() {
+            handler();
+        }
+    }
+
+    fn place_clear_button(&self) {
+        unsafe {
+            let mut client: RECT = zeroed();
+            _ = GetClientRect(self.handle(), &mut client);
+            let size = client.bottom - client.top;
+            _ = SetWindowPos(
+                self.clear_button,
+                None,
+                client.right - size,
+                0,
+                size,
+                size,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+            // Keep the text from running under the button.
+            SendMessageW(
+                self.handle(),
+                EM_SETMARGINS,
+                Some(WPARAM(EC_RIGHTMARGIN as usize)),
+                Some(LPARAM((size as isize) << 16)),
+            );
+        }
+    }
+}
+
+impl CommandHandlerTrait for SearchBox {
+    fn wm_command(&self, code: u32) -> bool {
+        if code == EN_CHANGE {
+            self.text_changed();
+        }
+        false
+    }
+}
+
+impl Drop for SearchBox {
+    fn drop(&mut self) {
+        unsafe {
+            _ = KillTimer(Some(self.handle()), SEARCH_TIMER_ID);
+            _ = RemoveWindowSubclass(
+                self.handle(),
+                Some(search_box_subclass_proc),
+                SEARCH_SUBCLASS_ID,
+            );
+        }
+    }
+}
+
+unsafe extern "system" fn search_box_subclass_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _subclass_id: usize,
+    ref_data: usize,
+) -> LRESULT {
+    let this: &SearchBox = &*(ref_data as *const SearchBox);
+    match message {
+        // Take Enter and Escape, so that they do not press the default or cancel
+        // button of the form.
+        WM_GETDLGCODE => {
+            let result = DefSubclassProc(hwnd, message, wparam, lparam);
+            let msg = lparam.0 as *const MSG;
+            if !msg.is_null()
+                && (*msg).message == WM_KEYDOWN
+                && ((*msg).wParam.0 == VK_RETURN.0 as usize
+                    || (*msg).wParam.0 == VK_ESCAPE.0 as usize)
+            {
+                return LRESULT(result.0 | DLGC_WANTMESSAGE as isize);
+            }
+            return result;
+        }
+
+        WM_KEYDOWN if wparam.0 == VK_RETURN.0 as usize => {
+            this.submit();
+            return LRESULT(0);
+        }
+
+        WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+            this.escape();
+            return LRESULT(0);
+        }
+
+        // Without this, the edit control beeps.
+        WM_CHAR if wparam.0 == '\r' as usize || wparam.0 == 0x1b => {
+            return LRESULT(0);
+        }
+
+        WM_TIMER if wparam.0 == SEARCH_TIMER_ID => {
+            this.timer_elapsed();
+            return LRESULT(0);
+        }
+
+        WM_COMMAND if HWND(lparam.0 as *mut c_void) == this.clear_button => {
+            if (wparam.0 >> 16) as u32 == BN_CLICKED {
+                this.clear();
+                _ = SetFocus(Some(hwnd));
+            }
+            return LRESULT(0);
+        }
+
+        WM_SIZE => {
+            let result = DefSubclassProc(hwnd, message, wparam, lparam);
+            this.place_clear_button();
+            return result;
+        }
+
+        _ => {}
+    }
+    DefSubclassProc(hwnd, message, wparam, lparam)
+}
+
+/// The result of `QueryDebouncer::poll`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DebouncePoll {
+    /// No text change is waiting to be reported.
+    Idle,
+    /// The text changed too recently. Poll again after this long.
+    Wait(Duration),
+    /// The user has stopped typing, and this is the new query.
+    Changed(String),
+}
+
+/// Decides when the text of a search box should be reported as a new query: once
+/// it has not changed for `delay`, and only if it differs from the last query.
+///
+/// The current time is passed in to each method, so this can be driven by any
+/// clock.
+#[derive(Clone, Debug)]
+pub struct QueryDebouncer {
+    delay: Duration,
+    /// The text that is waiting to be reported, and when it can be reported.
+    pending: Option<(String, Instant)>,
+    query: String,
+}
+
+impl QueryDebouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: None,
+            query: String::new(),
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Sets the delay. A text change that is already waiting keeps its deadline.
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// The query that was last reported.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Returns true if a text change is waiting to be reported.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Records that the text changed at `now`. Returns how long to wait before
+    /// calling `poll`.
+    pub fn text_changed(&mut self, text: &str, now: Instant) -> Duration {
+        self.pending = Some((text.to_string(), now + self.delay));
+        self.delay
+    }
+
+    /// Checks whether the text that is waiting should be reported at `now`.
+    pub fn poll(&mut self, now: Instant) -> DebouncePoll {
+        match &self.pending {
+            None => DebouncePoll::Idle,
+            Some((_, deadline)) if now < *deadline => DebouncePoll::Wait(*deadline - now),
+            Some(_) => match self.flush() {
+                Some(query) => DebouncePoll::Changed(query),
+                None => DebouncePoll::Idle,
+            },
+        }
+    }
+
+    /// Reports the text that is waiting, without waiting for the delay. Returns
+    /// the new query, or `None` if nothing is waiting or the query is unchanged.
+    pub fn flush(&mut self) -> Option<String> {
+        let (text, _) = self.pending.take()?;
+        if text == self.query {
+            return None;
+        }
+        self.query = text.clone();
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(300);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn reports_after_delay() {
+        let start = Instant::now();
+        let mut debouncer = QueryDebouncer::new(DELAY);
+        assert_eq!(debouncer.poll(start), DebouncePoll::Idle);
+        assert_eq!(debouncer.text_changed("ab", start), DELAY);
+        assert!(debouncer.is_pending());
+        assert_eq!(debouncer.poll(start + ms(100)), DebouncePoll::Wait(ms(200)));
+        assert_eq!(
+            debouncer.poll(start + ms(300)),
+            DebouncePoll::Changed("ab".to_string())
+        );
+        assert_eq!(debouncer.query(), "ab");
+        assert!(!debouncer.is_pending());
+        assert_eq!(debouncer.poll(start + ms(400)), DebouncePoll::Idle);
+    }
+
+    #[test]
+    fn typing_again_resets_deadline() {
+        let start = Instant::now();
+        let mut debouncer = QueryDebouncer::new(DELAY);
+        debouncer.text_changed("a", start);
+        debouncer.text_changed("ab", start + ms(200));
+        // The first deadline has passed, but the second has not.
+        assert_eq!(debouncer.poll(start + ms(300)), DebouncePoll::Wait(ms(200)));
+        debouncer.text_changed("abc", start + ms(450));
+        assert_eq!(debouncer.poll(start + ms(500)), DebouncePoll::Wait(ms(250)));
+        assert_eq!(
+            debouncer.poll(start + ms(750)),
+            DebouncePoll::Changed("abc".to_string())
+        );
+    }
+
+    #[test]
+    fn unchanged_query_is_not_reported() {
+        let start = Instant::now();
+        let mut debouncer = QueryDebouncer::new(DELAY);
+        debouncer.text_changed("ab", start);
+        assert_eq!(
+            debouncer.poll(start + DELAY),
+            DebouncePoll::Changed("ab".to_string())
+        );
+        // Typing a character and deleting it gives the same query.
+        debouncer.text_changed("abc", start + ms(400));
+        debouncer.text_changed("ab", start + ms(500));
+        assert_eq!(debouncer.poll(start + ms(900)), DebouncePoll::Idle);
+        assert!(!debouncer.is_pending());
+        assert_eq!(debouncer.query(), "ab");
+    }
+
+    #[test]
+    fn flush_reports_at_once() {
+        let start = Instant::now();
+        let mut debouncer = QueryDebouncer::new(DELAY);
+        assert_eq!(debouncer.flush(), None);
+        debouncer.text_changed("query", start);
+        assert_eq!(debouncer.flush(), Some("query".to_string()));
+        assert_eq!(debouncer.poll(start + DELAY), DebouncePoll::Idle);
+        debouncer.text_changed("query", start + ms(10));
+        assert_eq!(debouncer.flush(), None);
+        debouncer.text_changed("", start + ms(20));
+        assert_eq!(debouncer.flush(), Some(String::new()));
+    }
+
+    #[test]
+    fn set_delay_keeps_pending_deadline() {
+        let start = Instant::now();
+        let mut debouncer = QueryDebouncer::new(DELAY);
+        debouncer.text_changed("a", start);
+        debouncer.set_delay(ms(50));
+        assert_eq!(debouncer.delay(), ms(50));
+        assert_eq!(debouncer.poll(start + ms(100)), DebouncePoll::Wait(ms(200)));
+        assert_eq!(debouncer.text_changed("ab", start + ms(100)), ms(50));
+        assert_eq!(
+            debouncer.poll(start + ms(150)),
+            DebouncePoll::Changed("ab".to_string())
+        );
+    }
+}
@@ -1,9 +1,20 @@
 // https://docs.microsoft.com/en-us/windows/win32/controls/status-bars
 
 use super::*;
+use crate::gdi::dc::Dc;
+use std::cell::OnceCell;
+use windows::Win32::UI::Controls::{NM_CLICK, NM_DBLCLK};
 
+/// The status bar at the bottom of a form. Use `Form::create_status_bar`.
+///
+/// A new status bar shows a single text, set with `set_status`. Use `set_panes`
+/// to divide it into panes, such as "row count | filter | elapsed time".
 pub struct StatusBar {
     control: ControlState,
+    panes: RefCell<Vec<Pane>>,
+    click_handler: OnceCell<Box<dyn Fn(usize)>>,
+    double_click_handler: OnceCell<Box<dyn Fn(usize)>>,
+    draw_handler: OnceCell<Box<dyn Fn(usize, &Dc, &Rect)>>,
 }
 
 impl core::ops::Deref for StatusBar {
@@ -13,6 +24,24 @@ impl core::ops::Deref for StatusBar {
     }
 }
 
+/// The width of a status bar pane.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaneWidth {
+    /// A width in pixels.
+    Fixed(i32),
+    /// Takes a share of the width that is left after the fixed panes. The space is
+    /// divided between stretch panes in proportion to their weights.
+    Stretch(f32),
+}
+
+struct Pane {
+    width: PaneWidth,
+    text: String,
+    owner_draw: bool,
+    /// Owned by the status bar, and destroyed when it is replaced.
+    icon: Option<HICON>,
+}
+
 const STATUSCLASSNAME: &str = "msctls_statusbar32";
 
 impl StatusBar {
@@ -35,6 +64,10 @@ impl StatusBar {
 
             let state: Rc<StatusBar> = Rc::new(StatusBar {
                 control: ControlState::new(hwnd),
+                panes: RefCell::new(Vec::new()),
+                click_handler: OnceCell::new(),
+                double_click_handler: OnceCell::new(),
+                draw_handler: OnceCell::new(),
             });
 
             _ = SendMessageW(
@@ -44,12 +77,20 @@ impl StatusBar {
                 Some(LPARAM(0)),
             );
 
+            register_notify_handler(hwnd, Rc::downgrade(&state) as Weak<dyn NotifyHandlerTrait>);
+
             form.invalidate_layout();
             state
         }
     }
 
+    /// Sets the text of a status bar that has no panes. If it has panes, this sets
+    /// the text of the first pane.
     pub fn set_status(&self, s: &str) {
+        if !self.panes.borrow().is_empty() {
+            self.set_pane_text(0, s);
+            return;
+        }
         unsafe {
             let ws = U16CString::from_str_truncate(s);
 
@@ -61,4 +102,336 @@ impl StatusBar {
             );
         }
     }
+
+    /// Divides the status bar into panes. The text, icons and owner-draw state of
+    /// panes that already existed are kept. An empty list returns to showing the
+    /// single text of `set_status`.
+    pub fn set_panes(&self, widths: &[PaneWidth]) {
+        {
+            let mut panes = self.panes.borrow_mut();
+            let keep = widths.len().min(panes.len());
+            for pane in panes.drain(keep..) {
+                destroy_pane_icon(&pane);
+            }
+            for (i, &width) in widths.iter().enumerate() {
+                match panes.get_mut(i) {
+                    Some(pane) => pane.width = width,
+                    None => panes.push(Pane {
+                        width,
+                        text: String::new(),
+                        owner_draw: false,
+                        icon: None,
+                    }),
+                }
+            }
+        }
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                SB_SIMPLE,
+                Some(WPARAM(widths.is_empty() as usize)),
+                None,
+            );
+        }
+        self.update_parts();
+        for pane in 0..widths.len() {
+            self.send_pane_text(pane);
+        }
+    }
+
+    pub fn num_panes(&self) -> usize {
+        self.panes.borrow().len()
+    }
+
+    pub fn set_pane_text(&self, pane: usize, text: &str) {
+        match self.panes.borrow_mut().get_mut(pane) {
+            Some(p) => p.text = text.to_string(),
+            None => return,
+        }
+        self.send_pane_text(pane);
+    }
+
+    pub fn pane_text(&self, pane: usize) -> Option<String> {
+        self.panes.borrow().get(pane).map(|p| p.text.clone())
+    }
+
+    /// Shows an icon from an image list on the left of a pane, or removes it. The
+    /// status bar keeps its own copy of the icon.
+    pub fn set_pane_icon(&self, pane: usize, icon: Option<(&ImageList, u32)>) {
+        let hicon = match icon {
+            Some((image_list, index)) => match image_list.get_hicon(index) {
+                Ok(hicon) => Some(hicon),
+                Err(e) => {
+                    warn!("failed to get icon {index} from image list: {e:?}");
+                    return;
+                }
+            },
+            None => None,
+        };
+        let mut panes = self.panes.borrow_mut();
+        let Some(p) = panes.get_mut(pane) else {
+            if let Some(hicon) = hicon {
+                unsafe {
+                    _ = DestroyIcon(hicon);
+                }
+            }
+            return;
+        };
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                SB_SETICON,
+                Some(WPARAM(pane)),
+                Some(LPARAM(hicon.map_or(0, |h| h.0 as isize))),
+            );
+        }
+        destroy_pane_icon(p);
+        p.icon = hicon;
+    }
+
+    /// Makes a pane owner-drawn: instead of showing its text, the status bar calls
+    /// the draw handler to paint it.
+    pub fn set_pane_owner_draw(&self, pane: usize, owner_draw: bool) {
+        match self.panes.borrow_mut().get_mut(pane) {
+            Some(p) => p.owner_draw = owner_draw,
+            None => return,
+        }
+        self.send_pane_text(pane);
+    }
+
+    /// Redraws a pane, such as an owner-drawn pane whose contents changed.
+    pub fn invalidate_pane(&self, pane: usize) {
+        unsafe {
+            let mut rect: RECT = zeroed();
+            let found = SendMessageW(
+                self.handle(),
+                SB_GETRECT,
+                Some(WPARAM(pane)),
+                Some(LPARAM(&mut rect as *mut RECT as isize)),
+            );
+            if found.0 != 0 {
+                _ = InvalidateRect(Some(self.handle()), Some(&rect), true);
+            }
+        }
+    }
+
+    /// Sets the handler that is called when a pane is clicked, with the index of the
+    /// pane. Without panes, the index is 0.
+    pub fn click_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        let result = self.click_handler.set(Box::new(handler));
+        assert!(result.is_ok(), "cannot call click_handler() more than once");
+    }
+
+    /// Sets the handler that is called when a pane is double-clicked, with the index
+    /// of the pane. Without panes, the index is 0.
+    pub fn double_click_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        let result = self.double_click_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call double_click_handler() more than once"
+        );
+    }
+
+    /// Sets the handler that paints owner-drawn panes. It is called with the index
+    /// of the pane and the rectangle to paint, in status bar coordinates.
+    pub fn draw_handler<F>(&self, handler: F)
+    where
+        F: Fn(usize, &Dc, &Rect) + 'static,
+    {
+        let result = self.draw_handler.set(Box::new(handler));
+        assert!(result.is_ok(), "cannot call draw_handler() more than once");
+    }
+
+    /// Sets the right edges of the panes, after a change in the panes or the size of
+    /// the status bar.
+    pub(crate) fn update_parts(&self) {
+        let panes = self.panes.borrow();
+        if panes.is_empty() {
+            return;
+        }
+        let widths: Vec<PaneWidth> = panes.iter().map(|p| p.width).collect();
+        drop(panes);
+        unsafe {
+            let mut rect: RECT = zeroed();
+            _ = GetClientRect(self.handle(), &mut rect);
+            let edges = status_pane_edges(&widths, rect.right - rect.left);
+            SendMessageW(
+                self.handle(),
+                SB_SETPARTS,
+                Some(WPARAM(edges.len())),
+                Some(LPARAM(edges.as_ptr() as isize)),
+            );
+        }
+    }
+
+    fn send_pane_text(&self, pane: usize) {
+        let panes = self.panes.borrow();
+        let Some(p) = panes.get(pane) else {
+            return;
+        };
+        unsafe {
+            let ws = U16CString::from_str_truncate(&p.text);
+            // For owner-drawn panes, lParam is passed back in DRAWITEMSTRUCT.itemData,
+            // and is not used.
+            let (flags, lparam) = if p.owner_draw {
+                (SBT_OWNERDRAW, 0)
+            } else {
+                (0, ws.as_ptr() as isize)
+            };
+            _ = SendMessageW(
+                self.handle(),
+                SB_SETTEXTW,
+                Some(WPARAM(pane | flags as usize)),
+                Some(LPARAM(lparam)),
+            );
+        }
+    }
+
+    /// Handles `WM_DRAWITEM` for an owner-drawn pane. This is sent to the form.
+    pub(crate) unsafe fn draw_item(&self, dis: &DRAWITEMSTRUCT) {
+        if let Some(handler) = self.draw_handler.get() {
+            let dc = Dc { hdc: dis.hDC };
+            let rect = Rect {
+                left: dis.rcItem.left,
+                top: dis.rcItem.top,
+                right: dis.rcItem.right,
+                bottom: dis.rcItem.bottom,
+            };
+            handler(dis.itemID as usize, &dc, &rect);
+        }
+    }
+}
+
+impl Drop for StatusBar {
+    fn drop(&mut self) {
+        for pane in self.panes.get_mut().iter() {
+            destroy_pane_icon(pane);
+        }
+    }
+}
+
+fn destroy_pane_icon(pane: &Pane) {
+    if let Some(hicon) = pane.icon {
+        unsafe {
+            _ = DestroyIcon(hicon);
+        }
+    }
+}
+
+impl NotifyHandlerTrait for StatusBar {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        let handler = match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/nm-click-status-bar
+            NM_CLICK => self.click_handler.get(),
+            // https://learn.microsoft.com/en-us/windows/win32/controls/nm-dblclk-status-bar
+            NM_DBLCLK => self.double_click_handler.get(),
+            _ => return NotifyResult::NotConsumed,
+        };
+        let mouse = &*(nmhdr as *const NMMOUSE);
+        // In simple mode, the pane is SB_SIMPLEID. Clicks past the last pane give -1.
+        let pane = if mouse.dwItemSpec == SB_SIMPLEID as usize {
+            Some(0)
+        } else {
+            (mouse.dwItemSpec < self.num_panes()).then_some(mouse.dwItemSpec)
+        };
+        if let (Some(handler), Some(pane)) = (handler, pane) {
+            handler(pane);
+        }
+        // The return value is ignored, but TRUE would prevent default processing.
+        NotifyResult::Consumed(LRESULT(0))
+    }
+}
+
+/// Computes the right edge of each pane, as `SB_SETPARTS` expects, for a status bar
+/// that is `total_width` pixels wide. If the last pane stretches, its edge is -1,
+/// so that it reaches the right side of the status bar.
+pub fn status_pane_edges(widths: &[PaneWidth], total_width: i32) -> Vec<i32> {
+    let fixed: i32 = widths
+        .iter()
+        .map(|w| match w {
+            PaneWidth::Fixed(n) => (*n).max(0),
+            PaneWidth::Stretch(_) => 0,
+        })
+        .sum();
+    let total_weight: f32 = widths
+        .iter()
+        .map(|w| match w {
+            PaneWidth::Stretch(weight) => weight.max(0.0),
+            PaneWidth::Fixed(_) => 0.0,
+        })
+        .sum();
+    let extra = (total_width - fixed).max(0);
+
+    let mut edges = Vec::with_capacity(widths.len());
+    let mut right = 0;
+    for width in widths.iter() {
+        right += match width {
+            PaneWidth::Fixed(n) => (*n).max(0),
+            PaneWidth::Stretch(weight) if total_weight > 0.0 => {
+                (extra as f32 * weight.max(0.0) / total_weight) as i32
+            }
+            PaneWidth::Stretch(_) => 0,
+        };
+        edges.push(right);
+    }
+    if let (Some(PaneWidth::Stretch(_)), Some(last)) = (widths.last(), edges.last_mut()) {
+        *last = -1;
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PaneWidth::{Fixed, Stretch};
+
+    #[test]
+    fn fixed_panes_are_placed_side_by_side() {
+        assert_eq!(
+            status_pane_edges(&[Fixed(100), Fixed(50), Fixed(25)], 400),
+            [100, 150, 175]
+        );
+        // Negative widths count as zero.
+        assert_eq!(status_pane_edges(&[Fixed(-10), Fixed(30)], 400), [0, 30]);
+        assert_eq!(status_pane_edges(&[], 400), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn trailing_stretch_pane_reaches_the_right_side() {
+        assert_eq!(
+            status_pane_edges(&[Fixed(100), Stretch(1.0)], 300),
+            [100, -1]
+        );
+        assert_eq!(status_pane_edges(&[Stretch(1.0)], 300), [-1]);
+    }
+
+    #[test]
+    fn stretch_panes_share_the_extra_width() {
+        assert_eq!(
+            status_pane_edges(&[Stretch(1.0), Fixed(50), Stretch(3.0)], 450),
+            [100, 150, -1]
+        );
+        assert_eq!(
+            status_pane_edges(&[Stretch(1.0), Stretch(1.0), Fixed(50)], 250),
+            [100, 200, 250]
+        );
+        // Panes with no weight get no space.
+        assert_eq!(
+            status_pane_edges(&[Stretch(0.0), Fixed(50), Stretch(0.0)], 450),
+            [0, 50, -1]
+        );
+    }
+
+    #[test]
+    fn zero_width_bar() {
+        assert_eq!(status_pane_edges(&[Fixed(100), Stretch(1.0)], 0), [100, -1]);
+        assert_eq!(status_pane_edges(&[Stretch(1.0), Fixed(20)], 0), [0, 20]);
+        assert_eq!(status_pane_edges(&[Fixed(10), Fixed(20)], 0), [10, 30]);
+    }
 }
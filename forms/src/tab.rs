@@ -6,6 +6,7 @@ use windows::Win32::UI::Shell::{DefSubclassProc, SetWindowSubclass};
 pub struct TabControl {
     control: ControlState,
     tabs: RefCell<Vec<Tab>>,
+    images: RefCell<Option<Rc<ImageList>>>,
 }
 
 const TAB_WNDCLASS_NAME: PCWSTR = w!("rust_forms.tab");
@@ -69,6 +70,7 @@ impl TabControl {
             let rc = Rc::new(Self {
                 control: ControlState::new(hwnd),
                 tabs: RefCell::new(Vec::new()),
+                images: RefCell::new(None),
             });
 
             if true {
@@ -88,7 +90,30 @@ impl TabControl {
         }
     }
 
+    /// Sets the images that tabs refer to with `add_tab_with_image`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tcm-setimagelist>
+    pub fn set_image_list(&self, images: Rc<ImageList>) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                TCM_SETIMAGELIST,
+                None,
+                Some(LPARAM(images.himagelist.0)),
+            );
+        }
+        *self.images.borrow_mut() = Some(images);
+    }
+
     pub fn add_tab(&self, item_index: u32, label: &str) -> Rc<TabPane> {
+        self.insert_tab(item_index, label, None)
+    }
+
+    pub fn add_tab_with_image(&self, item_index: u32, label: &str, image: u32) -> Rc<TabPane> {
+        self.insert_tab(item_index, label, Some(image))
+    }
+
+    fn insert_tab(&self, item_index: u32, label: &str, image: Option<u32>) -> Rc<TabPane> {
         unsafe {
             let ex_style = WINDOW_EX_STYLE(0);
             let style = WS_CHILD | WS_CLIPSIBLINGS | WS_VISIBLE;
@@ -113,6 +138,10 @@ impl TabControl {
             let mut item: TCITEMW = core::mem::zeroed();
             item.mask = TCIF_TEXT;
             item.iImage = -1;
+            if let Some(image) = image {
+                item.mask |= TCIF_IMAGE;
+                item.iImage = image as i32;
+            }
             item.pszText = PWSTR(label_wstr.as_ptr() as *mut _);
 
             _ = SendMessageW(
@@ -274,8 +303,19 @@ extern "system" fn tab_wndproc(
 
     unsafe {
         match message {
-            WM_COMMAND | WM_NOTIFY => {
-                // Forward WM_COMMAND and WM_NOTIFY up the window tree.
+            WM_NOTIFY => {
+                // The tab control does not forward notifications to its parent, so
+                // controls placed on a tab pane are dispatched here.
+                let nmhdr_ptr = lparam.0 as *mut NMHDR;
+                if let NotifyResult::Consumed(result) = dispatch_notify(nmhdr_ptr) {
+                    return result;
+                }
+                let parent_hwnd = GetParent(hwnd).unwrap();
+                return SendMessageW(parent_hwnd, message, Some(wparam), Some(lparam));
+            }
+
+            WM_COMMAND => {
+                // Forward WM_COMMAND up the window tree.
                 let parent_hwnd = GetParent(hwnd).unwrap();
                 return SendMessageW(parent_hwnd, message, Some(wparam), Some(lparam));
             }
@@ -0,0 +1,238 @@
+//! A `ListView` that shows a collection of typed rows.
+
+use super::*;
+use crate::list_view::{CellStyle, Column, Comparator, ListSource, SortOrder};
+use core::cell::Ref;
+
+/// A type that can be shown as a row of a `TableView`.
+///
+/// This is usually implemented with `#[derive(TableRow)]`, which makes a column of
+/// each field. Each field accepts `#[column(...)]` with these options:
+///
+/// * `title = "..."`: the column header. By default, this is the field name with
+///   underscores replaced by spaces and the first letter capitalized.
+/// * `width = 120`: the width in pixels.
+/// * `align = "left"`, `"center"` or `"right"`
+/// * `format = "{:.2}"`: the format string for the field. By default, this is
+///   `"{}"`, which needs the field to implement `Display`.
+/// * `min_width = 40`, `max_width = 400`, `autosize = "content"` or `"header"`,
+///   and `hidden`: see `Column`.
+/// * `skip`: the field is not shown.
+pub trait TableRow {
+    /// Describes the columns, in the order they are shown.
+    fn columns() -> Vec<Column>;
+
+    /// Writes the text of the cell in column `col` to `text`, which is empty.
+    fn cell_text(&self, col: usize, text: &mut String);
+}
+
+/// A list view in `Details` mode that owns its rows, and shows one column for each
+/// column of `T`.
+///
+/// The list view is virtual, so it does not keep a copy of the text of each row.
+/// Clicking a column header sorts the rows by that column, comparing the text in
+/// natural order; indices always refer to the rows in the order they are shown.
+pub struct TableView<T: TableRow + 'static> {
+    list_view: Rc<ListView>,
+    rows: Rc<TableRows<T>>,
+}
+
+impl<T: TableRow + 'static> core::ops::Deref for TableView<T> {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.list_view
+    }
+}
+
+struct TableRows<T> {
+    rows: RefCell<Vec<T>>,
+}
+
+impl<T: TableRow> ListSource for TableRows<T> {
+    fn len(&self) -> usize {
+        self.rows.borrow().len()
+    }
+
+    fn cell_text(&self, row: usize, col: usize, text: &mut String) {
+        if let Some(row) = self.rows.borrow().get(row) {
+            row.cell_text(col, text);
+        }
+    }
+
+    fn sort(&self, col: usize, order: SortOrder, comparator: &Comparator) -> bool {
+        let mut rows = self.rows.borrow_mut();
+        // Sort the indices first, so that the rows are left as they were if the
+        // comparator (or `cell_text`) panics.
+        let mut sorted: Vec<usize> = (0..rows.len()).collect();
+        comparator.sort_rows(&mut sorted, order, |row, text| {
+            rows[row].cell_text(col, text)
+        });
+        let mut taken: Vec<Option<T>> = rows.drain(..).map(Some).collect();
+        rows.extend(sorted.into_iter().filter_map(|row| taken[row].take()));
+        true
+    }
+}
+
+impl<T: TableRow + 'static> TableView<T> {
+    pub fn new(parent: &ControlState) -> Rc<TableView<T>> {
+        let rows = Rc::new(TableRows {
+            rows: RefCell::new(Vec::new()),
+        });
+        let list_view = ListView::new_virtual(parent, rows.clone() as Rc<dyn ListSource>);
+        list_view.set_mode(Mode::Details);
+        list_view.set_full_row_select(true);
+        for (index, column) in T::columns().iter().enumerate() {
+            list_view.insert_column(index, column);
+            list_view.set_column_comparator(index, Comparator::Natural);
+        }
+        Rc::new(TableView { list_view, rows })
+    }
+
+    /// The list view that shows the rows, for the settings that `TableView` does not
+    /// cover.
+    pub fn list_view(&self) -> &Rc<ListView> {
+        &self.list_view
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.rows.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the row at `index`. The rows cannot be changed while the result is held.
+    pub fn get(&self, index: usize) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.rows.rows.borrow(), |rows| rows.get(index)).ok()
+    }
+
+    /// Gets all of the rows, in the order they are shown.
+    pub fn rows(&self) -> Ref<'_, [T]> {
+        Ref::map(self.rows.rows.borrow(), |rows| rows.as_slice())
+    }
+
+    /// Adds a row at the end.
+    pub fn push(&self, row: T) {
+        self.rows.rows.borrow_mut().push(row);
+        self.list_view.set_len(self.len());
+    }
+
+    /// Adds rows at the end.
+    pub fn extend<I: IntoIterator<Item = T>>(&self, rows: I) {
+        self.rows.rows.borrow_mut().extend(rows);
+        self.list_view.set_len(self.len());
+    }
+
+    /// Removes the row at `index`, and returns it. The selection is cleared, as the
+    /// rows after it move up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&self, index: usize) -> T {
+        let row = self.rows.rows.borrow_mut().remove(index);
+        self.list_view.set_item_selected(usize::MAX, false);
+        self.list_view.set_len(self.len());
+        row
+    }
+
+    /// Removes all rows.
+    pub fn clear(&self) {
+        self.rows.rows.borrow_mut().clear();
+        self.list_view.set_len(0);
+    }
+
+    /// Changes the row at `index` with `f`, and redraws it. Returns `None` if
+    /// `index` is out of bounds.
+    pub fn update<R>(&self, index: usize, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let result = self.rows.rows.borrow_mut().get_mut(index).map(f);
+        if result.is_some() {
+            self.list_view.refresh(index..=index);
+        }
+        result
+    }
+
+    /// Sets the handler that gives the colors and font of a cell, given its row and
+    /// column. See `ListView::cell_style_handler`.
+    pub fn cell_style_handler<F>(&self, handler: F)
+    where
+        F: Fn(&T, usize) -> Option<CellStyle> + 'static,
+    {
+        let rows = Rc::clone(&self.rows);
+        self.list_view.cell_style_handler(move |row, col| {
+            let rows = rows.rows.borrow();
+            handler(rows.get(row)?, col)
+        });
+    }
+
+    /// The indices of the selected rows.
+    pub fn selected_indices(&self) -> Vec<usize> {
+        self.list_view.iter_selected_items().collect()
+    }
+
+    /// The selected rows. The rows cannot be changed while the result is held.
+    pub fn selected_rows(&self) -> Vec<Ref<'_, T>> {
+        self.selected_indices()
+            .into_iter()
+            .filter_map(|index| self.get(index))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[derive(Debug, PartialEq, crate::TableRow)]
+    struct File {
+        name: String,
+        size: u32,
+    }
+
+    fn file(name: &str, size: u32) -> File {
+        File {
+            name: name.to_string(),
+            size,
+        }
+    }
+
+    fn table() -> TableRows<File> {
+        TableRows {
+            rows: RefCell::new(vec![file("b10", 3), file("a", 20), file("b2", 3)]),
+        }
+    }
+
+    fn names(rows: &TableRows<File>) -> Vec<String> {
+        rows.rows.borrow().iter().map(|f| f.name.clone()).collect()
+    }
+
+    #[test]
+    fn sort_moves_the_rows() {
+        let rows = table();
+        assert!(rows.sort(0, SortOrder::Ascending, &Comparator::Natural));
+        assert_eq!(names(&rows), ["a", "b2", "b10"]);
+        assert!(rows.sort(0, SortOrder::Descending, &Comparator::Natural));
+        assert_eq!(names(&rows), ["b10", "b2", "a"]);
+
+        // The sort is stable.
+        assert!(rows.sort(1, SortOrder::Ascending, &Comparator::Natural));
+        assert_eq!(names(&rows), ["b10", "b2", "a"]);
+        let mut text = String::new();
+        rows.cell_text(2, 1, &mut text);
+        assert_eq!(text, "20");
+    }
+
+    #[test]
+    fn sort_keeps_the_rows_if_the_comparator_panics() {
+        let rows = table();
+        let comparator = Comparator::custom(|_, _| panic!("comparator failed"));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            rows.sort(0, SortOrder::Ascending, &comparator)
+        }));
+        assert!(result.is_err());
+        assert_eq!(names(&rows), ["b10", "a", "b2"]);
+        assert_eq!(rows.len(), 3);
+    }
+}
@@ -0,0 +1,655 @@
+//! Task dialogs: message boxes with more parts, such as command links, radio buttons,
+//! a "don't ask again" checkbox, expandable details and a progress bar.
+//!
+//! ```ignore
+//! let result = TaskDialog::builder()
+//!     .title("Copy Files")
+//!     .main_instruction("A file with this name already exists.")
+//!     .command_link(1, "Replace the file", "The existing file will be deleted.")
+//!     .command_link(2, "Skip this file", "")
+//!     .common_buttons(&[TaskDialogButton::Cancel])
+//!     .verification("Do this for all files", false)
+//!     .parent(&form)
+//!     .show();
+//! if result.button == TaskDialogButton::Custom(1) { ... }
+//! ```
+
+use super::*;
+use core::time::Duration;
+use windows::core::{BOOL, HRESULT};
+
+/// The icon shown next to the main instruction, or in the footer.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TaskDialogIcon {
+    #[default]
+    None,
+    Information,
+    Warning,
+    Error,
+    /// The icon for actions that need administrator rights.
+    Shield,
+}
+
+/// A button of a task dialog, and the button that closed it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TaskDialogButton {
+    Ok,
+    Cancel,
+    Yes,
+    No,
+    Retry,
+    Close,
+    /// A button added with `button` or `command_link`, with the ID given there.
+    Custom(u32),
+}
+
+/// The state of the progress bar, which sets its color.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ProgressState {
+    #[default]
+    Normal,
+    Error,
+    Paused,
+}
+
+/// How the user closed a task dialog.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaskDialogResult {
+    /// The button that was clicked. Closing the dialog with Escape or the close box
+    /// gives `Cancel`.
+    pub button: TaskDialogButton,
+    /// The radio button that was selected, as an index into the radio buttons.
+    pub radio_button: Option<usize>,
+    /// Whether the verification checkbox was checked.
+    pub verification_checked: bool,
+}
+
+/// The IDs given to custom buttons start here, after the IDs of the common buttons.
+const CUSTOM_BUTTON_BASE: i32 = 100;
+/// The IDs given to radio buttons start here.
+const RADIO_BUTTON_BASE: i32 = 1000;
+
+impl TaskDialogIcon {
+    fn resource(self) -> PCWSTR {
+        match self {
+            Self::None => PCWSTR::null(),
+            Self::Information => TD_INFORMATION_ICON,
+            Self::Warning => TD_WARNING_ICON,
+            Self::Error => TD_ERROR_ICON,
+            Self::Shield => TD_SHIELD_ICON,
+        }
+    }
+}
+
+impl TaskDialogButton {
+    fn common_flag(self) -> TASKDIALOG_COMMON_BUTTON_FLAGS {
+        match self {
+            Self::Ok => TDCBF_OK_BUTTON,
+            Self::Cancel => TDCBF_CANCEL_BUTTON,
+            Self::Yes => TDCBF_YES_BUTTON,
+            Self::No => TDCBF_NO_BUTTON,
+            Self::Retry => TDCBF_RETRY_BUTTON,
+            Self::Close => TDCBF_CLOSE_BUTTON,
+            Self::Custom(_) => TASKDIALOG_COMMON_BUTTON_FLAGS(0),
+        }
+    }
+}
+
+struct CustomButton {
+    id: u32,
+    text: String,
+    /// The second line of a command link.
+    note: String,
+}
+
+/// Maps between buttons and the IDs that the task dialog uses for them. Custom button
+/// IDs are chosen by the caller, so they are given IDs of their own that cannot
+/// collide with `IDOK` and the other common buttons.
+struct ButtonIds<'a> {
+    custom: &'a [CustomButton],
+}
+
+impl<'a> ButtonIds<'a> {
+    fn id(&self, button: TaskDialogButton) -> Option<i32> {
+        Some(match button {
+            TaskDialogButton::Ok => IDOK.0,
+            TaskDialogButton::Cancel => IDCANCEL.0,
+            TaskDialogButton::Yes => IDYES.0,
+            TaskDialogButton::No => IDNO.0,
+            TaskDialogButton::Retry => IDRETRY.0,
+            TaskDialogButton::Close => IDCLOSE.0,
+            TaskDialogButton::Custom(id) => {
+                let index = self.custom.iter().position(|b| b.id == id)?;
+                CUSTOM_BUTTON_BASE + index as i32
+            }
+        })
+    }
+
+    fn button(&self, id: i32) -> TaskDialogButton {
+        match MESSAGEBOX_RESULT(id) {
+            IDOK => TaskDialogButton::Ok,
+            IDYES => TaskDialogButton::Yes,
+            IDNO => TaskDialogButton::No,
+            IDRETRY => TaskDialogButton::Retry,
+            IDCLOSE => TaskDialogButton::Close,
+            _ => {
+                let custom = usize::try_from(id - CUSTOM_BUTTON_BASE)
+                    .ok()
+                    .and_then(|index| self.custom.get(index));
+                match custom {
+                    Some(button) => TaskDialogButton::Custom(button.id),
+                    None => TaskDialogButton::Cancel,
+                }
+            }
+        }
+    }
+}
+
+/// Controls a task dialog while it is shown. This is passed to the handlers of
+/// `TaskDialogBuilder`.
+pub struct TaskDialogHandle<'a> {
+    hwnd: HWND,
+    ids: ButtonIds<'a>,
+}
+
+impl<'a> TaskDialogHandle<'a> {
+    fn send(&self, msg: TASKDIALOG_MESSAGES, wparam: usize, lparam: isize) -> LRESULT {
+        unsafe {
+            SendMessageW(
+                self.hwnd,
+                msg.0 as u32,
+                Some(WPARAM(wparam)),
+                Some(LPARAM(lparam)),
+            )
+        }
+    }
+
+    fn set_element_text(&self, element: TASKDIALOG_ELEMENTS, text: &str) {
+        let text = WCString::from_str_truncate(text);
+        // TDM_SET_ELEMENT_TEXT may change the size of the dialog, unlike
+        // TDM_UPDATE_ELEMENT_TEXT, so that longer text is not cut off.
+        self.send(
+            TDM_SET_ELEMENT_TEXT,
+            element.0 as usize,
+            text.as_ptr() as isize,
+        );
+    }
+
+    pub fn set_main_instruction(&self, text: &str) {
+        self.set_element_text(TDE_MAIN_INSTRUCTION, text);
+    }
+
+    pub fn set_content(&self, text: &str) {
+        self.set_element_text(TDE_CONTENT, text);
+    }
+
+    pub fn set_footer(&self, text: &str) {
+        self.set_element_text(TDE_FOOTER, text);
+    }
+
+    /// Sets the position of the progress bar, within its range.
+    pub fn set_progress(&self, position: u16) {
+        self.send(TDM_SET_PROGRESS_BAR_POS, position as usize, 0);
+    }
+
+    /// Sets the range of the progress bar. It is 0 to 100 by default.
+    pub fn set_progress_range(&self, min: u16, max: u16) {
+        let range = (min as isize) | ((max as isize) << 16);
+        self.send(TDM_SET_PROGRESS_BAR_RANGE, 0, range);
+    }
+
+    /// Switches the progress bar between showing a position, and showing that
+    /// something is happening without a known end.
+    pub fn set_progress_marquee(&self, marquee: bool) {
+        self.send(TDM_SET_MARQUEE_PROGRESS_BAR, marquee as usize, 0);
+        self.send(TDM_SET_PROGRESS_BAR_MARQUEE, marquee as usize, 0);
+    }
+
+    pub fn set_progress_state(&self, state: ProgressState) {
+        let state = match state {
+            ProgressState::Normal => PBST_NORMAL,
+            ProgressState::Error => PBST_ERROR,
+            ProgressState::Paused => PBST_PAUSED,
+        };
+        self.send(TDM_SET_PROGRESS_BAR_STATE, state as usize, 0);
+    }
+
+    pub fn set_button_enabled(&self, button: TaskDialogButton, enabled: bool) {
+        if let Some(id) = self.ids.id(button) {
+            self.send(TDM_ENABLE_BUTTON, id as usize, enabled as isize);
+        }
+    }
+
+    /// Clicks a button, as if the user had clicked it. This closes the dialog, unless
+    /// the button handler keeps it open.
+    pub fn click_button(&self, button: TaskDialogButton) {
+        if let Some(id) = self.ids.id(button) {
+            self.send(TDM_CLICK_BUTTON, id as usize, 0);
+        }
+    }
+}
+
+type TimerHandler<'a> = Box<dyn FnMut(&TaskDialogHandle, Duration) + 'a>;
+type ButtonHandler<'a> = Box<dyn FnMut(&TaskDialogHandle, TaskDialogButton) -> bool + 'a>;
+
+/// Shows task dialogs. Use `TaskDialog::builder()`.
+// Not a unit struct, which would clash with the `TaskDialog` function of the
+// `windows` crate.
+pub enum TaskDialog {}
+
+impl TaskDialog {
+    pub fn builder<'a>() -> TaskDialogBuilder<'a> {
+        TaskDialogBuilder {
+            title: String::new(),
+            main_instruction: String::new(),
+            content: String::new(),
+            icon: TaskDialogIcon::None,
+            common_buttons: Vec::new(),
+            custom_buttons: Vec::new(),
+            command_links: false,
+            default_button: None,
+            radio_buttons: Vec::new(),
+            default_radio_button: Some(0),
+            verification: None,
+            details: None,
+            details_expanded: false,
+            footer: String::new(),
+            footer_icon: TaskDialogIcon::None,
+            progress: None,
+            timer_handler: None,
+            button_handler: None,
+            parent: None,
+        }
+    }
+}
+
+pub struct TaskDialogBuilder<'a> {
+    title: String,
+    main_instruction: String,
+    content: String,
+    icon: TaskDialogIcon,
+    common_buttons: Vec<TaskDialogButton>,
+    custom_buttons: Vec<CustomButton>,
+    command_links: bool,
+    default_button: Option<TaskDialogButton>,
+    radio_buttons: Vec<String>,
+    default_radio_button: Option<usize>,
+    verification: Option<(String, bool)>,
+    /// The expanded information, and the labels of the expand button.
+    details: Option<(String, Option<(String, String)>)>,
+    details_expanded: bool,
+    footer: String,
+    footer_icon: TaskDialogIcon,
+    /// `Some(true)` for a marquee progress bar.
+    progress: Option<bool>,
+    timer_handler: Option<TimerHandler<'a>>,
+    button_handler: Option<ButtonHandler<'a>>,
+    parent: Option<&'a Form>,
+}
+
+impl<'a> TaskDialogBuilder<'a> {
+    /// Sets the text of the title bar.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Sets the large text at the top of the dialog.
+    pub fn main_instruction(mut self, text: &str) -> Self {
+        self.main_instruction = text.to_string();
+        self
+    }
+
+    /// Sets the text under the main instruction.
+    pub fn content(mut self, text: &str) -> Self {
+        self.content = text.to_string();
+        self
+    }
+
+    pub fn icon(mut self, icon: TaskDialogIcon) -> Self {
+        self.icon = icon;
+        self
+    }
+
+    /// Adds standard buttons, such as OK and Cancel. These are always shown in a
+    /// fixed order, after the custom buttons. If a dialog has no buttons, it gets an
+    /// OK button.
+    pub fn common_buttons(mut self, buttons: &[TaskDialogButton]) -> Self {
+        self.common_buttons.extend(
+            buttons
+                .iter()
+                .filter(|b| !matches!(b, TaskDialogButton::Custom(_))),
+        );
+        self
+    }
+
+    /// Adds a push button. When it is clicked, the result is
+    /// `TaskDialogButton::Custom(id)`.
+    pub fn button(mut self, id: u32, text: &str) -> Self {
+        self.custom_buttons.push(CustomButton {
+            id,
+            text: text.to_string(),
+            note: String::new(),
+        });
+        self
+    }
+
+    /// Adds a command link: a large button with a line of text under it, which may
+    /// be empty. A dialog shows either push buttons or command links, so once a
+    /// command link is added, the buttons added with `button` are also shown as
+    /// command links.
+    pub fn command_link(mut self, id: u32, text: &str, note: &str) -> Self {
+        self.custom_buttons.push(CustomButton {
+            id,
+            text: text.to_string(),
+            note: note.to_string(),
+        });
+        self.command_links = true;
+        self
+    }
+
+    /// Sets the button that has the focus, and is chosen by Enter. By default it is
+    /// the first button.
+    pub fn default_button(mut self, button: TaskDialogButton) -> Self {
+        self.default_button = Some(button);
+        self
+    }
+
+    /// Adds a radio button. The result gives the index of the selected radio button.
+    pub fn radio_button(mut self, text: &str) -> Self {
+        self.radio_buttons.push(text.to_string());
+        self
+    }
+
+    /// Selects a radio button when the dialog opens. `None` selects no radio button.
+    /// By default, the first one is selected.
+    pub fn default_radio_button(mut self, index: Option<usize>) -> Self {
+        self.default_radio_button = index;
+        self
+    }
+
+    /// Adds a checkbox at the bottom of the dialog, such as "Don't ask me again".
+    pub fn verification(mut self, text: &str, checked: bool) -> Self {
+        self.verification = Some((text.to_string(), checked));
+        self
+    }
+
+    /// Adds text that is hidden until the user clicks "See details".
+    pub fn details(mut self, text: &str) -> Self {
+        let labels = self.details.take().and_then(|(_, labels)| labels);
+        self.details = Some((text.to_string(), labels));
+        self
+    }
+
+    /// Sets the labels of the button that shows and hides the details.
+    pub fn details_labels(mut self, expanded: &str, collapsed: &str) -> Self {
+        let text = self
+            .details
+            .take()
+            .map(|(text, _)| text)
+            .unwrap_or_default();
+        self.details = Some((text, Some((expanded.to_string(), collapsed.to_string()))));
+        self
+    }
+
+    /// Shows the details when the dialog opens.
+    pub fn details_expanded(mut self, expanded: bool) -> Self {
+        self.details_expanded = expanded;
+        self
+    }
+
+    /// Sets the text at the bottom of the dialog.
+    pub fn footer(mut self, text: &str, icon: TaskDialogIcon) -> Self {
+        self.footer = text.to_string();
+        self.footer_icon = icon;
+        self
+    }
+
+    /// Shows a progress bar. Use `timer_handler` to update it.
+    pub fn progress_bar(mut self) -> Self {
+        self.progress = Some(false);
+        self
+    }
+
+    /// Shows a progress bar that shows that something is happening, without showing
+    /// how much of it is done.
+    pub fn marquee_progress_bar(mut self) -> Self {
+        self.progress = Some(true);
+        self
+    }
+
+    /// Sets a handler that is called about every 200 ms while the dialog is shown,
+    /// with the time since the dialog was opened. This is how the progress bar is
+    /// updated, such as by polling the state of a worker thread.
+    pub fn timer_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&TaskDialogHandle, Duration) + 'a,
+    {
+        self.timer_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets a handler that is called when a button is clicked. If it returns false,
+    /// the dialog stays open.
+    pub fn button_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&TaskDialogHandle, TaskDialogButton) -> bool + 'a,
+    {
+        self.button_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the window that owns the dialog. It is disabled while the dialog is shown,
+    /// and the dialog is centered on it.
+    pub fn parent(mut self, parent: &'a Form) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Shows the dialog, and waits until the user closes it.
+    pub fn show(self) -> TaskDialogResult {
+        let callback = CallbackState {
+            ids: ButtonIds {
+                custom: &self.custom_buttons,
+            },
+            timer_handler: RefCell::new(self.timer_handler),
+            button_handler: RefCell::new(self.button_handler),
+            marquee: self.progress == Some(true),
+        };
+
+        let wstr = |s: &str| (!s.is_empty()).then(|| WCString::from_str_truncate(s));
+        let ptr = |s: &Option<WCString>| s.as_ref().map_or(PCWSTR::null(), |s| PCWSTR(s.as_ptr()));
+
+        let title = wstr(&self.title);
+        let main_instruction = wstr(&self.main_instruction);
+        let content = wstr(&self.content);
+        let footer = wstr(&self.footer);
+        // A command link's note goes on the line after its text.
+        let button_texts: Vec<WCString> = self
+            .custom_buttons
+            .iter()
+            .map(|b| {
+                if b.note.is_empty() {
+                    WCString::from_str_truncate(&b.text)
+                } else {
+                    WCString::from_str_truncate(format!("{}\n{}", b.text, b.note))
+                }
+            })
+            .collect();
+        let buttons: Vec<TASKDIALOG_BUTTON> = button_texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| TASKDIALOG_BUTTON {
+                nButtonID: CUSTOM_BUTTON_BASE + i as i32,
+                pszButtonText: PCWSTR(text.as_ptr()),
+            })
+            .collect();
+        let radio_texts: Vec<WCString> = self
+            .radio_buttons
+            .iter()
+            .map(WCString::from_str_truncate)
+            .collect();
+        let radio_buttons: Vec<TASKDIALOG_BUTTON> = radio_texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| TASKDIALOG_BUTTON {
+                nButtonID: RADIO_BUTTON_BASE + i as i32,
+                pszButtonText: PCWSTR(text.as_ptr()),
+            })
+            .collect();
+        let verification = self
+            .verification
+            .as_ref()
+            .map(|(text, _)| WCString::from_str_truncate(text));
+        let details = self
+            .details
+            .as_ref()
+            .map(|(text, _)| WCString::from_str_truncate(text));
+        let details_labels = self.details.as_ref().and_then(|(_, labels)| {
+            let (expanded, collapsed) = labels.as_ref()?;
+            Some((
+                WCString::from_str_truncate(expanded),
+                WCString::from_str_truncate(collapsed),
+            ))
+        });
+
+        let mut flags = TDF_ALLOW_DIALOG_CANCELLATION | TDF_CALLBACK_TIMER;
+        if self.command_links {
+            flags |= TDF_USE_COMMAND_LINKS;
+        }
+        if self.default_radio_button.is_none() {
+            flags |= TDF_NO_DEFAULT_RADIO_BUTTON;
+        }
+        if matches!(self.verification, Some((_, true))) {
+            flags |= TDF_VERIFICATION_FLAG_CHECKED;
+        }
+        if self.details_expanded {
+            flags |= TDF_EXPANDED_BY_DEFAULT;
+        }
+        match self.progress {
+            Some(false) => flags |= TDF_SHOW_PROGRESS_BAR,
+            Some(true) => flags |= TDF_SHOW_MARQUEE_PROGRESS_BAR,
+            None => {}
+        }
+        if self.parent.is_some() {
+            flags |= TDF_POSITION_RELATIVE_TO_WINDOW;
+        }
+
+        let mut common_buttons = TASKDIALOG_COMMON_BUTTON_FLAGS(0);
+        for button in self.common_buttons.iter() {
+            common_buttons |= button.common_flag();
+        }
+
+        let mut config = TASKDIALOGCONFIG {
+            cbSize: size_of::<TASKDIALOGCONFIG>() as u32,
+            hwndParent: self.parent.map_or(HWND(null_mut()), |form| form.handle()),
+            dwFlags: flags,
+            dwCommonButtons: common_buttons,
+            pszWindowTitle: ptr(&title),
+            pszMainInstruction: ptr(&main_instruction),
+            pszContent: ptr(&content),
+            cButtons: buttons.len() as u32,
+            pButtons: buttons.as_ptr(),
+            nDefaultButton: self
+                .default_button
+                .and_then(|b| callback.ids.id(b))
+                .unwrap_or(0),
+            cRadioButtons: radio_buttons.len() as u32,
+            pRadioButtons: radio_buttons.as_ptr(),
+            nDefaultRadioButton: self
+                .default_radio_button
+                .map_or(0, |i| RADIO_BUTTON_BASE + i as i32),
+            pszVerificationText: ptr(&verification),
+            pszExpandedInformation: ptr(&details),
+            pszFooter: ptr(&footer),
+            pfCallback: Some(task_dialog_callback),
+            lpCallbackData: &callback as *const CallbackState as isize,
+            ..Default::default()
+        };
+        config.Anonymous1.pszMainIcon = self.icon.resource();
+        config.Anonymous2.pszFooterIcon = self.footer_icon.resource();
+        if let Some((expanded, collapsed)) = &details_labels {
+            config.pszExpandedControlText = PCWSTR(expanded.as_ptr());
+            config.pszCollapsedControlText = PCWSTR(collapsed.as_ptr());
+        }
+
+        let mut button_id: i32 = 0;
+        let mut radio_id: i32 = 0;
+        let mut verification_checked = BOOL(0);
+        let result = unsafe {
+            TaskDialogIndirect(
+                &config,
+                Some(&mut button_id),
+                Some(&mut radio_id),
+                Some(&mut verification_checked),
+            )
+        };
+        if let Err(e) = result {
+            warn!("TaskDialogIndirect failed: {e:?}");
+            return TaskDialogResult {
+                button: TaskDialogButton::Cancel,
+                radio_button: None,
+                verification_checked: false,
+            };
+        }
+
+        TaskDialogResult {
+            button: callback.ids.button(button_id),
+            radio_button: usize::try_from(radio_id - RADIO_BUTTON_BASE)
+                .ok()
+                .filter(|&i| i < self.radio_buttons.len()),
+            verification_checked: verification_checked.as_bool(),
+        }
+    }
+}
+
+/// Shared by the callback, which is re-entered when a handler sends a message to
+/// the dialog, such as `TDM_CLICK_BUTTON`. Each handler is taken out while it runs,
+/// and notifications for it that arrive meanwhile are ignored.
+struct CallbackState<'a> {
+    ids: ButtonIds<'a>,
+    timer_handler: RefCell<Option<TimerHandler<'a>>>,
+    button_handler: RefCell<Option<ButtonHandler<'a>>>,
+    marquee: bool,
+}
+
+// https://learn.microsoft.com/en-us/windows/win32/api/commctrl/nc-commctrl-pftaskdialogcallback
+unsafe extern "system" fn task_dialog_callback(
+    hwnd: HWND,
+    msg: TASKDIALOG_NOTIFICATIONS,
+    wparam: WPARAM,
+    _lparam: LPARAM,
+    data: isize,
+) -> HRESULT {
+    let state = &*(data as *const CallbackState);
+    let handle = TaskDialogHandle {
+        hwnd,
+        ids: ButtonIds {
+            custom: state.ids.custom,
+        },
+    };
+    match msg {
+        TDN_CREATED if state.marquee => handle.set_progress_marquee(true),
+        TDN_TIMER => {
+            let handler = state.timer_handler.borrow_mut().take();
+            if let Some(mut handler) = handler {
+                handler(&handle, Duration::from_millis(wparam.0 as u64));
+                *state.timer_handler.borrow_mut() = Some(handler);
+            }
+        }
+        TDN_BUTTON_CLICKED => {
+            let handler = state.button_handler.borrow_mut().take();
+            if let Some(mut handler) = handler {
+                let button = state.ids.button(wparam.0 as i32);
+                let close = handler(&handle, button);
+                *state.button_handler.borrow_mut() = Some(handler);
+                if !close {
+                    // S_FALSE keeps the dialog open.
+                    return S_FALSE;
+                }
+            }
+        }
+        _ => {}
+    }
+    S_OK
+}
@@ -0,0 +1,349 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/toolbar-controls-overview
+
+use super::*;
+
+/// A row of buttons docked at the top of a form.
+///
+/// Clicking a button sends `WM_COMMAND` to the form, exactly like choosing a menu
+/// item, so a single `Form::command_handler` can serve both. Give each button the
+/// same `id` as the `MenuItem` that it duplicates.
+pub struct Toolbar {
+    control: ControlState,
+    image_list: RefCell<Option<Rc<ImageList>>>,
+    // key is the button's command id
+    tooltips: RefCell<HashMap<usize, String>>,
+    dropdown_menus: RefCell<HashMap<usize, Rc<Menu>>>,
+}
+
+impl core::ops::Deref for Toolbar {
+    type Target = ControlState;
+    fn deref(&self) -> &ControlState {
+        &self.control
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ToolbarButtonKind {
+    /// An ordinary push button.
+    Push,
+    /// Stays pressed until it is clicked again, like a checked menu item.
+    Toggle,
+    /// A push button with a separate arrow that shows the button's drop-down menu.
+    /// See `Toolbar::set_dropdown_menu`.
+    DropDown,
+    /// A button that only shows its drop-down menu, and does not send `WM_COMMAND`
+    /// itself.
+    WholeDropDown,
+    Separator,
+}
+
+pub struct ToolbarButton<'a> {
+    pub kind: ToolbarButtonKind,
+    /// The command id sent in `WM_COMMAND`.
+    pub id: usize,
+    /// Text shown next to the image.
+    pub text: Option<&'a str>,
+    /// Index of the image, in the toolbar's image list.
+    pub image: Option<u32>,
+    /// Text shown when the mouse hovers over the button.
+    pub tooltip: Option<&'a str>,
+    pub enabled: bool,
+    pub checked: bool,
+}
+
+impl<'a> ToolbarButton<'a> {
+    pub fn separator() -> ToolbarButton<'a> {
+        Self {
+            kind: ToolbarButtonKind::Separator,
+            ..Default::default()
+        }
+    }
+}
+
+impl<'a> Default for ToolbarButton<'a> {
+    fn default() -> Self {
+        Self {
+            kind: ToolbarButtonKind::Push,
+            id: 0,
+            text: None,
+            image: None,
+            tooltip: None,
+            enabled: true,
+            checked: false,
+        }
+    }
+}
+
+/// The standard toolbar images provided by the system.
+/// See `Toolbar::load_standard_images`.
+#[repr(u32)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StandardImage {
+    Cut = STD_CUT,
+    Copy = STD_COPY,
+    Paste = STD_PASTE,
+    Undo = STD_UNDO,
+    Redo = STD_REDOW,
+    Delete = STD_DELETE,
+    FileNew = STD_FILENEW,
+    FileOpen = STD_FILEOPEN,
+    FileSave = STD_FILESAVE,
+    PrintPreview = STD_PRINTPRE,
+    Properties = STD_PROPERTIES,
+    Help = STD_HELP,
+    Find = STD_FIND,
+    Replace = STD_REPLACE,
+    Print = STD_PRINT,
+}
+
+const HINST_COMMCTRL: isize = -1;
+
+impl Toolbar {
+    pub fn new(form: &Rc<Form>) -> Rc<Self> {
+        unsafe {
+            let style = WS_CHILD
+                | WS_VISIBLE
+                | WINDOW_STYLE(TBSTYLE_FLAT | TBSTYLE_LIST | TBSTYLE_TOOLTIPS | CCS_TOP as u32);
+
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                TOOLBARCLASSNAMEW,
+                PCWSTR::null(),
+                style,
+                0,
+                0,
+                0,
+                0,
+                Some(form.handle()),
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            SendMessageW(
+                hwnd,
+                TB_BUTTONSTRUCTSIZE,
+                Some(WPARAM(size_of::<TBBUTTON>())),
+                None,
+            );
+
+            // Mixed buttons: only buttons with BTNS_SHOWTEXT show their text.
+            let ex_style =
+                TBSTYLE_EX_DRAWDDARROWS | TBSTYLE_EX_MIXEDBUTTONS | TBSTYLE_EX_DOUBLEBUFFER;
+            SendMessageW(
+                hwnd,
+                TB_SETEXTENDEDSTYLE,
+                None,
+                Some(LPARAM(ex_style as isize)),
+            );
+
+            let this = Rc::new(Toolbar {
+                control: ControlState::new(hwnd),
+                image_list: RefCell::new(None),
+                tooltips: RefCell::new(HashMap::new()),
+                dropdown_menus: RefCell::new(HashMap::new()),
+            });
+
+            register_notify_handler(hwnd, Rc::downgrade(&this) as Weak<dyn NotifyHandlerTrait>);
+
+            form.invalidate_layout();
+            this
+        }
+    }
+
+    /// Sets the images that buttons refer to with `ToolbarButton::image`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tb-setimagelist>
+    pub fn set_image_list(&self, images: Rc<ImageList>) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                TB_SETIMAGELIST,
+                None,
+                Some(LPARAM(images.himagelist.0)),
+            );
+        }
+        *self.image_list.borrow_mut() = Some(images);
+    }
+
+    /// Adds the system's standard (16x16) toolbar images to the toolbar's image
+    /// list. Returns the index of the first one; add a `StandardImage` to it to
+    /// get the index of that image.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tb-loadimages>
+    pub fn load_standard_images(&self) -> u32 {
+        unsafe {
+            let himagelist = HIMAGELIST(SendMessageW(self.handle(), TB_GETIMAGELIST, None, None).0);
+            let base = if himagelist.is_invalid() {
+                0
+            } else {
+                ImageList_GetImageCount(himagelist) as u32
+            };
+
+            SendMessageW(
+                self.handle(),
+                TB_LOADIMAGES,
+                Some(WPARAM(IDB_STD_SMALL_COLOR as usize)),
+                Some(LPARAM(HINST_COMMCTRL)),
+            );
+            base
+        }
+    }
+
+    // https://learn.microsoft.com/en-us/windows/win32/controls/tb-addbuttons
+    pub fn append_button(&self, button: &ToolbarButton<'_>) {
+        unsafe {
+            let mut tb: TBBUTTON = zeroed();
+            tb.idCommand = button.id as i32;
+            tb.iBitmap = match button.image {
+                Some(image) => image as i32,
+                None => I_IMAGENONE,
+            };
+            tb.iString = -1;
+
+            let mut style = BTNS_AUTOSIZE;
+            style |= match button.kind {
+                ToolbarButtonKind::Push => BTNS_BUTTON,
+                ToolbarButtonKind::Toggle => BTNS_CHECK,
+                ToolbarButtonKind::DropDown => BTNS_DROPDOWN,
+                ToolbarButtonKind::WholeDropDown => BTNS_WHOLEDROPDOWN,
+                ToolbarButtonKind::Separator => BTNS_SEP,
+            };
+
+            if let Some(text) = button.text {
+                let mut text_wstr: Vec<u16> = text.encode_utf16().collect();
+                // TB_ADDSTRING takes a list of strings, terminated by an empty string.
+                text_wstr.extend_from_slice(&[0, 0]);
+                tb.iString = SendMessageW(
+                    self.handle(),
+                    TB_ADDSTRINGW,
+                    None,
+                    Some(LPARAM(text_wstr.as_ptr() as isize)),
+                )
+                .0;
+                style |= BTNS_SHOWTEXT;
+            }
+            tb.fsStyle = style as u8;
+
+            let mut state = 0;
+            if button.enabled {
+                state |= TBSTATE_ENABLED;
+            }
+            if button.checked {
+                state |= TBSTATE_CHECKED;
+            }
+            tb.fsState = state as u8;
+
+            if let Some(tooltip) = button.tooltip {
+                self.tooltips
+                    .borrow_mut()
+                    .insert(button.id, tooltip.to_string());
+            }
+
+            if SendMessageW(
+                self.handle(),
+                TB_ADDBUTTONSW,
+                Some(WPARAM(1)),
+                Some(LPARAM(&tb as *const TBBUTTON as isize)),
+            )
+            .0 == 0
+            {
+                warn!("TB_ADDBUTTONS failed");
+            }
+
+            self.autosize();
+        }
+    }
+
+    /// Sets the menu that is shown when the arrow of a `DropDown` or `WholeDropDown`
+    /// button is clicked. The menu's items send `WM_COMMAND` to the form.
+    pub fn set_dropdown_menu(&self, id: usize, menu: Menu) {
+        self.dropdown_menus.borrow_mut().insert(id, Rc::new(menu));
+    }
+
+    /// Accesses the state of the button with command id `id`.
+    pub fn button(&self, id: usize) -> ToolbarItem<'_> {
+        ToolbarItem { toolbar: self, id }
+    }
+
+    /// Resizes the toolbar to fit its buttons and the width of the form.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tb-autosize>
+    pub fn autosize(&self) {
+        unsafe {
+            SendMessageW(self.handle(), TB_AUTOSIZE, None, None);
+        }
+    }
+}
+
+/// The state of one toolbar button. See `Toolbar::button`.
+pub struct ToolbarItem<'a> {
+    toolbar: &'a Toolbar,
+    id: usize,
+}
+
+impl<'a> ToolbarItem<'a> {
+    fn send(&self, message: u32, lparam: isize) -> bool {
+        unsafe {
+            SendMessageW(
+                self.toolbar.handle(),
+                message,
+                Some(WPARAM(self.id)),
+                Some(LPARAM(lparam)),
+            )
+            .0 != 0
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.send(TB_ISBUTTONENABLED, 0)
+    }
+
+    pub fn set_enabled(&self, value: bool) {
+        self.send(TB_ENABLEBUTTON, value as isize);
+    }
+
+    pub fn checked(&self) -> bool {
+        self.send(TB_ISBUTTONCHECKED, 0)
+    }
+
+    pub fn set_checked(&self, value: bool) {
+        self.send(TB_CHECKBUTTON, value as isize);
+    }
+}
+
+impl NotifyHandlerTrait for Toolbar {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tbn-dropdown
+            TBN_DROPDOWN => {
+                let nmtoolbar = &*(nmhdr as *const NMTOOLBARW);
+                let id = nmtoolbar.iItem as usize;
+                let menu = self.dropdown_menus.borrow().get(&id).cloned();
+                if let Some(menu) = menu {
+                    let pt = self.client_to_screen(POINT {
+                        x: nmtoolbar.rcButton.left,
+                        y: nmtoolbar.rcButton.bottom,
+                    });
+                    let owner = GetParent(self.handle()).unwrap_or_default();
+                    menu.track_popup_menu_owned_by(owner, pt.x, pt.y);
+                    return NotifyResult::Consumed(LRESULT(TBDDRET_DEFAULT as isize));
+                }
+                NotifyResult::NotConsumed
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tbn-getinfotip
+            TBN_GETINFOTIPW => {
+                let info = &mut *(nmhdr as *mut NMTBGETINFOTIPW);
+                let tooltips = self.tooltips.borrow();
+                if let Some(tooltip) = tooltips.get(&(info.iItem as usize)) {
+                    copy_to_text_buffer(tooltip, info.pszText, info.cchTextMax);
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
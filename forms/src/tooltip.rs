@@ -0,0 +1,184 @@
+// https://learn.microsoft.com/en-us/windows/win32/controls/tooltip-controls
+
+//! Hover help for controls. Each form has one tooltip window, which is created the
+//! first time one of its controls is given a tooltip. See `ControlState::set_tooltip`.
+
+use super::*;
+use std::time::Duration;
+
+/// Tooltip text is wrapped at this width when `TooltipOptions::max_width` is not set.
+const DEFAULT_MAX_WIDTH: i32 = 400;
+
+/// The text is requested with `TTN_GETDISPINFO` when the tooltip is shown.
+const LPSTR_TEXTCALLBACKW: PWSTR = PWSTR(-1isize as *mut u16);
+
+/// Settings for all of the tooltips of a form. See `Form::set_tooltip_options`.
+#[derive(Clone, Debug, Default)]
+pub struct TooltipOptions {
+    /// Draws tooltips as cartoon-style balloons.
+    pub balloon: bool,
+    /// Text is wrapped at this width, in pixels. Line breaks (`\n`) in the text
+    /// always start a new line.
+    pub max_width: Option<i32>,
+    /// How long the mouse must rest on a control before its tooltip appears.
+    pub initial_delay: Option<Duration>,
+    /// How long a tooltip stays visible while the mouse is still.
+    pub autopop_delay: Option<Duration>,
+    /// How long it takes for the next tooltip to appear, when the mouse moves
+    /// from one control to another.
+    pub reshow_delay: Option<Duration>,
+}
+
+thread_local! {
+    // key is HWND of the form, value is its tooltip window
+    static TOOLTIP_WINDOWS: RefCell<HashMap<isize, HWND>> = RefCell::new(HashMap::new());
+}
+
+/// Gets the tooltip window of the form that contains `hwnd`, creating it if needed.
+pub(crate) fn get_tooltip_window(hwnd: HWND) -> HWND {
+    unsafe {
+        let form = GetAncestor(hwnd, GA_ROOT);
+        let key = form.0 as isize;
+        if let Some(tooltip) = TOOLTIP_WINDOWS.with_borrow(|windows| windows.get(&key).copied()) {
+            return tooltip;
+        }
+
+        // TTS_NOPREFIX keeps '&' in the text, instead of treating it as a mnemonic.
+        let tooltip = CreateWindowExW(
+            WS_EX_TOPMOST,
+            TOOLTIPS_CLASSW,
+            PCWSTR::null(),
+            WS_POPUP | WINDOW_STYLE(TTS_ALWAYSTIP | TTS_NOPREFIX),
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            Some(form),
+            None,
+            Some(get_instance()),
+            None,
+        )
+        .unwrap();
+
+        // Without a maximum width, the tooltip ignores line breaks.
+        SendMessageW(
+            tooltip,
+            TTM_SETMAXTIPWIDTH,
+            None,
+            Some(LPARAM(DEFAULT_MAX_WIDTH as isize)),
+        );
+
+        TOOLTIP_WINDOWS.with_borrow_mut(|windows| windows.insert(key, tooltip));
+        tooltip
+    }
+}
+
+/// Called when a form is destroyed. Its tooltip window is destroyed along with it,
+/// because the form owns it.
+pub(crate) fn forget_tooltip_window(form: HWND) {
+    TOOLTIP_WINDOWS.with_borrow_mut(|windows| windows.remove(&(form.0 as isize)));
+}
+
+pub(crate) fn apply_tooltip_options(tooltip: HWND, options: &TooltipOptions) {
+    unsafe {
+        let style = GetWindowLongW(tooltip, GWL_STYLE) as u32;
+        let style = if options.balloon {
+            style | TTS_BALLOON
+        } else {
+            style & !TTS_BALLOON
+        };
+        SetWindowLongW(tooltip, GWL_STYLE, style as i32);
+
+        let max_width = options.max_width.unwrap_or(DEFAULT_MAX_WIDTH);
+        SendMessageW(
+            tooltip,
+            TTM_SETMAXTIPWIDTH,
+            None,
+            Some(LPARAM(max_width as isize)),
+        );
+
+        // https://learn.microsoft.com/en-us/windows/win32/controls/ttm-setdelaytime
+        // -1 restores the default delay.
+        for (which, delay) in [
+            (TTDT_INITIAL, options.initial_delay),
+            (TTDT_AUTOPOP, options.autopop_delay),
+            (TTDT_RESHOW, options.reshow_delay),
+        ] {
+            let ms = match delay {
+                Some(delay) => delay.as_millis().min(0x7fff) as isize,
+                None => -1,
+            };
+            SendMessageW(
+                tooltip,
+                TTM_SETDELAYTIME,
+                Some(WPARAM(which as usize)),
+                Some(LPARAM(ms)),
+            );
+        }
+    }
+}
+
+fn tool_info(control: HWND, notify: HWND) -> TTTOOLINFOW {
+    TTTOOLINFOW {
+        cbSize: size_of::<TTTOOLINFOW>() as u32,
+        uFlags: TTF_IDISHWND | TTF_SUBCLASS,
+        hwnd: notify,
+        uId: control.0 as usize,
+        ..Default::default()
+    }
+}
+
+/// Registers `control` with its form's tooltip window. If `text` is `None`, the
+/// text is requested from `notify` with `TTN_GETDISPINFO` each time the tooltip is
+/// shown. Any previous tooltip for `control` is replaced.
+pub(crate) fn add_tool(control: HWND, notify: HWND, text: Option<&str>) {
+    unsafe {
+        let tooltip = get_tooltip_window(control);
+        remove_tool(control, notify);
+
+        // The tooltip window keeps its own copy of the text.
+        let text_wstr: WCString;
+        let mut info = tool_info(control, notify);
+        info.lpszText = match text {
+            Some(text) => {
+                text_wstr = WCString::from_str_truncate(text);
+                PWSTR(text_wstr.as_ptr() as *mut u16)
+            }
+            None => LPSTR_TEXTCALLBACKW,
+        };
+
+        if SendMessageW(
+            tooltip,
+            TTM_ADDTOOLW,
+            None,
+            Some(LPARAM(&info as *const TTTOOLINFOW as isize)),
+        )
+        .0 == 0
+        {
+            warn!("TTM_ADDTOOL failed");
+        }
+    }
+}
+
+pub(crate) fn remove_tool(control: HWND, notify: HWND) {
+    unsafe {
+        let tooltip = get_tooltip_window(control);
+        let info = tool_info(control, notify);
+        SendMessageW(
+            tooltip,
+            TTM_DELTOOLW,
+            None,
+            Some(LPARAM(&info as *const TTTOOLINFOW as isize)),
+        );
+    }
+}
+
+/// Hides the tooltip and makes it request its text again, for controls whose
+/// tooltip depends on which part of the control the mouse is over.
+pub(crate) fn reset_tooltip(control: HWND) {
+    unsafe {
+        let tooltip = get_tooltip_window(control);
+        SendMessageW(tooltip, TTM_ACTIVATE, Some(WPARAM(0)), None);
+        SendMessageW(tooltip, TTM_ACTIVATE, Some(WPARAM(1)), None);
+    }
+}
@@ -0,0 +1,402 @@
+//! An icon in the notification area of the taskbar.
+
+use super::*;
+use std::cell::OnceCell;
+use std::sync::OnceLock;
+use windows::core::w;
+use windows::Win32::UI::Shell::{
+    Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_SHOWTIP, NIF_TIP, NIIF_ERROR,
+    NIIF_INFO, NIIF_NONE, NIIF_RESPECT_QUIET_TIME, NIIF_WARNING, NIM_ADD, NIM_DELETE, NIM_MODIFY,
+    NIM_SETVERSION, NIN_BALLOONUSERCLICK, NIN_SELECT, NOTIFYICONDATAW, NOTIFYICON_VERSION_4,
+    NOTIFY_ICON_DATA_FLAGS, NOTIFY_ICON_INFOTIP_FLAGS, NOTIFY_ICON_MESSAGE,
+};
+
+/// An icon in the notification area ("system tray"), with a tooltip, click events,
+/// an optional popup menu and balloon notifications.
+///
+/// The icon is shown as soon as it has been created, and removed when it is dropped.
+/// If Explorer restarts, the icon is added again.
+pub struct TrayIcon {
+    hwnd: HWND,
+    icon: Cell<HICON>,
+    tooltip: RefCell<String>,
+    menu: RefCell<Option<Menu>>,
+    click_handler: OnceCell<Box<dyn Fn()>>,
+    right_click_handler: OnceCell<Box<dyn Fn()>>,
+    double_click_handler: OnceCell<Box<dyn Fn()>>,
+    balloon_click_handler: OnceCell<Box<dyn Fn()>>,
+    menu_command_handler: OnceCell<Box<dyn Fn(ControlId)>>,
+}
+
+/// The icon shown in a balloon notification.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BalloonIcon {
+    None,
+    Info,
+    Warning,
+    Error,
+}
+
+impl BalloonIcon {
+    fn to_native(self) -> NOTIFY_ICON_INFOTIP_FLAGS {
+        match self {
+            Self::None => NIIF_NONE,
+            Self::Info => NIIF_INFO,
+            Self::Warning => NIIF_WARNING,
+            Self::Error => NIIF_ERROR,
+        }
+    }
+}
+
+/// There is only one icon per window, so its ID is fixed.
+const TRAY_ICON_ID: u32 = 1;
+
+const TRAY_WM_CALLBACK: u32 = WM_USER + 1;
+
+/// NIN_SELECT | NINF_KEY, sent when the icon is selected with the keyboard.
+const NIN_KEYSELECT: u32 = NIN_SELECT | 1;
+
+impl TrayIcon {
+    /// Adds an icon to the notification area. It has no image until `set_icon` or
+    /// `set_system_icon` is called.
+    pub fn new() -> Rc<TrayIcon> {
+        unsafe {
+            let class_atom = register_class_lazy();
+            // This is a hidden top-level window, rather than a message-only window,
+            // because only top-level windows receive the TaskbarCreated broadcast.
+            let hwnd = CreateWindowExW(
+                WS_EX_TOOLWINDOW,
+                PCWSTR::from_raw(class_atom as usize as *const u16),
+                w!("tray icon"),
+                WINDOW_STYLE(0),
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                Some(get_instance()),
+                None,
+            )
+            .unwrap();
+
+            let this = Rc::new(TrayIcon {
+                hwnd,
+                icon: Cell::new(HICON(null_mut())),
+                tooltip: RefCell::new(String::new()),
+                menu: RefCell::new(None),
+                click_handler: OnceCell::new(),
+                right_click_handler: OnceCell::new(),
+                double_click_handler: OnceCell::new(),
+                balloon_click_handler: OnceCell::new(),
+                menu_command_handler: OnceCell::new(),
+            });
+            let this_ptr: *const TrayIcon = &*this;
+            SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0), this_ptr as isize);
+
+            this.add();
+            this
+        }
+    }
+
+    /// Sets the image of the icon from an image list. The image list should hold
+    /// small icons, whose size is given by `SM_CXSMICON` and `SM_CYSMICON`.
+    pub fn set_icon(&self, image_list: &ImageList, index: u32) -> Result<()> {
+        let hicon = image_list.get_hicon(index)?;
+        let old = self.icon.replace(hicon);
+        self.modify(NIF_ICON);
+        if !old.is_invalid() {
+            unsafe {
+                _ = DestroyIcon(old);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the image of the icon to one of the system icons.
+    pub fn set_system_icon(&self, icon: SystemIcon) -> Result<()> {
+        let (width, height) =
+            unsafe { (GetSystemMetrics(SM_CXSMICON), GetSystemMetrics(SM_CYSMICON)) };
+        let image_list = ImageList::new(width, height)?;
+        let index = image_list.add_system_icon(icon)?;
+        self.set_icon(&image_list, index)
+    }
+
+    /// Sets the text that is shown when the mouse is over the icon. It is truncated
+    /// to 127 characters.
+    pub fn set_tooltip(&self, text: &str) {
+        *self.tooltip.borrow_mut() = text.to_string();
+        self.modify(NIF_TIP | NIF_SHOWTIP);
+    }
+
+    pub fn tooltip(&self) -> String {
+        self.tooltip.borrow().clone()
+    }
+
+    /// Sets the menu that is shown when the user right-clicks the icon, or opens its
+    /// context menu with the keyboard. Commands from the menu are passed to the
+    /// handler set with `menu_command_handler`.
+    pub fn set_menu(&self, menu: Option<Menu>) {
+        *self.menu.borrow_mut() = menu;
+    }
+
+    /// Shows a balloon notification next to the icon. Windows shows it as a toast
+    /// notification, and does not show it at all while the user does not want to
+    /// be disturbed.
+    pub fn show_balloon(&self, title: &str, text: &str, icon: BalloonIcon) {
+        let mut data = self.notify_data(NIF_INFO);
+        copy_truncated(&mut data.szInfoTitle, title);
+        copy_truncated(&mut data.szInfo, text);
+        data.dwInfoFlags = icon.to_native() | NIIF_RESPECT_QUIET_TIME;
+        self.notify(NIM_MODIFY, &data);
+    }
+
+    /// Hides the balloon notification, if it is shown.
+    pub fn hide_balloon(&self) {
+        // An empty text removes the balloon.
+        let data = self.notify_data(NIF_INFO);
+        self.notify(NIM_MODIFY, &data);
+    }
+
+    /// Sets the handler for a left click, or for selecting the icon with the
+    /// keyboard.
+    pub fn click_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.click_handler.set(Box::new(handler));
+        assert!(result.is_ok(), "cannot call click_handler() more than once");
+    }
+
+    /// Sets the handler for a right click, or for opening the context menu with the
+    /// keyboard. It is called before the menu set with `set_menu` is shown, so it can
+    /// update the menu.
+    pub fn right_click_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.right_click_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call right_click_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for a double click. A double click also gives a click before
+    /// it.
+    pub fn double_click_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.double_click_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call double_click_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for a click on a balloon notification.
+    pub fn balloon_click_handler<F>(&self, handler: F)
+    where
+        F: Fn() + 'static,
+    {
+        let result = self.balloon_click_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call balloon_click_handler() more than once"
+        );
+    }
+
+    /// Sets the handler for the items chosen in the menu set with `set_menu`. It is
+    /// given the `id` of the `MenuItem`.
+    pub fn menu_command_handler<F>(&self, handler: F)
+    where
+        F: Fn(ControlId) + 'static,
+    {
+        let result = self.menu_command_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call menu_command_handler() more than once"
+        );
+    }
+
+    fn notify_data(&self, flags: NOTIFY_ICON_DATA_FLAGS) -> NOTIFYICONDATAW {
+        let mut data: NOTIFYICONDATAW = unsafe { zeroed() };
+        data.cbSize = size_of::<NOTIFYICONDATAW>() as u32;
+        data.hWnd = self.hwnd;
+        data.uID = TRAY_ICON_ID;
+        data.uFlags = flags;
+        data.uCallbackMessage = TRAY_WM_CALLBACK;
+        data.hIcon = self.icon.get();
+        copy_truncated(&mut data.szTip, &self.tooltip.borrow());
+        data
+    }
+
+    fn notify(&self, message: NOTIFY_ICON_MESSAGE, data: &NOTIFYICONDATAW) -> bool {
+        let succeeded = unsafe { Shell_NotifyIconW(message, data).as_bool() };
+        if !succeeded {
+            warn!("Shell_NotifyIconW({}) failed", message.0);
+        }
+        succeeded
+    }
+
+    /// Adds the icon to the notification area, with everything that has been set.
+    fn add(&self) {
+        let mut flags = NIF_MESSAGE | NIF_TIP | NIF_SHOWTIP;
+        if !self.icon.get().is_invalid() {
+            flags |= NIF_ICON;
+        }
+        let mut data = self.notify_data(flags);
+        if self.notify(NIM_ADD, &data) {
+            // Version 4 gives the position of the icon with each event, and sends
+            // NIN_SELECT and WM_CONTEXTMENU for keyboard use as well as clicks.
+            data.Anonymous.uVersion = NOTIFYICON_VERSION_4;
+            self.notify(NIM_SETVERSION, &data);
+        }
+    }
+
+    fn modify(&self, flags: NOTIFY_ICON_DATA_FLAGS) {
+        let data = self.notify_data(flags);
+        self.notify(NIM_MODIFY, &data);
+    }
+
+    fn show_menu(&self, x: i32, y: i32) {
+        let menu = self.menu.borrow();
+        let Some(menu) = menu.as_ref() else {
+            return;
+        };
+        unsafe {
+            // Without this, the menu does not close when the user clicks elsewhere.
+            // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-trackpopupmenu#remarks
+            _ = SetForegroundWindow(self.hwnd);
+            menu.track_popup_menu_owned_by(self.hwnd, x, y);
+            _ = PostMessageW(Some(self.hwnd), WM_NULL, WPARAM(0), LPARAM(0));
+        }
+    }
+
+    fn on_callback(&self, wparam: WPARAM, lparam: LPARAM) {
+        // With version 4, the low word of lparam is the event, and wparam holds the
+        // position of the icon.
+        let event = (lparam.0 as u32) & 0xffff;
+        let x = get_x_lparam(LPARAM(wparam.0 as isize)) as i32;
+        let y = get_y_lparam(LPARAM(wparam.0 as isize)) as i32;
+        match event {
+            NIN_SELECT | NIN_KEYSELECT => {
+                if let Some(handler) = self.click_handler.get() {
+                    handler();
+                }
+            }
+            WM_LBUTTONDBLCLK => {
+                if let Some(handler) = self.double_click_handler.get() {
+                    handler();
+                }
+            }
+            WM_CONTEXTMENU => {
+                if let Some(handler) = self.right_click_handler.get() {
+                    handler();
+                }
+                self.show_menu(x, y);
+            }
+            NIN_BALLOONUSERCLICK => {
+                if let Some(handler) = self.balloon_click_handler.get() {
+                    handler();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            let data = self.notify_data(NOTIFY_ICON_DATA_FLAGS(0));
+            _ = Shell_NotifyIconW(NIM_DELETE, &data);
+            SetWindowLongPtrW(self.hwnd, WINDOW_LONG_PTR_INDEX(0), 0);
+            _ = DestroyWindow(self.hwnd);
+            let icon = self.icon.get();
+            if !icon.is_invalid() {
+                _ = DestroyIcon(icon);
+            }
+        }
+    }
+}
+
+/// Copies `s` into a fixed-size string field, truncating it so that it ends with a
+/// nul. A surrogate pair is not split.
+pub(crate) fn copy_truncated(dst: &mut [u16], s: &str) {
+    let Some(max_len) = dst.len().checked_sub(1) else {
+        return;
+    };
+    let mut len = 0;
+    for c in s.chars() {
+        let n = c.len_utf16();
+        if len + n > max_len {
+            break;
+        }
+        c.encode_utf16(&mut dst[len..len + n]);
+        len += n;
+    }
+    dst[len..].fill(0);
+}
+
+static TRAY_CLASS_ATOM: OnceLock<ATOM> = OnceLock::new();
+static TASKBAR_CREATED: OnceLock<u32> = OnceLock::new();
+
+fn register_class_lazy() -> ATOM {
+    *TRAY_CLASS_ATOM.get_or_init(|| unsafe {
+        TASKBAR_CREATED.get_or_init(|| RegisterWindowMessageW(w!("TaskbarCreated")));
+
+        let mut class_ex: WNDCLASSEXW = zeroed();
+        class_ex.cbSize = size_of::<WNDCLASSEXW>() as u32;
+        class_ex.hInstance = get_instance();
+        class_ex.lpszClassName = w!("RustForms_TrayIcon");
+        class_ex.lpfnWndProc = Some(tray_icon_wndproc);
+        class_ex.cbWndExtra = size_of::<*mut c_void>() as i32;
+
+        let atom = RegisterClassExW(&class_ex);
+        if atom == 0 {
+            panic!("Failed to register window class");
+        }
+        atom
+    })
+}
+
+extern "system" fn tray_icon_wndproc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        let state_ptr: isize = GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0));
+        if state_ptr == 0 {
+            return DefWindowProcW(hwnd, message, wparam, lparam);
+        }
+        let state: &TrayIcon = &*(state_ptr as *const TrayIcon);
+
+        match message {
+            TRAY_WM_CALLBACK => {
+                state.on_callback(wparam, lparam);
+                LRESULT(0)
+            }
+
+            WM_COMMAND => {
+                // Only the menu sends commands to this window.
+                let id = ControlId((wparam.0 & 0xffff) as u16);
+                if let Some(handler) = state.menu_command_handler.get() {
+                    handler(id);
+                }
+                LRESULT(0)
+            }
+
+            _ if Some(&message) == TASKBAR_CREATED.get() => {
+                debug!("TaskbarCreated: adding the tray icon again");
+                state.add();
+                LRESULT(0)
+            }
+
+            _ => DefWindowProcW(hwnd, message, wparam, lparam),
+        }
+    }
+}
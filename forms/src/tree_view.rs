@@ -2,6 +2,8 @@ use windows::core::w;
 
 use super::*;
 use core::any::Any;
+use std::cell::OnceCell;
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
 
 impl core::ops::Deref for TreeView {
     type Target = ControlState;
@@ -15,6 +17,13 @@ pub struct TreeView {
 
     // key is HTREEITEM
     items: RefCell<HashMap<isize, Rc<NodeState>>>,
+
+    // used to build TreeNode values for event handlers
+    this: Weak<TreeView>,
+    images: RefCell<Option<Rc<ImageList>>>,
+    item_tooltip_handler: OnceCell<Box<dyn Fn(&TreeNode) -> Option<String>>>,
+    begin_label_edit_handler: OnceCell<Box<dyn Fn(&TreeNode) -> bool>>,
+    end_label_edit_handler: OnceCell<Box<dyn Fn(&TreeNode, Option<&str>) -> bool>>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -26,6 +35,8 @@ pub struct TreeViewOptions {
     pub full_row_select: bool,
     pub single_expand: bool,
     pub checkboxes: bool,
+    /// Lets the user edit the text of a node by clicking it when it is selected.
+    pub edit_labels: bool,
 }
 
 const WC_TREEVIEW: &str = "SysTreeView32";
@@ -65,6 +76,9 @@ impl TreeView {
             if options.checkboxes {
                 style |= WINDOW_STYLE(TVS_CHECKBOXES);
             }
+            if options.edit_labels {
+                style |= WINDOW_STYLE(TVS_EDITLABELS);
+            }
 
             let hwnd = CreateWindowExW(
                 WINDOW_EX_STYLE(ex_style),
@@ -84,10 +98,17 @@ impl TreeView {
 
             form.invalidate_layout();
 
-            Rc::new(TreeView {
+            let tree = Rc::new_cyclic(|this| TreeView {
                 control: ControlState::new(hwnd),
                 items: RefCell::new(HashMap::new()),
-            })
+                this: this.clone(),
+                images: RefCell::new(None),
+                item_tooltip_handler: OnceCell::new(),
+                begin_label_edit_handler: OnceCell::new(),
+                end_label_edit_handler: OnceCell::new(),
+            });
+            register_notify_handler(hwnd, Rc::downgrade(&tree) as Weak<dyn NotifyHandlerTrait>);
+            tree
         }
     }
 
@@ -106,6 +127,11 @@ impl TreeView {
             .set_window_style_flag(WINDOW_STYLE(TVS_CHECKBOXES), value);
     }
 
+    pub fn set_edit_labels(&self, value: bool) {
+        self.control
+            .set_window_style_flag(WINDOW_STYLE(TVS_EDITLABELS), value);
+    }
+
     // Appearance properties
 
     #[allow(dead_code)]
@@ -133,11 +159,104 @@ impl TreeView {
         self.set_ex_style_flag(TVS_EX_DOUBLEBUFFER, value);
     }
 
+    /// Sets the handler that provides the tooltip for a node. If the handler returns
+    /// `None`, the tooltip shows the node's text when it is too wide to be displayed
+    /// in full.
+    pub fn item_tooltip_handler<F>(&self, handler: F)
+    where
+        F: Fn(&TreeNode) -> Option<String> + 'static,
+    {
+        let result = self.item_tooltip_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call item_tooltip_handler() more than once"
+        );
+        self.control
+            .set_window_style_flag(WINDOW_STYLE(TVS_INFOTIP), true);
+    }
+
+    /// Sets the handler that is called when the user starts editing the text of a
+    /// node. If the handler returns false, the text is not edited.
+    pub fn begin_label_edit_handler<F>(&self, handler: F)
+    where
+        F: Fn(&TreeNode) -> bool + 'static,
+    {
+        let result = self.begin_label_edit_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call begin_label_edit_handler() more than once"
+        );
+    }
+
+    /// Sets the handler that is called when the user has finished editing the text
+    /// of a node, given the new text, or `None` if the edit was cancelled. If the
+    /// handler returns true, the node gets the new text; if it returns false, the
+    /// node keeps its old text.
+    ///
+    /// Without this handler, the node always keeps its old text.
+    pub fn end_label_edit_handler<F>(&self, handler: F)
+    where
+        F: Fn(&TreeNode, Option<&str>) -> bool + 'static,
+    {
+        let result = self.end_label_edit_handler.set(Box::new(handler));
+        assert!(
+            result.is_ok(),
+            "cannot call end_label_edit_handler() more than once"
+        );
+    }
+
+    /// Stops editing the text of a node, if one is being edited. The node keeps its
+    /// old text.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tvm-endeditlabelnow>
+    pub fn cancel_label_edit(&self) {
+        unsafe {
+            SendMessageW(self.handle(), TVM_ENDEDITLABELNOW, Some(WPARAM(1)), None);
+        }
+    }
+
+    fn node_from_hitem(&self, hitem: HTREEITEM) -> Option<TreeNode> {
+        let state = self.items.borrow().get(&hitem.0).cloned()?;
+        Some(TreeNode {
+            tree: self.this.upgrade()?,
+            state,
+        })
+    }
+
+    /// Sets the images that nodes refer to with `NewItem::image`.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tvm-setimagelist>
+    pub fn set_image_list(&self, images: Rc<ImageList>) {
+        unsafe {
+            SendMessageW(
+                self.handle(),
+                TVM_SETIMAGELIST,
+                Some(WPARAM(TVSIL_NORMAL as usize)),
+                Some(LPARAM(images.himagelist.0)),
+            );
+        }
+        *self.images.borrow_mut() = Some(images);
+    }
+
     pub fn insert_root(self: &Rc<Self>, item: &str) -> Result<TreeNode> {
+        self.insert_at(
+            TVI_ROOT,
+            &NewItem {
+                text: item,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn insert_root_item(self: &Rc<Self>, item: &NewItem<'_>) -> Result<TreeNode> {
         self.insert_at(TVI_ROOT, item)
     }
 
-    fn insert_at(self: &Rc<Self>, parent_hitem: HTREEITEM, text: &str) -> Result<TreeNode> {
+    fn insert_at(
+        self: &Rc<Self>,
+        parent_hitem: HTREEITEM,
+        new_item: &NewItem<'_>,
+    ) -> Result<TreeNode> {
         unsafe {
             let mut item: TVINSERTSTRUCTW = zeroed();
             item.hParent = parent_hitem;
@@ -147,9 +266,15 @@ impl TreeView {
             itemex.hwnd = self.handle();
             itemex.mask = TVIF_TEXT;
 
-            let text_wstr = WCString::from_str(text).unwrap();
+            let text_wstr = WCString::from_str(new_item.text).unwrap();
             itemex.pszText = PWSTR(text_wstr.as_ptr() as *const _ as *mut _);
 
+            if let Some(image) = new_item.image {
+                itemex.mask |= TVIF_IMAGE | TVIF_SELECTEDIMAGE;
+                itemex.iImage = image as i32;
+                itemex.iSelectedImage = new_item.selected_image.unwrap_or(image) as i32;
+            }
+
             let hitem = HTREEITEM(
                 SendMessageW(
                     self.handle(),
@@ -182,8 +307,13 @@ impl TreeView {
     }
 }
 
+#[derive(Default)]
 pub struct NewItem<'a> {
     pub text: &'a str,
+    /// Index of the node's image, in the tree view's image list.
+    pub image: Option<u32>,
+    /// Image shown while the node is selected. If this is `None`, `image` is used.
+    pub selected_image: Option<u32>,
 }
 
 #[derive(Clone)]
@@ -200,6 +330,13 @@ struct NodeState {
 
 impl TreeNode {
     pub fn insert_child(&self, item: &str) -> Result<TreeNode> {
+        self.insert_child_item(&NewItem {
+            text: item,
+            ..Default::default()
+        })
+    }
+
+    pub fn insert_child_item(&self, item: &NewItem<'_>) -> Result<TreeNode> {
         if self.state.deleted.get() {
             return Err(Error::ItemDeleted);
         }
@@ -320,6 +457,28 @@ impl TreeNode {
         }
     }
 
+    /// Starts editing the text of the node, as if the user had clicked it. This
+    /// moves the focus to the tree view. Returns false if the edit did not start,
+    /// such as when the begin-edit handler refused it.
+    ///
+    /// See <https://learn.microsoft.com/en-us/windows/win32/controls/tvm-editlabel>
+    pub fn edit_label(&self) -> bool {
+        unsafe {
+            if self.state.deleted.get() {
+                return false;
+            }
+
+            _ = SetFocus(Some(self.tree.handle()));
+            let edit = SendMessageW(
+                self.tree.handle(),
+                TVM_EDITLABELW,
+                None,
+                Some(LPARAM(self.state.hitem.0)),
+            );
+            edit.0 != 0
+        }
+    }
+
     pub fn is_selected(&self) -> bool {
         unsafe {
             if self.state.deleted.get() {
@@ -376,6 +535,59 @@ fn remove_items_rec(
     }
 }
 
+impl NotifyHandlerTrait for TreeView {
+    unsafe fn wm_notify(&self, nmhdr: *mut NMHDR) -> NotifyResult {
+        match (*nmhdr).code {
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tvn-getinfotip
+            TVN_GETINFOTIPW => {
+                let info = &mut *(nmhdr as *mut NMTVGETINFOTIPW);
+                if let Some(handler) = self.item_tooltip_handler.get() {
+                    if let Some(node) = self.node_from_hitem(info.hItem) {
+                        if let Some(text) = handler(&node) {
+                            copy_to_text_buffer(&text, info.pszText, info.cchTextMax);
+                        }
+                    }
+                }
+                NotifyResult::Consumed(LRESULT(0))
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tvn-beginlabeledit
+            TVN_BEGINLABELEDITW => {
+                let info = &*(nmhdr as *const NMTVDISPINFOW);
+                let handler = self.begin_label_edit_handler.get();
+                match (handler, self.node_from_hitem(info.item.hItem)) {
+                    (Some(handler), Some(node)) => {
+                        // Returning TRUE cancels the edit.
+                        NotifyResult::Consumed(LRESULT(!handler(&node) as isize))
+                    }
+                    _ => NotifyResult::NotConsumed,
+                }
+            }
+
+            // https://learn.microsoft.com/en-us/windows/win32/controls/tvn-endlabeledit
+            TVN_ENDLABELEDITW => {
+                let info = &*(nmhdr as *const NMTVDISPINFOW);
+                let handler = self.end_label_edit_handler.get();
+                match (handler, self.node_from_hitem(info.item.hItem)) {
+                    (Some(handler), Some(node)) => {
+                        // The text is null if the user cancelled the edit.
+                        let text = if info.item.pszText.is_null() {
+                            None
+                        } else {
+                            Some(info.item.pszText.to_string().unwrap_or_default())
+                        };
+                        let accept = handler(&node, text.as_deref()) && text.is_some();
+                        NotifyResult::Consumed(LRESULT(accept as isize))
+                    }
+                    _ => NotifyResult::NotConsumed,
+                }
+            }
+
+            _ => NotifyResult::NotConsumed,
+        }
+    }
+}
+
 /*
 struct TreeViewNotifyShim {
     tree: Rc<TreeView>,
@@ -0,0 +1,396 @@
+//! Derive macros for the `forms` crate. Use them through the re-exports in `forms`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitInt, LitStr};
+
+/// Implements `forms::Properties` for a struct with named fields.
+///
+/// Each field accepts `#[property(...)]` with these options:
+///
+/// * `label = "..."`: the name shown in the grid. By default, this is the field name
+///   with underscores replaced by spaces and the first letter capitalized.
+/// * `category = "..."`
+/// * `description = "..."`
+/// * `read_only`
+/// * `skip`: the field is not shown at all.
+#[proc_macro_derive(Properties, attributes(property))]
+pub fn derive_properties(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_properties(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `forms::PropertyField` for an enum whose variants have no fields. The
+/// enum is edited with a drop-down list of its variants. A variant accepts
+/// `#[property(label = "...")]` to change the text shown for it.
+#[proc_macro_derive(PropertyField, attributes(property))]
+pub fn derive_property_field(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_property_field(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `forms::TableRow` for a struct with named fields, with a column for
+/// each field.
+///
+/// Each field accepts `#[column(...)]` with these options:
+///
+/// * `title = "..."`: the column header. By default, this is the field name with
+///   underscores replaced by spaces and the first letter capitalized.
+/// * `width = 120`: the width in pixels.
+/// * `align = "left"`, `"center"` or `"right"`
+/// * `format = "{:.2}"`: the format string for the field. By default, this is `"{}"`.
+/// * `min_width = 40`, `max_width = 400`: the limits on the width that the user can
+///   choose.
+/// * `autosize = "content"` or `"header"`: sizes the column to fit its cells, or its
+///   cells and its header.
+/// * `hidden`: the column starts out hidden.
+/// * `skip`: the field is not shown.
+#[proc_macro_derive(TableRow, attributes(column))]
+pub fn derive_table_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_table_row(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct PropertyAttrs {
+    label: Option<String>,
+    category: Option<String>,
+    description: Option<String>,
+    read_only: bool,
+    skip: bool,
+}
+
+fn parse_attrs(attrs: &[syn::Attribute]) -> syn::Result<PropertyAttrs> {
+    let mut result = PropertyAttrs::default();
+    for attr in attrs {
+        if !attr.path().is_ident("property") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("label") {
+                result.label = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("category") {
+                result.category = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("description") {
+                result.description = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("read_only") {
+                result.read_only = true;
+            } else if meta.path.is_ident("skip") {
+                result.skip = true;
+            } else {
+                return Err(meta.error("unknown property option"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(result)
+}
+
+/// `connect_timeout` -> `Connect timeout`
+fn default_label(ident: &str) -> String {
+    let words = ident.trim_start_matches("r#").replace('_', " ");
+    let mut chars = words.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn option_tokens(value: &Option<String>) -> TokenStream2 {
+    match value {
+        Some(s) => quote!(::core::option::Option::Some(::std::string::String::from(#s))),
+        None => quote!(::core::option::Option::None),
+    }
+}
+
+fn expand_properties(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "#[derive(Properties)] only supports structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "#[derive(Properties)] requires named fields",
+        ));
+    };
+
+    let mut describe = Vec::new();
+    let mut set_arms = Vec::new();
+    for field in &fields.named {
+        let attrs = parse_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let name = ident.to_string().trim_start_matches("r#").to_string();
+        let label = attrs.label.unwrap_or_else(|| default_label(&name));
+        let category = option_tokens(&attrs.category);
+        let description = option_tokens(&attrs.description);
+        let read_only = attrs.read_only;
+
+        describe.push(quote! {
+            ::forms::Property {
+                name: ::std::string::String::from(#name),
+                label: ::std::string::String::from(#label),
+                category: #category,
+                description: #description,
+                value: ::forms::PropertyField::to_property_value(&self.#ident),
+                read_only: #read_only,
+            }
+        });
+
+        set_arms.push(if read_only {
+            quote! {
+                #name => ::core::result::Result::Err(
+                    ::std::format!("'{}' cannot be changed.", #label)
+                ),
+            }
+        } else {
+            quote! {
+                #name => {
+                    self.#ident = <#ty as ::forms::PropertyField>::from_property_value(value)?;
+                    ::core::result::Result::Ok(())
+                }
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::forms::Properties for #ident #ty_generics #where_clause {
+            fn properties(&self) -> ::std::vec::Vec<::forms::Property> {
+                ::std::vec![#(#describe),*]
+            }
+
+            fn set_property(
+                &mut self,
+                name: &str,
+                value: ::forms::PropertyValue,
+            ) -> ::core::result::Result<(), ::std::string::String> {
+                match name {
+                    #(#set_arms)*
+                    _ => ::core::result::Result::Err(
+                        ::std::format!("There is no property called '{}'.", name)
+                    ),
+                }
+            }
+        }
+    })
+}
+
+fn expand_property_field(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "#[derive(PropertyField)] only supports enums",
+        ));
+    };
+
+    let mut labels = Vec::new();
+    let mut to_arms = Vec::new();
+    let mut from_arms = Vec::new();
+    for (index, variant) in data.variants.iter().enumerate() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "#[derive(PropertyField)] requires variants without fields",
+            ));
+        }
+        let attrs = parse_attrs(&variant.attrs)?;
+        let variant_ident = &variant.ident;
+        labels.push(attrs.label.unwrap_or_else(|| variant_ident.to_string()));
+        to_arms.push(quote!(Self::#variant_ident => #index,));
+        from_arms.push(quote!(#index => ::core::result::Result::Ok(Self::#variant_ident),));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::forms::PropertyField for #ident #ty_generics #where_clause {
+            fn to_property_value(&self) -> ::forms::PropertyValue {
+                let index = match self {
+                    #(#to_arms)*
+                };
+                ::forms::PropertyValue::Choice {
+                    index,
+                    choices: ::std::vec![#(::std::string::String::from(#labels)),*],
+                }
+            }
+
+            fn from_property_value(
+                value: ::forms::PropertyValue,
+            ) -> ::core::result::Result<Self, ::std::string::String> {
+                match value {
+                    ::forms::PropertyValue::Choice { index, .. } => match index {
+                        #(#from_arms)*
+                        _ => ::core::result::Result::Err(
+                            ::std::string::String::from("The choice is not valid.")
+                        ),
+                    },
+                    other => ::core::result::Result::Err(::std::format!(
+                        "'{}' is not a valid value.",
+                        other.display()
+                    )),
+                }
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct ColumnAttrs {
+    title: Option<String>,
+    width: Option<i32>,
+    align: Option<TokenStream2>,
+    format: Option<LitStr>,
+    min_width: Option<i32>,
+    max_width: Option<i32>,
+    autosize: Option<TokenStream2>,
+    hidden: bool,
+    skip: bool,
+}
+
+fn parse_column_attrs(attrs: &[syn::Attribute]) -> syn::Result<ColumnAttrs> {
+    let mut result = ColumnAttrs::default();
+    for attr in attrs {
+        if !attr.path().is_ident("column") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("title") {
+                result.title = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("width") {
+                result.width = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("align") {
+                let align = meta.value()?.parse::<LitStr>()?;
+                result.align = Some(match align.value().as_str() {
+                    "left" => quote!(::forms::ColumnAlign::Left),
+                    "center" => quote!(::forms::ColumnAlign::Center),
+                    "right" => quote!(::forms::ColumnAlign::Right),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            align,
+                            "expected \"left\", \"center\" or \"right\"",
+                        ))
+                    }
+                });
+            } else if meta.path.is_ident("format") {
+                result.format = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("min_width") {
+                result.min_width = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("max_width") {
+                result.max_width = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+            } else if meta.path.is_ident("autosize") {
+                let autosize = meta.value()?.parse::<LitStr>()?;
+                result.autosize = Some(match autosize.value().as_str() {
+                    "content" => quote!(::forms::ColumnAutoSize::Content),
+                    "header" => quote!(::forms::ColumnAutoSize::ContentAndHeader),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            autosize,
+                            "expected \"content\" or \"header\"",
+                        ))
+                    }
+                });
+            } else if meta.path.is_ident("hidden") {
+                result.hidden = true;
+            } else if meta.path.is_ident("skip") {
+                result.skip = true;
+            } else {
+                return Err(meta.error("unknown column option"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(result)
+}
+
+fn expand_table_row(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "#[derive(TableRow)] only supports structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "#[derive(TableRow)] requires named fields",
+        ));
+    };
+
+    let mut columns = Vec::new();
+    let mut cell_arms = Vec::new();
+    for field in &fields.named {
+        let attrs = parse_column_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let name = ident.to_string().trim_start_matches("r#").to_string();
+        let title = attrs.title.unwrap_or_else(|| default_label(&name));
+
+        let mut column = quote!(::forms::Column::new(#title));
+        if let Some(width) = attrs.width {
+            column = quote!(#column.width(#width));
+        }
+        if let Some(align) = attrs.align {
+            column = quote!(#column.align(#align));
+        }
+        if let Some(min_width) = attrs.min_width {
+            column = quote!(#column.min_width(#min_width));
+        }
+        if let Some(max_width) = attrs.max_width {
+            column = quote!(#column.max_width(#max_width));
+        }
+        if let Some(autosize) = attrs.autosize {
+            column = quote!(#column.autosize(#autosize));
+        }
+        if attrs.hidden {
+            column = quote!(#column.hidden(true));
+        }
+        columns.push(column);
+
+        let index = cell_arms.len();
+        let format = attrs
+            .format
+            .unwrap_or_else(|| LitStr::new("{}", proc_macro2::Span::call_site()));
+        cell_arms.push(quote! {
+            #index => {
+                let _ = ::core::fmt::Write::write_fmt(text, ::core::format_args!(#format, self.#ident));
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::forms::TableRow for #ident #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<::forms::Column> {
+                ::std::vec![#(#columns),*]
+            }
+
+            fn cell_text(&self, col: usize, text: &mut ::std::string::String) {
+                match col {
+                    #(#cell_arms)*
+                    _ => {}
+                }
+            }
+        }
+    })
+}